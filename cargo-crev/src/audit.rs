@@ -0,0 +1,306 @@
+//! `cargo crev audit`: dependencies with RustSec advisories, along their crev verification
+//!
+//! This complements `cargo audit` rather than replacing it: dependencies
+//! that were verified, but are affected by an advisory, need another look.
+
+use crate::deps::{scan, CrateStats};
+use crate::opts;
+use crate::prelude::*;
+use crate::shared::CommandExitStatus;
+use crate::term;
+use crev_lib::VerificationStatus;
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+pub const ADVISORY_DB_URL: &str = "https://github.com/rustsec/advisory-db";
+const ADVISORY_DB_CACHE_DIR: &str = "advisory-db";
+
+/// Metadata of an advisory, as in the `[advisory]` table of its file
+#[derive(Deserialize, Debug, Clone)]
+pub struct AdvisoryMetadata {
+    pub id: String,
+    pub package: String,
+    pub date: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Kind of an advisory that isn't about a vulnerability, eg. `unmaintained`
+    #[serde(default)]
+    pub informational: Option<String>,
+    /// Date the advisory was withdrawn, if it was
+    #[serde(default)]
+    pub withdrawn: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct AdvisoryVersions {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AdvisoryFile {
+    advisory: AdvisoryMetadata,
+    #[serde(default)]
+    versions: AdvisoryVersions,
+}
+
+/// A RustSec advisory
+#[derive(Debug, Clone)]
+pub struct Advisory {
+    pub metadata: AdvisoryMetadata,
+    pub title: String,
+    pub patched: Vec<VersionReq>,
+    pub unaffected: Vec<VersionReq>,
+}
+
+fn parse_version_reqs(reqs: &[String]) -> Result<Vec<VersionReq>> {
+    reqs.iter()
+        .map(|req| {
+            VersionReq::parse(req)
+                .map_err(|e| format_err!("Invalid version requirement `{}`: {}", req, e))
+        })
+        .collect()
+}
+
+impl Advisory {
+    /// Parse an advisory in the markdown format of the advisory database
+    ///
+    /// The metadata are in a TOML code block, followed by the title and the description.
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim_start();
+        if !text.starts_with("```toml") {
+            bail!("Advisory doesn't start with a ```toml block");
+        }
+        let front_matter = &text["```toml".len()..];
+        let end = front_matter
+            .find("\n```")
+            .ok_or_else(|| format_err!("Unterminated ```toml block"))?;
+        let file: AdvisoryFile = toml::from_str(&front_matter[..end])?;
+        let title = front_matter[end + "\n```".len()..]
+            .lines()
+            .find(|line| line.starts_with("# "))
+            .map_or("", |line| line["# ".len()..].trim())
+            .to_owned();
+        Ok(Advisory {
+            metadata: file.advisory,
+            title,
+            patched: parse_version_reqs(&file.versions.patched)?,
+            unaffected: parse_version_reqs(&file.versions.unaffected)?,
+        })
+    }
+
+    pub fn affects(&self, version: &Version) -> bool {
+        self.metadata.withdrawn.is_none()
+            && !self
+                .patched
+                .iter()
+                .chain(&self.unaffected)
+                .any(|req| req.matches(version))
+    }
+
+    pub fn is_vulnerability(&self) -> bool {
+        self.metadata.informational.is_none()
+    }
+
+    /// `vulnerability`, or the kind of informational advisory
+    pub fn kind(&self) -> &str {
+        self.metadata
+            .informational
+            .as_ref()
+            .map_or("vulnerability", String::as_str)
+    }
+}
+
+/// Advisories of a checkout of the RustSec advisory database, by crate name
+#[derive(Debug, Default)]
+pub struct AdvisoryDb {
+    by_crate: HashMap<String, Vec<Advisory>>,
+}
+
+impl AdvisoryDb {
+    /// Load the advisories of the crates.io crates in `dir`
+    ///
+    /// Advisories that can't be parsed are skipped with a warning.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let crates_dir = dir.join("crates");
+        if !crates_dir.is_dir() {
+            bail!(
+                "{} doesn't look like a RustSec advisory database",
+                dir.display()
+            );
+        }
+        let mut db = AdvisoryDb::default();
+        for path in crev_common::fs::find_files_with_extension(&crates_dir, "md")? {
+            match Advisory::parse(&crev_common::read_file_to_string(&path)?) {
+                Ok(advisory) => db.add(advisory),
+                Err(e) => eprintln!("Skipping advisory {}: {}", path.display(), e),
+            }
+        }
+        Ok(db)
+    }
+
+    pub fn add(&mut self, advisory: Advisory) {
+        self.by_crate
+            .entry(advisory.metadata.package.clone())
+            .or_default()
+            .push(advisory);
+    }
+
+    pub fn advisory_count(&self) -> usize {
+        self.by_crate.values().map(Vec::len).sum()
+    }
+
+    /// Advisories affecting this version of the crate
+    pub fn advisories_for(&self, name: &str, version: &Version) -> Vec<&Advisory> {
+        self.by_crate
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter(|advisory| advisory.affects(version))
+            .collect()
+    }
+}
+
+/// Clone the advisory database into the crev cache, or update the clone
+pub fn fetch_advisory_db(local: &crev_lib::Local, update: bool) -> Result<PathBuf> {
+    let dir = local.get_root_cache_dir().join(ADVISORY_DB_CACHE_DIR);
+    if !dir.exists() {
+        eprintln!("Fetching {}", ADVISORY_DB_URL);
+        std::fs::create_dir_all(local.get_root_cache_dir())?;
+        crev_lib::util::git::shallow_clone_git_repo(ADVISORY_DB_URL, &dir)?;
+    } else if update {
+        eprintln!("Updating {}", ADVISORY_DB_URL);
+        crev_lib::util::git::shallow_fetch_and_checkout_git_repo(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// A dependency with advisories, and whether crev verified it
+struct AuditedDep<'a> {
+    stats: CrateStats,
+    advisories: Vec<&'a Advisory>,
+}
+
+impl AuditedDep<'_> {
+    /// Verified, but affected by an advisory
+    fn needs_rereview(&self) -> bool {
+        self.stats.details().map_or(false, |details| {
+            details.trust == VerificationStatus::Verified
+        })
+    }
+}
+
+fn trust_string(stats: &CrateStats) -> String {
+    match &stats.details {
+        Ok(Some(details)) => details.trust.to_string(),
+        Ok(None) => "skipped".to_owned(),
+        Err(_) => "error".to_owned(),
+    }
+}
+
+fn print_audited_deps(deps: &[AuditedDep<'_>]) -> Result<()> {
+    let mut term = term::Term::new();
+    println!(
+        "{:20} {:12} {:18} {:14} {:8} patched",
+        "crate", "version", "advisory", "kind", "trust"
+    );
+    for dep in deps {
+        for advisory in &dep.advisories {
+            let patched: Vec<_> = advisory.patched.iter().map(ToString::to_string).collect();
+            print!(
+                "{:20} {:12} {:18} {:14} ",
+                dep.stats.info.id.name(),
+                dep.stats.info.id.version(),
+                advisory.metadata.id,
+                advisory.kind(),
+            );
+            let trust = trust_string(&dep.stats);
+            if dep.needs_rereview() {
+                term.print_bold(format_args!("{:8}", trust), ::term::color::RED)?;
+            } else {
+                term.print(format_args!("{:8}", trust), None)?;
+            }
+            println!(
+                " {}",
+                if patched.is_empty() {
+                    "none".to_owned()
+                } else {
+                    patched.join(", ")
+                }
+            );
+            if !advisory.title.is_empty() {
+                println!("    {}", advisory.title);
+            }
+        }
+        if dep.needs_rereview() {
+            term.eprint(
+                format_args!(
+                    "{} {} is verified, but has advisories: review it again\n",
+                    dep.stats.info.id.name(),
+                    dep.stats.info.id.version()
+                ),
+                ::term::color::RED,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run_audit(args: &opts::Audit) -> Result<CommandExitStatus> {
+    let db_dir = match &args.db_path {
+        Some(path) => path.clone(),
+        None => {
+            let local = crev_lib::Local::auto_create_or_open()?;
+            fetch_advisory_db(&local, !args.no_fetch)?
+        }
+    };
+    let advisory_db = AdvisoryDb::load(&db_dir)?;
+
+    let mut verify_args = opts::Verify::from_iter(&["verify"]);
+    verify_args.trust_params = args.trust_params.clone();
+    verify_args.requirements = args.requirements.clone();
+    let scanner = scan::Scanner::new(&verify_args)?;
+    let mut deps: Vec<_> = scanner
+        .crates()
+        .iter()
+        .filter_map(|info| {
+            let advisories = advisory_db.advisories_for(&info.id.name(), info.id.version());
+            if advisories.is_empty() {
+                return None;
+            }
+            Some(AuditedDep {
+                stats: scanner.scan_crate(info.clone()),
+                advisories,
+            })
+        })
+        .collect();
+    deps.sort_by(|a, b| a.stats.info.id.cmp(&b.stats.info.id));
+
+    if !deps.is_empty() {
+        print_audited_deps(&deps)?;
+    }
+    let vulnerable = deps
+        .iter()
+        .filter(|dep| dep.advisories.iter().any(|a| a.is_vulnerability()))
+        .count();
+    eprintln!(
+        "Checked {} dependencies against {} advisories: {} with advisories, {} vulnerable, {} to review again",
+        scanner.total_crate_count(),
+        advisory_db.advisory_count(),
+        deps.len(),
+        vulnerable,
+        deps.iter().filter(|dep| dep.needs_rereview()).count(),
+    );
+    Ok(if vulnerable > 0 {
+        CommandExitStatus::VerificationFailed
+    } else {
+        CommandExitStatus::Successs
+    })
+}
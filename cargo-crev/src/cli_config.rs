@@ -0,0 +1,184 @@
+//! Defaults of command line flags, from `cli.yaml` in the user config directory
+
+use crate::opts;
+use crate::prelude::*;
+use crev_data::Level;
+use crev_lib::local::Local;
+use serde::{Deserialize, Serialize};
+use structopt::clap::ArgMatches;
+
+/// Defaults of command line flags
+///
+/// Flags given on the command line take precedence, and fields left
+/// unset keep the built-in defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct CliConfig {
+    pub trust_preset: Option<opts::TrustPreset>,
+    pub depth: Option<u64>,
+    pub high_cost: Option<u64>,
+    pub medium_cost: Option<u64>,
+    pub low_cost: Option<u64>,
+    pub trust: Option<Level>,
+    pub redundancy: Option<u64>,
+    pub understanding: Option<Level>,
+    pub thoroughness: Option<Level>,
+    pub deny_severity: Option<Level>,
+    pub skip_verified: Option<bool>,
+    pub skip_known_owners: Option<bool>,
+    pub geiger: Option<bool>,
+    pub for_id: Option<String>,
+    pub analyzers: Option<Vec<opts::AnalyzerCommand>>,
+}
+
+/// Whether the flag `name` was given to the command or any of its subcommands
+fn is_explicit(matches: &ArgMatches<'_>, name: &str) -> bool {
+    matches.occurrences_of(name) > 0
+        || matches
+            .subcommand()
+            .1
+            .map_or(false, |sub| is_explicit(sub, name))
+}
+
+fn fill<T: Clone>(field: &mut T, value: &Option<T>, explicit: bool) {
+    if let (false, Some(value)) = (explicit, value) {
+        *field = value.clone();
+    }
+}
+
+fn fill_opt<T: Clone>(field: &mut Option<T>, value: &Option<T>, explicit: bool) {
+    if !explicit && value.is_some() {
+        *field = value.clone();
+    }
+}
+
+impl CliConfig {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(for_id) = &self.for_id {
+            crev_data::Id::crevid_from_str(for_id)
+                .map_err(|e| format_err!("Invalid `for-id` {}: {}", for_id, e))?;
+        }
+        Ok(())
+    }
+
+    fn apply_trust_params(
+        &self,
+        params: &mut opts::TrustDistanceParams,
+        explicit: &dyn Fn(&str) -> bool,
+    ) {
+        // An explicit preset replaces all the parameters of the config
+        if explicit("preset") {
+            return;
+        }
+        fill(&mut params.preset, &self.trust_preset, false);
+        fill_opt(&mut params.depth, &self.depth, explicit("depth"));
+        fill_opt(
+            &mut params.high_cost,
+            &self.high_cost,
+            explicit("high_cost"),
+        );
+        fill_opt(
+            &mut params.medium_cost,
+            &self.medium_cost,
+            explicit("medium_cost"),
+        );
+        fill_opt(&mut params.low_cost, &self.low_cost, explicit("low_cost"));
+    }
+
+    fn apply_requirements(
+        &self,
+        requirements: &mut opts::VerificationRequirements,
+        explicit: &dyn Fn(&str) -> bool,
+    ) {
+        fill(
+            &mut requirements.trust_level,
+            &self.trust,
+            explicit("trust_level"),
+        );
+        fill(
+            &mut requirements.redundancy,
+            &self.redundancy,
+            explicit("redundancy"),
+        );
+        fill(
+            &mut requirements.understanding_level,
+            &self.understanding,
+            explicit("understanding_level"),
+        );
+        fill(
+            &mut requirements.thoroughness_level,
+            &self.thoroughness,
+            explicit("thoroughness_level"),
+        );
+    }
+
+    /// Fill the flags of `command` that weren't given in `matches`
+    pub fn apply(&self, command: &mut opts::Command, matches: &ArgMatches<'_>) {
+        let explicit = |name: &str| is_explicit(matches, name);
+        match command {
+            opts::Command::Verify(args) => {
+                self.apply_trust_params(&mut args.trust_params, &explicit);
+                self.apply_requirements(&mut args.requirements, &explicit);
+                fill(
+                    &mut args.skip_verified,
+                    &self.skip_verified,
+                    explicit("skip_verified"),
+                );
+                fill(
+                    &mut args.skip_known_owners,
+                    &self.skip_known_owners,
+                    explicit("skip_known_owners"),
+                );
+                fill(&mut args.geiger, &self.geiger, explicit("geiger"));
+                fill_opt(&mut args.for_id, &self.for_id, explicit("for_id"));
+                fill(&mut args.analyzers, &self.analyzers, explicit("analyzers"));
+                fill_opt(
+                    &mut args.deny_severity,
+                    &self.deny_severity,
+                    explicit("deny_severity"),
+                );
+            }
+            opts::Command::Audit(args) => {
+                self.apply_trust_params(&mut args.trust_params, &explicit);
+                self.apply_requirements(&mut args.requirements, &explicit);
+            }
+            opts::Command::Review(args) => {
+                self.apply_trust_params(&mut args.trust_params, &explicit);
+                self.apply_requirements(&mut args.requirements, &explicit);
+            }
+            opts::Command::Diff(args) | opts::Command::Crate(opts::Crate::Diff(args)) => {
+                self.apply_trust_params(&mut args.trust_params, &explicit);
+                self.apply_requirements(&mut args.requirements, &explicit);
+            }
+            opts::Command::Fetch(opts::Fetch::Trusted(args)) => {
+                self.apply_trust_params(&mut args.trust_params, &explicit);
+            }
+            opts::Command::Query(opts::Query::Id(opts::QueryId::Trusted {
+                trust_params,
+                for_id,
+                ..
+            })) => {
+                self.apply_trust_params(trust_params, &explicit);
+                fill_opt(for_id, &self.for_id, explicit("for_id"));
+            }
+            opts::Command::Query(opts::Query::Issue(args)) => {
+                self.apply_trust_params(&mut args.trust_params, &explicit);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Apply the defaults from the user's `cli.yaml`, if there's a user config at all
+///
+/// Not for `config` commands, which must keep working to fix a broken `cli.yaml`.
+pub fn apply_user_cli_config(command: &mut opts::Command, matches: &ArgMatches<'_>) -> Result<()> {
+    if let opts::Command::Config(_) = command {
+        return Ok(());
+    }
+    if let Ok(local) = Local::auto_open() {
+        let config: CliConfig = local.load_cli_config()?;
+        config.apply(command, matches);
+    }
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use crate::shared::PROJECT_SOURCE_CRATES_IO;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     fs,
@@ -8,8 +9,56 @@ use std::{
 };
 
 pub struct Client {
-    client: crates_io_api::SyncClient,
+    /// `None` when `offline`, or for registries that don't provide crates.io API
+    client: Option<crates_io_api::SyncClient>,
+    /// The registry provides crates.io API
+    has_api: bool,
     cache_dir: PathBuf,
+    /// Use only cached responses, even if stale
+    offline: bool,
+    retry: RetryPolicy,
+}
+
+/// How to retry requests that failed for a transient reason
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every next one
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry`, counting from 0
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.initial_delay
+            .checked_mul(1 << retry.min(16))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+/// Rate limiting, server errors and timeouts may go away when retried
+fn is_transient(error: &crates_io_api::Error) -> bool {
+    match error {
+        crates_io_api::Error::Http(e) => {
+            e.is_timeout()
+                || e.status().map_or(false, |status| {
+                    status.as_u16() == 429 || status.is_server_error()
+                })
+        }
+        _ => false,
+    }
 }
 
 fn is_fresh(path: &Path) -> Result<bool> {
@@ -21,15 +70,23 @@ fn is_fresh(path: &Path) -> Result<bool> {
 
 trait Cacheable: Sized {
     fn get_cache_path(base: &Path, name: &str, version: &str) -> PathBuf;
-    fn fetch(client: &crates_io_api::SyncClient, crate_: &str, _version: &str) -> Result<Self>;
+    fn fetch(
+        client: &crates_io_api::SyncClient,
+        crate_: &str,
+        _version: &str,
+    ) -> std::result::Result<Self, crates_io_api::Error>;
 }
 
 impl Cacheable for crates_io_api::CrateResponse {
     fn get_cache_path(base: &Path, name: &str, _version: &str) -> PathBuf {
         base.join("crate").join(format!("{}.json", name))
     }
-    fn fetch(client: &crates_io_api::SyncClient, crate_: &str, _version: &str) -> Result<Self> {
-        Ok(client.get_crate(crate_)?)
+    fn fetch(
+        client: &crates_io_api::SyncClient,
+        crate_: &str,
+        _version: &str,
+    ) -> std::result::Result<Self, crates_io_api::Error> {
+        client.get_crate(crate_)
     }
 }
 
@@ -37,7 +94,11 @@ impl Cacheable for crates_io_api::Owners {
     fn get_cache_path(base: &Path, name: &str, _version: &str) -> PathBuf {
         base.join("owners").join(format!("{}.json", name))
     }
-    fn fetch(client: &crates_io_api::SyncClient, crate_: &str, _version: &str) -> Result<Self> {
+    fn fetch(
+        client: &crates_io_api::SyncClient,
+        crate_: &str,
+        _version: &str,
+    ) -> std::result::Result<Self, crates_io_api::Error> {
         Ok(crates_io_api::Owners {
             users: client.crate_owners(crate_)?,
         })
@@ -56,15 +117,62 @@ fn get_downloads_stats(resp: &crates_io_api::CrateResponse, version: &Version) -
 }
 
 impl Client {
-    pub fn new(local: &crev_lib::Local) -> Result<Self> {
+    /// Create a client for the registry used as `source`
+    ///
+    /// Only crates.io provides download counts and owners; for other
+    /// registries all the queries fail. When `offline`, only previously
+    /// cached responses are returned.
+    pub fn new(local: &crev_lib::Local, source: &str, offline: bool) -> Result<Self> {
         let cache_dir = local.get_root_cache_dir().join("crates_io");
         fs::create_dir_all(&cache_dir)?;
+        let has_api = source == PROJECT_SOURCE_CRATES_IO;
         Ok(Self {
-            client: crates_io_api::SyncClient::new(),
+            // so that offline runs can't reach the network by accident
+            client: if has_api && !offline {
+                Some(crates_io_api::SyncClient::new())
+            } else {
+                None
+            },
+            has_api,
             cache_dir,
+            offline,
+            retry: RetryPolicy::default(),
         })
     }
 
+    /// Retry failed requests up to `retries` times, instead of the default
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retry.retries = retries;
+        self
+    }
+
+    /// Make a request, retrying it after transient failures
+    ///
+    /// The error of the last attempt is returned.
+    fn request<T>(
+        &self,
+        what: &str,
+        f: impl Fn() -> std::result::Result<T, crates_io_api::Error>,
+    ) -> std::result::Result<T, crates_io_api::Error> {
+        let mut retry = 0;
+        loop {
+            match f() {
+                Err(ref e) if retry < self.retry.retries && is_transient(e) => {
+                    let delay = self.retry.delay(retry);
+                    log::debug!(
+                        "crates.io request for {} failed ({}); retrying in {:?}",
+                        what,
+                        e,
+                        delay
+                    );
+                    std::thread::sleep(delay);
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
     fn load_cache(&self, path: &Path) -> Result<String> {
         let mut file = std::fs::File::open(path)?;
         let mut content = String::new();
@@ -94,7 +202,11 @@ impl Client {
     }
 
     fn fetch<T: Cacheable + Serialize>(&self, crate_: &str, version: &str) -> Result<T> {
-        let resp = T::fetch(&self.client, crate_, version)?;
+        let client = self
+            .client
+            .as_ref()
+            .ok_or_else(|| format_err!("Can't query crates.io"))?;
+        let resp = self.request(crate_, || T::fetch(client, crate_, version))?;
         self.store_in_cache(&T::get_cache_path(&self.cache_dir, crate_, version), &resp)?;
         Ok(resp)
     }
@@ -104,8 +216,16 @@ impl Client {
         crate_: &str,
         version: &str,
     ) -> Result<T> {
+        if !self.has_api {
+            bail!("Registry doesn't provide crates.io API");
+        }
         let cached: Option<(T, bool)> = self.get_from_cache(crate_, version)?;
 
+        if self.offline {
+            return cached
+                .map(|(resp, _fresh)| resp)
+                .ok_or_else(|| format_err!("No cached crates.io data for {}", crate_));
+        }
         match cached {
             Some((resp, true)) => Ok(resp),
             Some((resp, false)) => match self.fetch(crate_, version) {
@@ -123,6 +243,23 @@ impl Client {
         ))
     }
 
+    /// Names of crates matching a crates.io search for `query`
+    pub fn search_names(&self, query: &str) -> Result<Vec<String>> {
+        let client = match &self.client {
+            Some(client) => client,
+            None => bail!("Can't search crates.io"),
+        };
+        let resp = self.request(query, || {
+            client.crates(crates_io_api::ListOptions {
+                sort: crates_io_api::Sort::Relevance,
+                per_page: 20,
+                page: 1,
+                query: Some(query.to_owned()),
+            })
+        })?;
+        Ok(resp.crates.into_iter().map(|c| c.name).collect())
+    }
+
     pub fn get_owners(&self, crate_: &str) -> Result<Vec<String>> {
         let owners = self.get::<crates_io_api::Owners>(crate_, "")?;
         Ok(owners.users.into_iter().map(|u| u.login).collect())
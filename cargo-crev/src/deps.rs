@@ -1,15 +1,26 @@
 use semver::Version;
-use std::path::PathBuf;
+use std::{
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    env, fmt,
+    hash::Hash,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use crev_data::*;
 use crev_lib::*;
 
 use crate::opts::*;
 use crate::prelude::*;
+use crate::repo::Repo;
 use crate::shared::*;
 use crate::term;
+use serde::Serialize;
 
-mod print_term;
+pub mod analyze;
+pub mod explain;
+pub mod print_term;
+pub mod risk;
 pub mod scan;
 
 #[derive(Copy, Clone, Debug)]
@@ -46,20 +57,471 @@ pub struct TrustCount {
     pub total: usize,
 }
 
+/// Time spent in each phase of scanning crates
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Durations {
+    pub digest: Duration,
+    pub crates_io: Duration,
+    /// Running the analyzers, which count the lines of code among other things
+    pub loc: Duration,
+    pub issues: Duration,
+    pub latest_trusted: Duration,
+    /// Only spent with `verify --geiger`, on crates not in the cache
+    pub geiger: Duration,
+}
+
+impl Durations {
+    pub fn get(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Digest => self.digest,
+            Phase::CratesIo => self.crates_io,
+            Phase::Loc => self.loc,
+            Phase::Issues => self.issues,
+            Phase::LatestTrusted => self.latest_trusted,
+            Phase::Geiger => self.geiger,
+            Phase::Total => {
+                self.digest
+                    + self.crates_io
+                    + self.loc
+                    + self.issues
+                    + self.latest_trusted
+                    + self.geiger
+            }
+        }
+    }
+}
+
+impl std::ops::AddAssign for Durations {
+    fn add_assign(&mut self, other: Durations) {
+        self.digest += other.digest;
+        self.crates_io += other.crates_io;
+        self.loc += other.loc;
+        self.issues += other.issues;
+        self.latest_trusted += other.latest_trusted;
+        self.geiger += other.geiger;
+    }
+}
+
+/// A phase of scanning a crate, timed in `Durations`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Digest,
+    CratesIo,
+    Loc,
+    Issues,
+    LatestTrusted,
+    Geiger,
+    /// All the phases
+    Total,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 7] = [
+        Phase::Digest,
+        Phase::CratesIo,
+        Phase::Loc,
+        Phase::Issues,
+        Phase::LatestTrusted,
+        Phase::Geiger,
+        Phase::Total,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Digest => "digest",
+            Phase::CratesIo => "crates-io",
+            Phase::Loc => "loc",
+            Phase::Issues => "issues",
+            Phase::LatestTrusted => "latest-trusted",
+            Phase::Geiger => "geiger",
+            Phase::Total => "total",
+        }
+    }
+}
+
+/// Timings of `verify --timings`
+///
+/// Only collected from the `Durations` of the scanned crates when asked for,
+/// so scanning doesn't pay for it otherwise.
+#[derive(Clone, Debug)]
+pub struct Timings {
+    /// Time it took to scan all the crates, in parallel
+    pub wall_clock: Duration,
+    /// Every (crate, phase, duration) of the scan
+    pub samples: Vec<(cargo::core::PackageId, Phase, Duration)>,
+}
+
+/// JSON report of `verify --timings-out`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct TimingsReport {
+    date: String,
+    wall_clock_secs: f64,
+    /// Seconds spent in each phase, summed over all crates
+    phases: BTreeMap<&'static str, f64>,
+    crates: Vec<CrateTimingsReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct CrateTimingsReport {
+    name: String,
+    version: String,
+    phases: BTreeMap<&'static str, f64>,
+}
+
+impl Timings {
+    pub fn from_deps(deps: &[CrateStats], wall_clock: Duration) -> Self {
+        let samples = deps
+            .iter()
+            .flat_map(|stats| {
+                Phase::ALL
+                    .iter()
+                    .map(move |&phase| (stats.info.id, phase, stats.durations.get(phase)))
+            })
+            .collect();
+        Timings {
+            wall_clock,
+            samples,
+        }
+    }
+
+    /// Time spent in `phase`, summed over all crates
+    pub fn phase_total(&self, phase: Phase) -> Duration {
+        self.samples
+            .iter()
+            .filter(|(_, sample_phase, _)| *sample_phase == phase)
+            .map(|(_, _, duration)| *duration)
+            .sum()
+    }
+
+    /// The `n` slowest crates in `phase`, slowest first
+    pub fn slowest(&self, phase: Phase, n: usize) -> Vec<(cargo::core::PackageId, Duration)> {
+        let mut crates: Vec<_> = self
+            .samples
+            .iter()
+            .filter(|(_, sample_phase, _)| *sample_phase == phase)
+            .map(|(id, _, duration)| (*id, *duration))
+            .collect();
+        crates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        crates.truncate(n);
+        crates
+    }
+
+    fn report(&self) -> TimingsReport {
+        let secs = print_term::duration_secs;
+        let mut crates: Vec<CrateTimingsReport> = vec![];
+        let mut last_id = None;
+        for (id, phase, duration) in &self.samples {
+            if last_id != Some(id) {
+                last_id = Some(id);
+                crates.push(CrateTimingsReport {
+                    name: id.name().to_string(),
+                    version: id.version().to_string(),
+                    phases: BTreeMap::new(),
+                });
+            }
+            if let Some(last) = crates.last_mut() {
+                last.phases.insert(phase.name(), secs(*duration));
+            }
+        }
+        TimingsReport {
+            date: crev_common::now().to_rfc3339(),
+            wall_clock_secs: secs(self.wall_clock),
+            phases: Phase::ALL
+                .iter()
+                .map(|&phase| (phase.name(), secs(self.phase_total(phase))))
+                .collect(),
+            crates,
+        }
+    }
+
+    /// Write the timings as JSON, to compare the performance of scans over time
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let report = serde_json::to_string_pretty(&self.report())?;
+        crev_common::store_str_to_file(path, &report)?;
+        Ok(())
+    }
+}
+
 /// Crate statistics - details
 #[derive(Clone, Debug)]
 pub struct CrateDetails {
     pub digest: Digest,
     pub latest_trusted_version: Option<Version>,
     pub trust: VerificationStatus,
+    /// Reviews by trusted ids that `trust` is based on
+    pub contributing_reviews: Vec<crev_lib::proofdb::ContributingReview>,
     pub reviews: ReviewCount,
     pub downloads: Option<DownloadCount>,
     pub owners: Option<TrustCount>,
     pub issues: TrustCount,
+    /// Highest severity of issues reported by trusted ids
+    pub issues_severity: Option<Level>,
     pub loc: Option<usize>,
     pub unclean_digest: bool,
     pub verified: bool,
     pub geiger_count: Option<u64>,
+    /// Outputs of all the analyzers, including the ones of `loc` and `geiger_count`
+    pub analyses: analyze::Analyses,
+    pub has_notes: bool,
+    /// All the notes were written about different content
+    pub stale_notes: bool,
+    pub partial_coverage: Option<crev_lib::PartialReviewCoverage>,
+    /// Last version reviewed positively, if this one isn't verified
+    pub previously_reviewed: Option<Version>,
+}
+
+/// How the workspace uses a dependency
+///
+/// Ordered from the most to the least important; a crate used in more
+/// than one way is of the most important kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DependencyKind {
+    /// Built into the workspace crates, and their dependents
+    Normal,
+    /// Built and run (or needed by something that is) at build time
+    Build,
+    /// Needed only to test the workspace crates
+    Dev,
+}
+
+impl fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            DependencyKind::Normal => "normal",
+            DependencyKind::Build => "build",
+            DependencyKind::Dev => "dev",
+        })
+    }
+}
+
+/// Which dependencies of the workspace to verify
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DepsScope {
+    /// Only the ones listed in the `Cargo.toml` of the current package
+    /// (or of every member of a virtual workspace)
+    DirectOnly,
+    /// Only the ones listed in the `Cargo.toml` of any workspace member
+    Workspace,
+    /// All of them, including the transitive ones
+    #[default]
+    All,
+}
+
+impl std::str::FromStr for DepsScope {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "direct-only" | "direct" => DepsScope::DirectOnly,
+            "workspace" => DepsScope::Workspace,
+            "all" => DepsScope::All,
+            _ => bail!("Unknown scope `{}`", s),
+        })
+    }
+}
+
+/// The packages that `scope` limits verification to, or `None` for all of them
+///
+/// `members` are the workspace members, and `current` the package of the
+/// current dir, if any; `deps` returns the dependencies of a package.
+/// Without a current package (a virtual workspace), `DirectOnly` falls back
+/// to the dependencies of all the members.
+pub fn packages_in_scope<T, I>(
+    scope: DepsScope,
+    members: &[T],
+    current: Option<T>,
+    deps: impl Fn(T) -> I,
+) -> Option<HashSet<T>>
+where
+    T: Copy + Eq + Hash,
+    I: IntoIterator<Item = T>,
+{
+    let roots = match (scope, current) {
+        (DepsScope::All, _) => return None,
+        (DepsScope::DirectOnly, Some(current)) => vec![current],
+        (DepsScope::DirectOnly, None) | (DepsScope::Workspace, _) => members.to_vec(),
+    };
+    Some(roots.into_iter().flat_map(deps).collect())
+}
+
+/// Kind of every package reachable from `roots` (the workspace members)
+///
+/// `deps` returns the dependencies of a package, with kinds of the
+/// `Cargo.toml` entries that pulled each of them in. Everything that
+/// a build dependency depends on is a build dependency too, and so on.
+/// Dev-dependencies only count for `roots`, as cargo doesn't build
+/// them for other packages.
+pub fn classify_dependencies<T, I>(roots: &[T], deps: impl Fn(T) -> I) -> HashMap<T, DependencyKind>
+where
+    T: Copy + Eq + Hash,
+    I: IntoIterator<Item = (T, Vec<cargo::core::dependency::Kind>)>,
+{
+    use cargo::core::dependency::Kind;
+
+    let mut kinds: HashMap<T, DependencyKind> = roots
+        .iter()
+        .map(|&root| (root, DependencyKind::Normal))
+        .collect();
+    let mut queue = roots.to_vec();
+    while let Some(pkg) = queue.pop() {
+        let pkg_kind = kinds[&pkg];
+        for (dep, dep_kinds) in deps(pkg) {
+            let edge_kind = dep_kinds
+                .into_iter()
+                .filter_map(|kind| match kind {
+                    Kind::Normal => Some(DependencyKind::Normal),
+                    Kind::Build => Some(DependencyKind::Build),
+                    Kind::Development if roots.contains(&pkg) => Some(DependencyKind::Dev),
+                    Kind::Development => None,
+                })
+                .min();
+            let kind = match edge_kind {
+                Some(edge_kind) => edge_kind.max(pkg_kind),
+                None => continue,
+            };
+            if kinds.get(&dep).map_or(true, |&old| kind < old) {
+                kinds.insert(dep, kind);
+                queue.push(dep);
+            }
+        }
+    }
+    kinds
+}
+
+/// Shortest path from one of `roots` to every package reachable from them
+///
+/// A path starts with the package and ends with the root it's reached
+/// from, eg. `[serde, serde_json, my-app]`. Of equally short paths, the one
+/// through the dependencies `deps` returns first wins.
+pub fn shortest_dependency_paths<T, I>(roots: &[T], deps: impl Fn(T) -> I) -> HashMap<T, Vec<T>>
+where
+    T: Copy + Eq + Hash,
+    I: IntoIterator<Item = T>,
+{
+    let mut parents: HashMap<T, Option<T>> = roots.iter().map(|&root| (root, None)).collect();
+    let mut queue: VecDeque<T> = roots.iter().cloned().collect();
+    while let Some(pkg) = queue.pop_front() {
+        for dep in deps(pkg) {
+            if let Entry::Vacant(entry) = parents.entry(dep) {
+                entry.insert(Some(pkg));
+                queue.push_back(dep);
+            }
+        }
+    }
+
+    parents
+        .keys()
+        .map(|&pkg| {
+            let mut path = vec![pkg];
+            while let Some(parent) = parents[path.last().expect("not empty")] {
+                path.push(parent);
+            }
+            (pkg, path)
+        })
+        .collect()
+}
+
+/// Shortest path to every dependency, see `shortest_dependency_paths`
+pub type DependencyPaths = HashMap<cargo::core::PackageId, Vec<cargo::core::PackageId>>;
+
+/// Paths from one of `roots` to `target`, shortest first
+///
+/// Paths of the same length are in the order `deps` returns dependencies.
+/// No path goes through a package twice. There can be exponentially many
+/// paths in a graph of shared dependencies, so the search stops after
+/// `max_paths` of them.
+pub fn all_dependency_paths<T, I>(
+    roots: &[T],
+    deps: impl Fn(T) -> I,
+    target: T,
+    max_paths: usize,
+) -> Vec<Vec<T>>
+where
+    T: Copy + Eq + Hash,
+    I: IntoIterator<Item = T>,
+{
+    let mut edges: HashMap<T, Vec<T>> = HashMap::new();
+    let mut queue = roots.to_vec();
+    while let Some(pkg) = queue.pop() {
+        if edges.contains_key(&pkg) {
+            continue;
+        }
+        let pkg_deps: Vec<T> = deps(pkg).into_iter().collect();
+        queue.extend(pkg_deps.iter().cloned());
+        edges.insert(pkg, pkg_deps);
+    }
+
+    // Only walk through packages that `target` can be reached from
+    let mut leads_to_target = HashSet::new();
+    leads_to_target.insert(target);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (&pkg, pkg_deps) in &edges {
+            if !leads_to_target.contains(&pkg)
+                && pkg_deps.iter().any(|dep| leads_to_target.contains(dep))
+            {
+                leads_to_target.insert(pkg);
+                changed = true;
+            }
+        }
+    }
+
+    fn walk<T: Copy + Eq + Hash>(
+        edges: &HashMap<T, Vec<T>>,
+        leads_to_target: &HashSet<T>,
+        target: T,
+        path: &mut Vec<T>,
+        paths: &mut Vec<Vec<T>>,
+        max_paths: usize,
+    ) {
+        let pkg = *path.last().expect("not empty");
+        if pkg == target {
+            paths.push(path.iter().rev().cloned().collect());
+            return;
+        }
+        for &dep in &edges[&pkg] {
+            if paths.len() >= max_paths {
+                return;
+            }
+            if leads_to_target.contains(&dep) && !path.contains(&dep) {
+                path.push(dep);
+                walk(edges, leads_to_target, target, path, paths, max_paths);
+                path.pop();
+            }
+        }
+    }
+
+    let mut paths = vec![];
+    for &root in roots {
+        if leads_to_target.contains(&root) && paths.len() < max_paths {
+            walk(
+                &edges,
+                &leads_to_target,
+                target,
+                &mut vec![root],
+                &mut paths,
+                max_paths,
+            );
+        }
+    }
+    paths.sort_by_key(Vec::len);
+    paths
+}
+
+/// `serde ← serde_json ← my-app`
+pub fn format_dependency_path(path: &[cargo::core::PackageId], with_versions: bool) -> String {
+    path.iter()
+        .map(|pkg_id| {
+            if with_versions {
+                format!("{} {}", pkg_id.name(), pkg_id.version())
+            } else {
+                pkg_id.name().to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ← ")
 }
 
 /// Basic crate info of a crate we're scanning
@@ -68,10 +530,13 @@ pub struct CrateInfo {
     pub id: cargo::core::PackageId, // contains the name, version
     pub root: PathBuf,
     pub has_custom_build: bool,
+    pub kind: DependencyKind,
+    /// Shortest path from a workspace member, see `shortest_dependency_paths`
+    pub path: Vec<cargo::core::PackageId>,
 }
 
 impl CrateInfo {
-    pub fn from_pkg(pkg: &cargo::core::Package) -> Self {
+    pub fn from_pkg(pkg: &cargo::core::Package, kind: DependencyKind) -> Self {
         let id = pkg.package_id();
         let root = pkg.root().to_path_buf();
         let has_custom_build = pkg.has_custom_build();
@@ -79,6 +544,8 @@ impl CrateInfo {
             id,
             root,
             has_custom_build,
+            kind,
+            path: vec![id],
         }
     }
 
@@ -118,6 +585,7 @@ impl Eq for CrateInfo {}
 pub struct CrateStats {
     pub info: CrateInfo,
     pub details: Result<Option<CrateDetails>>,
+    pub durations: Durations,
 }
 
 impl CrateStats {
@@ -133,6 +601,13 @@ impl CrateStats {
         self.info.has_custom_build
     }
 
+    /// Without any review of its version, and more than `threshold` lines of code
+    pub fn is_large_unreviewed(&self, threshold: usize) -> bool {
+        self.details().map_or(false, |details| {
+            details.reviews.version == 0 && details.loc.map_or(false, |loc| loc > threshold)
+        })
+    }
+
     pub fn details(&self) -> Option<&CrateDetails> {
         if let Ok(Some(ref details)) = self.details {
             Some(details)
@@ -163,44 +638,392 @@ pub fn latest_trusted_version_string(
     }
 }
 
+/// Counts of crates shown in the summary footer of `verify`
+#[derive(Clone, Debug, Default)]
+pub struct Totals {
+    pub verified: usize,
+    pub unverified: usize,
+    /// Crates with negative trusted reviews
+    pub flagged: usize,
+    /// Crates with issues reported by trusted ids
+    pub with_issues: usize,
+    /// Crates without any review of their version
+    pub unreviewed: usize,
+    /// Crates without any known owner on crates.io
+    pub without_known_owners: usize,
+    /// Unverified crates with a build script
+    pub unverified_build_scripts: usize,
+    /// Crates with a locally modified source
+    pub dirty: usize,
+    /// Crates without details (skipped, or failed)
+    pub skipped: usize,
+    pub verified_loc: usize,
+    pub unverified_loc: usize,
+    /// Sum of risk scores of all crates
+    pub risk: f64,
+    pub unverified_risk: f64,
+}
+
+impl Totals {
+    pub fn from_deps(deps: &[CrateStats], risk_model: &risk::RiskModel) -> Self {
+        let mut totals = Totals::default();
+        for stats in deps {
+            let details = match stats.details() {
+                Some(details) => details,
+                None => {
+                    totals.skipped += 1;
+                    continue;
+                }
+            };
+            let loc = details.loc.unwrap_or(0);
+            let risk = risk_model.score(stats).unwrap_or(0.0);
+            totals.risk += risk;
+            if details.verified {
+                totals.verified += 1;
+                totals.verified_loc += loc;
+            } else {
+                totals.unverified += 1;
+                totals.unverified_loc += loc;
+                totals.unverified_risk += risk;
+                if stats.has_custom_build() {
+                    totals.unverified_build_scripts += 1;
+                }
+            }
+            if details.unclean_digest {
+                totals.dirty += 1;
+            }
+            if details.trust == VerificationStatus::Negative {
+                totals.flagged += 1;
+            }
+            if details.issues.trusted > 0 {
+                totals.with_issues += 1;
+            }
+            if details.reviews.version == 0 {
+                totals.unreviewed += 1;
+            }
+            if details.owners.map_or(false, |owners| owners.trusted == 0) {
+                totals.without_known_owners += 1;
+            }
+        }
+        totals
+    }
+}
+
+/// Verified crates and lines of code of a group of dependencies
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CoverageBucket {
+    pub crates: usize,
+    pub verified_crates: usize,
+    /// Lines of code of crates with a known line count
+    pub loc: usize,
+    pub verified_loc: usize,
+    /// Crates whose line count is unknown, so not counted in `loc`
+    pub unknown_loc_crates: usize,
+}
+
+impl CoverageBucket {
+    fn add(&mut self, stats: &CrateStats) {
+        self.crates += 1;
+        // Crates without details (skipped, or failed) count as unverified
+        let verified = stats.details().map_or(false, |details| details.verified);
+        if verified {
+            self.verified_crates += 1;
+        }
+        match stats.details().and_then(|details| details.loc) {
+            Some(loc) => {
+                self.loc += loc;
+                if verified {
+                    self.verified_loc += loc;
+                }
+            }
+            None => self.unknown_loc_crates += 1,
+        }
+    }
+
+    fn sum(self, other: CoverageBucket) -> CoverageBucket {
+        CoverageBucket {
+            crates: self.crates + other.crates,
+            verified_crates: self.verified_crates + other.verified_crates,
+            loc: self.loc + other.loc,
+            verified_loc: self.verified_loc + other.verified_loc,
+            unknown_loc_crates: self.unknown_loc_crates + other.unknown_loc_crates,
+        }
+    }
+}
+
+/// Coverage of `verify --coverage`, by direct and transitive dependencies
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Coverage {
+    pub direct: CoverageBucket,
+    pub transitive: CoverageBucket,
+}
+
+impl Coverage {
+    pub fn from_deps(deps: &[CrateStats]) -> Self {
+        let mut coverage = Coverage::default();
+        for stats in deps {
+            // The path is the crate, and whatever pulls it in up to a workspace member
+            if stats.info.path.len() <= 2 {
+                coverage.direct.add(stats);
+            } else {
+                coverage.transitive.add(stats);
+            }
+        }
+        coverage
+    }
+
+    pub fn total(&self) -> CoverageBucket {
+        self.direct.sum(self.transitive)
+    }
+}
+
+/// Output of `verify --count-only`
+#[derive(Debug, Serialize)]
+struct Counts {
+    total: usize,
+    verified: usize,
+    unreviewed: usize,
+    distrusted: usize,
+    with_issues: usize,
+}
+
+impl Counts {
+    fn from_deps(deps: &[CrateStats]) -> Self {
+        let totals = Totals::from_deps(deps, &risk::RiskModel::default());
+        Counts {
+            total: deps.len(),
+            verified: totals.verified,
+            unreviewed: totals.unreviewed,
+            distrusted: totals.flagged,
+            with_issues: totals.with_issues,
+        }
+    }
+
+    fn print(&self, format: OutputFormat) -> Result<()> {
+        match format {
+            OutputFormat::Json => println!("{}", serde_json::to_string(self)?),
+            OutputFormat::Text => {
+                println!("total={}", self.total);
+                println!("verified={}", self.verified);
+                println!("unreviewed={}", self.unreviewed);
+                println!("distrusted={}", self.distrusted);
+                println!("with_issues={}", self.with_issues);
+            }
+            OutputFormat::Markdown => {
+                println!(
+                    "{}",
+                    markdown_header(&[
+                        "total",
+                        "✅ verified",
+                        "⚠️ unreviewed",
+                        "❌ distrusted",
+                        "with issues"
+                    ])
+                );
+                println!(
+                    "{}",
+                    markdown_row(
+                        &[
+                            self.total,
+                            self.verified,
+                            self.unreviewed,
+                            self.distrusted,
+                            self.with_issues
+                        ]
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                    )
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare crates by a column of the `verify` output
+///
+/// Crates without details (skipped or failed) go before all others.
+fn compare_by_column(column: SortColumn, a: &CrateStats, b: &CrateStats) -> std::cmp::Ordering {
+    fn trust_rank(status: VerificationStatus) -> u8 {
+        match status {
+            VerificationStatus::Negative => 0,
+            VerificationStatus::Insufficient => 1,
+            VerificationStatus::Verified => 2,
+        }
+    }
+
+    let (da, db) = (a.details(), b.details());
+    match column {
+        SortColumn::Name => a.info.id.name().cmp(&b.info.id.name()),
+        SortColumn::Version => a.info.id.version().cmp(b.info.id.version()),
+        SortColumn::Verified => da
+            .map(|d| trust_rank(d.trust))
+            .cmp(&db.map(|d| trust_rank(d.trust))),
+        SortColumn::Reviews => da
+            .map(|d| (d.reviews.version, d.reviews.total))
+            .cmp(&db.map(|d| (d.reviews.version, d.reviews.total))),
+        SortColumn::Loc => da.and_then(|d| d.loc).cmp(&db.and_then(|d| d.loc)),
+        SortColumn::Issues => da
+            .map(|d| (d.issues.trusted, d.issues.total))
+            .cmp(&db.map(|d| (d.issues.trusted, d.issues.total))),
+        SortColumn::Downloads => da
+            .and_then(|d| d.downloads.map(|d| (d.version, d.total)))
+            .cmp(&db.and_then(|d| d.downloads.map(|d| (d.version, d.total)))),
+    }
+    .then_with(|| a.info.cmp(&b.info))
+}
+
 pub fn verify_deps(args: Verify) -> Result<CommandExitStatus> {
+    if let Some(ref root) = args.all_workspaces {
+        return verify_all_workspaces(&args, root);
+    }
     let mut term = term::Term::new();
-    if term.stderr_is_tty && term.stdout_is_tty {
-        self::print_term::print_header(&mut term, args.verbose);
+    let machine_readable = args.machine_readable && !args.count_only;
+    let markdown = args.output == OutputFormat::Markdown && !args.count_only && !machine_readable;
+    let print_header = term.stderr_is_tty
+        && term.stdout_is_tty
+        && !args.count_only
+        && !markdown
+        && !machine_readable;
+    // Rows can be printed as they come, unless they need to be sorted first
+    let print_immediately = args.sort_by.is_none() && !args.count_only;
+    if machine_readable {
+        print_term::print_machine_readable_header(&[]);
+    } else if markdown {
+        print_term::print_markdown_header(args.verbose);
+    } else if print_header && print_immediately {
+        print_term::print_header(&mut term, args.verbose, Default::default());
     }
 
+    let risk_model = match args.risk_model {
+        Some(ref path) => risk::RiskModel::read_from_file(path)?,
+        None => risk::RiskModel::default(),
+    };
+
     let scanner = scan::Scanner::new(&args)?;
+    let mut progress = print_term::ProgressBar::new(
+        scanner.total_crate_count(),
+        term.stderr_is_tty && !args.quiet,
+    );
     let events = scanner.run();
 
-    let deps: Vec<_> = events
+    let mut deps: Vec<_> = events
         .into_iter()
         .map(|stats| {
-            print_term::print_dep(&stats, &mut term, args.verbose)?;
+            progress.clear();
+            if print_immediately && machine_readable {
+                print_term::print_dep_machine_readable(&stats, &[]);
+            } else if print_immediately && markdown {
+                print_term::print_dep_markdown(&stats, args.verbose);
+            } else if print_immediately {
+                print_term::print_dep(
+                    &stats,
+                    &mut term,
+                    args.verbose,
+                    Default::default(),
+                    args.warn_build_scripts(),
+                    args.loc_warn_threshold(),
+                )?;
+                if args.show_path {
+                    print_term::print_dep_path(&stats);
+                }
+            }
+            progress.update(&stats);
             Ok(stats)
         })
         .collect::<Result<_>>()?;
-
-    let mut nb_unclean_digests = 0;
-    let mut nb_unverified = 0;
-    for dep in &deps {
-        if dep.is_digest_unclean() {
-            let details = dep.details().unwrap();
-            if details.unclean_digest {
-                nb_unclean_digests += 1;
-            }
-            if !details.verified {
-                nb_unverified += 1;
+    progress.clear();
+    if args.timings || args.timings_out.is_some() {
+        let timings = Timings::from_deps(&deps, progress.elapsed());
+        if args.timings {
+            print_term::print_timings(&timings, 10);
+        }
+        if let Some(ref path) = args.timings_out {
+            timings.write_json(path)?;
+        }
+    }
+    if args.count_only {
+        Counts::from_deps(&deps).print(args.output)?;
+        let (_, nb_unverified) = count_unclean(&deps);
+        let nb_denied = count_denied_issues(&deps, args.deny_severity);
+        let nb_large_unreviewed = count_large_unreviewed(&deps, args.fail_loc_threshold);
+        return Ok(
+            if args.fail_build_scripts && count_unverified_build_scripts(&deps) > 0 {
+                CommandExitStatus::UnverifiedBuildScripts
+            } else if nb_unverified == 0 && nb_denied == 0 && nb_large_unreviewed == 0 {
+                CommandExitStatus::Successs
+            } else {
+                CommandExitStatus::VerificationFailed
+            },
+        );
+    }
+    if let Some(column) = args.sort_by {
+        sort_deps(&mut deps, column, args.sort_desc);
+        let widths = print_term::ColumnWidths::fit(&deps);
+        if print_header {
+            print_term::print_header(&mut term, args.verbose, widths);
+        }
+        for stats in &deps {
+            if machine_readable {
+                print_term::print_dep_machine_readable(stats, &[]);
+            } else if markdown {
+                print_term::print_dep_markdown(stats, args.verbose);
+            } else {
+                print_term::print_dep(
+                    stats,
+                    &mut term,
+                    args.verbose,
+                    widths,
+                    args.warn_build_scripts(),
+                    args.loc_warn_threshold(),
+                )?;
+                if args.show_path {
+                    print_term::print_dep_path(stats);
+                }
             }
         }
     }
+    if let Some(n) = args.top {
+        print_term::print_top_risks(&deps, &risk_model, n);
+    }
+    if !args.quiet {
+        print_term::print_totals(
+            &Totals::from_deps(&deps, &risk_model),
+            args.warn_build_scripts(),
+        );
+        print_large_unreviewed_count(&deps, args.loc_warn_threshold());
+        progress.print_summary();
+    }
+
+    if let Some(ref git_ref) = args.changed_since {
+        eprintln!(
+            "Skipped {} dependencies unchanged since {}",
+            scanner.unchanged_crate_count(),
+            git_ref
+        );
+    }
+
+    if args.coverage {
+        print_term::print_coverage(&Coverage::from_deps(&deps));
+        let coverage = scanner.coverage_stats(&deps);
+        println!(
+            "Trusted reviews cover {} of {} lines of code ({:.1}%)",
+            coverage.trusted_loc,
+            coverage.trusted_loc + coverage.untrusted_loc,
+            coverage.coverage_fraction * 100.0
+        );
+    }
+
+    let (nb_unclean_digests, nb_unverified) = count_unclean(&deps);
 
     if nb_unclean_digests > 0 {
         println!(
-            "{} unclean package{} detected. Use `cargo crev clean <crate>` to wipe the local source.",
+            "{} unclean package{} detected. Use `cargo crev clean <crate>` (or `--all-dirty`) to wipe and re-download the local source.",
             nb_unclean_digests,
             if nb_unclean_digests > 1 { "s" } else { "" },
         );
-        for dep in deps {
+        for dep in &deps {
             if dep.is_digest_unclean() {
                 term.eprint(
                     format_args!(
@@ -214,9 +1037,238 @@ pub fn verify_deps(args: Verify) -> Result<CommandExitStatus> {
         }
     }
 
-    Ok(if nb_unverified == 0 {
-        CommandExitStatus::Successs
-    } else {
-        CommandExitStatus::VerificationFailed
-    })
+    let nb_denied = count_denied_issues(&deps, args.deny_severity);
+    if nb_denied > 0 {
+        eprintln!(
+            "{} crate{} with open issues of denied severity",
+            nb_denied,
+            if nb_denied > 1 { "s" } else { "" },
+        );
+    }
+
+    let nb_build_scripts = count_unverified_build_scripts(&deps);
+    if args.fail_build_scripts && nb_build_scripts > 0 {
+        eprintln!(
+            "{} unverified crate{} with build scripts",
+            nb_build_scripts,
+            if nb_build_scripts > 1 { "s" } else { "" },
+        );
+        return Ok(CommandExitStatus::UnverifiedBuildScripts);
+    }
+
+    let nb_large_unreviewed = count_large_unreviewed(&deps, args.fail_loc_threshold);
+    Ok(
+        if nb_unverified == 0 && nb_denied == 0 && nb_large_unreviewed == 0 {
+            CommandExitStatus::Successs
+        } else {
+            CommandExitStatus::VerificationFailed
+        },
+    )
+}
+
+fn sort_deps(deps: &mut [CrateStats], column: SortColumn, desc: bool) {
+    deps.sort_by(|a, b| {
+        let ordering = compare_by_column(column, a, b);
+        if desc {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+/// Number of crates with unclean digests, and of those, the unverified ones
+fn count_unclean(deps: &[CrateStats]) -> (usize, usize) {
+    let mut nb_unclean_digests = 0;
+    let mut nb_unverified = 0;
+    for dep in deps {
+        if dep.is_digest_unclean() {
+            let details = dep.details().unwrap();
+            if details.unclean_digest {
+                nb_unclean_digests += 1;
+            }
+            if !details.verified {
+                nb_unverified += 1;
+            }
+        }
+    }
+    (nb_unclean_digests, nb_unverified)
+}
+
+/// Number of unverified crates with a build script
+fn count_unverified_build_scripts(deps: &[CrateStats]) -> usize {
+    deps.iter()
+        .filter(|dep| dep.has_custom_build())
+        .filter_map(|dep| dep.details())
+        .filter(|details| !details.verified)
+        .count()
+}
+
+fn print_large_unreviewed_count(deps: &[CrateStats], threshold: Option<usize>) {
+    if let Some(threshold) = threshold {
+        let count = count_large_unreviewed(deps, Some(threshold));
+        eprintln!(
+            "{} unreviewed crate{} with more than {} lines of code",
+            count,
+            if count == 1 { "" } else { "s" },
+            threshold
+        );
+    }
+}
+
+/// Number of unreviewed crates with more lines of code than `threshold`
+fn count_large_unreviewed(deps: &[CrateStats], threshold: Option<usize>) -> usize {
+    match threshold {
+        Some(threshold) => deps
+            .iter()
+            .filter(|dep| dep.is_large_unreviewed(threshold))
+            .count(),
+        None => 0,
+    }
+}
+
+/// Number of crates with open issues reported by trusted ids of at least `severity`
+fn count_denied_issues(deps: &[CrateStats], severity: Option<Level>) -> usize {
+    let severity = match severity {
+        Some(severity) => severity,
+        None => return 0,
+    };
+    deps.iter()
+        .filter_map(|dep| dep.details())
+        .filter(|details| details.issues_severity.map_or(false, |s| s >= severity))
+        .count()
+}
+
+/// Root directories of all workspaces with a `Cargo.toml` under `root`
+///
+/// `target` and hidden directories are not searched. Also returns the
+/// number of manifests that couldn't be opened.
+fn find_workspace_roots(root: &Path, offline: bool) -> Result<(BTreeSet<PathBuf>, usize)> {
+    let mut roots = BTreeSet::new();
+    let mut failed = 0;
+    let walker = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !(name == "target" || name.starts_with('.'))
+        });
+    for entry in walker {
+        let entry = entry?;
+        if entry.file_name() != "Cargo.toml" {
+            continue;
+        }
+        env::set_current_dir(entry.path().parent().expect("Not a root dir"))?;
+        match Repo::auto_open_cwd_offline(offline).and_then(|repo| repo.workspace_root()) {
+            Ok(workspace_root) => {
+                roots.insert(workspace_root);
+            }
+            Err(e) => {
+                eprintln!("Can't open {}: {}", entry.path().display(), e);
+                failed += 1;
+            }
+        }
+    }
+    Ok((roots, failed))
+}
+
+/// Verify all workspaces under `root`, printing one table prefixed with the workspace
+///
+/// Fails if verification of any of the workspaces does.
+fn verify_all_workspaces(args: &Verify, root: &Path) -> Result<CommandExitStatus> {
+    let cwd = env::current_dir()?;
+    let root = root.canonicalize()?;
+    let (workspaces, mut nb_failed) = find_workspace_roots(&root, args.offline)?;
+    let names: Vec<String> = workspaces
+        .iter()
+        .map(|workspace| match workspace.strip_prefix(&root) {
+            Ok(rel_path) if rel_path == Path::new("") => ".".to_owned(),
+            Ok(rel_path) => rel_path.display().to_string(),
+            Err(_) => workspace.display().to_string(),
+        })
+        .collect();
+    let name_width = names
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max("workspace".len());
+
+    let risk_model = match args.risk_model {
+        Some(ref path) => risk::RiskModel::read_from_file(path)?,
+        None => risk::RiskModel::default(),
+    };
+    let mut term = term::Term::new();
+    let machine_readable = args.machine_readable && !args.count_only;
+    if machine_readable {
+        print_term::print_machine_readable_header(&["workspace"]);
+    } else if term.stderr_is_tty && term.stdout_is_tty && !args.count_only {
+        eprint!("{:<1$} ", "workspace", name_width);
+        print_term::print_header(&mut term, args.verbose, Default::default());
+    }
+
+    let mut all_deps = vec![];
+    for (workspace, name) in workspaces.iter().zip(&names) {
+        env::set_current_dir(workspace)?;
+        let scanner = match scan::Scanner::new(args) {
+            Ok(scanner) => scanner,
+            Err(e) => {
+                eprintln!("Can't verify {}: {}", name, e);
+                nb_failed += 1;
+                continue;
+            }
+        };
+        let mut deps: Vec<_> = scanner.run().into_iter().collect();
+        if let Some(column) = args.sort_by {
+            sort_deps(&mut deps, column, args.sort_desc);
+        }
+        for stats in deps.iter().filter(|_| !args.count_only) {
+            if machine_readable {
+                print_term::print_dep_machine_readable(stats, &[name]);
+                continue;
+            }
+            print!("{:<1$} ", name, name_width);
+            print_term::print_dep(
+                stats,
+                &mut term,
+                args.verbose,
+                Default::default(),
+                args.warn_build_scripts(),
+                args.loc_warn_threshold(),
+            )?;
+        }
+        if count_unclean(&deps).1 > 0
+            || count_denied_issues(&deps, args.deny_severity) > 0
+            || count_large_unreviewed(&deps, args.fail_loc_threshold) > 0
+        {
+            nb_failed += 1;
+        }
+        all_deps.extend(deps);
+    }
+    env::set_current_dir(cwd)?;
+
+    if args.count_only {
+        Counts::from_deps(&all_deps).print(args.output)?;
+    } else if let Some(n) = args.top {
+        print_term::print_top_risks(&all_deps, &risk_model, n);
+    }
+    if !args.quiet && !args.count_only {
+        eprintln!("Verified {} workspaces", workspaces.len());
+        print_term::print_totals(
+            &Totals::from_deps(&all_deps, &risk_model),
+            args.warn_build_scripts(),
+        );
+        print_large_unreviewed_count(&all_deps, args.loc_warn_threshold());
+    }
+
+    Ok(
+        if args.fail_build_scripts && count_unverified_build_scripts(&all_deps) > 0 {
+            CommandExitStatus::UnverifiedBuildScripts
+        } else if nb_failed == 0 {
+            CommandExitStatus::Successs
+        } else {
+            CommandExitStatus::VerificationFailed
+        },
+    )
 }
@@ -0,0 +1,235 @@
+//! Per-crate analyses of `verify`, built in or given as shell commands
+//!
+//! Each analyzer looks at the source of a crate and reports a short
+//! summary, shown along the rest of the crate's details.
+
+use crate::opts::AnalyzerCommand;
+use crate::prelude::*;
+use crate::shared::get_geiger_count;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+};
+
+/// Name of the built-in analyzer counting lines of Rust code
+pub const LOC: &str = "loc";
+/// Name of the built-in analyzer counting `unsafe` code
+pub const GEIGER: &str = "geiger";
+
+/// What an analyzer found in a crate
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnalyzerOutput {
+    /// Short, single-line text shown in the output of `verify`
+    pub summary: String,
+    /// The value counted by the analyzer, if the summary is a number
+    pub count: Option<u64>,
+}
+
+impl AnalyzerOutput {
+    pub fn from_count(count: u64) -> Self {
+        AnalyzerOutput {
+            summary: count.to_string(),
+            count: Some(count),
+        }
+    }
+
+    /// Output of the first line of `text`, which is a count if it parses as a number
+    pub fn from_text(text: &str) -> Self {
+        let summary = text.lines().next().unwrap_or("").trim().replace('\t', " ");
+        AnalyzerOutput {
+            count: summary.parse().ok(),
+            summary,
+        }
+    }
+}
+
+/// A check run on the source of every scanned crate
+pub trait Analyzer: Send + Sync {
+    /// Name shown along the output, unique among the analyzers of a scan
+    fn name(&self) -> &str;
+
+    fn analyze(&self, crate_root: &Path) -> Result<AnalyzerOutput>;
+}
+
+/// Lines of Rust code, without comments and blank lines
+pub struct LocAnalyzer;
+
+impl Analyzer for LocAnalyzer {
+    fn name(&self) -> &str {
+        LOC
+    }
+
+    fn analyze(&self, crate_root: &Path) -> Result<AnalyzerOutput> {
+        let loc = crate::tokei::get_rust_line_count(crate_root)?;
+        Ok(AnalyzerOutput::from_count(loc as u64))
+    }
+}
+
+/// Number of `unsafe` functions, expressions, impls, traits and methods
+pub struct GeigerAnalyzer;
+
+impl Analyzer for GeigerAnalyzer {
+    fn name(&self) -> &str {
+        GEIGER
+    }
+
+    fn analyze(&self, crate_root: &Path) -> Result<AnalyzerOutput> {
+        Ok(AnalyzerOutput::from_count(get_geiger_count(crate_root)?))
+    }
+}
+
+/// Shell command run in the crate root, its output being the first line it prints
+///
+/// The command fails the analysis by exiting with a non-zero status.
+pub struct CommandAnalyzer {
+    pub command: AnalyzerCommand,
+}
+
+impl Analyzer for CommandAnalyzer {
+    fn name(&self) -> &str {
+        &self.command.name
+    }
+
+    fn analyze(&self, crate_root: &Path) -> Result<AnalyzerOutput> {
+        let mut command = if cfg!(windows) {
+            let mut command = process::Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = process::Command::new("sh");
+            command.arg("-c");
+            command
+        };
+        let output = command
+            .arg(&self.command.command)
+            .current_dir(crate_root)
+            .stdin(process::Stdio::null())
+            .output()?;
+        if !output.status.success() {
+            bail!(
+                "`{}` failed with {}: {}",
+                self.command.command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(AnalyzerOutput::from_text(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+}
+
+/// Outputs of analyzers saved on disk, by digest of the analyzed crate
+///
+/// The digest identifies the source, so a saved output is never stale.
+pub struct DigestCache {
+    dir: PathBuf,
+}
+
+impl DigestCache {
+    pub fn new(dir: PathBuf) -> Self {
+        DigestCache { dir }
+    }
+
+    pub fn for_local(local: &crev_lib::Local) -> Self {
+        Self::new(local.get_root_cache_dir().join("analyses"))
+    }
+
+    fn path(&self, analyzer: &dyn Analyzer, digest: &crev_data::Digest) -> PathBuf {
+        self.dir.join(analyzer.name()).join(digest.to_string())
+    }
+
+    /// Output of `analyzer` saved for `digest`, or computed and saved now
+    pub fn analyze(
+        &self,
+        analyzer: &dyn Analyzer,
+        digest: &crev_data::Digest,
+        crate_root: &Path,
+    ) -> Result<AnalyzerOutput> {
+        let path = self.path(analyzer, digest);
+        if let Ok(summary) = std::fs::read_to_string(&path) {
+            return Ok(AnalyzerOutput::from_text(&summary));
+        }
+        let output = analyzer.analyze(crate_root)?;
+        // Not saving it only means computing it again next time
+        let _ = crev_common::store_str_to_file(&path, &output.summary);
+        Ok(output)
+    }
+}
+
+/// The built-in analyzers run on every crate, followed by the ones running `commands`
+///
+/// The geiger count is too slow to always run, see `verify --geiger`.
+pub fn analyzers(commands: &[AnalyzerCommand]) -> Result<Vec<Arc<dyn Analyzer>>> {
+    let mut analyzers: Vec<Arc<dyn Analyzer>> = vec![Arc::new(LocAnalyzer)];
+    for command in commands {
+        analyzers.push(Arc::new(CommandAnalyzer {
+            command: command.clone(),
+        }));
+    }
+    let mut names: HashSet<_> = vec![GEIGER].into_iter().collect();
+    for analyzer in &analyzers {
+        if !names.insert(analyzer.name()) {
+            bail!("More than one analyzer is named `{}`", analyzer.name());
+        }
+    }
+    Ok(analyzers)
+}
+
+/// Outputs of the analyzers run on a crate, in the order they were run
+///
+/// Analyzers that failed are left out, with a warning for the ones
+/// given by the user.
+#[derive(Clone, Debug, Default)]
+pub struct Analyses(Vec<(String, AnalyzerOutput)>);
+
+impl Analyses {
+    pub fn run(analyzers: &[Arc<dyn Analyzer>], crate_root: &Path) -> Self {
+        Analyses(
+            analyzers
+                .iter()
+                .filter_map(|analyzer| match analyzer.analyze(crate_root) {
+                    Ok(output) => Some((analyzer.name().to_owned(), output)),
+                    // the built-in ones fail on crates they can't handle, which
+                    // shows as missing counts; a failing command is worth a word
+                    Err(e) => {
+                        if analyzer.name() != LOC && analyzer.name() != GEIGER {
+                            eprintln!(
+                                "Warning: analyzer `{}` failed on {}: {}",
+                                analyzer.name(),
+                                crate_root.display(),
+                                e
+                            );
+                        }
+                        None
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    pub fn push(&mut self, name: &str, output: AnalyzerOutput) {
+        self.0.push((name.to_owned(), output));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AnalyzerOutput> {
+        self.0
+            .iter()
+            .find(|(analyzer, _)| analyzer == name)
+            .map(|(_, output)| output)
+    }
+
+    pub fn count(&self, name: &str) -> Option<u64> {
+        self.get(name).and_then(|output| output.count)
+    }
+
+    /// Outputs of the analyzers given by the user, which have no column of their own
+    pub fn custom(&self) -> impl Iterator<Item = (&str, &AnalyzerOutput)> {
+        self.0
+            .iter()
+            .filter(|(analyzer, _)| analyzer != LOC && analyzer != GEIGER)
+            .map(|(analyzer, output)| (analyzer.as_str(), output))
+    }
+}
@@ -0,0 +1,135 @@
+// `cargo crev verify explain`: why a dependency passed or failed verification
+
+use super::*;
+use crate::repo::Repo;
+use crev_lib::proofdb::{ReviewVerdict, TrustSet};
+
+pub fn explain_verification(args: &Verify, explain: &VerifyExplain) -> Result<CommandExitStatus> {
+    let local = crev_lib::Local::auto_create_or_open()?;
+    let db = local.load_db()?;
+    let trust_set = if let Some(for_id) = local.get_for_id_from_str_opt(args.for_id.as_deref())? {
+        crate::policy::calculate_trust_set(&db, &for_id, &args.trust_params.clone().into())?
+    } else {
+        TrustSet::default()
+    };
+    let requirements = crev_lib::VerificationRequirements::from(args.requirements.clone());
+
+    let repo = Repo::auto_open_cwd_offline(args.offline)?;
+    let pkg = repo
+        .find_dependency(&explain.name, explain.version.as_ref())?
+        .ok_or_else(|| format_err!("{} is not a dependency", explain.name))?;
+    let (_, kinds, paths) = repo.get_deps_package_set(DepsScope::All)?;
+    let kind = kinds
+        .get(&pkg.package_id())
+        .cloned()
+        .unwrap_or(DependencyKind::Normal);
+    let mut info = CrateInfo::from_pkg(&pkg, kind);
+    if let Some(path) = paths.get(&pkg.package_id()) {
+        info.path = path.clone();
+    }
+    if args.offline {
+        if !info.root.exists() {
+            bail!("Crate source not downloaded; run once without `--offline`");
+        }
+    } else {
+        info.download_if_needed()?;
+    }
+    let name = info.id.name();
+    let version = info.id.version();
+    let source = repo.registry_source();
+    let digest = crev_lib::get_dir_digest(&info.root, &cargo_min_ignore_list().into())?;
+    let status = db.verify_package_digest(&digest, &trust_set, &requirements);
+
+    println!(
+        "{} {} ({} dependency): {}",
+        name, version, info.kind, status
+    );
+    if info.path.len() > 1 {
+        println!(
+            "Pulled in by: {}",
+            format_dependency_path(&info.path, false)
+        );
+    }
+    println!("Digest: {}", digest);
+    println!(
+        "Requirements: {} review(s) by ids trusted at least `{}`, with understanding `{}` and thoroughness `{}`",
+        requirements.redundancy,
+        requirements.trust_level,
+        requirements.understanding,
+        requirements.thoroughness
+    );
+
+    let explanations = db.explain_package_digest(&digest, &trust_set, &requirements);
+    let counted = explanations
+        .iter()
+        .filter(|e| e.verdict == ReviewVerdict::Counted)
+        .count();
+    println!("Reviews of this digest:");
+    if explanations.is_empty() {
+        println!("  none");
+    }
+    for explanation in &explanations {
+        let from = &explanation.review.from;
+        let trust = match (explanation.distance, explanation.effective_trust) {
+            (Some(distance), Some(level)) => format!("distance {}, trust {}", distance, level),
+            _ => "untrusted".into(),
+        };
+        println!(
+            "  {} {} ({}; rating {}, understanding {}, thoroughness {}): {}",
+            from.id,
+            from.url.url,
+            trust,
+            explanation.review.review.rating,
+            explanation.review.review.understanding,
+            explanation.review.review.thoroughness,
+            explanation.verdict
+        );
+    }
+    println!(
+        "Counted reviews: {} of {} required",
+        counted, requirements.redundancy
+    );
+
+    if !is_digest_clean(&db, source, &name, version, &digest) {
+        println!(
+            "Local source differs from every reviewed copy of this version; use `cargo crev clean {}` to wipe it",
+            name
+        );
+    }
+
+    let issues = db.get_open_issues_for_version(
+        source,
+        &name,
+        version,
+        &trust_set,
+        requirements.trust_level.into(),
+    );
+    if !issues.is_empty() {
+        println!("Open issues reported by trusted ids:");
+        let mut issues: Vec<_> = issues.into_iter().collect();
+        issues.sort_by(|a, b| a.0.cmp(&b.0));
+        for (issue_id, details) in issues {
+            let mut reported_by: Vec<_> = details
+                .issues
+                .iter()
+                .chain(details.advisories.iter())
+                .filter_map(|id| db.get_pkg_review_by_pkg_review_id(id))
+                .map(|review| review.from.id.to_string())
+                .collect();
+            reported_by.sort();
+            reported_by.dedup();
+            println!(
+                "  {} (severity {}) reported by {}",
+                issue_id,
+                details.severity,
+                reported_by.join(", ")
+            );
+        }
+    }
+
+    Ok(if status.is_verified() {
+        CommandExitStatus::Successs
+    } else {
+        CommandExitStatus::VerificationFailed
+    })
+}
@@ -1,7 +1,7 @@
 // Functions related to printing dependencies in the standard
 // terminal (not in the context of a real terminal application)
 
-use super::*;
+use super::{risk::RiskModel, *};
 use crate::term::{self, *};
 
 fn pad_left_manually(s: String, width: usize) -> String {
@@ -15,25 +15,75 @@ fn pad_left_manually(s: String, width: usize) -> String {
     }
 }
 
-pub fn print_header(_term: &mut Term, verbose: bool) {
+/// Widths of the columns with crate names and versions
+#[derive(Copy, Clone, Debug)]
+pub struct ColumnWidths {
+    pub name: usize,
+    pub version: usize,
+}
+
+impl Default for ColumnWidths {
+    fn default() -> Self {
+        ColumnWidths {
+            name: 20,
+            version: 15,
+        }
+    }
+}
+
+impl ColumnWidths {
+    /// Widths wide enough for all given crates
+    pub fn fit(deps: &[CrateStats]) -> Self {
+        let default = Self::default();
+        ColumnWidths {
+            name: deps
+                .iter()
+                .map(|stats| stats.info.id.name().len())
+                .fold(default.name, std::cmp::max),
+            version: deps
+                .iter()
+                .map(|stats| stats.info.id.version().to_string().len())
+                .fold(default.version, std::cmp::max),
+        }
+    }
+}
+
+pub fn print_header(_term: &mut Term, verbose: bool, widths: ColumnWidths) {
     if verbose {
         eprint!("{:43} ", "digest");
     }
     eprint!(
-        "{:6} {:8} {:^15} {:4} {:6} {:6} {:6} {:4}",
-        "status", "reviews", "downloads", "own.", "issues", "lines", "geiger", "flgs"
+        "{:6} {:8} {:^15} {:4} {:6} {:6} {:6} {:4} {:6}",
+        "status", "reviews", "downloads", "own.", "issues", "lines", "geiger", "flgs", "kind"
+    );
+    eprintln!(
+        " {:<name_width$} {:<version_width$} {:<15}",
+        "crate",
+        "version",
+        "latest_t",
+        name_width = widths.name,
+        version_width = widths.version
     );
-    eprintln!(" {:<20} {:<15} {:<15}", "crate", "version", "latest_t");
 }
 
-pub fn print_details(cdep: &CrateDetails, term: &mut Term, verbose: bool) -> Result<()> {
+pub fn print_details(
+    cdep: &CrateDetails,
+    term: &mut Term,
+    verbose: bool,
+    large_unreviewed: bool,
+) -> Result<()> {
     if verbose {
         print!("{:43} ", cdep.digest);
     }
-    term.print(
-        format_args!("{:6}", cdep.trust),
-        term::verification_status_color(&cdep.trust),
-    )?;
+    if cdep.unclean_digest {
+        // The reviews can't apply, as the local source was modified
+        term.print(format_args!("{:6}", "DIRTY"), ::term::color::YELLOW)?;
+    } else {
+        term.print(
+            format_args!("{:6}", cdep.trust),
+            term::verification_status_color(&cdep.trust),
+        )?;
+    }
     print!(" {:2} {:2}", cdep.reviews.version, cdep.reviews.total);
     if let Some(downloads) = &cdep.downloads {
         term.print(
@@ -53,7 +103,7 @@ pub fn print_details(cdep: &CrateDetails, term: &mut Term, verbose: bool) -> Res
             },
         )?;
     } else {
-        println!(" {:>8} {:>9}", "?", "?");
+        print!(" {:>8} {:>9}", "-", "-");
     }
     if let Some(owners) = &cdep.owners {
         term.print(
@@ -62,16 +112,12 @@ pub fn print_details(cdep: &CrateDetails, term: &mut Term, verbose: bool) -> Res
         )?;
         term.print(format_args!(" {}", owners.total), None)?;
     } else {
-        println!(" ???");
+        print!(" - -");
     }
 
     term.print(
         format_args!("{:4}", cdep.issues.trusted),
-        if cdep.issues.trusted > 0 {
-            Some(::term::color::RED)
-        } else {
-            None
-        },
+        term::issue_severity_color(cdep.issues_severity),
     )?;
     print!("/");
     term.print(
@@ -83,6 +129,9 @@ pub fn print_details(cdep: &CrateDetails, term: &mut Term, verbose: bool) -> Res
         },
     )?;
     match cdep.loc {
+        Some(loc) if large_unreviewed => {
+            term.print_bold(format_args!(" {:>6}", loc), ::term::color::MAGENTA)?
+        }
         Some(loc) => print!(" {:>6}", loc),
         None => print!(" {:>6}", "err"),
     }
@@ -90,35 +139,48 @@ pub fn print_details(cdep: &CrateDetails, term: &mut Term, verbose: bool) -> Res
     Ok(())
 }
 
-fn print_stats_crate_id(stats: &CrateStats, _term: &mut Term) {
+fn print_stats_crate_id(stats: &CrateStats, _term: &mut Term, widths: ColumnWidths) {
     print!(
-        " {:<20} {:<15}",
+        " {:<name_width$} {}",
         stats.info.id.name(),
-        pad_left_manually(stats.info.id.version().to_string(), 15)
+        pad_left_manually(stats.info.id.version().to_string(), widths.version),
+        name_width = widths.name
     );
 }
 
-pub fn print_dep(stats: &CrateStats, term: &mut Term, verbose: bool) -> Result<()> {
+pub fn print_dep(
+    stats: &CrateStats,
+    term: &mut Term,
+    verbose: bool,
+    widths: ColumnWidths,
+    warn_build_scripts: bool,
+    loc_warn_threshold: Option<usize>,
+) -> Result<()> {
     match &stats.details {
-        Err(_) => {
-            print_stats_crate_id(stats, term);
-            println!(" -- computation failed");
+        Err(e) => {
+            print_stats_crate_id(stats, term, widths);
+            println!(" -- computation failed: {}", e);
         }
         Ok(None) => {
-            print_stats_crate_id(stats, term);
+            print_stats_crate_id(stats, term, widths);
             println!(" -- skipped");
         }
         Ok(Some(details)) => {
-            print_details(&details, term, verbose)?;
+            let large_unreviewed =
+                loc_warn_threshold.map_or(false, |threshold| stats.is_large_unreviewed(threshold));
+            print_details(&details, term, verbose, large_unreviewed)?;
             match details.geiger_count {
                 Some(geiger_count) => print!(" {:>7}", geiger_count),
-                None => print!(" {:>7}", "err"),
+                None => print!(" {:>7}", "-"),
+            }
+            let build_script = if stats.has_custom_build() { "CB" } else { "" };
+            if warn_build_scripts && stats.has_custom_build() && !details.verified {
+                term.print_bold(format_args!(" {:4}", build_script), ::term::color::RED)?;
+            } else {
+                term.print(format_args!(" {:4}", build_script), ::term::color::YELLOW)?;
             }
-            term.print(
-                format_args!(" {:4}", if stats.has_custom_build() { "CB" } else { "" }),
-                ::term::color::YELLOW,
-            )?;
-            print_stats_crate_id(stats, term);
+            print!(" {:6}", stats.info.kind);
+            print_stats_crate_id(stats, term, widths);
             print!(
                 " {}",
                 latest_trusted_version_string(
@@ -126,8 +188,436 @@ pub fn print_dep(stats: &CrateStats, term: &mut Term, verbose: bool) -> Result<(
                     &details.latest_trusted_version
                 )
             );
+            if details.stale_notes {
+                term.print(format_args!(" [N?]"), ::term::color::YELLOW)?;
+            } else if details.has_notes {
+                term.print(format_args!(" [N]"), ::term::color::CYAN)?;
+            }
+            if let Some(coverage) = details.partial_coverage {
+                term.print(
+                    format_args!(" [P {}/{}]", coverage.reviewed_files, coverage.total_files),
+                    ::term::color::YELLOW,
+                )?;
+            }
+            if let Some(previous) = &details.previously_reviewed {
+                term.print(
+                    format_args!(" [Δ {}→{}]", previous, stats.info.id.version()),
+                    ::term::color::CYAN,
+                )?;
+            }
+            for (analyzer, output) in details.analyses.custom() {
+                term.print(
+                    format_args!(" [{}: {}]", analyzer, output.summary),
+                    ::term::color::MAGENTA,
+                )?;
+            }
             println!();
+            if verbose {
+                print_contributing_reviews(details);
+            }
         }
     }
     Ok(())
 }
+
+/// Trusted reviewers the verification status of a crate is based on, one per line
+fn print_contributing_reviews(details: &CrateDetails) {
+    for review in &details.contributing_reviews {
+        println!(
+            "    reviewed by {} ({} trust) on {}: {}",
+            review.id,
+            review.trust_level,
+            review.date.format("%Y-%m-%d"),
+            review.rating
+        );
+    }
+}
+
+/// Verification status as an emoji, for output without colors
+fn status_emoji(details: &CrateDetails) -> String {
+    if details.unclean_digest {
+        return "⚠️ dirty".to_owned();
+    }
+    let emoji = match details.trust {
+        VerificationStatus::Verified => "✅",
+        VerificationStatus::Insufficient => "⚠️",
+        VerificationStatus::Negative => "❌",
+    };
+    format!("{} {}", emoji, details.trust)
+}
+
+const MARKDOWN_COLUMNS: &[&str] = &[
+    "status",
+    "reviews",
+    "downloads",
+    "owners",
+    "issues",
+    "lines",
+    "geiger",
+    "flags",
+    "kind",
+    "crate",
+    "version",
+    "latest trusted",
+];
+
+pub fn print_markdown_header(verbose: bool) {
+    let mut columns = MARKDOWN_COLUMNS.to_vec();
+    if verbose {
+        columns.insert(0, "digest");
+    }
+    println!("{}", markdown_header(&columns));
+}
+
+/// Indented line under the `print_dep` row, with the path the crate is pulled in by
+pub fn print_dep_path(stats: &CrateStats) {
+    if stats.info.path.len() > 1 {
+        println!("    {}", format_dependency_path(&stats.info.path, false));
+    }
+}
+
+/// Like `print_dep`, as a row of a Markdown table
+pub fn print_dep_markdown(stats: &CrateStats, verbose: bool) {
+    fn or_dash<T: ToString>(value: Option<T>) -> String {
+        value.map_or("-".to_owned(), |value| value.to_string())
+    }
+
+    let mut cells = vec![];
+    match &stats.details {
+        Ok(Some(details)) => {
+            if verbose {
+                cells.push(details.digest.to_string());
+            }
+            cells.push(status_emoji(details));
+            cells.push(format!(
+                "{}/{}",
+                details.reviews.version, details.reviews.total
+            ));
+            cells.push(or_dash(
+                details
+                    .downloads
+                    .as_ref()
+                    .map(|d| format!("{}/{}", d.version, d.total)),
+            ));
+            cells.push(or_dash(
+                details
+                    .owners
+                    .as_ref()
+                    .map(|o| format!("{}/{}", o.trusted, o.total)),
+            ));
+            cells.push(format!(
+                "{}/{}",
+                details.issues.trusted, details.issues.total
+            ));
+            cells.push(or_dash(details.loc));
+            cells.push(or_dash(details.geiger_count));
+        }
+        other => {
+            if verbose {
+                cells.push("".to_owned());
+            }
+            cells.push(match other {
+                Err(e) => format!("computation failed: {}", e),
+                _ => "skipped".to_owned(),
+            });
+            cells.resize(cells.len() + 6, "".to_owned());
+        }
+    }
+    cells.push(if stats.has_custom_build() { "CB" } else { "" }.to_owned());
+    cells.push(stats.info.kind.to_string());
+    cells.push(stats.info.id.name().to_string());
+    cells.push(stats.info.id.version().to_string());
+    cells.push(
+        stats
+            .details()
+            .map(|details| {
+                latest_trusted_version_string(
+                    stats.info.id.version(),
+                    &details.latest_trusted_version,
+                )
+            })
+            .unwrap_or_default(),
+    );
+    println!("{}", markdown_row(&cells));
+}
+
+/// Columns of `print_dep_machine_readable`, named after the fields they come from
+pub const MACHINE_READABLE_COLUMNS: &[&str] = &[
+    "name",
+    "version",
+    "kind",
+    "has_custom_build",
+    "trust",
+    "reviews",
+    "downloads",
+    "owners",
+    "issues",
+    "issues_severity",
+    "loc",
+    "geiger_count",
+    "unclean_digest",
+    "latest_trusted_version",
+    "digest",
+    "analyses",
+];
+
+pub fn print_machine_readable_header(prefix_columns: &[&str]) {
+    let columns: Vec<_> = prefix_columns
+        .iter()
+        .chain(MACHINE_READABLE_COLUMNS)
+        .cloned()
+        .collect();
+    println!("{}", columns.join("\t"));
+}
+
+/// Cells of a row of `print_dep_machine_readable`
+///
+/// Missing values are empty, and the `trust` of crates that couldn't
+/// be verified is `skipped` or `error`.
+pub fn machine_readable_cells(stats: &CrateStats) -> Vec<String> {
+    fn or_empty<T: ToString>(value: Option<T>) -> String {
+        value.map_or_else(String::new, |value| value.to_string())
+    }
+
+    let mut cells = vec![
+        stats.info.id.name().to_string(),
+        stats.info.id.version().to_string(),
+        stats.info.kind.to_string(),
+        stats.has_custom_build().to_string(),
+    ];
+    match &stats.details {
+        Ok(Some(details)) => {
+            cells.push(details.trust.to_string());
+            cells.push(format!(
+                "{}/{}",
+                details.reviews.version, details.reviews.total
+            ));
+            cells.push(or_empty(
+                details
+                    .downloads
+                    .as_ref()
+                    .map(|d| format!("{}/{}", d.version, d.total)),
+            ));
+            cells.push(or_empty(
+                details
+                    .owners
+                    .as_ref()
+                    .map(|o| format!("{}/{}", o.trusted, o.total)),
+            ));
+            cells.push(format!(
+                "{}/{}",
+                details.issues.trusted, details.issues.total
+            ));
+            cells.push(or_empty(details.issues_severity));
+            cells.push(or_empty(details.loc));
+            cells.push(or_empty(details.geiger_count));
+            cells.push(details.unclean_digest.to_string());
+            cells.push(or_empty(details.latest_trusted_version.as_ref()));
+            cells.push(details.digest.to_string());
+            let analyses: Vec<_> = details
+                .analyses
+                .custom()
+                .map(|(analyzer, output)| format!("{}={}", analyzer, output.summary))
+                .collect();
+            cells.push(analyses.join(","));
+        }
+        other => {
+            cells.push(if other.is_err() { "error" } else { "skipped" }.to_owned());
+            cells.resize(MACHINE_READABLE_COLUMNS.len(), String::new());
+        }
+    }
+    cells
+}
+
+/// Like `print_dep`, as a line of tab-separated values without any colors
+pub fn print_dep_machine_readable(stats: &CrateStats, prefix_cells: &[&str]) {
+    let cells: Vec<_> = prefix_cells
+        .iter()
+        .map(|cell| cell.to_string())
+        .chain(machine_readable_cells(stats))
+        .collect();
+    println!("{}", cells.join("\t"));
+}
+
+/// Progress of scanning crates, shown on the last line of stderr
+///
+/// Needs to be cleared before printing anything else, and updated after.
+pub struct ProgressBar {
+    progress: Progress,
+    enabled: bool,
+    start: std::time::Instant,
+    durations: Durations,
+}
+
+impl ProgressBar {
+    pub fn new(total: usize, enabled: bool) -> Self {
+        ProgressBar {
+            progress: Progress { done: 0, total },
+            enabled,
+            start: std::time::Instant::now(),
+            durations: Durations::default(),
+        }
+    }
+
+    pub fn update(&mut self, stats: &CrateStats) {
+        self.progress.done += 1;
+        self.durations += stats.durations;
+        if !self.enabled || self.progress.is_complete() {
+            return;
+        }
+
+        let elapsed = self.start.elapsed();
+        let remaining =
+            elapsed / self.progress.done as u32 * (self.progress.total - self.progress.done) as u32;
+        eprint!(
+            "\r[{}/{}] {} {} ETA {}s",
+            self.progress.done,
+            self.progress.total,
+            stats.info.id.name(),
+            stats.info.id.version(),
+            remaining.as_secs()
+        );
+    }
+
+    pub fn clear(&self) {
+        if self.enabled && self.progress.done > 0 {
+            eprint!("\r\x1b[K");
+        }
+    }
+
+    /// Time since the scan started
+    pub fn elapsed(&self) -> std::time::Duration {
+        self.start.elapsed()
+    }
+
+    /// Print the total time, and the time spent in each phase summed over all crates
+    pub fn print_summary(&self) {
+        eprintln!(
+            "Scanned {} crates in {:.1}s (digest: {:.1}s, crates.io: {:.1}s, loc: {:.1}s, issues: {:.1}s, latest trusted: {:.1}s, geiger: {:.1}s)",
+            self.progress.done,
+            duration_secs(self.start.elapsed()),
+            duration_secs(self.durations.digest),
+            duration_secs(self.durations.crates_io),
+            duration_secs(self.durations.loc),
+            duration_secs(self.durations.issues),
+            duration_secs(self.durations.latest_trusted),
+            duration_secs(self.durations.geiger),
+        );
+    }
+}
+
+/// Print the `verify --timings` breakdown, with the `top` slowest crates of each phase
+pub fn print_timings(timings: &Timings, top: usize) {
+    eprintln!(
+        "Timings, summed over all crates (wall clock: {:.1}s):",
+        duration_secs(timings.wall_clock)
+    );
+    for &phase in &Phase::ALL {
+        eprintln!(
+            "{:>16} {:>8.1}s",
+            phase.name(),
+            duration_secs(timings.phase_total(phase))
+        );
+    }
+    for &phase in &Phase::ALL {
+        eprintln!("Slowest crates, {}:", phase.name());
+        for (id, duration) in timings.slowest(phase, top) {
+            eprintln!(
+                "{:>16.3}s {} {}",
+                duration_secs(duration),
+                id.name(),
+                id.version()
+            );
+        }
+    }
+}
+
+pub fn duration_secs(d: std::time::Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_millis()) / 1000.0
+}
+
+pub fn print_totals(totals: &Totals, warn_build_scripts: bool) {
+    eprintln!(
+        "Totals: {} verified, {} unverified, {} flagged, {} with open issues, {} without known owners{}{}{}",
+        totals.verified,
+        totals.unverified,
+        totals.flagged,
+        totals.with_issues,
+        totals.without_known_owners,
+        if warn_build_scripts {
+            format!(
+                ", {} unverified with build scripts",
+                totals.unverified_build_scripts
+            )
+        } else {
+            "".into()
+        },
+        if totals.dirty > 0 {
+            format!(", {} dirty", totals.dirty)
+        } else {
+            "".into()
+        },
+        if totals.skipped > 0 {
+            format!(", {} skipped", totals.skipped)
+        } else {
+            "".into()
+        }
+    );
+    eprintln!(
+        "Lines of code: {} in verified crates, {} in unverified crates",
+        totals.verified_loc, totals.unverified_loc
+    );
+    eprintln!(
+        "Risk score: {:.1} in total, {:.1} in unverified crates",
+        totals.risk, totals.unverified_risk
+    );
+}
+
+/// Print the `verify --coverage` table
+pub fn print_coverage(coverage: &Coverage) {
+    fn fraction(part: usize, total: usize) -> String {
+        let percent = if total == 0 {
+            100.0
+        } else {
+            part as f64 * 100.0 / total as f64
+        };
+        format!("{}/{} ({:.1}%)", part, total, percent)
+    }
+
+    println!(
+        "{:10} {:>20} {:>28} {:>12}",
+        "coverage", "verified crates", "verified lines of code", "unknown loc"
+    );
+    for (name, bucket) in &[
+        ("direct", coverage.direct),
+        ("transitive", coverage.transitive),
+        ("total", coverage.total()),
+    ] {
+        println!(
+            "{:10} {:>20} {:>28} {:>12}",
+            name,
+            fraction(bucket.verified_crates, bucket.crates),
+            fraction(bucket.verified_loc, bucket.loc),
+            bucket.unknown_loc_crates
+        );
+    }
+}
+
+/// Print `n` crates with the highest risk score
+pub fn print_top_risks(deps: &[CrateStats], risk_model: &RiskModel, n: usize) {
+    let mut scored: Vec<_> = deps
+        .iter()
+        .filter_map(|stats| Some((risk_model.score(stats)?, stats)))
+        .collect();
+    scored.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+    println!("Top {} crates by risk score:", n);
+    for (score, stats) in scored.into_iter().take(n) {
+        println!(
+            "{:>8.1} {} {}",
+            score,
+            stats.info.id.name(),
+            stats.info.id.version()
+        );
+    }
+}
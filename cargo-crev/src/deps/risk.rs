@@ -0,0 +1,58 @@
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::deps::CrateStats;
+use crate::prelude::*;
+
+const DEFAULT_RISK_MODEL: &str = include_str!("risk_model_defaults.toml");
+
+/// Weights used to compute the risk score of a crate
+///
+/// The higher the score, the more a crate deserves a review. All fields
+/// are required; `risk_model_defaults.toml` is a good starting point.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskModel {
+    /// Risk per 1000 lines of Rust code
+    pub loc_weight: f64,
+    /// Risk of having a build script
+    pub has_build_script_weight: f64,
+    /// Risk of having no reviews of the version
+    pub no_reviews_weight: f64,
+    /// Risk per issue reported by trusted ids
+    pub open_issues_weight: f64,
+    /// Risk reduction per order of magnitude of total downloads
+    pub download_count_factor: f64,
+}
+
+impl Default for RiskModel {
+    fn default() -> Self {
+        toml::from_str(DEFAULT_RISK_MODEL).expect("default risk model is valid")
+    }
+}
+
+impl RiskModel {
+    pub fn read_from_file(path: &Path) -> Result<Self> {
+        crev_common::read_from_toml_file(path)
+            .map_err(|e| format_err!("Can't read risk model {}: {}", path.display(), e))
+    }
+
+    /// Risk score of a crate, `None` if it was skipped or failed to scan
+    pub fn score(&self, stats: &CrateStats) -> Option<f64> {
+        let details = stats.details()?;
+
+        let mut score = self.loc_weight * details.loc.unwrap_or(0) as f64 / 1000.0;
+        if stats.has_custom_build() {
+            score += self.has_build_script_weight;
+        }
+        if details.reviews.version == 0 {
+            score += self.no_reviews_weight;
+        }
+        score += self.open_issues_weight * details.issues.trusted as f64;
+        if let Some(downloads) = details.downloads {
+            score -= self.download_count_factor * (downloads.total as f64 + 1.0).log10();
+        }
+
+        Some(score.max(0.0))
+    }
+}
@@ -1,7 +1,7 @@
+use super::analyze::{self, Analyses, Analyzer};
 use super::*;
 use crate::crates_io;
-use crate::repo::*;
-use crate::shared::get_geiger_count;
+use crate::repo::Repo;
 use crev_common::convert::OptionDeref;
 use crev_lib;
 use crossbeam::{
@@ -9,7 +9,8 @@ use crossbeam::{
     channel::{unbounded, Receiver},
 };
 use std::sync::Arc;
-use std::{collections::HashSet, default::Default, path::PathBuf};
+use std::time::Instant;
+use std::{collections::HashSet, default::Default};
 
 use crev_lib::proofdb::*;
 
@@ -17,70 +18,139 @@ use crev_lib::proofdb::*;
 pub struct Scanner {
     db: Arc<ProofDB>,
     trust_set: TrustSet,
-    ignore_list: HashSet<PathBuf>,
+    for_id: Option<crev_data::Id>,
+    previous_by_trusted: bool,
+    digest_options: crev_lib::DigestOptions,
     crates_io: Arc<crates_io::Client>,
+    source: String,
+    offline: bool,
     known_owners: HashSet<String>,
+    notes: Option<Arc<crev_lib::notes::Notes>>,
     requirements: crev_lib::VerificationRequirements,
     skip_verified: bool,
     skip_known_owners: bool,
     crates: Vec<CrateInfo>,
+    unchanged_crate_count: usize,
+    analyzers: Arc<Vec<Arc<dyn Analyzer>>>,
+    geiger_cache: Option<Arc<analyze::DigestCache>>,
 }
 
 impl Scanner {
     pub fn new(args: &Verify) -> Result<Scanner> {
         let local = crev_lib::Local::auto_create_or_open()?;
         let db = local.load_db()?;
-        let trust_set =
-            if let Some(for_id) = local.get_for_id_from_str_opt(args.for_id.as_deref())? {
-                db.calculate_trust_set(&for_id, &args.trust_params.clone().into())
-            } else {
-                crev_lib::proofdb::TrustSet::default()
-            };
-        let ignore_list = cargo_min_ignore_list();
-        let crates_io = crates_io::Client::new(&local)?;
+        let for_id = local.get_for_id_from_str_opt(args.for_id.as_deref())?;
+        let trust_set = if let Some(for_id) = for_id.as_ref() {
+            crate::policy::calculate_trust_set(&db, for_id, &args.trust_params.clone().into())?
+        } else {
+            crev_lib::proofdb::TrustSet::default()
+        };
+        let digest_options = cargo_min_ignore_list().into();
+        let repo = Repo::auto_open_cwd_offline(args.offline)?;
+        let offline = repo.is_offline()?;
+        let mut crates_io = crates_io::Client::new(&local, repo.registry_source(), offline)?;
+        if let Some(retries) = args.crates_io_retries {
+            crates_io = crates_io.with_retries(retries);
+        }
+        let crates_io = Arc::new(crates_io);
         let known_owners = read_known_owners_list().unwrap_or_else(|_| HashSet::new());
+        let notes = if args.show_notes {
+            Some(Arc::new(local.load_notes()?))
+        } else {
+            None
+        };
         let requirements = crev_lib::VerificationRequirements::from(args.requirements.clone());
         let skip_verified = args.skip_verified;
         let skip_known_owners = args.skip_known_owners;
-        let repo = Repo::auto_open_cwd()?;
-        let package_set = repo.get_deps_package_set()?;
+        let analyzers = Arc::new(analyze::analyzers(&args.analyzers)?);
+        let geiger_cache = if args.geiger {
+            Some(Arc::new(analyze::DigestCache::for_local(&local)))
+        } else {
+            None
+        };
+        let (package_set, kinds, paths) = repo.get_deps_package_set(args.scope)?;
         let pkg_ids = package_set.package_ids();
-        let crates = package_set
+        let mut crates: Vec<_> = package_set
             .get_many(pkg_ids)?
             .into_iter()
             .filter(|pkg| pkg.summary().source_id().is_registry())
-            .map(|pkg| CrateInfo::from_pkg(pkg))
+            .filter_map(|pkg| {
+                // not in the resolve graph of the workspace, eg. for another platform
+                let kind = *kinds.get(&pkg.package_id())?;
+                let mut info = CrateInfo::from_pkg(pkg, kind);
+                if let Some(path) = paths.get(&pkg.package_id()) {
+                    info.path = path.clone();
+                }
+                Some(info)
+            })
+            .filter(|info| args.includes_dep_kind(info.kind))
             .collect();
+        let mut unchanged_crate_count = 0;
+        if let Some(ref git_ref) = args.changed_since {
+            let previous = repo.get_locked_packages_at(git_ref)?;
+            let total = crates.len();
+            crates.retain(|info| {
+                !previous.contains(&(info.id.name().to_string(), info.id.version().clone()))
+            });
+            unchanged_crate_count = total - crates.len();
+        }
         Ok(Scanner {
             db: Arc::new(db),
             trust_set,
-            ignore_list,
-            crates_io: Arc::new(crates_io),
+            for_id,
+            previous_by_trusted: args.previous_by_trusted,
+            digest_options,
+            crates_io,
+            source: repo.registry_source().to_owned(),
+            offline,
             known_owners,
+            notes,
             requirements,
             skip_verified,
             skip_known_owners,
             crates,
+            unchanged_crate_count,
+            analyzers,
+            geiger_cache,
         })
     }
 
+    /// Load the proofs again, to see the reviews created since
+    ///
+    /// Only the proof db is replaced; the trust set and the list of
+    /// crates are kept.
+    pub fn reload_db(&mut self) -> Result<()> {
+        let local = crev_lib::Local::auto_create_or_open()?;
+        self.db = Arc::new(local.load_db()?);
+        Ok(())
+    }
+
+    /// The crates to scan
+    pub fn crates(&self) -> &[CrateInfo] {
+        &self.crates
+    }
+
     pub fn total_crate_count(&self) -> usize {
         self.crates.len()
     }
 
+    /// Number of crates left out by `--changed-since`
+    pub fn unchanged_crate_count(&self) -> usize {
+        self.unchanged_crate_count
+    }
+
     /// start computations on a new thread, and return
     /// - a channel receiver, to get new events
     /// - a channel sender, to ask for computation stop
-    pub fn run(self) -> Receiver<CrateStats> {
+    pub fn run(&self) -> Receiver<CrateStats> {
         let (tx, rx) = unbounded();
 
         let pool = threadpool::Builder::new().build();
         for info in self.crates.clone().into_iter() {
-            let mut self_clone = self.clone();
+            let self_clone = self.clone();
             let tx = tx.clone();
             pool.execute(move || {
-                let details = self_clone.get_crate_details(&info);
-                tx.send(CrateStats { info, details })
+                tx.send(self_clone.scan_crate(info))
                     .expect("channel will be there waiting for the pool");
             });
         }
@@ -88,34 +158,92 @@ impl Scanner {
         rx
     }
 
-    fn get_crate_details(&mut self, info: &CrateInfo) -> Result<Option<CrateDetails>> {
+    /// Compute the stats of a single crate, on the current thread
+    pub fn scan_crate(&self, info: CrateInfo) -> CrateStats {
+        let mut durations = Durations::default();
+        let details = self.clone().get_crate_details(&info, &mut durations);
+        CrateStats {
+            info,
+            details,
+            durations,
+        }
+    }
+
+    /// Lines of code of scanned dependencies covered by trusted reviews
+    ///
+    /// Dependencies without details or line count are not included.
+    pub fn coverage_stats(&self, deps: &[CrateStats]) -> CoverageStats {
+        self.db.get_coverage_stats(
+            &self.trust_set,
+            &self.requirements,
+            deps.iter()
+                .filter_map(|dep| dep.details())
+                .filter_map(|details| Some((&details.digest, details.loc? as u64))),
+        )
+    }
+
+    /// Reviews of the exact version of a crate, trusted first
+    ///
+    /// Each review comes with whether its author is trusted.
+    pub fn crate_reviews(
+        &self,
+        info: &CrateInfo,
+    ) -> Vec<(crev_data::proof::review::Package, bool)> {
+        let mut reviews: Vec<_> = self
+            .db
+            .get_pkg_reviews_for_version(&self.source, &info.id.name(), info.id.version())
+            .map(|review| {
+                let trusted = self.trust_set.contains_trusted(&review.from.id);
+                (review.clone(), trusted)
+            })
+            .collect();
+        reviews.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.date.cmp(&a.0.date)));
+        reviews
+    }
+
+    fn get_crate_details(
+        &mut self,
+        info: &CrateInfo,
+        durations: &mut Durations,
+    ) -> Result<Option<CrateDetails>> {
         let pkg_name = info.id.name();
         let pkg_version = info.id.version();
-        info.download_if_needed()?;
-        let geiger_count = get_geiger_count(&info.root).ok();
-        let digest = crev_lib::get_dir_digest(&info.root, &self.ignore_list)?;
-        let unclean_digest = !is_digest_clean(&self.db, &pkg_name, &pkg_version, &digest);
-        let result = self
-            .db
-            .verify_package_digest(&digest, &self.trust_set, &self.requirements);
-        let verified = result.is_verified();
+        let start = Instant::now();
+        if self.offline {
+            if !info.root.exists() {
+                bail!("Crate source not downloaded; run once without `--offline`");
+            }
+        } else {
+            info.download_if_needed()?;
+        }
+        let digest = crev_lib::get_dir_digest(&info.root, &self.digest_options)?;
+        durations.digest += start.elapsed();
+        let unclean_digest =
+            !is_digest_clean(&self.db, &self.source, &pkg_name, &pkg_version, &digest);
+        let verification = self.db.verify_package_digest_with_details(
+            &digest,
+            &self.trust_set,
+            &self.requirements,
+        );
+        let verified = verification.status.is_verified();
         if verified && self.skip_verified {
             return Ok(None);
         }
 
         let version_reviews_count = self.db.get_package_review_count(
-            PROJECT_SOURCE_CRATES_IO,
+            &self.source,
             Some(&info.id.name()),
             Some(&info.id.version()),
         );
         let total_reviews_count =
             self.db
-                .get_package_review_count(PROJECT_SOURCE_CRATES_IO, Some(&pkg_name), None);
+                .get_package_review_count(&self.source, Some(&pkg_name), None);
         let reviews = ReviewCount {
             version: version_reviews_count as u64,
             total: total_reviews_count as u64,
         };
 
+        let start = Instant::now();
         let downloads = match self.crates_io.get_downloads_count(&pkg_name, &pkg_version) {
             Ok((version, total)) => Some(DownloadCount { version, total }),
             Err(_) => None,
@@ -138,16 +266,18 @@ impl Scanner {
             }
             Err(_) => None,
         };
+        durations.crates_io += start.elapsed();
 
+        let start = Instant::now();
         let issues_from_trusted = self.db.get_open_issues_for_version(
-            PROJECT_SOURCE_CRATES_IO,
+            &self.source,
             &pkg_name,
             &pkg_version,
             &self.trust_set,
             self.requirements.trust_level.into(),
         );
         let issues_from_all = self.db.get_open_issues_for_version(
-            PROJECT_SOURCE_CRATES_IO,
+            &self.source,
             &pkg_name,
             &pkg_version,
             &self.trust_set,
@@ -157,27 +287,99 @@ impl Scanner {
             trusted: issues_from_trusted.len(),
             total: issues_from_all.len(),
         };
+        let issues_severity = issues_from_trusted
+            .values()
+            .map(|details| details.severity)
+            .max();
+
+        durations.issues += start.elapsed();
+
+        let start = Instant::now();
+        let mut analyses = Analyses::run(&self.analyzers, &info.root);
+        let loc = analyses.count(analyze::LOC).map(|loc| loc as usize);
+        durations.loc += start.elapsed();
+
+        let start = Instant::now();
+        if let Some(cache) = &self.geiger_cache {
+            if let Ok(output) = cache.analyze(&analyze::GeigerAnalyzer, &digest, &info.root) {
+                analyses.push(analyze::GEIGER, output);
+            }
+        }
+        let geiger_count = analyses.count(analyze::GEIGER);
+        durations.geiger += start.elapsed();
+
+        let partial_coverage = if verified {
+            None
+        } else {
+            let reviewed_files = self.db.get_trusted_reviewed_files(
+                &self.source,
+                &pkg_name,
+                pkg_version,
+                &self.trust_set,
+                &self.requirements,
+            );
+            if reviewed_files.is_empty() {
+                None
+            } else {
+                crev_lib::dir_partial_review_coverage(
+                    &info.root,
+                    &self.digest_options,
+                    &reviewed_files,
+                )
+                .ok()
+            }
+        };
 
-        let loc = crate::tokei::get_rust_line_count(&info.root).ok();
+        let notes: Vec<_> = self.notes.as_ref().map_or(vec![], |notes| {
+            notes
+                .get_for_package(&self.source, &pkg_name, pkg_version, &digest)
+                .collect()
+        });
+        let has_notes = !notes.is_empty();
+        let stale_notes = has_notes && notes.iter().all(|note| note.is_stale(&digest));
 
+        let start = Instant::now();
         let latest_trusted_version = self.db.find_latest_trusted_version(
             &self.trust_set,
-            PROJECT_SOURCE_CRATES_IO,
+            &self.source,
             &pkg_name,
             &self.requirements,
         );
+        let previously_reviewed = if verified {
+            None
+        } else {
+            let min_trust_level = TrustLevel::from(self.requirements.trust_level);
+            self.db
+                .find_previously_reviewed_version(&self.source, &pkg_name, pkg_version, |id| {
+                    if self.previous_by_trusted {
+                        self.trust_set
+                            .get_effective_trust_level(id)
+                            .map_or(false, |level| min_trust_level <= level)
+                    } else {
+                        self.for_id.as_ref() == Some(id)
+                    }
+                })
+        };
+        durations.latest_trusted += start.elapsed();
         Ok(Some(CrateDetails {
             geiger_count,
+            analyses,
             digest,
             latest_trusted_version,
-            trust: result,
+            trust: verification.status,
+            contributing_reviews: verification.reviews,
             reviews,
             downloads,
             owners,
             issues,
+            issues_severity,
             loc,
             unclean_digest,
             verified,
+            has_notes,
+            stale_notes,
+            partial_coverage,
+            previously_reviewed,
         }))
     }
 }
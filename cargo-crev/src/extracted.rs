@@ -0,0 +1,207 @@
+//! Crates reviewed in place, in the directory cargo extracted them to (`review --here`)
+//!
+//! Cargo extracts the `.crate` tarball of every downloaded crate to
+//! `<cargo home>/registry/src/<index>/<name>-<version>`, and keeps the
+//! tarball in `<cargo home>/registry/cache/<index>/`. Instead of
+//! re-downloading the crate, the reviewed code is compared with that
+//! tarball, after checking it against the checksum of the registry index.
+
+use crate::prelude::*;
+use crate::repo::Repo;
+use crate::shared::*;
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A crate extracted by cargo from a registry tarball
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedCrate {
+    pub root: PathBuf,
+    pub name: String,
+    pub version: Version,
+}
+
+/// Split a `<name>-<version>` directory name
+///
+/// Both names and versions can contain `-`, so the version is the
+/// longest suffix that parses as one.
+pub fn split_dir_name(dir_name: &str) -> Option<(&str, Version)> {
+    dir_name
+        .match_indices('-')
+        .filter(|&(i, _)| i > 0)
+        .find_map(|(i, _)| {
+            let version = Version::parse(&dir_name[i + 1..]).ok()?;
+            Some((&dir_name[..i], version))
+        })
+}
+
+/// Checksum of a tarball, in the format of the registry index
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = cargo::util::Sha256::new();
+    hasher.update(bytes);
+    hasher.finish().iter().fold(String::new(), |mut hex, b| {
+        let _ = write!(hex, "{:02x}", b);
+        hex
+    })
+}
+
+impl ExtractedCrate {
+    /// The crate extracted in `dir`, if `dir` looks like `<registry>/src/<index>/<name>-<version>`
+    ///
+    /// Fails if it does, but its `Cargo.toml` is for another crate.
+    pub fn detect(dir: &Path) -> Result<Option<Self>> {
+        let index_dir = match dir.parent() {
+            Some(index_dir) => index_dir,
+            None => return Ok(None),
+        };
+        let src_dir = match index_dir.parent() {
+            Some(src_dir) if src_dir.file_name() == Some("src".as_ref()) => src_dir,
+            _ => return Ok(None),
+        };
+        if src_dir.parent().and_then(Path::file_name) != Some("registry".as_ref()) {
+            return Ok(None);
+        }
+        let dir_name = match dir.file_name().and_then(|name| name.to_str()) {
+            Some(dir_name) => dir_name,
+            None => return Ok(None),
+        };
+        let (name, version) = match split_dir_name(dir_name) {
+            Some(split) => split,
+            None => return Ok(None),
+        };
+
+        let manifest: toml::Value =
+            toml::from_str(&crev_common::read_file_to_string(&dir.join("Cargo.toml"))?)?;
+        let package = manifest.get("package").ok_or_else(|| {
+            format_err!(
+                "The `Cargo.toml` of {} has no `[package]` section",
+                dir.display()
+            )
+        })?;
+        if package.get("name").and_then(toml::Value::as_str) != Some(name)
+            || package.get("version").and_then(toml::Value::as_str) != Some(&version.to_string())
+        {
+            bail!(
+                "The `Cargo.toml` of {} isn't the one of {} {}",
+                dir.display(),
+                name,
+                version
+            );
+        }
+        Ok(Some(ExtractedCrate {
+            root: dir.to_owned(),
+            name: name.to_owned(),
+            version,
+        }))
+    }
+
+    /// The `.crate` tarball the crate was extracted from
+    pub fn tarball_path(&self) -> PathBuf {
+        let index_dir = self.root.parent().expect("detected with a parent");
+        let registry_dir = index_dir
+            .parent()
+            .and_then(Path::parent)
+            .expect("detected with a parent");
+        registry_dir
+            .join("cache")
+            .join(index_dir.file_name().expect("detected with a name"))
+            .join(format!("{}-{}.crate", self.name, self.version))
+    }
+
+    /// Read the tarball, making sure it has the checksum given by the registry index
+    pub fn read_tarball(&self, checksum: &str) -> Result<Vec<u8>> {
+        let path = self.tarball_path();
+        let tarball = fs::read(&path).map_err(|e| {
+            format_err!(
+                "Can't read {}, the tarball {} was extracted from: {}",
+                path.display(),
+                self.root.display(),
+                e
+            )
+        })?;
+        let actual = sha256_hex(&tarball);
+        if actual != checksum {
+            bail!(
+                "The checksum of {} is {}, but the registry index says {}",
+                path.display(),
+                actual,
+                checksum
+            );
+        }
+        Ok(tarball)
+    }
+
+    /// Extract `tarball` to a temporary directory, returning the directory and the crate root in it
+    pub fn unpack_tarball(&self, tarball: &[u8]) -> Result<(tempdir::TempDir, PathBuf)> {
+        let tmp_dir = tempdir::TempDir::new("crev-tarball")?;
+        tar::Archive::new(flate2::read::GzDecoder::new(tarball)).unpack(tmp_dir.path())?;
+        let root = tmp_dir
+            .path()
+            .join(format!("{}-{}", self.name, self.version));
+        if !root.is_dir() {
+            bail!(
+                "The tarball of {} {} has no `{}-{}` directory",
+                self.name,
+                self.version,
+                self.name,
+                self.version
+            );
+        }
+        Ok((tmp_dir, root))
+    }
+
+    /// Digest of the tarball the crate was extracted from, and its vcs info
+    ///
+    /// Like `check_package_clean_state`, the reviewed code is compared
+    /// with a pristine copy, and the review is for the pristine copy.
+    /// The differences are reported, loudly, since a modified copy
+    /// means the review might not be about the published code, and
+    /// going on with the review must be confirmed.
+    pub fn check_clean_state(
+        &self,
+        repo: &Repo,
+        json_diff_report: bool,
+    ) -> Result<(crev_data::Digest, Option<VcsInfoJson>)> {
+        let checksum = repo
+            .registry_checksum(&self.name, &self.version)?
+            .ok_or_else(|| {
+                format_err!(
+                    "{} {} has no checksum in the registry index",
+                    self.name,
+                    self.version
+                )
+            })?;
+        let tarball = self.read_tarball(&checksum)?;
+        let (_tmp_dir, clean_root) = self.unpack_tarball(&tarball)?;
+        let compared_ignore_list = cargo_full_ignore_list().into();
+        let (digest_clean, digest_published) = clean_crate_digests(&clean_root)?;
+        let digest_reviewed =
+            crev_lib::get_recursive_digest_for_dir(&self.root, &compared_ignore_list)?;
+
+        if digest_published != digest_reviewed {
+            eprintln!(
+                r#"WARNING: THE REVIEWED CODE IS NOT THE PUBLISHED CODE.
+            The digest of {} is different from the digest of the tarball it was extracted from.
+            Files were created or modified since; the review will be for the published tarball.
+            Tarball: {}"#,
+                self.root.display(),
+                self.tarball_path().display(),
+            );
+            let report =
+                crev_lib::diff_dir_digests(&clean_root, &self.root, &compared_ignore_list)?;
+            if json_diff_report {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print_dir_diff_report(&report)?;
+            }
+            if !crev_common::yes_or_no_was_y("Review the published tarball anyway? (y/n) ")? {
+                bail!("Review canceled");
+            }
+        }
+
+        let vcs = VcsInfoJson::read_from_crate_dir(&self.root)?;
+        Ok((digest_clean, vcs))
+    }
+}
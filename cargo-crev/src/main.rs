@@ -22,9 +22,13 @@ extern crate crossbeam;
 /// Documentation
 pub mod doc;
 
+mod audit;
+mod cli_config;
 mod crates_io;
 mod deps;
+mod extracted;
 mod opts;
+mod policy;
 mod prelude;
 mod repo;
 mod review;
@@ -32,6 +36,10 @@ mod shared;
 mod term;
 mod tokei;
 mod tui;
+mod verify_git;
+
+#[cfg(test)]
+mod tests;
 
 use crate::repo::*;
 use crate::review::*;
@@ -58,6 +66,10 @@ fn run_command(command: opts::Command) -> Result<CommandExitStatus> {
             let status = run_diff(&args)?;
             std::process::exit(status.code().unwrap_or(-159));
         }
+        opts::Command::Config(opts::Config::Edit) => {
+            let local = Local::auto_open()?;
+            local.edit_cli_config(cli_config::CliConfig::validate)?;
+        }
         opts::Command::Edit(cmd) => match cmd {
             opts::Edit::Readme => {
                 let local = crev_lib::Local::auto_open()?;
@@ -72,12 +84,56 @@ fn run_command(command: opts::Command) -> Result<CommandExitStatus> {
             }
         },
         opts::Command::Verify(args) => {
-            return if args.interactive {
+            return if let Some(opts::VerifyCommand::Explain(ref explain)) = args.command {
+                deps::explain::explain_verification(&args, explain)
+            } else if args.interactive {
                 tui::verify_deps(args)
             } else {
                 deps::verify_deps(args)
             };
         }
+        opts::Command::Id(opts::Id::VerifySignature(args)) => {
+            let s = if args.path == std::path::Path::new("-") {
+                load_stdin_with_prompt()?
+            } else {
+                std::fs::read(&args.path)?
+            };
+            // Only used to show where signers are known from, if anywhere
+            let db = Local::auto_open().and_then(|local| local.load_db()).ok();
+            verify_proof_signatures(db.as_ref(), &s)?;
+        }
+        opts::Command::Id(opts::Id::SetUrl(args)) => {
+            let local = Local::auto_open()?;
+            local.change_current_id_url(&args.url, args.migrate_proofs)?;
+            eprintln!("Current Id now uses {}", args.url);
+            eprintln!("Old proofs still point to the old URL; new proofs will use the new one.");
+        }
+        opts::Command::Id(opts::Id::RotatePassphrase) => {
+            let local = Local::auto_open()?;
+            eprintln!("Unlock the current Id with the old passphrase first.");
+            local.change_current_id_passphrase(
+                &crev_common::read_passphrase,
+                &crev_common::read_new_passphrase,
+            )?;
+            eprintln!("Passphrase changed. Make sure to back up the re-encrypted Id again.");
+        }
+        opts::Command::Id(opts::Id::Expired(args)) => {
+            let local = Local::auto_open()?;
+            let id = local.get_current_userid()?;
+            let db = local.load_db()?;
+            let now = crev_common::now().with_timezone(&chrono::Utc);
+            let before = now + chrono::Duration::days(args.within);
+            for (to, level, expires) in db.get_trust_expiring_before(&id, before) {
+                println!(
+                    "{} {} {:<8} {} {}",
+                    if expires <= now { "expired" } else { "expires" },
+                    expires.format("%Y-%m-%d"),
+                    level,
+                    to,
+                    db.lookup_url(to).map(|url| url.url.as_str()).unwrap_or("")
+                );
+            }
+        }
         opts::Command::Id(opts::Id::Show) => {
             let local = Local::auto_open()?;
             local.show_own_ids()?;
@@ -95,12 +151,18 @@ fn run_command(command: opts::Command) -> Result<CommandExitStatus> {
                 opts::QueryId::Trusted {
                     for_id,
                     trust_params,
+                    json,
                 } => {
                     let local = crev_lib::Local::auto_open()?;
                     let db = local.load_db()?;
                     let for_id = local.get_for_id_from_str(for_id.as_deref())?;
-                    let trust_set = db.calculate_trust_set(&for_id, &trust_params.into());
+                    let trust_set =
+                        policy::calculate_trust_set(&db, &for_id, &trust_params.into())?;
 
+                    if json {
+                        print_trusted_ids_json(&db, &trust_set)?;
+                        return Ok(CommandExitStatus::Successs);
+                    }
                     for id in trust_set.trusted_ids() {
                         println!(
                             "{} {:6} {}",
@@ -115,18 +177,13 @@ fn run_command(command: opts::Command) -> Result<CommandExitStatus> {
                 // TODO: move to crev-lib
                 opts::QueryId::All => {
                     let local = crev_lib::Local::auto_create_or_open()?;
-                    let db = local.load_db()?;
 
-                    for id in &db.all_known_ids() {
-                        println!(
-                            "{} {}",
-                            id,
-                            db.lookup_url(id).map(|url| url.url.as_str()).unwrap_or("")
-                        );
+                    for (id, url) in local.list_all_known_ids()? {
+                        println!("{} {}", id, url.unwrap_or_default());
                     }
                 }
             },
-            opts::Query::Review(args) => list_reviews(&args.crate_)?,
+            opts::Query::Review(args) => list_reviews(&args)?,
             opts::Query::Dir(args) => show_dir(
                 &args.common.crate_,
                 UnrelatedOrDependency::from_unrelated_flag(args.common.unrelated),
@@ -135,16 +192,48 @@ fn run_command(command: opts::Command) -> Result<CommandExitStatus> {
             opts::Query::Issue(args) => list_issues(&args)?,
         },
         opts::Command::Review(args) => {
-            handle_goto_mode_command(&args.common, |c, v, i| {
+            if let Some(ref output) = args.record_screen {
+                let status = rerun_with_screen_recording(output, "--record-screen")?;
+                std::process::exit(status.code().unwrap_or(-159));
+            }
+            if args.no_fetch && !args.i_know_what_im_doing {
+                bail!("`--no-fetch` skips the integrity check of the reviewed code; confirm with `--i-know-what-im-doing`");
+            }
+            if args.here
+                && (args.common.crate_.name.is_some()
+                    || args.no_fetch
+                    || args.all_unreviewed
+                    || args.interactive)
+            {
+                bail!("`--here` can't be combined with a crate name, `--no-fetch`, `--all-unreviewed` or `--interactive`");
+            }
+            if args.all_unreviewed {
+                if args.common.crate_.name.is_some() || args.advisory || args.issue {
+                    bail!("`--all-unreviewed` can't be combined with a crate name, `--advisory` or `--issue`");
+                }
+                review_all_unreviewed(&args)?;
+                return Ok(CommandExitStatus::Successs);
+            }
+            if args.interactive {
+                if args.common.crate_.name.is_some() || args.advisory || args.issue {
+                    bail!("`--interactive` can't be combined with a crate name, `--advisory` or `--issue`");
+                }
+                return tui::review_deps(&args);
+            }
+            let review = |c: &str, v: Option<&Version>, i, integrity| {
                 let is_advisory = args.advisory
-                    || args.affected.is_some()
-                    || (!args.issue && args.severity.is_some());
+                    || (!args.issue && (args.affected.is_some() || args.severity.is_some()));
+                let affected_versions = args.affected.as_ref().and_then(opts::Affected::versions);
                 create_review_proof(
                     c,
                     v,
                     i,
                     if args.issue {
-                        Some(crev_data::Level::Medium)
+                        Some(opts::IssueCommon {
+                            severity: args.severity.unwrap_or(crev_data::Level::Medium),
+                            affected_versions: affected_versions.clone(),
+                            id: args.id.clone(),
+                        })
                     } else {
                         None
                     },
@@ -153,7 +242,11 @@ fn run_command(command: opts::Command) -> Result<CommandExitStatus> {
                             severity: args.severity.unwrap_or(crev_data::Level::Medium),
                             affected: args
                                 .affected
+                                .as_ref()
+                                .and_then(opts::Affected::range)
                                 .unwrap_or(crev_data::proof::review::package::VersionRange::Major),
+                            affected_versions: affected_versions.clone(),
+                            id: args.id.clone(),
                         })
                     } else {
                         None
@@ -166,8 +259,40 @@ fn run_command(command: opts::Command) -> Result<CommandExitStatus> {
                     &args.common_proof_create,
                     &args.diff,
                     args.skip_activity_check || is_advisory || args.issue,
+                    &args.content,
+                    args.json,
+                    integrity,
+                    None,
                 )
-            })?;
+            };
+            // without a crate name, the current directory might be a crate extracted by cargo
+            let extracted = if args.here
+                || (args.common.crate_.name.is_none() && are_we_called_from_goto_shell().is_none())
+            {
+                extracted::ExtractedCrate::detect(&std::env::current_dir()?)?
+            } else {
+                None
+            };
+            match extracted {
+                Some(extracted) => {
+                    if args.no_fetch {
+                        bail!("`--no-fetch` can't be used to review the crate in the current directory");
+                    }
+                    let (name, version) = (extracted.name.clone(), extracted.version.clone());
+                    review(
+                        &name,
+                        Some(&version),
+                        UnrelatedOrDependency::Unrelated,
+                        IntegrityCheck::Tarball(extracted),
+                    )?;
+                }
+                None if args.here => bail!(
+                    "The current directory isn't a crate extracted by cargo, in `<cargo home>/registry/src/<index>/<name>-<version>`"
+                ),
+                None => handle_goto_mode_command(&args.common, |c, v, i| {
+                    review(c, v, i, IntegrityCheck::from_no_fetch_flag(args.no_fetch))
+                })?,
+            }
         }
         opts::Command::Goto(args) => {
             goto_crate_src(
@@ -180,81 +305,138 @@ fn run_command(command: opts::Command) -> Result<CommandExitStatus> {
                 crate_open(c, v, i, args.cmd, args.cmd_save)
             })?;
         }
+        opts::Command::Audit(args) => return audit::run_audit(&args),
+        opts::Command::Why(args) => {
+            let repo = Repo::auto_open_cwd()?;
+            let paths =
+                repo.find_dependency_paths(&args.name, args.version.as_ref(), args.max_paths)?;
+            for path in &paths {
+                println!("{}", deps::format_dependency_path(path, true));
+            }
+            if paths.len() == args.max_paths {
+                eprintln!("There may be more paths; see `--max-paths`");
+            }
+        }
         opts::Command::Clean(args) => {
-            if args.crate_.is_empty() && are_we_called_from_goto_shell().is_none() {
+            if args.all_dirty
+                || (args.common.crate_.is_empty() && are_we_called_from_goto_shell().is_none())
+            {
                 clean_all_unclean_crates()?;
             } else {
-                handle_goto_mode_command(&args, |c, v, i| clean_crate(c, v, i))?;
+                handle_goto_mode_command(&args.common, |c, v, i| clean_crate(c, v, i))?;
             }
         }
         opts::Command::Trust(args) => {
-            create_trust_proof(args.pub_ids, Trust, &args.common_proof_create)?;
+            let expires = args.expiration()?;
+            create_trust_proof(args.ids()?, Trust, expires, &args.common_proof_create)?;
         }
         opts::Command::Distrust(args) => {
-            create_trust_proof(args.pub_ids, Distrust, &args.common_proof_create)?;
+            let expires = args.expiration()?;
+            create_trust_proof(args.ids()?, Distrust, expires, &args.common_proof_create)?;
         }
         opts::Command::Git(git) => {
             let local = Local::auto_open()?;
             let status = local.run_git(git.args)?;
             std::process::exit(status.code().unwrap_or(-159));
         }
-        opts::Command::Publish => {
+        opts::Command::Publish(args) => {
             let local = Local::auto_open()?;
-            let mut status = local.run_git(vec!["diff".into(), "--exit-code".into()])?;
-
-            if status.code().unwrap_or(-2) == 1 {
-                status = local.run_git(vec![
-                    "commit".into(),
-                    "-a".into(),
-                    "-m".into(),
-                    "auto-commit on `crev publish`".into(),
-                ])?;
-            }
-
-            if status.code().unwrap_or(-1) == 0 {
-                status = local.run_git(vec!["pull".into(), "--rebase".into()])?;
+            let changes = local.proof_dir_changes()?;
+            for path in &changes {
+                eprintln!("Modified: {}", path.display());
             }
-            if status.code().unwrap_or(-1) == 0 {
-                status = local.run_git(vec!["push".into()])?;
+            let sign_commits = args.sign_commits || local.sign_commits()?;
+            let signer = if !changes.is_empty() && sign_commits {
+                Some(local.read_current_unlocked_id(&crev_common::read_passphrase)?)
+            } else {
+                None
+            };
+            // Nothing is to be committed in between
+            let _lock = local.lock_proof_dir()?;
+            if local.proof_dir_commit_all("auto-commit on `crev publish`", signer.as_ref())? {
+                eprintln!("Committed local changes");
             }
-            std::process::exit(status.code().unwrap_or(-159));
+            local.proof_dir_pull(|| match signer {
+                Some(signer) => Ok(Some(signer)),
+                None if sign_commits => Ok(Some(
+                    local.read_current_unlocked_id(&crev_common::read_passphrase)?,
+                )),
+                None => Ok(None),
+            })?;
+            local.proof_dir_push()?;
+            eprintln!("Proofs published");
         }
         opts::Command::Fetch(cmd) => match cmd {
             opts::Fetch::Trusted(params) => {
                 let local = Local::auto_create_or_open()?;
-                local.fetch_trusted(params.into())?;
+                local.set_fetch_full_history(params.common.full);
+                local.set_verify_fetched_signatures(params.common.verify_signatures);
+                local.set_require_signed_commits(params.common.require_signed_commits);
+                local.fetch_trusted(params.trust_params.into())?;
             }
             opts::Fetch::Url(params) => {
                 let local = Local::auto_create_or_open()?;
+                local.set_fetch_full_history(params.common.full);
+                local.set_verify_fetched_signatures(params.common.verify_signatures);
+                local.set_require_signed_commits(params.common.require_signed_commits);
                 local.fetch_url(&params.url)?;
             }
-            opts::Fetch::All => {
+            opts::Fetch::All(params) => {
                 let local = Local::auto_create_or_open()?;
-                local.fetch_all()?;
+                local.set_fetch_full_history(params.common.full);
+                local.set_verify_fetched_signatures(params.common.verify_signatures);
+                local.set_require_signed_commits(params.common.require_signed_commits);
+                if let Some(name) = params.only_for_crate {
+                    local.fetch_for_package(&current_registry_source(), &name)?;
+                } else {
+                    local.fetch_all()?;
+                }
             }
         },
         opts::Command::Update => {
             let local = Local::auto_open()?;
-            let status = local.run_git(vec!["pull".into(), "--rebase".into()])?;
-            if !status.success() {
-                std::process::exit(status.code().unwrap_or(-159));
-            }
+            local.proof_dir_pull(|| {
+                Ok(if local.sign_commits()? {
+                    Some(local.read_current_unlocked_id(&crev_common::read_passphrase)?)
+                } else {
+                    None
+                })
+            })?;
             let repo = Repo::auto_open_cwd()?;
-            repo.update_source()?;
-            repo.update_counts()?;
+            if repo.is_offline()? {
+                eprintln!("Cargo is offline; not updating the registry index and crates.io data");
+            } else {
+                repo.update_source()?;
+                repo.update_counts()?;
+            }
         }
         opts::Command::Id(opts::Id::Export(params)) => {
             let local = Local::auto_open()?;
             println!("{}", local.export_locked_id(params.id)?);
         }
-        opts::Command::Id(opts::Id::Import) => {
+        opts::Command::Id(opts::Id::Import(args)) => {
             let local = Local::auto_create_or_open()?;
-            let s = load_stdin_with_prompt()?;
-            let id = local.import_locked_id(&String::from_utf8(s)?)?;
+            let mut id = if let Some(key_file) = &args.key_file {
+                local.import_locked_id_file(key_file, &crev_common::read_passphrase)?
+            } else {
+                let s = load_stdin_with_prompt()?;
+                local.import_locked_id(&String::from_utf8(s)?)?
+            };
             // Note: It's unclear how much of this should be done by
             // the library
             local.save_current_id(&id.id)?;
 
+            if args.key_file.is_some() {
+                let url = crev_common::read_line_or(
+                    &format!("Proof repository URL [{}]: ", id.url.url),
+                    &id.url.url,
+                )?;
+                if url != id.url.url {
+                    local.change_current_id_url(&url, false)?;
+                    id = local.read_current_locked_id()?.to_pubid();
+                }
+            }
+
             let proof_dir_path = local.get_proofs_dir_path_for_url(&id.url)?;
             if !proof_dir_path.exists() {
                 local.clone_proof_dir_from_git(&id.url.url, false)?;
@@ -276,7 +458,115 @@ fn run_command(command: opts::Command) -> Result<CommandExitStatus> {
                     }
                     content.set_author(&id.as_pubid());
                     let proof = content.sign_by(&id)?;
-                    maybe_store(&local, &proof, &commit_msg, &args.common)?;
+                    maybe_store(&local, &proof, &commit_msg, Some(&id), &args.common)?;
+                }
+            }
+        },
+        opts::Command::Export(cmd) => match cmd {
+            opts::Export::Proofs(args) => {
+                let local = Local::auto_open()?;
+                let s = export_own_proofs(&local, args.since.as_ref())?;
+                if args.path == std::path::Path::new("-") {
+                    print!("{}", s);
+                } else {
+                    crev_common::store_str_to_file(&args.path, &s)?;
+                }
+            }
+        },
+        opts::Command::Completions(args) => {
+            opts::gen_completions(args.shell, &mut std::io::stdout());
+        }
+        opts::Command::Crate(opts::Crate::VerifyGit(args)) => {
+            return verify_git::verify_crate_against_git(&args);
+        }
+        opts::Command::Crate(opts::Crate::Digest(args)) => {
+            let digest = crate_digest(
+                &args.name,
+                args.version.as_ref(),
+                UnrelatedOrDependency::from_unrelated_flag(args.independent),
+            )?;
+            println!("{}", digest);
+            if let Some(expected) = args.verify {
+                if digest != expected {
+                    eprintln!("Digest mismatch: expected {}", expected);
+                    return Ok(CommandExitStatus::VerificationFailed);
+                }
+            }
+        }
+        opts::Command::Crate(opts::Crate::Open(args)) => crate_open_independent(&args)?,
+        opts::Command::Crate(opts::Crate::Diff(mut args)) => {
+            if args.args.is_empty() && std::env::var_os(CREV_DIFF_ENV).is_none() {
+                args.args.push("-u".into());
+            }
+            let status = run_diff(&args)?;
+            std::process::exit(status.code().unwrap_or(-159));
+        }
+        opts::Command::Repo(opts::Repo::Import(args)) => {
+            let local = Local::auto_open()?;
+            let s = if args.path == std::path::Path::new("-") {
+                load_stdin_with_prompt()?
+            } else {
+                std::fs::read(&args.path)?
+            };
+            import_signed_proofs(&local, &s, args.allow_foreign, &args.common)?;
+        }
+        opts::Command::Repo(opts::Repo::Verify(args)) => {
+            let local = Local::auto_open()?;
+            let broken = local.find_broken_proof_files()?;
+            for file in &broken {
+                println!("{}: {}", file.path.display(), file.problem);
+                if args.fix {
+                    let new_path = local.quarantine_proof_file(&file.path)?;
+                    println!("  moved to {}", new_path.display());
+                }
+            }
+            // Proofs that parse, but were still skipped or changed when loading
+            let (_db, report) = local.load_db_with_report()?;
+            for warning in &report.warnings {
+                if !broken.iter().any(|file| file.path == warning.path) {
+                    println!("{}", warning);
+                }
+            }
+            if !broken.is_empty() {
+                eprintln!("{} broken proof file(s) found", broken.len());
+                return Ok(CommandExitStatus::VerificationFailed);
+            }
+        }
+        opts::Command::Note(cmd) => match cmd {
+            opts::Note::Add(args) => {
+                let local = Local::auto_create_or_open()?;
+                let digest = args
+                    .version
+                    .as_ref()
+                    .and_then(|version| crate_digest_for_note(&args.name, version));
+                let mut notes = local.load_notes()?;
+                notes.add(crev_lib::notes::Note::new(
+                    &current_registry_source(),
+                    &args.name,
+                    args.version.as_ref(),
+                    digest.as_ref(),
+                    args.text,
+                ));
+                local.store_notes(&notes)?;
+            }
+            opts::Note::Show(args) => {
+                let local = Local::auto_create_or_open()?;
+                let notes = local.load_notes()?;
+                let digest = args
+                    .version
+                    .as_ref()
+                    .and_then(|version| crate_digest_for_note(&args.name, version));
+                let source = current_registry_source();
+                let found: Vec<_> = match (&args.version, &digest) {
+                    (Some(version), Some(digest)) => notes
+                        .get_for_package(&source, &args.name, version, digest)
+                        .collect(),
+                    _ => notes
+                        .get_for(&source, &args.name, args.version.as_ref())
+                        .collect(),
+                };
+                for note in found {
+                    println!("{}", format_note(note, digest.as_ref()));
                 }
             }
         },
@@ -299,11 +589,20 @@ fn load_stdin_with_prompt() -> Result<Vec<u8>> {
 
 fn main() {
     env_logger::init();
-    let opts = opts::Opts::from_args();
-    let opts::MainCommand::Crev(command) = opts.command;
-    match run_command(command) {
+    let matches = opts::Opts::clap().get_matches();
+    let opts = opts::Opts::from_clap(&matches);
+    if let Some(id) = &opts.id {
+        // Every `Local` opened for this command picks it up
+        std::env::set_var(crev_lib::local::CREV_ID_ENV, id);
+    }
+    let opts::MainCommand::Crev(mut command) = opts.command;
+    match cli_config::apply_user_cli_config(&mut command, &matches)
+        .and_then(|()| policy::apply_project_policy(&mut command, &matches))
+        .and_then(|()| run_command(command))
+    {
         Ok(CommandExitStatus::Successs) => {}
         Ok(CommandExitStatus::VerificationFailed) => std::process::exit(-1),
+        Ok(CommandExitStatus::UnverifiedBuildScripts) => std::process::exit(3),
         Err(e) => {
             eprintln!("{}", e.display_causes_and_backtrace());
             std::process::exit(-2)
@@ -1,6 +1,10 @@
-use crev_data::Level;
-use semver::Version;
-use std::ffi::OsString;
+use crate::deps::{DependencyKind, DepsScope};
+use crev_data::proof::review::package::VersionRange;
+use crev_data::{Level, Rating};
+use failure::{bail, format_err};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::{ffi::OsString, path::PathBuf, str::FromStr};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt, Clone)]
@@ -41,35 +45,132 @@ pub struct SwitchId {
     pub id: String,
 }
 
+#[derive(Debug, StructOpt, Clone)]
+pub struct VerifySignature {
+    /// File to read proofs from (`-` for stdin)
+    #[structopt(parse(from_os_str))]
+    pub path: PathBuf,
+}
+
 /// Parameters describing trust graph traversal
-#[derive(Debug, StructOpt, Clone, Default)]
+#[derive(Debug, StructOpt, Clone)]
 pub struct TrustDistanceParams {
-    #[structopt(long = "depth", default_value = "10")]
-    /// Maximum allowed distance from the root identity when traversing trust graph
-    pub depth: u64,
+    #[structopt(long = "trust-preset", default_value = "balanced")]
+    /// Preset of the parameters below [conservative balanced permissive]
+    ///
+    /// conservative: --depth 1 --high-cost 1 --medium-cost 2 --low-cost 2 (only Ids directly trusted at high level);
+    /// balanced: --depth 10 --high-cost 0 --medium-cost 1 --low-cost 5;
+    /// permissive: --depth 5 --high-cost 0 --medium-cost 1 --low-cost 1
+    pub preset: TrustPreset,
+
+    #[structopt(long = "depth")]
+    /// Maximum allowed distance from the root identity when traversing trust graph [default: from the preset]
+    pub depth: Option<u64>,
+
+    /// Cost of traversing trust graph edge of high trust level [default: from the preset]
+    #[structopt(long = "high-cost")]
+    pub high_cost: Option<u64>,
+    /// Cost of traversing trust graph edge of medium trust level [default: from the preset]
+    #[structopt(long = "medium-cost")]
+    pub medium_cost: Option<u64>,
+    /// Cost of traversing trust graph edge of low trust level [default: from the preset]
+    #[structopt(long = "low-cost")]
+    pub low_cost: Option<u64>,
+}
 
-    /// Cost of traversing trust graph edge of high trust level
-    #[structopt(long = "high-cost", default_value = "0")]
-    pub high_cost: u64,
-    /// Cost of traversing trust graph edge of medium trust level
-    #[structopt(long = "medium-cost", default_value = "1")]
-    pub medium_cost: u64,
-    /// Cost of traversing trust graph edge of low trust level
-    #[structopt(long = "low-cost", default_value = "5")]
-    pub low_cost: u64,
+impl Default for TrustDistanceParams {
+    fn default() -> Self {
+        TrustDistanceParams {
+            preset: TrustPreset::Balanced,
+            depth: None,
+            high_cost: None,
+            medium_cost: None,
+            low_cost: None,
+        }
+    }
 }
 
 impl From<TrustDistanceParams> for crev_lib::TrustDistanceParams {
     fn from(params: TrustDistanceParams) -> Self {
+        let preset = params.preset.params();
         crev_lib::TrustDistanceParams {
-            max_distance: params.depth,
-            high_trust_distance: params.high_cost,
-            medium_trust_distance: params.medium_cost,
-            low_trust_distance: params.low_cost,
+            max_distance: params.depth.unwrap_or(preset.max_distance),
+            high_trust_distance: params.high_cost.unwrap_or(preset.high_trust_distance),
+            medium_trust_distance: params.medium_cost.unwrap_or(preset.medium_trust_distance),
+            low_trust_distance: params.low_cost.unwrap_or(preset.low_trust_distance),
+        }
+    }
+}
+
+/// Shell command run by `verify` on every crate, see `deps::analyze`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnalyzerCommand {
+    pub name: String,
+    pub command: String,
+}
+
+impl FromStr for AnalyzerCommand {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(command))
+                if !name.trim().is_empty() && !command.trim().is_empty() =>
+            {
+                Ok(AnalyzerCommand {
+                    name: name.trim().to_owned(),
+                    command: command.to_owned(),
+                })
+            }
+            _ => bail!("Invalid analyzer `{}`; expected `<name>=<command>`", s),
         }
     }
 }
 
+/// Named set of `TrustDistanceParams`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrustPreset {
+    Conservative,
+    Balanced,
+    Permissive,
+}
+
+impl TrustPreset {
+    pub fn params(self) -> crev_lib::TrustDistanceParams {
+        match self {
+            TrustPreset::Conservative => crev_lib::TrustDistanceParams {
+                max_distance: 1,
+                high_trust_distance: 1,
+                medium_trust_distance: 2,
+                low_trust_distance: 2,
+            },
+            TrustPreset::Balanced => crev_lib::TrustDistanceParams::default(),
+            TrustPreset::Permissive => crev_lib::TrustDistanceParams {
+                max_distance: 5,
+                high_trust_distance: 0,
+                medium_trust_distance: 1,
+                low_trust_distance: 1,
+            },
+        }
+    }
+}
+
+impl FromStr for TrustPreset {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "conservative" => TrustPreset::Conservative,
+            "balanced" => TrustPreset::Balanced,
+            "permissive" => TrustPreset::Permissive,
+            _ => bail!("Unknown trust preset `{}`", s),
+        })
+    }
+}
+
 #[derive(Debug, StructOpt, Clone)]
 pub struct Diff {
     /// Source version - defaults to the last reviewed one
@@ -89,7 +190,7 @@ pub struct Diff {
     /// Crate name
     pub name: String,
 
-    /// Arguments to the `diff` command
+    /// Arguments to the `diff` command (or `$CREV_DIFF`, if set)
     #[structopt(parse(from_os_str))]
     pub args: Vec<OsString>,
 }
@@ -101,8 +202,12 @@ pub struct VerificationRequirements {
     #[structopt(long = "trust", default_value = "low")]
     pub trust_level: crev_data::Level,
 
-    /// Number of reviews required
-    #[structopt(long = "redundancy", default_value = "1")]
+    /// Number of reviews, by distinct trusted reviewers, required
+    #[structopt(
+        long = "redundancy",
+        raw(aliases = r#"&["min-reviews", "min-reviews-for-verified"]"#),
+        default_value = "1"
+    )]
     pub redundancy: u64,
     /// Required understanding
     #[structopt(long = "understanding", default_value = "none")]
@@ -129,6 +234,7 @@ pub struct Verify {
     /// Display more informations about the crates
     pub verbose: bool,
 
+    /// Browse the dependencies in a terminal UI, and review them from there
     #[structopt(long = "interactive", short = "i")]
     pub interactive: bool,
 
@@ -146,9 +252,209 @@ pub struct Verify {
     /// Skip crate from known owners (use `edit known` to edit the list)
     pub skip_known_owners: bool,
 
+    #[structopt(long = "geiger")]
+    /// Count the `unsafe` code of every crate; slow, but cached by crate digest
+    pub geiger: bool,
+
     #[structopt(long = "for-id")]
     /// Root identity to calculate the Web of Trust for [default: current user id]
     pub for_id: Option<String>,
+
+    #[structopt(long = "offline")]
+    /// Don't access the network; use only the local proof db and crate sources
+    pub offline: bool,
+
+    #[structopt(long = "crates-io-retries")]
+    /// Retry crates.io requests failing for transient reasons up to N times [default: 3]
+    pub crates_io_retries: Option<u32>,
+
+    #[structopt(long = "show-path")]
+    /// Show which workspace member (and direct dependency) pulls in each crate
+    pub show_path: bool,
+
+    #[structopt(long = "show-notes")]
+    /// Mark crates that have private notes with `[N]`, or `[N?]` if written about different content
+    pub show_notes: bool,
+
+    #[structopt(long = "previous-by-trusted")]
+    /// Mark crates with the last version reviewed by any trusted id, not only by the root id, with `[Δ old→new]`
+    pub previous_by_trusted: bool,
+
+    #[structopt(long = "quiet", short = "q")]
+    /// Don't show the progress bar, the totals and the timing summary
+    pub quiet: bool,
+
+    #[structopt(long = "changed-since")]
+    /// Verify only dependencies added or updated since given git revision
+    pub changed_since: Option<String>,
+
+    #[structopt(long = "sort-by", raw(aliases = r#"&["sort", "table-sort"]"#))]
+    /// Sort the output by given column [name version status reviews loc issues downloads]
+    pub sort_by: Option<SortColumn>,
+
+    #[structopt(long = "sort-desc", raw(aliases = r#"&["reverse", "desc"]"#))]
+    /// Sort in descending order
+    pub sort_desc: bool,
+
+    #[structopt(long = "coverage")]
+    /// Print the fraction of crates and lines of code that are verified, and covered by trusted reviews
+    pub coverage: bool,
+
+    #[structopt(long = "timings")]
+    /// Print the time spent in each phase of the scan, and the slowest crates of each
+    pub timings: bool,
+
+    #[structopt(long = "timings-out", parse(from_os_str))]
+    /// Write the timings of the scan, per crate and phase, to this JSON file
+    pub timings_out: Option<PathBuf>,
+
+    #[structopt(long = "analyzer", number_of_values = 1)]
+    /// Run `<name>=<command>` with `sh` in the root of every crate, and show the first line it prints
+    pub analyzers: Vec<AnalyzerCommand>,
+
+    #[structopt(long = "risk-model", parse(from_os_str))]
+    /// TOML file with weights of the risk score [default: built-in model]
+    pub risk_model: Option<PathBuf>,
+
+    #[structopt(long = "top")]
+    /// Print N crates with the highest risk score
+    pub top: Option<usize>,
+
+    #[structopt(long = "deny-severity")]
+    /// Fail if trusted ids reported an open issue of at least this severity [low medium high]
+    pub deny_severity: Option<Level>,
+
+    #[structopt(long = "all-workspaces", parse(from_os_str))]
+    /// Verify all cargo workspaces found in given directory, in one table
+    pub all_workspaces: Option<PathBuf>,
+
+    #[structopt(long = "warn-build-scripts")]
+    /// Highlight unverified crates with a build script, and count them in the totals
+    pub warn_build_scripts: bool,
+
+    #[structopt(long = "fail-build-scripts")]
+    /// Exit with code 3 if any unverified crate has a build script (implies `--warn-build-scripts`)
+    pub fail_build_scripts: bool,
+
+    #[structopt(long = "loc-warn-threshold")]
+    /// Highlight unreviewed crates with more lines of code than this, and count them in the totals
+    pub loc_warn_threshold: Option<usize>,
+
+    #[structopt(long = "fail-loc-threshold")]
+    /// Fail if any unreviewed crate has more lines of code than this (implies `--loc-warn-threshold`)
+    pub fail_loc_threshold: Option<usize>,
+
+    #[structopt(long = "deps", raw(use_delimiter = "true"))]
+    /// Verify only dependencies of given kinds, eg. `normal,build` [normal build dev all] [default: all]
+    pub deps: Vec<DepsKind>,
+
+    #[structopt(long = "scope", default_value = "all")]
+    /// Verify only the dependencies listed in the current package, or any workspace member [direct-only workspace all]
+    pub scope: DepsScope,
+
+    #[structopt(long = "count-only")]
+    /// Print only the number of crates by verification status, instead of the table
+    pub count_only: bool,
+
+    /// Print the table as tab-separated values, with a header line of the column names
+    #[structopt(long = "machine-readable", short = "m")]
+    pub machine_readable: bool,
+
+    #[structopt(long = "output", alias = "format", default_value = "json")]
+    /// Format of the `--count-only` output [json text markdown]; with `markdown` the table is printed as Markdown too
+    pub output: OutputFormat,
+
+    #[structopt(subcommand)]
+    pub command: Option<VerifyCommand>,
+}
+
+impl Verify {
+    pub fn warn_build_scripts(&self) -> bool {
+        self.warn_build_scripts || self.fail_build_scripts
+    }
+
+    pub fn loc_warn_threshold(&self) -> Option<usize> {
+        self.loc_warn_threshold.or(self.fail_loc_threshold)
+    }
+
+    /// Whether dependencies of `kind` were selected with `--deps`
+    pub fn includes_dep_kind(&self, kind: DependencyKind) -> bool {
+        self.deps.is_empty()
+            || self.deps.iter().any(|deps| match deps {
+                DepsKind::All => true,
+                DepsKind::Only(only) => *only == kind,
+            })
+    }
+}
+
+/// Value of `verify --deps`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepsKind {
+    All,
+    Only(DependencyKind),
+}
+
+impl FromStr for DepsKind {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "all" => DepsKind::All,
+            "normal" => DepsKind::Only(DependencyKind::Normal),
+            "build" => DepsKind::Only(DependencyKind::Build),
+            "dev" => DepsKind::Only(DependencyKind::Dev),
+            _ => bail!(
+                "Unknown dependency kind `{}`; expected `normal`, `build`, `dev` or `all`",
+                s
+            ),
+        })
+    }
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub enum VerifyCommand {
+    /// Explain the verification result of a dependency
+    #[structopt(name = "explain")]
+    Explain(VerifyExplain),
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct VerifyExplain {
+    /// Name of the dependency
+    pub name: String,
+
+    #[structopt(long = "version")]
+    /// Version of the dependency, if there are more than one
+    pub version: Option<Version>,
+}
+
+/// Column of the `verify` output to sort by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    Name,
+    Version,
+    Verified,
+    Reviews,
+    Loc,
+    Issues,
+    Downloads,
+}
+
+impl FromStr for SortColumn {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "name" => SortColumn::Name,
+            "version" => SortColumn::Version,
+            "status" | "verified" | "trust" => SortColumn::Verified,
+            "reviews" => SortColumn::Reviews,
+            "loc" => SortColumn::Loc,
+            "issues" => SortColumn::Issues,
+            "downloads" => SortColumn::Downloads,
+            _ => bail!("Unknown column `{}`", s),
+        })
+    }
 }
 
 #[derive(Debug, StructOpt, Clone)]
@@ -156,21 +462,134 @@ pub struct Trust {
     /// Public IDs to create Trust Proof for
     pub pub_ids: Vec<String>,
 
+    /// Read more public IDs from a file, one per line, optionally followed by their proof repository URL
+    #[structopt(long = "from-file", parse(from_os_str))]
+    pub from_file: Option<PathBuf>,
+
+    /// Make the proof expire after this many days; expired proofs are ignored
+    #[structopt(long = "expires-in", value_name = "days")]
+    pub expires_in: Option<i64>,
+
+    /// Make the proof expire at a date (eg. `2020-01-01`) or after a duration (eg. `90d`, `12w`, `1y`)
+    #[structopt(
+        long = "expires",
+        parse(try_from_str = "parse_expiration"),
+        conflicts_with = "expires_in"
+    )]
+    pub expires: Option<crev_data::proof::Date>,
+
     #[structopt(flatten)]
     pub common_proof_create: CommonProofCreate,
 }
 
+impl Trust {
+    /// Expiration date set with either `--expires` or `--expires-in`
+    pub fn expiration(&self) -> Result<Option<crev_data::proof::Date>, failure::Error> {
+        Ok(match self.expires_in {
+            Some(days) if days <= 0 => bail!("`--expires-in` must be a positive number of days"),
+            Some(days) => {
+                Some(days_from_now(days).ok_or_else(|| {
+                    format_err!("`--expires-in {}` is too far in the future", days)
+                })?)
+            }
+            None => self.expires,
+        })
+    }
+
+    /// Ids from the command line and `--from-file`, with their URL if the file gave one
+    pub fn ids(&self) -> Result<Vec<(String, Option<String>)>, failure::Error> {
+        let mut ids: Vec<_> = self.pub_ids.iter().map(|id| (id.clone(), None)).collect();
+        if let Some(path) = &self.from_file {
+            let content = crev_common::read_file_to_string(path)?;
+            ids.extend(
+                crev_lib::local::parse_trust_id_list(&content)
+                    .map_err(|e| format_err!("{}: {}", path.display(), e))?,
+            );
+        }
+        Ok(ids)
+    }
+}
+
+/// Parse a date, or a number of days (`d`), weeks (`w`) or years (`y`) from now
+/// The date `days` days from now, unless it's out of the range of dates
+fn days_from_now(days: i64) -> Option<crev_data::proof::Date> {
+    let millis = days.checked_mul(24 * 60 * 60 * 1000)?;
+    crev_common::now().checked_add_signed(chrono::Duration::milliseconds(millis))
+}
+
+fn parse_expiration(s: &str) -> Result<crev_data::proof::Date, failure::Error> {
+    let now = crev_common::now();
+    let (number, unit) = s.split_at(s.len() - s.chars().last().map_or(0, char::len_utf8));
+    let days_per_unit = match unit {
+        "d" => Some(1),
+        "w" => Some(7),
+        "y" => Some(365),
+        _ => None,
+    };
+    let date = match (number.parse::<i64>(), days_per_unit) {
+        (Ok(number), Some(days)) => number
+            .checked_mul(days)
+            .and_then(days_from_now)
+            .ok_or_else(|| format_err!("Expiration `{}` is too far in the future", s))?,
+        _ => parse_date(s).map_err(|_| {
+            format_err!(
+                "Invalid expiration `{}`; expected a date (eg. `2020-01-01`) or a duration (eg. `90d`)",
+                s
+            )
+        })?,
+    };
+    if date <= now {
+        bail!("Expiration `{}` is not in the future", s);
+    }
+    Ok(date)
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct FetchCommon {
+    /// Clone proof repositories with full history, instead of only the latest commit
+    #[structopt(long = "full")]
+    pub full: bool,
+
+    /// Check signatures of fetched proofs, and quarantine files with invalid ones
+    #[structopt(long = "verify-signatures")]
+    pub verify_signatures: bool,
+
+    /// Skip proof repositories whose recent commits are not signed by their owner
+    #[structopt(long = "require-signed-commits")]
+    pub require_signed_commits: bool,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct Publish {
+    /// Sign the auto-commit with the current Id (always on with `sign-commits` in the config)
+    #[structopt(long = "sign-commits")]
+    pub sign_commits: bool,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct FetchTrusted {
+    #[structopt(flatten)]
+    pub trust_params: TrustDistanceParams,
+
+    #[structopt(flatten)]
+    pub common: FetchCommon,
+}
+
 #[derive(Debug, StructOpt, Clone)]
 pub struct FetchUrl {
-    /// URL to public proof repository
+    /// URL to public proof repository: a git repository, a static HTTPS directory with a
+    /// `crev-manifest.txt` listing its proof files, or a single `.crev` file
     pub url: String,
+
+    #[structopt(flatten)]
+    pub common: FetchCommon,
 }
 
 #[derive(Debug, StructOpt, Clone)]
 pub enum Fetch {
     #[structopt(name = "trusted")]
     /// Fetch updates from trusted Ids
-    Trusted(TrustDistanceParams),
+    Trusted(FetchTrusted),
 
     #[structopt(name = "url")]
     /// Fetch from a single public proof repository
@@ -178,7 +597,17 @@ pub enum Fetch {
 
     #[structopt(name = "all")]
     /// Fetch all previously retrieved public proof repositories
-    All,
+    All(FetchAll),
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct FetchAll {
+    /// Fetch only repositories of Ids that reviewed a given crate
+    #[structopt(long = "only-for-crate")]
+    pub only_for_crate: Option<String>,
+
+    #[structopt(flatten)]
+    pub common: FetchCommon,
 }
 
 #[derive(Debug, StructOpt, Clone)]
@@ -203,6 +632,10 @@ pub enum QueryId {
 
         #[structopt(long = "for-id")]
         for_id: Option<String>,
+
+        /// Print as JSON, with trust distance, the trust chain and review count of every Id
+        #[structopt(long = "json")]
+        json: bool,
     },
 }
 
@@ -210,6 +643,103 @@ pub enum QueryId {
 pub struct QueryReview {
     #[structopt(flatten)]
     pub crate_: CrateSelector,
+
+    /// Only reviews created by the current Id
+    #[structopt(long = "by-me")]
+    pub by_me: bool,
+
+    /// Only reviews created since given ISO 8601 date (eg. `2019-07-01`)
+    #[structopt(long = "since", parse(try_from_str = "parse_date"))]
+    pub since: Option<crev_data::proof::Date>,
+
+    /// Only reviews created before given ISO 8601 date (eg. `2019-08-01`)
+    #[structopt(long = "until", parse(try_from_str = "parse_date"))]
+    pub until: Option<crev_data::proof::Date>,
+
+    /// Only reviews of exactly this content, by its base64 digest, whatever crate it claims to be
+    #[structopt(long = "digest", parse(try_from_str = "parse_digest"))]
+    pub digest: Option<crev_data::Digest>,
+
+    /// Like `--digest`, with the digest of this directory (ignoring the same files as for crates)
+    #[structopt(long = "path", parse(from_os_str), conflicts_with = "digest")]
+    pub path: Option<PathBuf>,
+
+    /// Sort by [date trust reviewer]; the newest and most trusted first
+    #[structopt(long = "sort", default_value = "date")]
+    pub sort: ReviewSort,
+
+    /// Reverse the order
+    #[structopt(long = "reverse")]
+    pub reverse: bool,
+
+    /// Output format [text json markdown]
+    #[structopt(long = "format", default_value = "text")]
+    pub format: OutputFormat,
+}
+
+/// Order of the `query review` output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewSort {
+    Date,
+    Trust,
+    Reviewer,
+}
+
+impl FromStr for ReviewSort {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "date" => ReviewSort::Date,
+            "trust" => ReviewSort::Trust,
+            "reviewer" => ReviewSort::Reviewer,
+            _ => bail!("Unknown sort order `{}`", s),
+        })
+    }
+}
+
+fn parse_digest(s: &str) -> Result<crev_data::Digest, failure::Error> {
+    let bytes = crev_common::base64_decode(s)?;
+    if bytes.len() != 32 {
+        bail!("Invalid digest `{}`; expected 32 bytes", s);
+    }
+    Ok(crev_data::Digest::from_vec(bytes))
+}
+
+fn parse_date(s: &str) -> Result<crev_data::proof::Date, failure::Error> {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    if let Ok(date) = crev_common::parse_rfc3339_lenient(s) {
+        return Ok(date);
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| format_err!("Invalid date `{}`; expected eg. `2019-07-01`", s))?;
+    Ok(Utc.from_utc_date(&date).and_hms(0, 0, 0).into())
+}
+
+/// Format of machine-readable output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    /// A table to paste eg. in a pull request
+    Markdown,
+}
+
+impl FromStr for OutputFormat {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "text" => OutputFormat::Text,
+            "json" => OutputFormat::Json,
+            "markdown" | "md" => OutputFormat::Markdown,
+            _ => bail!(
+                "Unknown format `{}`; expected `text`, `json` or `markdown`",
+                s
+            ),
+        })
+    }
 }
 
 #[derive(Debug, StructOpt, Clone)]
@@ -272,7 +802,7 @@ pub enum Id {
 
     /// Import an Id as your own
     #[structopt(name = "import")]
-    Import,
+    Import(ImportId),
 
     /// Show your own Id
     #[structopt(name = "show")]
@@ -281,6 +811,39 @@ pub enum Id {
     /// Change current Id
     #[structopt(name = "switch")]
     Switch(SwitchId),
+
+    /// Check signatures of proofs in a file
+    #[structopt(name = "verify-signature")]
+    VerifySignature(VerifySignature),
+
+    /// Change the URL of the public proof repository of the current Id
+    #[structopt(name = "set-url")]
+    SetUrl(SetUrl),
+
+    /// Re-encrypt the current Id with a new passphrase (the Id itself doesn't change)
+    #[structopt(name = "rotate-passphrase")]
+    RotatePassphrase,
+
+    /// List your own trust proofs that have expired, or will soon
+    #[structopt(name = "expired")]
+    Expired(ExpiredId),
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExpiredId {
+    /// Also list trust proofs expiring within this many days
+    #[structopt(long = "within", value_name = "days", default_value = "0")]
+    pub within: i64,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct SetUrl {
+    /// New URL of the public proof repository
+    pub url: String,
+
+    /// Move the existing local proof repository, instead of cloning the new URL
+    #[structopt(long = "migrate-proofs")]
+    pub migrate_proofs: bool,
 }
 
 #[derive(Debug, StructOpt, Clone)]
@@ -298,6 +861,13 @@ pub enum Edit {
     Known,
 }
 
+#[derive(Debug, StructOpt, Clone)]
+pub enum Config {
+    /// Edit the defaults of command line flags (`cli.yaml`)
+    #[structopt(name = "edit")]
+    Edit,
+}
+
 #[derive(Debug, StructOpt, Clone)]
 pub struct Git {
     /// Arguments to the `git` command
@@ -329,6 +899,16 @@ pub struct Open {
     pub common: ReviewOrGotoCommon,
 }
 
+#[derive(Debug, StructOpt, Clone)]
+pub struct Clean {
+    /// Clean all dependencies with a locally modified source (after a confirmation)
+    #[structopt(long = "all-dirty")]
+    pub all_dirty: bool,
+
+    #[structopt(flatten)]
+    pub common: ReviewOrGotoCommon,
+}
+
 #[derive(Debug, StructOpt, Clone)]
 pub struct CommonProofCreate {
     /// Don't auto-commit local Proof Repository
@@ -346,6 +926,10 @@ pub struct CommonProofCreate {
     /// Don't store the proof
     #[structopt(long = "no-store")]
     pub no_store: bool,
+
+    /// Sign the commit with the current Id (always on with `sign-commits` in the config)
+    #[structopt(long = "sign-commits")]
+    pub sign_commits: bool,
 }
 
 #[derive(Debug, StructOpt, Clone)]
@@ -360,14 +944,18 @@ pub struct Review {
     #[structopt(long = "advisory")]
     pub advisory: bool,
 
-    /// This release contains advisory (important fix)
+    /// Affected versions: [all major minor] relative to this release, or a semver range (eg. "< 0.3.5")
     #[structopt(long = "affected")]
-    pub affected: Option<crev_data::proof::review::package::VersionRange>,
+    pub affected: Option<Affected>,
 
     /// Severity of bug/security issue [none low medium high]
     #[structopt(long = "severity")]
     pub severity: Option<Level>,
 
+    /// Id of the advisory or issue (eg. RUSTSEC-2019-0001)
+    #[structopt(long = "advisory-id", alias = "id")]
+    pub id: Option<String>,
+
     /// Flag the crate as buggy/low-quality/dangerous
     #[structopt(long = "issue")]
     pub issue: bool,
@@ -378,13 +966,174 @@ pub struct Review {
     #[structopt(long = "diff")]
     #[allow(clippy::option_option)]
     pub diff: Option<Option<semver::Version>>,
+
+    /// Print the report on files that differ from a fresh copy as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    /// Don't re-download the crate to check the reviewed copy wasn't modified
+    #[structopt(long = "no-fetch", raw(aliases = r#"&["trust-local"]"#))]
+    pub no_fetch: bool,
+
+    /// Confirm that skipping the integrity check with `--no-fetch` is intended
+    #[structopt(long = "i-know-what-im-doing")]
+    pub i_know_what_im_doing: bool,
+
+    /// Record the review session with `asciinema` to the given file
+    #[structopt(long = "record-screen", parse(from_os_str))]
+    pub record_screen: Option<PathBuf>,
+
+    /// Go through all dependencies that don't pass verification yet, smallest first
+    #[structopt(long = "all-unreviewed")]
+    pub all_unreviewed: bool,
+
+    // Which dependencies pass verification, for `--all-unreviewed` and `--interactive`
+    #[structopt(flatten)]
+    pub trust_params: TrustDistanceParams,
+
+    #[structopt(flatten)]
+    pub requirements: VerificationRequirements,
+
+    /// Pick dependencies to review from a list, least trusted first
+    #[structopt(long = "interactive", short = "i")]
+    pub interactive: bool,
+
+    /// Review the crate in the current directory, extracted by cargo in its registry,
+    /// checking it against the downloaded tarball (the default when no crate name is given)
+    #[structopt(long = "here")]
+    pub here: bool,
+
+    #[structopt(flatten)]
+    pub content: ReviewContent,
+}
+
+/// Review fields that can be given directly on the command line
+#[derive(Debug, StructOpt, Clone, Default)]
+pub struct ReviewContent {
+    /// Don't open the editor; create the review from command line arguments only
+    #[structopt(long = "no-editor")]
+    pub no_editor: bool,
+
+    /// Rating of the review [strong positive neutral negative dangerous]
+    #[structopt(long = "rating")]
+    pub rating: Option<Rating>,
+
+    /// Review comment
+    #[structopt(long = "comment")]
+    pub comment: Option<String>,
+
+    /// Report an issue as `<id>[:<severity>]` (can be used multiple times)
+    #[structopt(long = "issues")]
+    pub issues: Vec<IssueArg>,
+
+    /// Review only these files (relative to the crate root), instead of the whole crate
+    #[structopt(long = "files", parse(from_os_str))]
+    pub files: Vec<PathBuf>,
+
+    /// Attach a log of building the crate (eg. `cargo build -vv` output) to the review
+    #[structopt(long = "cargo-build-log", parse(from_os_str))]
+    pub build_log: Option<PathBuf>,
+
+    /// Record the time from `cargo crev open` until the proof is signed as the duration of the review
+    #[structopt(long = "timed")]
+    pub timed: bool,
+
+    /// Record the duration of the review, in minutes
+    #[structopt(long = "duration", conflicts_with = "timed")]
+    pub duration: Option<u64>,
+
+    /// Start from the comment, rating and issues of your review of this other version
+    #[structopt(long = "inherit-from")]
+    pub inherit_from: Option<Version>,
+}
+
+/// Issue given on the command line as `<id>[:<severity>]`
+#[derive(Debug, Clone)]
+pub struct IssueArg {
+    pub id: String,
+    pub severity: Level,
+}
+
+impl FromStr for IssueArg {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, severity) = match s.rfind(':') {
+            Some(i) => (
+                &s[..i],
+                s[i + 1..]
+                    .parse()
+                    .map_err(|_| format_err!("Invalid issue severity in `{}`", s))?,
+            ),
+            None => (s, Level::default()),
+        };
+        if id.is_empty() {
+            return Err(format_err!("Issue id can't be empty"));
+        }
+        Ok(IssueArg {
+            id: id.to_owned(),
+            severity,
+        })
+    }
+}
+
+/// Versions affected by an advisory or issue, given on the command line
+#[derive(Debug, Clone)]
+pub enum Affected {
+    /// Relative to the reviewed version
+    Range(VersionRange),
+    /// Exact semver requirement
+    Versions(VersionReq),
+}
+
+impl FromStr for Affected {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(range) = s.parse() {
+            return Ok(Affected::Range(range));
+        }
+        Ok(Affected::Versions(VersionReq::parse(s).map_err(|e| {
+            format_err!(
+                "Invalid affected versions `{}`: expected `all`, `major`, `minor` or a semver range ({})",
+                s,
+                e
+            )
+        })?))
+    }
+}
+
+impl Affected {
+    pub fn range(&self) -> Option<VersionRange> {
+        match self {
+            Affected::Range(range) => Some(*range),
+            Affected::Versions(_) => None,
+        }
+    }
+
+    pub fn versions(&self) -> Option<VersionReq> {
+        match self {
+            Affected::Range(_) => None,
+            Affected::Versions(req) => Some(req.clone()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct AdviseCommon {
     /// This release contains advisory (important fix)
-    pub affected: crev_data::proof::review::package::VersionRange,
+    pub affected: VersionRange,
+    pub affected_versions: Option<VersionReq>,
     pub severity: Level,
+    pub id: Option<String>,
+}
+
+/// Issue reported with `--issue`
+#[derive(Debug, Clone, Default)]
+pub struct IssueCommon {
+    pub severity: Level,
+    pub affected_versions: Option<VersionReq>,
+    pub id: Option<String>,
 }
 
 #[derive(Debug, StructOpt, Clone)]
@@ -392,6 +1141,13 @@ pub struct ExportId {
     pub id: Option<String>,
 }
 
+#[derive(Debug, StructOpt, Clone)]
+pub struct ImportId {
+    /// Key file of the Id (as in the `ids` config directory); read from stdin if not given
+    #[structopt(parse(from_os_str))]
+    pub key_file: Option<PathBuf>,
+}
+
 #[derive(Debug, StructOpt, Clone)]
 pub enum Import {
     /// Import proofs: resign proofs using current id
@@ -401,6 +1157,121 @@ pub enum Import {
     Proof(ImportProof),
 }
 
+#[derive(Debug, StructOpt, Clone)]
+pub enum Repo {
+    /// Check that all proofs in the local proof repository parse and have valid signatures
+    #[structopt(name = "verify")]
+    Verify(RepoVerify),
+
+    /// Import signed proofs as they are, without resigning
+    ///
+    /// Useful for proofs created with your Id on another machine,
+    /// eg. with `export proofs`
+    #[structopt(name = "import")]
+    Import(RepoImport),
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct RepoVerify {
+    /// Move broken proof files into the `.broken` directory of the proof repository
+    #[structopt(long = "fix")]
+    pub fix: bool,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub enum Crate {
+    /// Check that a crate published on crates.io matches the tagged source in its git repository
+    #[structopt(name = "verify-git", alias = "verify-provenance")]
+    VerifyGit(CrateVerifyGit),
+
+    /// Diff between two versions of a crate
+    #[structopt(name = "diff")]
+    Diff(Diff),
+
+    /// Print the digest of a crate's source, as recorded in reviews of it
+    #[structopt(name = "digest")]
+    Digest(CrateDigest),
+
+    /// Download a crate (even if it's not a dependency), print its path and open it for inspection
+    #[structopt(name = "open")]
+    Open(CrateOpen),
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct CrateOpen {
+    /// Name of the crate
+    pub name: String,
+
+    /// Version of the crate (the latest one by default)
+    pub version: Option<Version>,
+
+    /// Program to open the crate directory with, eg. `code`, `vim` or `ranger` [env: CREV_OPEN_CMD]
+    #[structopt(long = "cmd")]
+    pub cmd: Option<String>,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct CrateDigest {
+    /// Name of the crate
+    pub name: String,
+
+    /// Version of the crate (the one in the dependencies, or the latest one with `--independent`)
+    #[structopt(long = "version")]
+    pub version: Option<Version>,
+
+    /// Download the crate from crates.io, instead of using the one in the dependencies
+    #[structopt(long = "independent", alias = "unrelated", short = "u")]
+    pub independent: bool,
+
+    /// Fail unless the digest is this one (base64)
+    #[structopt(long = "verify", parse(try_from_str = "parse_digest"))]
+    pub verify: Option<crev_data::Digest>,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct CrateVerifyGit {
+    /// Name of the crate
+    pub name: String,
+
+    /// Version of the crate (the latest one by default)
+    #[structopt(long = "version")]
+    pub version: Option<Version>,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub enum Export {
+    /// Export your own proofs into a single file
+    ///
+    /// The output can be imported elsewhere with `repo import`
+    #[structopt(name = "proofs")]
+    Proofs(ExportProofs),
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportProofs {
+    /// File to write proofs to (`-` for stdout)
+    #[structopt(parse(from_os_str), default_value = "-")]
+    pub path: PathBuf,
+
+    /// Only proofs created since given ISO 8601 date (eg. `2019-07-01`)
+    #[structopt(long = "since", parse(try_from_str = "parse_date"))]
+    pub since: Option<crev_data::proof::Date>,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct RepoImport {
+    /// File to read proofs from (`-` for stdin)
+    #[structopt(parse(from_os_str), default_value = "-")]
+    pub path: PathBuf,
+
+    /// Accept proofs signed by Ids other than your own
+    #[structopt(long = "allow-foreign")]
+    pub allow_foreign: bool,
+
+    #[structopt(flatten)]
+    pub common: CommonProofCreate,
+}
+
 #[derive(Debug, StructOpt, Clone)]
 pub struct ImportProof {
     /// Reset proof date to current date
@@ -411,6 +1282,41 @@ pub struct ImportProof {
     pub common: CommonProofCreate,
 }
 
+#[derive(Debug, StructOpt, Clone)]
+pub enum Note {
+    /// Add a private note about a crate
+    #[structopt(name = "add")]
+    #[structopt(raw(setting = "structopt::clap::AppSettings::DisableVersion"))]
+    Add(NoteAdd),
+
+    /// Show private notes about a crate
+    #[structopt(name = "show")]
+    Show(NoteShow),
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct NoteAdd {
+    /// Crate name
+    pub name: String,
+
+    /// Crate version the note is about, also recording the digest of its source [default: all versions]
+    #[structopt(long = "version")]
+    pub version: Option<Version>,
+
+    /// Text of the note
+    pub text: String,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct NoteShow {
+    /// Crate name
+    pub name: String,
+
+    /// Show only notes applying to this version, and check if they were written about its current source
+    #[structopt(long = "version")]
+    pub version: Option<Version>,
+}
+
 #[derive(Debug, StructOpt, Clone)]
 pub enum Command {
     /// Manage your own Id (create new, show, export, import, switch)
@@ -421,6 +1327,10 @@ pub enum Command {
     #[structopt(name = "edit")]
     Edit(Edit),
 
+    /// Set defaults of command line flags
+    #[structopt(name = "config")]
+    Config(Config),
+
     /// Verify dependencies
     #[structopt(
         name = "verify",
@@ -437,7 +1347,14 @@ pub enum Command {
   - CB         - Custom Build
 - name       - Crate name
 - version    - Crate version
-- latest_t   - Latest trusted version"
+- latest_t   - Latest trusted version
+
+Crates that are not verified, but have some files reviewed by trusted code reviews (`review --files`),
+are marked with `[P <reviewed files>/<all files>]`.
+
+The risk score of a crate is computed from its lines of code, build script, reviews, issues
+and downloads. Use `--top N` to list the riskiest crates, and `--risk-model <file>` to change
+the weights (see `risk_model_defaults.toml` in the cargo-crev sources)."
     )]
     Verify(Verify),
 
@@ -469,7 +1386,7 @@ pub enum Command {
 
     /// Commit and Push local changes to the public proof repository (alias to `git commit -a && git push HEAD`)
     #[structopt(name = "publish", alias = "push")]
-    Publish,
+    Publish(Publish),
 
     /// Start a shell in source directory of a crate under review
     #[structopt(name = "goto")]
@@ -481,12 +1398,16 @@ pub enum Command {
 
     /// Clean a crate source code (eg. after review)
     #[structopt(name = "clean")]
-    Clean(ReviewOrGotoCommon),
+    Clean(Clean),
 
     /// Import proofs, ...
     #[structopt(name = "import")]
     Import(Import),
 
+    /// Export proofs, ...
+    #[structopt(name = "export")]
+    Export(Export),
+
     /// Update data from online sources (proof repositories, crates.io)
     #[structopt(name = "update", alias = "pull")]
     Update,
@@ -496,6 +1417,87 @@ pub enum Command {
     #[structopt(raw(setting = "structopt::clap::AppSettings::TrailingVarArg"))]
     #[structopt(raw(setting = "structopt::clap::AppSettings::AllowLeadingHyphen"))]
     Diff(Diff),
+
+    /// Keep private notes about crates (never published)
+    #[structopt(name = "note")]
+    Note(Note),
+
+    /// Maintain the local proof repository
+    #[structopt(name = "repo")]
+    Repo(Repo),
+
+    /// Inspect published crates
+    #[structopt(name = "crate")]
+    Crate(Crate),
+
+    /// Show how a dependency is pulled into the workspace
+    #[structopt(name = "why")]
+    Why(Why),
+
+    /// List dependencies with RustSec advisories, along their verification status
+    #[structopt(name = "audit")]
+    Audit(Audit),
+
+    /// Print a shell completion script to stdout
+    #[structopt(name = "completions")]
+    Completions(Completions),
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct Why {
+    /// Name of the dependency
+    pub name: String,
+
+    #[structopt(long = "version")]
+    /// Version of the dependency, if there are more than one
+    pub version: Option<Version>,
+
+    #[structopt(long = "max-paths", default_value = "20")]
+    /// Show at most this many paths to the dependency
+    pub max_paths: usize,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct Audit {
+    #[structopt(long = "db-path", parse(from_os_str))]
+    /// Checkout of the advisory database to use [default: a clone in the crev cache]
+    pub db_path: Option<PathBuf>,
+
+    #[structopt(long = "no-fetch")]
+    /// Don't update the cached clone of the advisory database
+    pub no_fetch: bool,
+
+    #[structopt(flatten)]
+    pub trust_params: TrustDistanceParams,
+
+    #[structopt(flatten)]
+    pub requirements: VerificationRequirements,
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct Completions {
+    /// Shell to generate the completion script for
+    #[structopt(raw(possible_values = "&structopt::clap::Shell::variants()"))]
+    pub shell: structopt::clap::Shell,
+}
+
+const BIN_NAME: &str = "cargo-crev";
+
+/// Write the completion script of `cargo-crev` for `shell`
+pub fn gen_completions(shell: structopt::clap::Shell, out: &mut impl std::io::Write) {
+    use structopt::clap::Shell;
+
+    // Generated for our own binary, so installing it doesn't replace
+    // the completion script of `cargo` itself
+    match shell {
+        // clap's zsh generator recurses forever on the `id`/`new` aliases,
+        // so zsh gets the bash script through its bash compatibility mode
+        Shell::Zsh => {
+            let _ = writeln!(out, "autoload -U +X bashcompinit && bashcompinit");
+            Opts::clap().gen_completions_to(BIN_NAME, Shell::Bash, out);
+        }
+        shell => Opts::clap().gen_completions_to(BIN_NAME, shell, out),
+    }
 }
 
 /// Cargo will pass the name of the `cargo-<tool>`
@@ -511,6 +1513,10 @@ pub enum MainCommand {
 pub struct Opts {
     #[structopt(subcommand)]
     pub command: MainCommand,
+
+    /// Use this Id (or a unique prefix of it) instead of the current one
+    #[structopt(name = "crev-id", long = "id", raw(global = "true"))]
+    pub id: Option<String>,
     //    #[structopt(flatten)]
     //    verbosity: Verbosity,
 }
@@ -0,0 +1,152 @@
+//! Team trust policy, from `crev-policy.toml` in the project root
+//!
+//! Lets an organization distribute the verification requirements and
+//! the Ids trusted by everyone working on a project. Flags given on the
+//! command line take precedence over the policy, which takes precedence
+//! over the user's `cli.yaml`.
+//!
+//! Only the directories of the project are searched for a policy, up to
+//! its workspace root, so a policy file in a parent directory (eg. the
+//! home directory) or in a downloaded crate can't add trusted Ids.
+
+use crate::cli_config::CliConfig;
+use crate::opts;
+use crate::prelude::*;
+use crate::repo::Repo;
+use crev_data::{proof::trust::TrustLevel, Id, Level};
+use crev_lib::proofdb::{ProofDB, TrustSet};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use structopt::clap::ArgMatches;
+
+pub const POLICY_FILE_NAME: &str = "crev-policy.toml";
+
+/// Id trusted by everyone following the policy
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PinnedId {
+    pub id: String,
+    pub level: TrustLevel,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct Policy {
+    pub trust: Option<Level>,
+    pub redundancy: Option<u64>,
+    pub understanding: Option<Level>,
+    pub thoroughness: Option<Level>,
+    #[serde(alias = "fail-on")]
+    pub deny_severity: Option<Level>,
+    pub trusted_ids: Vec<PinnedId>,
+}
+
+impl Policy {
+    /// Policy file in `dir` or the closest of its parents, up to `root`
+    pub fn find(dir: &Path, root: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .take_while(|dir| dir.starts_with(root))
+            .map(|dir| dir.join(POLICY_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let policy: Policy = toml::from_str(&crev_common::read_file_to_string(path)?)
+            .map_err(|e| format_err!("Invalid policy file {}: {}", path.display(), e))?;
+        policy
+            .validate()
+            .map_err(|e| format_err!("Invalid policy file {}: {}", path.display(), e))?;
+        Ok(policy)
+    }
+
+    /// Policy of the project in the current directory, if there's any
+    ///
+    /// Crates downloaded by cargo are not projects with a policy.
+    pub fn load_from_cwd() -> Result<Option<Self>> {
+        let cwd = std::env::current_dir()?;
+        let repo = match Repo::auto_open_cwd_offline(true) {
+            Ok(repo) => repo,
+            Err(_) => return Ok(None),
+        };
+        if cwd.starts_with(repo.cargo_home()) {
+            return Ok(None);
+        }
+        Self::find(&cwd, &repo.workspace_root()?)
+            .map(|path| Self::load(&path))
+            .transpose()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.pinned_ids()?;
+        Ok(())
+    }
+
+    fn pinned_ids(&self) -> Result<Vec<(Id, TrustLevel)>> {
+        self.trusted_ids
+            .iter()
+            .map(|pinned| {
+                let id = Id::crevid_from_str(&pinned.id)
+                    .map_err(|e| format_err!("Invalid trusted id `{}`: {}", pinned.id, e))?;
+                Ok((id, pinned.level))
+            })
+            .collect()
+    }
+
+    /// Trusted Ids of the policy, all of which must be known to `db`
+    pub fn trust_roots(&self, db: &ProofDB) -> Result<Vec<(Id, TrustLevel)>> {
+        let roots = self.pinned_ids()?;
+        for (id, _) in &roots {
+            if db.lookup_url(id).is_none() {
+                bail!(
+                    "Trusted id {} of the policy is unknown; \
+                     fetch its proofs with `cargo crev repo fetch url <url>` first",
+                    id
+                );
+            }
+        }
+        Ok(roots)
+    }
+
+    fn cli_config(&self) -> CliConfig {
+        CliConfig {
+            trust: self.trust,
+            redundancy: self.redundancy,
+            understanding: self.understanding,
+            thoroughness: self.thoroughness,
+            deny_severity: self.deny_severity,
+            ..Default::default()
+        }
+    }
+
+    /// Fill the flags of `command` that weren't given in `matches`
+    pub fn apply(&self, command: &mut opts::Command, matches: &ArgMatches<'_>) {
+        self.cli_config().apply(command, matches);
+    }
+}
+
+/// Apply the policy of the project in the current directory, if there's any
+///
+/// `config` commands are left alone, so a broken policy doesn't stand in
+/// the way of fixing the rest of the configuration.
+pub fn apply_project_policy(command: &mut opts::Command, matches: &ArgMatches<'_>) -> Result<()> {
+    if let opts::Command::Config(_) = command {
+        return Ok(());
+    }
+    if let Some(policy) = Policy::load_from_cwd()? {
+        policy.apply(command, matches);
+    }
+    Ok(())
+}
+
+/// Trust set of `for_id`, including the trusted Ids of the project policy
+pub fn calculate_trust_set(
+    db: &ProofDB,
+    for_id: &Id,
+    params: &crev_lib::TrustDistanceParams,
+) -> Result<TrustSet> {
+    let roots = match Policy::load_from_cwd()? {
+        Some(policy) => policy.trust_roots(db)?,
+        None => vec![],
+    };
+    Ok(db.calculate_trust_set_with_roots(for_id, &roots, params))
+}
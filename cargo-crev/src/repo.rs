@@ -3,14 +3,21 @@ use cargo::{
         dependency::Dependency, package::PackageSet, source::SourceMap, Package, PackageId,
         SourceId,
     },
-    util::important_paths::find_root_manifest_for_wd,
+    util::{important_paths::find_root_manifest_for_wd, ToUrl},
 };
 use crev_common::convert::OptionDeref;
 use crev_lib;
-use failure::format_err;
-use std::{collections::HashSet, env, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::PathBuf,
+};
 
 use crate::crates_io;
+use crate::deps::{
+    all_dependency_paths, classify_dependencies, packages_in_scope, shortest_dependency_paths,
+    DependencyKind, DependencyPaths, DepsScope,
+};
 use crate::prelude::*;
 use crate::shared::*;
 
@@ -18,14 +25,34 @@ use crate::shared::*;
 pub struct Repo {
     manifest_path: PathBuf,
     config: cargo::util::config::Config,
+    /// Index URL of the registry to use instead of crates.io
+    registry: Option<String>,
 }
 
 impl Repo {
     pub fn auto_open_cwd() -> Result<Self> {
-        cargo::core::enable_nightly_features();
+        Self::auto_open_cwd_offline(false)
+    }
+
+    /// Like `auto_open_cwd`, but with cargo's network access disabled
+    /// when `offline` is set
+    pub fn auto_open_cwd_offline(offline: bool) -> Result<Self> {
         let cwd = env::current_dir()?;
         let manifest_path = find_root_manifest_for_wd(&cwd)?;
-        let mut config = cargo::util::config::Config::default()?;
+        Self::open_with_config(
+            manifest_path,
+            cargo::util::config::Config::default()?,
+            offline,
+        )
+    }
+
+    /// Open the project of `manifest_path`, with the cargo config `config`
+    pub fn open_with_config(
+        manifest_path: PathBuf,
+        mut config: cargo::util::config::Config,
+        offline: bool,
+    ) -> Result<Self> {
+        cargo::core::enable_nightly_features();
         config.configure(
             0,
             None,
@@ -33,14 +60,55 @@ impl Repo {
             /* frozen: */ false,
             /* locked: */ true,
             &None,
-            &[],
+            &if offline {
+                vec!["offline".to_owned()]
+            } else {
+                vec![]
+            },
         )?;
+        // `crev.registry` in cargo config, or `CARGO_CREV_REGISTRY` env
+        let registry = config.get_string("crev.registry")?.map(|v| v.val);
         Ok(Repo {
             manifest_path,
             config,
+            registry,
         })
     }
 
+    /// Source id of the registry crates are downloaded from
+    pub fn registry_source_id(&self) -> Result<SourceId> {
+        Ok(match self.registry {
+            Some(ref url) => SourceId::for_registry(&url.as_str().to_url()?)?,
+            None => SourceId::crates_io(&self.config)?,
+        })
+    }
+
+    /// Value of `source` used in proofs for crates of the registry
+    pub fn registry_source(&self) -> &str {
+        self.registry.as_deref().unwrap_or(PROJECT_SOURCE_CRATES_IO)
+    }
+
+    /// Is network access disabled, either explicitly or by cargo's
+    /// `net.offline` config
+    pub fn is_offline(&self) -> Result<bool> {
+        Ok(!self.config.network_allowed()
+            || self
+                .config
+                .get_bool("net.offline")?
+                .map_or(false, |v| v.val))
+    }
+
+    /// Cargo home directory, with the registry and the crates downloaded from it
+    pub fn cargo_home(&self) -> PathBuf {
+        self.config.home().clone().into_path_unlocked()
+    }
+
+    /// Root directory of the workspace the package belongs to
+    pub fn workspace_root(&self) -> Result<PathBuf> {
+        let workspace = cargo::core::Workspace::new(&self.manifest_path, &self.config)?;
+        Ok(workspace.root().to_owned())
+    }
+
     pub fn name(&self) -> std::borrow::Cow<'_, str> {
         self.manifest_path
             .parent()
@@ -58,7 +126,7 @@ impl Repo {
 
     pub fn update_counts(&self) -> Result<()> {
         let local = crev_lib::Local::auto_create_or_open()?;
-        let crates_io = crates_io::Client::new(&local)?;
+        let crates_io = crates_io::Client::new(&local, self.registry_source(), false)?;
 
         self.for_every_non_local_dep_crate(|crate_| {
             let _ = crates_io.get_downloads_count(&crate_.name(), &crate_.version());
@@ -69,7 +137,7 @@ impl Repo {
     }
 
     pub fn load_source<'a>(&'a self) -> Result<Box<dyn cargo::core::source::Source + 'a>> {
-        let source_id = SourceId::crates_io(&self.config)?;
+        let source_id = self.registry_source_id()?;
         let map = cargo::sources::SourceConfigMap::new(&self.config)?;
         let yanked_whitelist = HashSet::new();
         let source = map.load(source_id, &yanked_whitelist)?;
@@ -80,7 +148,7 @@ impl Repo {
         &'a self,
         yanked_whitelist: HashSet<PackageId>,
     ) -> Result<Box<dyn cargo::core::source::Source + 'a>> {
-        let source_id = SourceId::crates_io(&self.config)?;
+        let source_id = self.registry_source_id()?;
         let map = cargo::sources::SourceConfigMap::new(&self.config)?;
         let source = map.load(source_id, &yanked_whitelist)?;
         Ok(source)
@@ -121,10 +189,59 @@ impl Repo {
         Ok(())
     }
 
-    pub fn get_deps_package_set(&self) -> Result<PackageSet<'_>> {
+    /// Packages listed in `Cargo.lock` as of git revision `git_ref`
+    pub fn get_locked_packages_at(&self, git_ref: &str) -> Result<HashSet<(String, Version)>> {
+        let root_dir = self
+            .manifest_path
+            .parent()
+            .expect("manifest path has a parent dir")
+            .canonicalize()?;
+        let git_repo = git2::Repository::discover(&root_dir)?;
+        let workdir = git_repo
+            .workdir()
+            .ok_or_else(|| format_err!("{} is a bare git repository", root_dir.display()))?
+            .canonicalize()?;
+        let lock_path = root_dir.strip_prefix(&workdir)?.join("Cargo.lock");
+        let lock_blob = git_repo
+            .revparse_single(git_ref)
+            .and_then(|object| object.peel_to_tree())
+            .and_then(|tree| tree.get_path(&lock_path))
+            .and_then(|entry| entry.to_object(&git_repo))
+            .and_then(|object| object.peel_to_blob())
+            .map_err(|e| format_err!("Could not read `Cargo.lock` at `{}`: {}", git_ref, e))?;
+        let lock: toml::Value = toml::from_str(std::str::from_utf8(lock_blob.content())?)?;
+
+        let mut packages = HashSet::new();
+        for package in lock
+            .get("package")
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let name = package.get("name").and_then(toml::Value::as_str);
+            let version = package.get("version").and_then(toml::Value::as_str);
+            if let (Some(name), Some(version)) = (name, version) {
+                packages.insert((name.to_owned(), Version::parse(version)?));
+            }
+        }
+        Ok(packages)
+    }
+
+    /// All the packages of the workspace, with the kinds of dependency they are
+    /// and the shortest paths to them from the workspace members
+    ///
+    /// Only the packages in `scope` have a kind.
+    pub fn get_deps_package_set(
+        &self,
+        scope: DepsScope,
+    ) -> Result<(
+        PackageSet<'_>,
+        HashMap<PackageId, DependencyKind>,
+        DependencyPaths,
+    )> {
         let workspace = cargo::core::Workspace::new(&self.manifest_path, &self.config)?;
         let specs = cargo::ops::Packages::All.to_package_id_specs(&workspace)?;
-        let (package_set, _resolve) = cargo::ops::resolve_ws_precisely(
+        let (package_set, resolve) = cargo::ops::resolve_ws_precisely(
             &workspace,
             None,
             &[],
@@ -132,7 +249,65 @@ impl Repo {
             false, // no_default_features
             &specs,
         )?;
-        Ok(package_set)
+        let members: Vec<_> = workspace.members().map(Package::package_id).collect();
+        let mut kinds = classify_dependencies(&members, |pkg_id| {
+            resolve
+                .deps(pkg_id)
+                .map(|(dep_id, deps)| (dep_id, deps.iter().map(Dependency::kind).collect()))
+                .collect::<Vec<_>>()
+        });
+        let paths = shortest_dependency_paths(&members, |pkg_id| {
+            resolve.deps(pkg_id).map(|(dep_id, _)| dep_id)
+        });
+
+        let current = workspace.current_opt().map(Package::package_id);
+        if let Some(in_scope) = packages_in_scope(scope, &members, current, |pkg_id| {
+            resolve.deps(pkg_id).map(|(dep_id, _)| dep_id)
+        }) {
+            kinds.retain(|pkg_id, _| in_scope.contains(pkg_id));
+        }
+        Ok((package_set, kinds, paths))
+    }
+
+    /// Up to `max_paths` paths from a workspace member to the dependency `name`
+    pub fn find_dependency_paths(
+        &self,
+        name: &str,
+        version: Option<&Version>,
+        max_paths: usize,
+    ) -> Result<Vec<Vec<PackageId>>> {
+        let workspace = cargo::core::Workspace::new(&self.manifest_path, &self.config)?;
+        let specs = cargo::ops::Packages::All.to_package_id_specs(&workspace)?;
+        let (_package_set, resolve) = cargo::ops::resolve_ws_precisely(
+            &workspace,
+            None,
+            &[],
+            true,  // all_features
+            false, // no_default_features
+            &specs,
+        )?;
+        let matching: Vec<PackageId> = resolve
+            .iter()
+            .filter(|pkg_id| {
+                name == pkg_id.name().as_str()
+                    && (version.is_none() || version == Some(pkg_id.version()))
+            })
+            .collect();
+        let target = match matching.len() {
+            0 => bail!("{} is not a dependency", name),
+            1 => matching[0],
+            n => bail!(
+                "Ambiguous selection: {} matches found; use `--version` to pick one",
+                n
+            ),
+        };
+        let members: Vec<_> = workspace.members().map(Package::package_id).collect();
+        Ok(all_dependency_paths(
+            &members,
+            |pkg_id| resolve.deps(pkg_id).map(|(dep_id, _)| dep_id),
+            target,
+            max_paths,
+        ))
     }
 
     pub fn find_idependent_crate_dir(
@@ -143,7 +318,7 @@ impl Repo {
         let mut source = if let Some(version) = version {
             // special case - we need to whitelist the crate, in case it was yanked
             let mut yanked_whitelist = HashSet::default();
-            let source_id = SourceId::crates_io(&self.config)?;
+            let source_id = self.registry_source_id()?;
             yanked_whitelist.insert(PackageId::new(name, version, source_id)?);
             self.load_source_with_whitelist(yanked_whitelist)?
         } else {
@@ -180,6 +355,33 @@ impl Repo {
         Ok(Some(package_set.get_one(pkg_id)?.to_owned()))
     }
 
+    /// Checksum of the tarball of a crate, as given by the registry index
+    ///
+    /// The index is updated if it doesn't have the crate yet, unless offline.
+    pub fn registry_checksum(&self, name: &str, version: &Version) -> Result<Option<String>> {
+        let mut yanked_whitelist = HashSet::default();
+        yanked_whitelist.insert(PackageId::new(name, version, self.registry_source_id()?)?);
+        let mut source = self.load_source_with_whitelist(yanked_whitelist)?;
+        let version_str = version.to_string();
+        let dependency_request =
+            Dependency::parse_no_deprecated(name, Some(&version_str), source.source_id())?;
+        let query = |source: &mut dyn cargo::core::source::Source| -> Result<Option<String>> {
+            let mut checksum = None;
+            source.query(&dependency_request, &mut |summary| {
+                if summary.version() == version {
+                    checksum = summary.checksum().map(ToOwned::to_owned);
+                }
+            })?;
+            Ok(checksum)
+        };
+        let checksum = query(&mut *source)?;
+        if checksum.is_some() || self.is_offline()? {
+            return Ok(checksum);
+        }
+        source.update()?;
+        query(&mut *source)
+    }
+
     pub fn find_dependency(
         &self,
         name: &str,
@@ -210,11 +412,90 @@ impl Repo {
         version: Option<&Version>,
         unrelated: UnrelatedOrDependency,
     ) -> Result<Package> {
-        if unrelated.is_unrelated() {
+        let found = if unrelated.is_unrelated() {
             self.find_idependent_crate_dir(name, version)?
         } else {
             self.find_dependency(name, version)?
+        };
+        if let Some(pkg) = found {
+            return Ok(pkg);
+        }
+
+        if let Some(version) = version {
+            let mut versions = self.available_versions(name, unrelated);
+            if !versions.is_empty() {
+                versions.sort();
+                versions.dedup();
+                let versions: Vec<_> = versions
+                    .iter()
+                    .rev()
+                    .take(10)
+                    .map(ToString::to_string)
+                    .collect();
+                bail!(
+                    "Could not find requested crate {} {}; available versions: {}",
+                    name,
+                    version,
+                    versions.join(", ")
+                );
+            }
+        }
+
+        let suggestions = self.suggest_crate_names(name, unrelated);
+        if suggestions.is_empty() {
+            bail!("Could not find requested crate");
+        }
+        bail!(
+            "Could not find requested crate; did you mean {}?",
+            suggestions.join(", ")
+        )
+    }
+
+    /// Versions of the crate `name`, best effort
+    fn available_versions(&self, name: &str, unrelated: UnrelatedOrDependency) -> Vec<Version> {
+        let mut versions = vec![];
+        let found = if unrelated.is_unrelated() {
+            self.load_source().and_then(|mut source| {
+                let dependency_request =
+                    Dependency::parse_no_deprecated(name, None, source.source_id())?;
+                source.query(&dependency_request, &mut |summary| {
+                    versions.push(summary.version().clone())
+                })?;
+                Ok(())
+            })
+        } else {
+            self.for_every_non_local_dep_crate(|pkg| {
+                if pkg.name().as_str() == name {
+                    versions.push(pkg.version().clone());
+                }
+                Ok(())
+            })
+        };
+        match found {
+            Ok(()) => versions,
+            Err(_) => vec![],
+        }
+    }
+
+    /// Names similar to a crate name that wasn't found, best effort
+    fn suggest_crate_names(&self, name: &str, unrelated: UnrelatedOrDependency) -> Vec<String> {
+        let candidates = if unrelated.is_unrelated() {
+            crev_lib::Local::auto_open()
+                .and_then(|local| {
+                    crates_io::Client::new(&local, self.registry_source(), self.is_offline()?)
+                })
+                .and_then(|client| client.search_names(name))
+        } else {
+            let mut names = HashSet::new();
+            self.for_every_non_local_dep_crate(|pkg| {
+                names.insert(pkg.name().to_string());
+                Ok(())
+            })
+            .map(|()| names.into_iter().collect())
+        };
+        match candidates {
+            Ok(candidates) => suggest_names(name, candidates.iter().map(String::as_str)),
+            Err(_) => vec![],
         }
-        .ok_or_else(|| format_err!("Could not find requested crate"))
     }
 }
@@ -1,7 +1,7 @@
 use crev_data::Rating;
 use crev_lib::{self, local::Local};
 use failure::format_err;
-use std::default::Default;
+use std::{collections::BTreeSet, default::Default, path::Path};
 
 use crate::opts;
 use crate::prelude::*;
@@ -11,39 +11,117 @@ use crev_lib::TrustOrDistrust;
 use crate::repo::*;
 use crate::shared::*;
 
+/// Fail if fields that the editor would fill in are missing with `--no-editor`
+pub fn check_no_editor_args(
+    report_issue: Option<&opts::IssueCommon>,
+    advise_common: Option<&opts::AdviseCommon>,
+    content: &opts::ReviewContent,
+) -> Result<()> {
+    if !content.no_editor {
+        return Ok(());
+    }
+    if advise_common.map_or(false, |a| a.id.is_none()) {
+        bail!("Advisories created with `--no-editor` require `--advisory-id <id>`");
+    }
+    if report_issue.map_or(false, |i| i.id.is_none()) && content.issues.is_empty() {
+        bail!(
+            "Issue reports created with `--no-editor` need the id of the issue: give it with `--advisory-id <id>`, or list the issues with `--issues <id>[:<severity>]`"
+        );
+    }
+    if report_issue.is_none() && advise_common.is_none() && content.rating.is_none() {
+        bail!("`--no-editor` requires `--rating <strong|positive|neutral|negative|dangerous>`");
+    }
+    Ok(())
+}
+
 /// Review a crate
 ///
 /// * `unrelated` - the crate might not actually be a dependency
+/// * `integrity` - how to check the reviewed code is the published one
+/// * `unlocked_id` - id to sign with; if `None`, the current id is unlocked with a passphrase
 pub fn create_review_proof(
     name: &str,
     version: Option<&Version>,
     unrelated: UnrelatedOrDependency,
-    report_severity: Option<crev_data::Level>,
+    report_issue: Option<opts::IssueCommon>,
     advise_common: Option<opts::AdviseCommon>,
     trust: TrustOrDistrust,
     proof_create_opt: &opts::CommonProofCreate,
     diff_version: &Option<Option<Version>>,
     skip_activity_check: bool,
+    content: &opts::ReviewContent,
+    json_diff_report: bool,
+    integrity: IntegrityCheck,
+    unlocked_id: Option<&crev_data::OwnId>,
 ) -> Result<()> {
-    let repo = Repo::auto_open_cwd()?;
+    if !content.files.is_empty()
+        && (advise_common.is_some() || report_issue.is_some() || diff_version.is_some())
+    {
+        bail!("`--files` can't be combined with `--advisory`, `--issue` or `--diff`");
+    }
+    if !content.files.is_empty() && content.build_log.is_some() {
+        bail!("`--files` can't be combined with `--cargo-build-log`");
+    }
+    if !content.files.is_empty() && content.inherit_from.is_some() {
+        bail!("`--files` can't be combined with `--inherit-from`");
+    }
+    check_no_editor_args(report_issue.as_ref(), advise_common.as_ref(), content)?;
 
-    let crate_ = repo.find_crate(name, version, unrelated)?;
-    let crate_root = crate_.root();
-    let effective_crate_version = crate_.version();
+    let repo = Repo::auto_open_cwd()?;
 
-    assert!(!crate_root.starts_with(std::env::current_dir()?));
+    let crate_;
+    let (crate_root, effective_crate_version) = match &integrity {
+        // reviewed in place, so it is the current directory
+        IntegrityCheck::Tarball(extracted) => (extracted.root.as_path(), &extracted.version),
+        _ => {
+            crate_ = repo.find_crate(name, version, unrelated)?;
+            assert!(!crate_.root().starts_with(std::env::current_dir()?));
+            (crate_.root(), crate_.version())
+        }
+    };
     let local = Local::auto_open()?;
+    if content.inherit_from.as_ref() == Some(effective_crate_version) {
+        bail!("`--inherit-from` must be another version than the reviewed one");
+    }
 
     let diff_base_version = crate_review_activity_check(
         &local,
+        repo.registry_source(),
         name,
         &effective_crate_version,
         &diff_version,
         skip_activity_check,
     )?;
 
-    let (digest_clean, vcs) =
-        check_package_clean_state(&repo, &crate_root, name, &effective_crate_version)?;
+    let (digest_clean, vcs) = match &integrity {
+        IntegrityCheck::Tarball(extracted) => {
+            extracted.check_clean_state(&repo, json_diff_report)?
+        }
+        _ => check_package_clean_state(
+            &repo,
+            &crate_root,
+            name,
+            &effective_crate_version,
+            json_diff_report,
+            integrity.skips_fetch(),
+        )?,
+    };
+
+    let notes = local.load_notes()?;
+    let notes: Vec<_> = notes
+        .get_for_package(
+            repo.registry_source(),
+            name,
+            effective_crate_version,
+            &digest_clean,
+        )
+        .collect();
+    if !notes.is_empty() {
+        eprintln!("Your private notes about {}:", name);
+        for note in notes {
+            print_note(note, Some(&digest_clean));
+        }
+    }
 
     let diff_base = if let Some(ref diff_base_version) = diff_base_version {
         let crate_ = repo.find_crate(
@@ -53,12 +131,18 @@ pub fn create_review_proof(
         )?;
         let crate_root = crate_.root();
 
-        let (digest, vcs) =
-            check_package_clean_state(&repo, &crate_root, name, &diff_base_version)?;
+        let (digest, vcs) = check_package_clean_state(
+            &repo,
+            &crate_root,
+            name,
+            &diff_base_version,
+            json_diff_report,
+            integrity.skips_fetch(),
+        )?;
 
         Some(proof::PackageInfo {
             id: None,
-            source: PROJECT_SOURCE_CRATES_IO.to_owned(),
+            source: repo.registry_source().to_owned(),
             name: name.to_owned(),
             version: diff_base_version.to_owned(),
             digest: digest.into_vec(),
@@ -70,22 +154,43 @@ pub fn create_review_proof(
         None
     };
 
-    let id = local.read_current_unlocked_id(&crev_common::read_passphrase)?;
+    let id_storage;
+    let id = match unlocked_id {
+        Some(id) => id,
+        None => {
+            id_storage = local.read_current_unlocked_id(&crev_common::read_passphrase)?;
+            &id_storage
+        }
+    };
+
+    let package_info = proof::PackageInfo {
+        id: None,
+        source: repo.registry_source().to_owned(),
+        name: name.to_owned(),
+        version: effective_crate_version.to_owned(),
+        digest: digest_clean.into_vec(),
+        digest_type: proof::default_digest_type(),
+        revision: vcs_info_to_revision_string(vcs),
+        revision_type: proof::default_revision_type(),
+    };
+
+    if !content.files.is_empty() {
+        return create_code_review_proof(
+            &local,
+            id,
+            package_info,
+            crate_root,
+            trust,
+            content,
+            proof_create_opt,
+        );
+    }
 
     let db = local.load_db()?;
     let mut review = proof::review::PackageBuilder::default()
         .from(id.id.to_owned())
-        .package(proof::PackageInfo {
-            id: None,
-            source: PROJECT_SOURCE_CRATES_IO.to_owned(),
-            name: name.to_owned(),
-            version: effective_crate_version.to_owned(),
-            digest: digest_clean.into_vec(),
-            digest_type: proof::default_digest_type(),
-            revision: vcs_info_to_revision_string(vcs),
-            revision_type: proof::default_revision_type(),
-        })
-        .review(if advise_common.is_some() || report_severity.is_some() {
+        .package(package_info)
+        .review(if advise_common.is_some() || report_issue.is_some() {
             crev_data::Review::new_none()
         } else {
             trust.to_review()
@@ -94,54 +199,403 @@ pub fn create_review_proof(
         .build()
         .map_err(|e| format_err!("{}", e))?;
 
-    let previous_date =
-        if let Some((prev_date, prev_review, prev_advisories, prev_issues, prev_comment)) =
-            find_previous_review_data(
-                &db,
-                &id.id,
-                name,
-                effective_crate_version,
-                &diff_base_version,
-            )
-        {
-            review.review = prev_review;
-            review.comment = prev_comment;
-            review.advisories = prev_advisories;
-            review.issues = prev_issues;
-            prev_date
-        } else {
-            None
-        };
+    let previous_date = if let Some((
+        prev_date,
+        prev_review,
+        prev_scope,
+        prev_advisories,
+        prev_issues,
+        prev_comment,
+    )) = find_previous_review_data(
+        &db,
+        repo.registry_source(),
+        &id.id,
+        name,
+        effective_crate_version,
+        &diff_base_version,
+    ) {
+        review.review = prev_review;
+        review.scope_of_review = prev_scope;
+        review.comment = prev_comment;
+        review.advisories = prev_advisories;
+        review.issues = prev_issues;
+        prev_date
+    } else {
+        None
+    };
+
+    let mut notes = String::new();
+    // Never overwrite the review of this very version, with its advisories and issues
+    if let (Some(inherit_from), Some(_)) = (&content.inherit_from, &previous_date) {
+        eprintln!(
+            "You already reviewed {} {}; editing that review instead of inheriting from {}",
+            name, effective_crate_version, inherit_from
+        );
+    } else if let Some(ref inherit_from) = content.inherit_from {
+        let inherited = db
+            .get_pkg_review(repo.registry_source(), name, inherit_from, &id.id.id)
+            .ok_or_else(|| {
+                format_err!(
+                    "You have no review of {} {} to inherit from",
+                    name,
+                    inherit_from
+                )
+            })?;
+        review.review.rating = inherited.review.rating.clone();
+        review.comment = inherited.comment.clone();
+        review.issues = inherited.issues.clone();
+
+        let inherited_crate =
+            repo.find_crate(name, Some(inherit_from), UnrelatedOrDependency::Unrelated)?;
+        notes = format!(
+            "Inherited from your review of {} {}; the source changed since:\n{}",
+            name,
+            inherit_from,
+            source_diff_stat(inherited_crate.root(), crate_root)
+        );
+    }
 
     if let Some(advise_common) = advise_common {
         let mut advisory: proof::review::package::Advisory = advise_common.affected.into();
         advisory.severity = advise_common.severity;
+        advisory.affected = advise_common.affected_versions;
+        if let Some(id) = advise_common.id {
+            advisory.ids = vec![id];
+        }
         review.advisories.push(advisory);
     }
-    if let Some(severity) = report_severity {
-        let mut report = proof::review::package::Issue::new_with_severity("".into(), severity);
-        report.severity = severity;
-        review.issues.push(report);
+    let issue_affected_versions = report_issue
+        .as_ref()
+        .and_then(|issue| issue.affected_versions.clone());
+    if let Some(report_issue) = report_issue {
+        if content.issues.is_empty() || report_issue.id.is_some() {
+            let mut report = proof::review::package::Issue::new_with_severity(
+                report_issue.id.unwrap_or_default(),
+                report_issue.severity,
+            );
+            report.affected = report_issue.affected_versions;
+            review.issues.push(report);
+        }
         review.review.rating = Rating::Negative;
     }
-    let review = crev_lib::util::edit_proof_content_iteractively(
-        &review.into(),
-        previous_date.as_ref(),
-        diff_base_version.as_ref(),
-    )?;
+    for issue in &content.issues {
+        let mut report =
+            proof::review::package::Issue::new_with_severity(issue.id.clone(), issue.severity);
+        report.affected = issue_affected_versions.clone();
+        review.issues.push(report);
+    }
+    if let Some(ref rating) = content.rating {
+        review.review.rating = rating.clone();
+    }
+    if let Some(ref comment) = content.comment {
+        review.comment = comment.clone();
+    }
+    if let Some(ref build_log) = content.build_log {
+        review.build_log = Some(local.store_build_log(build_log, name, effective_crate_version)?);
+    }
+    // timed until the proof is signed, below
+    let review_start = if content.timed {
+        Some(
+            local
+                .read_review_start(repo.registry_source(), name, effective_crate_version)?
+                .ok_or_else(|| {
+                    format_err!(
+                        "No review session of {} {} to time; open it with `cargo crev open` first",
+                        name,
+                        effective_crate_version
+                    )
+                })?,
+        )
+    } else {
+        None
+    };
+    if content.duration.is_some() {
+        review.review_duration_minutes = content.duration;
+    }
+    if let Some(recording) = std::env::var_os(RECORD_SCREEN_ENV) {
+        if !review.comment.is_empty() {
+            review.comment.push_str("\n\n");
+        }
+        review.comment.push_str(&format!(
+            "Review session recording: {}",
+            std::path::Path::new(&recording).display()
+        ));
+    }
 
-    let proof = review.sign_by(&id)?;
+    let mut review = if content.no_editor {
+        let review = proof::Content::from(review);
+        review.validate_data()?;
+        review
+    } else {
+        crev_lib::util::edit_proof_content_iteractively_with_notes(
+            &review.into(),
+            previous_date.as_ref(),
+            diff_base_version.as_ref(),
+            &notes,
+        )?
+    };
+
+    if let (Some(start), proof::Content::Package(review)) = (&review_start, &mut review) {
+        let elapsed = crev_common::now().signed_duration_since(start.timestamp);
+        review.review_duration_minutes = Some(elapsed.num_minutes().max(0) as u64);
+    }
+    let proof = review.sign_by(id)?;
 
     let commit_msg = format!(
         "Add review for {crate} v{version}",
         crate = name,
         version = effective_crate_version
     );
-    maybe_store(&local, &proof, &commit_msg, proof_create_opt)
+    maybe_store(&local, &proof, &commit_msg, Some(id), proof_create_opt)?;
+    if review_start.is_some() {
+        local.clear_review_start(repo.registry_source(), name, effective_crate_version)?;
+    }
+    Ok(())
+}
+
+/// Files changed between two source trees, as summarized by `git diff --stat`
+///
+/// Only informative, so failing to run `git` is reported in place of the summary.
+pub fn source_diff_stat(old_root: &Path, new_root: &Path) -> String {
+    let output = std::process::Command::new("git")
+        .args(["diff", "--no-index", "--stat=100", "--"])
+        .arg(old_root)
+        .arg(new_root)
+        .output();
+    match output {
+        // exits with 1 when the trees differ
+        Ok(ref output) if output.status.code().map_or(false, |code| code <= 1) => {
+            let stat = String::from_utf8_lossy(&output.stdout).into_owned();
+            if stat.trim().is_empty() {
+                "no changes".to_owned()
+            } else {
+                stat
+            }
+        }
+        Ok(output) => format!(
+            "`git diff` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => format!("`git diff` failed: {}", e),
+    }
+}
+
+/// Review only some files of a crate
+///
+/// Creates a code review proof, with digests of the reviewed files,
+/// which never vouches for the crate as a whole.
+fn create_code_review_proof(
+    local: &Local,
+    id: &crev_data::OwnId,
+    package_info: proof::PackageInfo,
+    crate_root: &std::path::Path,
+    trust: TrustOrDistrust,
+    content: &opts::ReviewContent,
+    proof_create_opt: &opts::CommonProofCreate,
+) -> Result<()> {
+    let crate_root = crate_root.canonicalize()?;
+    let mut files = vec![];
+    for path in &content.files {
+        let full_path = crate_root
+            .join(path)
+            .canonicalize()
+            .map_err(|e| format_err!("Can't review `{}`: {}", path.display(), e))?;
+        if !full_path.is_file() {
+            bail!("`{}` is not a file", path.display());
+        }
+        let rel_path = full_path
+            .strip_prefix(&crate_root)
+            .map_err(|_| format_err!("`{}` is outside of the crate", path.display()))?;
+        files.push(proof::review::code::File {
+            path: rel_path.to_owned(),
+            digest: crev_common::blake2b256sum_file(&full_path)?,
+            digest_type: proof::default_digest_type(),
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files.dedup_by(|a, b| a.path == b.path);
+
+    let mut review = trust.to_review();
+    if let Some(ref rating) = content.rating {
+        review.rating = rating.clone();
+    }
+    let name = package_info.name.clone();
+    let version = package_info.version.clone();
+    let review = proof::review::CodeBuilder::default()
+        .from(id.id.to_owned())
+        .package(package_info)
+        .review(review)
+        .comment(content.comment.clone().unwrap_or_default())
+        .files(files)
+        .build()
+        .map_err(|e| format_err!("{}", e))?;
+
+    let review = if content.no_editor {
+        let review = proof::Content::from(review);
+        review.validate_data()?;
+        review
+    } else {
+        crev_lib::util::edit_proof_content_iteractively(&review.into(), None, None)?
+    };
+
+    let proof = review.sign_by(id)?;
+
+    let commit_msg = format!(
+        "Add partial review for {crate} v{version}",
+        crate = name,
+        version = version
+    );
+    maybe_store(local, &proof, &commit_msg, Some(id), proof_create_opt)
+}
+
+/// Crates skipped during `review --all-unreviewed`, kept across runs
+const BATCH_REVIEW_SKIPPED_FILE: &str = "review-batch-skipped.txt";
+
+fn read_batch_review_skipped(path: &Path) -> Result<BTreeSet<String>> {
+    if !path.exists() {
+        return Ok(BTreeSet::new());
+    }
+    Ok(crev_common::read_file_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(ToString::to_string)
+        .collect())
+}
+
+fn write_batch_review_skipped(path: &Path, skipped: &BTreeSet<String>) -> Result<()> {
+    let mut content = String::new();
+    for entry in skipped {
+        content.push_str(entry);
+        content.push('\n');
+    }
+    crev_common::store_str_to_file(path, &content)?;
+    Ok(())
+}
+
+/// Review all dependencies that don't pass verification yet, one after another
+///
+/// Smallest crates go first. The current id is unlocked only once, and
+/// skipped crates are remembered, so the session can be resumed later.
+pub fn review_all_unreviewed(args: &opts::Review) -> Result<()> {
+    let local = Local::auto_open()?;
+    let db = local.load_db()?;
+    let id = local.read_current_unlocked_id(&crev_common::read_passphrase)?;
+    let trust_set =
+        crate::policy::calculate_trust_set(&db, &id.id.id, &args.trust_params.clone().into())?;
+    let requirements = args.requirements.clone().into();
+
+    let skipped_path = local.get_root_cache_dir().join(BATCH_REVIEW_SKIPPED_FILE);
+    let mut skipped = read_batch_review_skipped(&skipped_path)?;
+
+    let repo = Repo::auto_open_cwd()?;
+    let source = repo.registry_source().to_owned();
+    let mut crates = vec![];
+    let mut previously_skipped = 0;
+    repo.for_every_non_local_dep_crate(|pkg| {
+        let pkg_id = pkg.package_id();
+        let name = pkg_id.name().to_string();
+        let version = pkg_id.version().to_owned();
+        if skipped.contains(&format!("{} {}", name, version)) {
+            previously_skipped += 1;
+            return Ok(());
+        }
+        if db
+            .get_pkg_review(&source, &name, &version, &id.id.id)
+            .is_some()
+        {
+            return Ok(());
+        }
+        let digest = crev_lib::get_dir_digest(pkg.root(), &cargo_min_ignore_list().into())?;
+        if db
+            .verify_package_digest(&digest, &trust_set, &requirements)
+            .is_verified()
+        {
+            return Ok(());
+        }
+        let loc = crate::tokei::get_rust_line_count(pkg.root()).ok();
+        crates.push((loc, name, version));
+        Ok(())
+    })?;
+    // Crates of unknown size last
+    crates.sort_by(|a, b| (a.0.is_none(), &a.0, &a.1).cmp(&(b.0.is_none(), &b.0, &b.1)));
+
+    if previously_skipped > 0 {
+        eprintln!(
+            "Not showing {} crates skipped before; remove {} to include them again",
+            previously_skipped,
+            skipped_path.display()
+        );
+    }
+    let crates_io = crate::crates_io::Client::new(&local, &source, repo.is_offline()?)?;
+
+    let total = crates.len();
+    for (i, (loc, name, version)) in crates.into_iter().enumerate() {
+        let owners = crates_io
+            .get_owners(&name)
+            .ok()
+            .map_or_else(|| "unknown".into(), |owners| owners.join(", "));
+        eprintln!();
+        eprintln!("[{}/{}] {} {}", i + 1, total, name, version);
+        eprintln!(
+            "  lines: {}, reviews: {} (all versions: {}), owners: {}",
+            loc.map_or_else(|| "?".into(), |loc| loc.to_string()),
+            db.get_package_review_count(&source, Some(&name), Some(&version)),
+            db.get_package_review_count(&source, Some(&name), None),
+            owners
+        );
+        loop {
+            let choice = crev_common::read_choice(
+                "Review now, Skip, Open in editor, Quit? [r/s/o/q] ",
+                &["r", "s", "o", "q"],
+            )?;
+            let result = match choice.as_str() {
+                "r" => create_review_proof(
+                    &name,
+                    Some(&version),
+                    UnrelatedOrDependency::Dependency,
+                    None,
+                    None,
+                    TrustOrDistrust::Trust,
+                    &args.common_proof_create,
+                    &None,
+                    args.skip_activity_check,
+                    &args.content,
+                    args.json,
+                    IntegrityCheck::from_no_fetch_flag(args.no_fetch),
+                    Some(&id),
+                ),
+                "o" => {
+                    if let Err(e) = crate_open(
+                        &name,
+                        Some(&version),
+                        UnrelatedOrDependency::Dependency,
+                        None,
+                        false,
+                    ) {
+                        eprintln!("Error: {}", e);
+                    }
+                    continue;
+                }
+                "s" => {
+                    skipped.insert(format!("{} {}", name, version));
+                    write_batch_review_skipped(&skipped_path, &skipped)
+                }
+                _ => return Ok(()),
+            };
+            match result {
+                Ok(()) => break,
+                Err(e) => eprintln!("Error: {}", e),
+            }
+        }
+    }
+
+    Ok(())
 }
 
 pub fn find_previous_review_data(
     db: &crev_lib::ProofDB,
+    source: &str,
     id: &crev_data::PubId,
     name: &str,
     crate_version: &Version,
@@ -149,27 +603,26 @@ pub fn find_previous_review_data(
 ) -> Option<(
     Option<crev_data::proof::Date>,
     crev_data::proof::review::Review,
+    Option<crev_data::proof::review::package::ReviewScope>,
     Vec<crev_data::proof::review::package::Advisory>,
     Vec<crev_data::proof::review::package::Issue>,
     String,
 )> {
-    if let Some(previous_review) =
-        db.get_pkg_review(PROJECT_SOURCE_CRATES_IO, name, crate_version, &id.id)
-    {
+    if let Some(previous_review) = db.get_pkg_review(source, name, crate_version, &id.id) {
         return Some((
             Some(previous_review.date),
             previous_review.review.to_owned(),
+            previous_review.scope_of_review.to_owned(),
             previous_review.advisories.to_owned(),
             previous_review.issues.to_owned(),
             previous_review.comment.to_owned(),
         ));
     } else if let Some(diff_base_version) = diff_base_version {
-        if let Some(base_review) =
-            db.get_pkg_review(PROJECT_SOURCE_CRATES_IO, name, &diff_base_version, &id.id)
-        {
+        if let Some(base_review) = db.get_pkg_review(source, name, &diff_base_version, &id.id) {
             return Some((
                 None,
                 base_review.review.to_owned(),
+                base_review.scope_of_review.to_owned(),
                 vec![],
                 vec![],
                 base_review.comment.to_owned(),
@@ -179,23 +632,244 @@ pub fn find_previous_review_data(
     None
 }
 
-pub fn find_reviews(crate_: &opts::CrateSelector) -> Result<Vec<proof::review::Package>> {
+/// Recorded duration of a review, eg. `45m`
+fn review_duration(review: &proof::review::Package) -> String {
+    review
+        .review_duration_minutes
+        .map_or("-".to_owned(), |minutes| format!("{}m", minutes))
+}
+
+pub fn list_reviews(args: &opts::QueryReview) -> Result<()> {
+    if let (Some(since), Some(until)) = (args.since, args.until) {
+        if since >= until {
+            bail!("`--until` date must be after the `--since` date");
+        }
+    }
     let local = crev_lib::Local::auto_open()?;
     let db = local.load_db()?;
-    Ok(db
-        .get_package_reviews_for_package(
-            PROJECT_SOURCE_CRATES_IO,
-            crate_.name.as_ref().map(String::as_str),
-            crate_.version.as_ref(),
-        )
-        .cloned()
-        .collect())
+    let current_id = if args.by_me {
+        Some(local.get_current_userid()?)
+    } else {
+        None
+    };
+    let digest = match (&args.digest, &args.path) {
+        (Some(digest), _) => Some(digest.clone()),
+        (None, Some(path)) => Some(crev_lib::get_dir_digest(
+            path,
+            &cargo_min_ignore_list().into(),
+        )?),
+        (None, None) => None,
+    };
+    if let Some(digest) = digest {
+        if args.crate_.name.is_some() {
+            bail!("Crate name can't be given with `--digest` or `--path`");
+        }
+        return list_reviews_by_digest(args, &local, &db, &digest, current_id.as_ref());
+    }
+    let trust_set = current_trust_set(&local, &db)?;
+    // outside of a project, reviews from any registry are listed
+    let repo = Repo::auto_open_cwd().ok();
+    let query = crev_lib::proofdb::ReviewQuery {
+        source: repo.as_ref().map(Repo::registry_source),
+        name: args.crate_.name.as_deref(),
+        version: args.crate_.version.as_ref(),
+        reviewer_id: current_id.as_ref(),
+        since_date: args.since.map(|since| since.with_timezone(&chrono::Utc)),
+        until_date: args.until.map(|until| until.with_timezone(&chrono::Utc)),
+        ..Default::default()
+    };
+    let mut reviews: Vec<_> = db
+        .query_reviews(query, &trust_set)
+        .map(|queried| queried.review.clone())
+        .collect();
+    sort_reviews(&mut reviews, args.sort, args.reverse, |id| {
+        trust_key(&trust_set, id)
+    });
+
+    match args.format {
+        opts::OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&reviews)?),
+        opts::OutputFormat::Text if args.by_me => {
+            for review in &reviews {
+                println!(
+                    "{:<24} {:<10} {:<8} {} {:>5} {}",
+                    review.package.name,
+                    review.package.version,
+                    review.review.rating,
+                    review.date.format("%Y-%m-%d"),
+                    review_duration(review),
+                    comment_excerpt(&review.comment)
+                );
+            }
+        }
+        opts::OutputFormat::Text => {
+            for review in &reviews {
+                println!("{}", review);
+            }
+        }
+        opts::OutputFormat::Markdown => {
+            println!(
+                "{}",
+                markdown_header(&[
+                    "crate", "version", "rating", "reviewer", "date", "time", "comment"
+                ])
+            );
+            for review in &reviews {
+                println!(
+                    "{}",
+                    markdown_row(&[
+                        review.package.name.clone(),
+                        review.package.version.to_string(),
+                        rating_emoji(&review.review.rating),
+                        review.from.id.to_string(),
+                        review.date.format("%Y-%m-%d").to_string(),
+                        review_duration(review),
+                        comment_excerpt(&review.comment),
+                    ])
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Trust set of the current Id, empty if there's none
+fn current_trust_set(local: &Local, db: &crev_lib::ProofDB) -> Result<crev_lib::proofdb::TrustSet> {
+    Ok(match local.get_current_userid_opt()? {
+        Some(id) => crate::policy::calculate_trust_set(db, &id, &Default::default())?,
+        None => crev_lib::proofdb::TrustSet::default(),
+    })
+}
+
+/// Orders distrusted ids first, then untrusted ones, then by trust level
+fn trust_key(
+    trust_set: &crev_lib::proofdb::TrustSet,
+    id: &crev_data::Id,
+) -> (bool, Option<crev_data::proof::trust::TrustLevel>) {
+    (
+        !trust_set.contains_distrusted(id),
+        trust_set.get_effective_trust_level(id),
+    )
+}
+
+/// Sort reviews by `sort`, the newest or the most trusted first, unless `reverse`
+///
+/// Ties are broken by reviewer, then crate and date, so the order doesn't
+/// depend on the order of proofs in the db.
+pub fn sort_reviews<K: Ord>(
+    reviews: &mut [proof::review::Package],
+    sort: opts::ReviewSort,
+    reverse: bool,
+    trust_key: impl Fn(&crev_data::Id) -> K,
+) {
+    reviews.sort_by(|a, b| {
+        let order = match sort {
+            opts::ReviewSort::Date => b.date.cmp(&a.date),
+            opts::ReviewSort::Trust => trust_key(&b.from.id).cmp(&trust_key(&a.from.id)),
+            opts::ReviewSort::Reviewer => a.from.id.cmp(&b.from.id),
+        }
+        .then_with(|| a.from.id.cmp(&b.from.id))
+        .then_with(|| {
+            (&a.package.name, &a.package.version).cmp(&(&b.package.name, &b.package.version))
+        })
+        .then_with(|| b.date.cmp(&a.date));
+        if reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    });
 }
 
-pub fn list_reviews(crate_: &opts::CrateSelector) -> Result<()> {
-    for review in find_reviews(crate_)? {
-        println!("{}", review);
+/// `list_reviews` of any crate with the given content
+fn list_reviews_by_digest(
+    args: &opts::QueryReview,
+    local: &Local,
+    db: &crev_lib::ProofDB,
+    digest: &crev_data::Digest,
+    reviewer_id: Option<&crev_data::Id>,
+) -> Result<()> {
+    let trust_set = current_trust_set(local, db)?;
+    let mut reviews: Vec<_> = db
+        .get_package_reviews_by_digest(digest)
+        .filter(|review| reviewer_id.map_or(true, |id| review.from.id == *id))
+        .filter(|review| args.since.map_or(true, |since| review.date >= since))
+        .filter(|review| args.until.map_or(true, |until| review.date < until))
+        .collect();
+    sort_reviews(&mut reviews, args.sort, args.reverse, |id| {
+        trust_key(&trust_set, id)
+    });
+
+    let trust = |id: &crev_data::Id| {
+        if trust_set.contains_distrusted(id) {
+            "distrusted".to_owned()
+        } else {
+            trust_set
+                .get_effective_trust_level(id)
+                .map_or("untrusted".to_owned(), |level| level.to_string())
+        }
+    };
+
+    match args.format {
+        opts::OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&reviews)?),
+        opts::OutputFormat::Markdown => {
+            println!(
+                "{}",
+                markdown_header(&["reviewer", "trust", "crate", "version", "rating", "date"])
+            );
+            for review in &reviews {
+                println!(
+                    "{}",
+                    markdown_row(&[
+                        review.from.id.to_string(),
+                        trust(&review.from.id),
+                        review.package.name.clone(),
+                        review.package.version.to_string(),
+                        rating_emoji(&review.review.rating),
+                        review.date.format("%Y-%m-%d").to_string(),
+                    ])
+                );
+            }
+        }
+        opts::OutputFormat::Text => {
+            if reviews.is_empty() {
+                eprintln!("No reviews of {}", digest);
+            }
+            for review in &reviews {
+                let trust = trust(&review.from.id);
+                println!(
+                    "{} {:<10} {:<24} {:<10} {:<8} {}",
+                    review.from.id,
+                    trust,
+                    review.package.name,
+                    review.package.version,
+                    review.review.rating,
+                    review.date.format("%Y-%m-%d"),
+                );
+            }
+        }
     }
 
     Ok(())
 }
+
+/// Rating with an emoji, for output without colors
+fn rating_emoji(rating: &Rating) -> String {
+    let emoji = match rating {
+        Rating::Strong | Rating::Positive => "✅",
+        Rating::Neutral => "⚠️",
+        Rating::Negative => "❌",
+    };
+    format!("{} {}", emoji, rating)
+}
+
+/// First line of a review comment, shortened to fit in a table
+fn comment_excerpt(comment: &str) -> String {
+    const MAX_LEN: usize = 40;
+    let line = comment.lines().next().unwrap_or("");
+    if line.chars().count() > MAX_LEN || comment.lines().nth(1).is_some() {
+        format!("{}...", line.chars().take(MAX_LEN).collect::<String>())
+    } else {
+        line.to_owned()
+    }
+}
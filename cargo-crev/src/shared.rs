@@ -1,16 +1,16 @@
 // Here are the structs and functions which still need to be sorted
 //
 use crate::deps::scan;
+use crate::extracted::ExtractedCrate;
 use crate::opts;
 use crate::prelude::*;
 use crate::repo::*;
+use crate::term;
 use crev_data::proof;
 use crev_lib::TrustOrDistrust;
 use crev_lib::{self, local::Local, ProofStore, ReviewMode};
 use failure::format_err;
-use insideout::InsideOutIter;
-use resiter::FlatMap;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::ffi::OsString;
 use std::{
     collections::HashSet,
@@ -28,6 +28,15 @@ pub const GOTO_CRATE_NAME_ENV: &str = "CARGO_CREV_GOTO_ORIGINAL_NAME";
 /// Name of ENV with version of the crate that we've `goto`ed to
 pub const GOTO_CRATE_VERSION_ENV: &str = "CARGO_CREV_GOTO_ORIGINAL_VERSION";
 
+/// Name of ENV with the diff program to use instead of `diff -r`
+pub const CREV_DIFF_ENV: &str = "CREV_DIFF";
+
+/// Name of ENV with the program `crate open` opens crates with
+pub const CREV_OPEN_CMD_ENV: &str = "CREV_OPEN_CMD";
+
+/// Name of ENV with the path of a screen recording of the current review session
+pub const RECORD_SCREEN_ENV: &str = "CARGO_CREV_RECORD_SCREEN";
+
 /// Name of file we store user-personalized
 pub const KNOWN_CARGO_OWNERS_FILE: &str = "known_cargo_owners.txt";
 
@@ -54,7 +63,7 @@ pub enum VcsInfoJsonGit {
 }
 
 impl VcsInfoJson {
-    fn read_from_crate_dir(pkg_dir: &Path) -> Result<Option<Self>> {
+    pub fn read_from_crate_dir(pkg_dir: &Path) -> Result<Option<Self>> {
         let path = pkg_dir.join(VCS_INFO_JSON_FILE);
 
         if path.exists() {
@@ -65,7 +74,7 @@ impl VcsInfoJson {
             Ok(None)
         }
     }
-    fn get_git_revision(&self) -> Option<String> {
+    pub fn get_git_revision(&self) -> Option<String> {
         let VcsInfoJsonGit::Sha1(ref s) = self.git;
         Some(s.to_string())
     }
@@ -86,6 +95,27 @@ pub fn cargo_min_ignore_list() -> HashSet<PathBuf> {
     ignore_list
 }
 
+/// Digests of a clean copy of a crate, with `cargo_min_ignore_list`
+/// (as recorded in reviews) and with `cargo_full_ignore_list` (to compare
+/// with the reviewed copy)
+///
+/// A clean copy has nothing more to ignore, unless the crate ships it,
+/// so usually the dir is digested only once.
+pub fn clean_crate_digests(root: &Path) -> Result<(crev_data::Digest, crev_data::Digest)> {
+    let full_ignore_list = cargo_full_ignore_list();
+    let shipped_ignored = full_ignore_list
+        .difference(&cargo_min_ignore_list())
+        .any(|path| root.join(path).exists());
+    let digest_clean =
+        crev_lib::get_recursive_digest_for_dir(root, &cargo_min_ignore_list().into())?;
+    let digest_compared = if shipped_ignored {
+        crev_lib::get_recursive_digest_for_dir(root, &full_ignore_list.into())?
+    } else {
+        digest_clean.clone()
+    };
+    Ok((digest_clean, digest_compared))
+}
+
 #[cfg(target_family = "unix")]
 // on Unix we use `exec` so that stuff like Ctrl-C works
 // we don't care about destructors at this point
@@ -104,6 +134,57 @@ pub fn exec_into(mut command: process::Command) -> Result<()> {
     Ok(())
 }
 
+/// Run the current `cargo crev` command again, recorded with `asciinema rec`
+///
+/// `record_screen_arg` is the name of the argument requesting the recording,
+/// which gets removed from the arguments of the recorded command.
+pub fn rerun_with_screen_recording(
+    output: &Path,
+    record_screen_arg: &str,
+) -> Result<process::ExitStatus> {
+    if env::var_os(RECORD_SCREEN_ENV).is_some() {
+        bail!("The review session is already being recorded");
+    }
+    let output = env::current_dir()?.join(output);
+
+    let mut args = vec![];
+    let mut args_iter = env::args().skip(1);
+    while let Some(arg) = args_iter.next() {
+        if arg == record_screen_arg {
+            args_iter.next();
+        } else if !arg.starts_with(&format!("{}=", record_screen_arg)) {
+            args.push(arg);
+        }
+    }
+    let mut recorded_command = shell_quote(&env::current_exe()?.display().to_string());
+    for arg in &args {
+        recorded_command.push(' ');
+        recorded_command.push_str(&shell_quote(arg));
+    }
+
+    eprintln!("Recording review session to: {}", output.display());
+    let status = process::Command::new("asciinema")
+        .arg("rec")
+        .arg("--command")
+        .arg(recorded_command)
+        .arg(&output)
+        .env(RECORD_SCREEN_ENV, &output)
+        .status()
+        .map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                format_err!("`asciinema` not found; it's required for `--record-screen`")
+            } else {
+                e.into()
+            }
+        })?;
+
+    Ok(status)
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
 /// `cd` into crate source code and start shell
 ///
 /// Set some `envs` to help other commands work
@@ -125,11 +206,16 @@ pub fn goto_crate_src(
     let crate_version = crate_.version();
     let local = crev_lib::Local::auto_create_or_open()?;
     local.record_review_activity(
-        PROJECT_SOURCE_CRATES_IO,
+        repo.registry_source(),
         &crate_.name().to_string(),
         crate_version,
         &crev_lib::ReviewActivity::new_full(),
     )?;
+    local.record_review_start(
+        repo.registry_source(),
+        &crate_.name().to_string(),
+        crate_version,
+    )?;
 
     let shell = env::var_os("SHELL").ok_or_else(|| format_err!("$SHELL not set"))?;
     let cwd = env::current_dir()?;
@@ -182,18 +268,37 @@ pub fn edit_known_owners_list() -> Result<()> {
     Ok(())
 }
 
+/// Re-download all dependencies with a locally modified source, after asking the user
 pub fn clean_all_unclean_crates() -> Result<()> {
     let scanner = scan::Scanner::new(&opts::Verify::default())?;
-    let events = scanner.run();
-
-    for stats in events.into_iter() {
-        if stats.is_digest_unclean() {
-            clean_crate(
-                &stats.info.id.name().to_string(),
-                Some(stats.info.id.version()),
-                UnrelatedOrDependency::Dependency,
-            )?;
-        }
+    let dirty: Vec<_> = scanner
+        .run()
+        .into_iter()
+        .filter(|stats| stats.is_digest_unclean())
+        .map(|stats| stats.info.id)
+        .collect();
+
+    if dirty.is_empty() {
+        eprintln!("No dirty dependencies");
+        return Ok(());
+    }
+    for id in &dirty {
+        eprintln!("{} {}", id.name(), id.version());
+    }
+    if !crev_common::yes_or_no_was_y(&format!(
+        "Wipe and re-download the source of {} dirty dependenc{} (y/n) ",
+        dirty.len(),
+        if dirty.len() > 1 { "ies" } else { "y" }
+    ))? {
+        return Ok(());
+    }
+
+    for id in &dirty {
+        clean_crate(
+            &id.name().to_string(),
+            Some(id.version()),
+            UnrelatedOrDependency::Dependency,
+        )?;
     }
 
     Ok(())
@@ -268,11 +373,16 @@ pub fn crate_open(
         get_open_cmd(&local)?
     };
     local.record_review_activity(
-        PROJECT_SOURCE_CRATES_IO,
+        repo.registry_source(),
         &crate_.name().to_string(),
         &crate_.version(),
         &crev_lib::ReviewActivity::new_full(),
     )?;
+    local.record_review_start(
+        repo.registry_source(),
+        &crate_.name().to_string(),
+        crate_.version(),
+    )?;
     let status = crev_lib::util::run_with_shell_cmd(open_cmd.into(), crate_root)?;
 
     if !status.success() {
@@ -282,6 +392,56 @@ pub fn crate_open(
     Ok(())
 }
 
+/// Download a crate for inspection, print its path, and open it with `--cmd` or `CREV_OPEN_CMD`
+pub fn crate_open_independent(args: &opts::CrateOpen) -> Result<()> {
+    let local = Local::auto_create_or_open()?;
+    let repo = Repo::auto_open_cwd()?;
+    let crate_ = repo.find_crate(
+        &args.name,
+        args.version.as_ref(),
+        UnrelatedOrDependency::Unrelated,
+    )?;
+    let crate_root = crate_.root();
+    println!("{}", crate_root.display());
+
+    let open_cmd = args
+        .cmd
+        .clone()
+        .map(OsString::from)
+        .or_else(|| env::var_os(CREV_OPEN_CMD_ENV));
+    open_for_inspection(
+        &local,
+        repo.registry_source(),
+        &crate_.name().to_string(),
+        crate_.version(),
+        crate_root,
+        open_cmd,
+    )
+}
+
+/// Record that a crate was opened for review, and open it with `open_cmd`, if any
+///
+/// Nothing is written inside `crate_root`, so that its digest stays clean.
+pub fn open_for_inspection(
+    local: &Local,
+    source: &str,
+    name: &str,
+    version: &Version,
+    crate_root: &Path,
+    open_cmd: Option<OsString>,
+) -> Result<()> {
+    local.record_review_activity(source, name, version, &crev_lib::ReviewActivity::new_full())?;
+    local.record_review_start(source, name, version)?;
+
+    if let Some(open_cmd) = open_cmd {
+        let status = crev_lib::util::run_with_shell_cmd(open_cmd, crate_root)?;
+        if !status.success() {
+            bail!("Shell returned {}", status);
+        }
+    }
+    Ok(())
+}
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum UnrelatedOrDependency {
     Unrelated,
@@ -302,17 +462,43 @@ impl UnrelatedOrDependency {
     }
 }
 
+/// How the reviewed code is checked to be the code published in the registry
+#[derive(Debug, PartialEq, Eq)]
+pub enum IntegrityCheck {
+    /// Compare it with a fresh download
+    Fetch,
+    /// Don't check it (`--no-fetch`)
+    NoFetch,
+    /// Compare the crate extracted by cargo with the tarball it came from (`--here`)
+    Tarball(ExtractedCrate),
+}
+
+impl IntegrityCheck {
+    pub fn from_no_fetch_flag(no_fetch: bool) -> Self {
+        if no_fetch {
+            IntegrityCheck::NoFetch
+        } else {
+            IntegrityCheck::Fetch
+        }
+    }
+
+    pub fn skips_fetch(&self) -> bool {
+        *self == IntegrityCheck::NoFetch
+    }
+}
+
 /// Check `diff` command line argument against previous activity
 ///
 /// Return `Option<Version>` indicating final ReviewMode settings to use.
 pub fn crate_review_activity_check(
     local: &Local,
+    source: &str,
     name: &str,
     version: &Version,
     diff: &Option<Option<Version>>,
     skip_activity_check: bool,
 ) -> Result<Option<Version>> {
-    let activity = local.read_review_activity(PROJECT_SOURCE_CRATES_IO, name, version)?;
+    let activity = local.read_review_activity(source, name, version)?;
 
     let diff = match diff {
         None => None,
@@ -385,12 +571,84 @@ pub fn crate_review_activity_check(
     Ok(diff)
 }
 
+/// Print files that differ between a fresh copy (`a`) and reviewed code (`b`)
+pub fn print_dir_diff_report(report: &crev_lib::DirDiffReport) -> Result<()> {
+    let mut term = term::Term::new();
+    let size = |size: Option<u64>| size.map_or_else(|| "-".to_owned(), |s| s.to_string());
+
+    eprintln!("status        fresh   reviewed path");
+    for (entries, status, color) in &[
+        (&report.only_in_a, "missing", ::term::color::RED),
+        (&report.only_in_b, "added", ::term::color::YELLOW),
+        (&report.different, "modified", ::term::color::YELLOW),
+    ] {
+        for entry in entries.iter() {
+            term.eprint(format_args!("{:8}", status), *color)?;
+            eprintln!(
+                " {:>10} {:>10} {}",
+                size(entry.size_a),
+                size(entry.size_b),
+                entry.path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Deal with the reviewed code moved aside by a previous review
+///
+/// If the review was interrupted before the fresh copy was fully
+/// downloaded, the moved code is the only copy and is moved back.
+/// Otherwise it's a copy kept for inspection and is removed.
+fn clean_leftover_reviewed_dir(crate_root: &Path, reviewed_pkg_dir: &Path) -> Result<()> {
+    if !crate_root.join(".cargo-ok").exists() {
+        eprintln!(
+            "Restoring {} left by an interrupted review",
+            crate_root.display()
+        );
+        if crate_root.is_dir() {
+            std::fs::remove_dir_all(crate_root)?;
+        }
+        crev_common::fs::move_dir_content(reviewed_pkg_dir, crate_root)?;
+        std::fs::remove_dir(reviewed_pkg_dir)?;
+        return Ok(());
+    }
+
+    let digest_left =
+        crev_lib::get_recursive_digest_for_dir(reviewed_pkg_dir, &cargo_full_ignore_list().into())?;
+    let digest_current =
+        crev_lib::get_recursive_digest_for_dir(crate_root, &cargo_full_ignore_list().into())?;
+    if digest_left != digest_current {
+        eprintln!(
+            "Removing modified code left by a previous review: {}",
+            reviewed_pkg_dir.display()
+        );
+    }
+    std::fs::remove_dir_all(reviewed_pkg_dir)?;
+    Ok(())
+}
+
 pub fn check_package_clean_state(
     repo: &Repo,
     crate_root: &Path,
     name: &str,
     version: &Version,
+    json_diff_report: bool,
+    no_fetch: bool,
 ) -> Result<(crev_data::Digest, Option<VcsInfoJson>)> {
+    if no_fetch {
+        eprintln!(
+            r#"WARNING: `--no-fetch` given; the integrity check was skipped.
+            The digest is calculated from the local copy, which was not compared with a fresh download
+            and might contain modifications: {}"#,
+            crate_root.display(),
+        );
+        let digest =
+            crev_lib::get_recursive_digest_for_dir(crate_root, &cargo_min_ignore_list().into())?;
+        let vcs = VcsInfoJson::read_from_crate_dir(crate_root)?;
+        return Ok((digest, vcs));
+    }
+
     // to protect from creating a digest from a crate in unclean state
     // we move the old directory, download a fresh one and double
     // check if the digest was the same
@@ -398,14 +656,12 @@ pub fn check_package_clean_state(
     let reviewed_pkg_dir: PathBuf =
         crev_common::fs::append_to_path(crate_root.to_owned(), ".crev.reviewed");
     if reviewed_pkg_dir.is_dir() {
-        std::fs::remove_dir_all(&reviewed_pkg_dir)?;
+        clean_leftover_reviewed_dir(crate_root, &reviewed_pkg_dir)?;
     }
 
-    // to prevent user calling `crev review` from the source dir,
-    // having the cwd pulled from under them and confusing their
-    // shells, we move all the entries in a dir, instead of the whole
-    // dir. this is not a perfect solution, but better than nothing.
-    crev_common::fs::move_dir_content(&crate_root, &reviewed_pkg_dir)?;
+    // until committed, any failure (including a failed download) moves
+    // the reviewed code back, so the registry src dir stays usable
+    let swap_guard = crev_common::fs::DirSwapGuard::new(&crate_root, &reviewed_pkg_dir)?;
     let crate_second = repo.find_crate(name, Some(version), UnrelatedOrDependency::Unrelated)?;
     let crate_root_second = crate_second.root();
     let crate_version_second = crate_second.version();
@@ -413,24 +669,32 @@ pub fn check_package_clean_state(
     assert_eq!(crate_root, crate_root_second);
     assert_eq!(version, crate_version_second);
 
-    let digest_clean =
-        crev_lib::get_recursive_digest_for_dir(&crate_root, &cargo_min_ignore_list())?;
+    // build artifacts of the reviewed code are not a modification; the
+    // same list is used for the report, so it shows what was compared
+    let compared_ignore_list = cargo_full_ignore_list().into();
+    let (digest_clean, digest_fresh) = clean_crate_digests(crate_root)?;
     let digest_reviewed =
-        crev_lib::get_recursive_digest_for_dir(&reviewed_pkg_dir, &cargo_full_ignore_list())?;
-
-    if digest_clean != digest_reviewed {
-        eprintln!(
-            r#"The digest of the reviewed source code is different from the digest of a freshly downloaded copy.
+        crev_lib::get_recursive_digest_for_dir(&reviewed_pkg_dir, &compared_ignore_list)?;
+    let reviewed_pkg_dir = swap_guard.commit();
+
+    if digest_fresh != digest_reviewed {
+        let report =
+            crev_lib::diff_dir_digests(crate_root, &reviewed_pkg_dir, &compared_ignore_list)?;
+        if json_diff_report {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            print_dir_diff_report(&report)?;
+        }
+        bail!(
+            r#"The reviewed source code is different from a freshly downloaded copy.
             This is most probably caused by your actions: creating new or modified files.
-            Will continue with the digest of a fresh copy.
             Fresh copy: {}
-            Reviewed code: {}."#,
+            Reviewed code: {}"#,
             crate_root.display(),
             reviewed_pkg_dir.display(),
         );
-    } else {
-        std::fs::remove_dir_all(&reviewed_pkg_dir)?;
     }
+    std::fs::remove_dir_all(&reviewed_pkg_dir)?;
 
     let vcs = VcsInfoJson::read_from_crate_dir(&crate_root)?;
 
@@ -468,23 +732,27 @@ pub fn run_diff(args: &opts::Diff) -> Result<std::process::ExitStatus> {
     let local = crev_lib::Local::auto_create_or_open()?;
     let current_id = local.get_current_userid()?;
     let db = local.load_db()?;
-    let trust_set = db.calculate_trust_set(&current_id, &trust_distance_params);
+    let trust_set = crate::policy::calculate_trust_set(&db, &current_id, &trust_distance_params)?;
     let src_version = args
         .src
         .clone()
         .or_else(|| {
-            db.find_latest_trusted_version(
-                &trust_set,
-                PROJECT_SOURCE_CRATES_IO,
-                &name,
-                &requirements,
-            )
+            // prefer the latest version we've reviewed ourselves
+            db.get_pkg_reviews_for_name(repo.registry_source(), name)
+                .filter(|review| {
+                    review.from.id == current_id && review.package.version < *dst_crate.version()
+                })
+                .map(|review| review.package.version.clone())
+                .max()
+        })
+        .or_else(|| {
+            db.find_latest_trusted_version(&trust_set, repo.registry_source(), &name, &requirements)
         })
         .ok_or_else(|| format_err!("No previously reviewed version found"))?;
     let src_crate = repo.find_crate(&name, Some(&src_version), UnrelatedOrDependency::Unrelated)?;
 
     local.record_review_activity(
-        PROJECT_SOURCE_CRATES_IO,
+        repo.registry_source(),
         &name,
         &dst_crate.version(),
         &crev_lib::ReviewActivity::new_diff(&src_version),
@@ -492,11 +760,29 @@ pub fn run_diff(args: &opts::Diff) -> Result<std::process::ExitStatus> {
 
     use std::process::Command;
 
+    if let Some(exe) = env::var_os(CREV_DIFF_ENV) {
+        let mut command = Command::new(&exe);
+        command
+            .arg(src_crate.root())
+            .arg(dst_crate.root())
+            .args(&args.args);
+        return command.status().map_err(|err| {
+            format_err!(
+                "Failed to execute {} from ${}: {}",
+                exe.to_string_lossy(),
+                CREV_DIFF_ENV,
+                err
+            )
+        });
+    }
+
     let diff = |exe| {
         let mut command = Command::new(exe);
+        command.arg("-r").arg("-N");
+        for ignored in cargo_min_ignore_list() {
+            command.arg("-x").arg(ignored);
+        }
         command
-            .arg("-r")
-            .arg("-N")
             .arg(src_crate.root())
             .arg(dst_crate.root())
             .args(&args.args);
@@ -552,7 +838,7 @@ pub fn list_issues(args: &opts::QueryIssue) -> Result<()> {
     let local = crev_lib::Local::auto_open()?;
     let current_id = local.get_current_userid()?;
     let db = local.load_db()?;
-    let trust_set = db.calculate_trust_set(&current_id, &trust_distance_params);
+    let trust_set = crate::policy::calculate_trust_set(&db, &current_id, &trust_distance_params)?;
 
     for review in db.get_pkg_reviews_with_issues_for(
         PROJECT_SOURCE_CRATES_IO,
@@ -616,61 +902,85 @@ where
     Ok(())
 }
 
+/// Entry of `query id trusted --json`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct TrustedIdJson {
+    id: String,
+    url: Option<String>,
+    trust: String,
+    distance: u64,
+    /// Ids through which this one is trusted, starting with the root
+    trust_chain: Vec<String>,
+    package_reviews: usize,
+}
+
+pub fn print_trusted_ids_json(
+    db: &crev_lib::ProofDB,
+    trust_set: &crev_lib::proofdb::TrustSet,
+) -> Result<()> {
+    let review_counts = db.all_package_review_author_ids();
+    let mut ids: Vec<_> = trust_set
+        .trusted_ids()
+        .map(|id| TrustedIdJson {
+            id: id.to_string(),
+            url: db.lookup_url(id).map(|url| url.url.clone()),
+            trust: trust_set
+                .get_effective_trust_level(id)
+                .expect("Some trust level")
+                .to_string(),
+            distance: trust_set.get_distance(id).expect("Some distance"),
+            trust_chain: trust_set
+                .get_trust_chain(id)
+                .expect("Some trust chain")
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            package_reviews: review_counts.get(id).cloned().unwrap_or(0),
+        })
+        .collect();
+    ids.sort_by(|a, b| (a.distance, &a.id).cmp(&(b.distance, &b.id)));
+    println!("{}", serde_json::to_string_pretty(&ids)?);
+    Ok(())
+}
+
 pub fn create_trust_proof(
-    ids: Vec<String>,
+    ids: Vec<(String, Option<String>)>,
     trust_or_distrust: TrustOrDistrust,
+    expires: Option<proof::Date>,
     proof_create_opt: &opts::CommonProofCreate,
 ) -> Result<()> {
     let local = Local::auto_open()?;
 
     let own_id = local.read_current_unlocked_id(&crev_common::read_passphrase)?;
 
-    let trust = local.build_trust_proof(own_id.as_pubid(), ids.clone(), trust_or_distrust)?;
+    let id_strings: Vec<_> = ids.iter().map(|(id, _)| id.clone()).collect();
+    let trust = local.build_trust_proof(own_id.as_pubid(), ids, trust_or_distrust, expires)?;
 
     let proof = trust.sign_by(&own_id)?;
     let commit_msg = format!(
         "Add {t_or_d} for {ids}",
         t_or_d = trust_or_distrust,
-        ids = ids.join(", ")
+        ids = id_strings.join(", ")
     );
 
-    maybe_store(&local, &proof, &commit_msg, proof_create_opt)?;
+    maybe_store(&local, &proof, &commit_msg, Some(&own_id), proof_create_opt)?;
 
     Ok(())
 }
 
-pub fn is_file_with_ext(entry: &walkdir::DirEntry, file_ext: &str) -> bool {
-    if !entry.file_type().is_file() {
-        return false;
-    }
-    entry
-        .path()
-        .extension()
-        .map(|ext| ext.to_string_lossy().as_ref() == file_ext)
-        .unwrap_or(false)
-}
-
-pub fn iter_rs_files_in_dir(dir: &Path) -> impl Iterator<Item = Result<PathBuf>> {
-    let walker = walkdir::WalkDir::new(dir).into_iter();
-    walker
-        .map(|entry| {
-            let entry = entry?;
-            if !is_file_with_ext(&entry, "rs") {
-                return Ok(None);
-            }
-            Ok(Some(entry.path().canonicalize()?))
-        })
-        .inside_out_iter()
-        .filter_map(|res| res)
+pub fn find_rs_files_in_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    crev_common::fs::find_files_with_extension(dir, "rs")?
+        .into_iter()
+        .map(|path| Ok(path.canonicalize()?))
+        .collect()
 }
 
 // Note: this function is very slow
 pub fn get_geiger_count(path: &Path) -> Result<u64> {
     let mut count = 0;
-    for metrics in iter_rs_files_in_dir(path)
-        .flat_map_ok(|path| geiger::find_unsafe_in_file(&path, geiger::IncludeTests::No))
-    {
-        let counters = metrics?.counters;
+    for path in find_rs_files_in_dir(path)? {
+        let counters = geiger::find_unsafe_in_file(&path, geiger::IncludeTests::No)?.counters;
         count += counters.functions.unsafe_
             + counters.exprs.unsafe_
             + counters.item_impls.unsafe_
@@ -690,16 +1000,19 @@ pub enum CommandExitStatus {
     VerificationFailed,
     // Success, exit code 0
     Successs,
+    // `verify deps --fail-build-scripts` found unverified build scripts, exit code 3
+    UnverifiedBuildScripts,
 }
 
 pub fn is_digest_clean(
     db: &crev_lib::ProofDB,
+    source: &str,
     name: &str,
     version: &Version,
     digest: &crev_data::Digest,
 ) -> bool {
     let mut at_least_one = false;
-    !db.get_package_reviews_for_package(PROJECT_SOURCE_CRATES_IO, Some(name), Some(version))
+    !db.get_package_reviews_for_package(source, Some(name), Some(version))
         .map(|review| {
             at_least_one = true;
             review
@@ -708,10 +1021,139 @@ pub fn is_digest_clean(
         || !at_least_one
 }
 
+/// Store already signed proofs, skipping duplicates and invalid ones
+pub fn import_signed_proofs(
+    local: &Local,
+    s: &[u8],
+    allow_foreign: bool,
+    proof_create_opt: &opts::CommonProofCreate,
+) -> Result<()> {
+    let own_ids: HashSet<_> = local.list_ids()?.into_iter().map(|id| id.id).collect();
+    let mut known_signatures: HashSet<String> = local
+        .proofs_iter()?
+        .map(|proof| proof.signature().to_owned())
+        .collect();
+    let commit_msg = "Import signed proofs";
+    let commits = !proof_create_opt.no_store && !proof_create_opt.no_commit;
+    let signer = if commits && (proof_create_opt.sign_commits || local.sign_commits()?) {
+        Some(local.read_current_unlocked_id(&crev_common::read_passphrase)?)
+    } else {
+        None
+    };
+
+    let mut imported = 0;
+    let mut skipped = 0;
+    let mut rejected = 0;
+    for (i, proof) in proof::Proof::parse_each(s)?.into_iter().enumerate() {
+        let proof = match proof {
+            Ok(proof) => proof,
+            Err(e) => {
+                eprintln!("Proof #{}: malformed: {}", i, e);
+                rejected += 1;
+                continue;
+            }
+        };
+        if let Err(e) = proof.verify() {
+            eprintln!("Proof #{}: invalid signature: {}", i, e);
+            rejected += 1;
+            continue;
+        }
+        if !allow_foreign && !own_ids.contains(&proof.content.author_id()) {
+            eprintln!(
+                "Proof #{}: signed by {}, which is not your Id (use `--allow-foreign` to accept)",
+                i,
+                proof.content.author_id()
+            );
+            rejected += 1;
+            continue;
+        }
+        if !known_signatures.insert(proof.signature().to_owned()) {
+            eprintln!("Proof #{}: already present", i);
+            skipped += 1;
+            continue;
+        }
+
+        maybe_store(local, &proof, commit_msg, signer.as_ref(), proof_create_opt)?;
+        imported += 1;
+    }
+
+    eprintln!(
+        "Imported {} proofs; skipped {} already present; rejected {}",
+        imported, skipped, rejected
+    );
+    Ok(())
+}
+
+/// Serialize all proofs signed by any of own Ids
+///
+/// Proofs are sorted by date and signature, so the same set of proofs
+/// always gives the same output.
+pub fn export_own_proofs(local: &Local, since: Option<&proof::Date>) -> Result<String> {
+    let own_ids: HashSet<_> = local.list_ids()?.into_iter().map(|id| id.id).collect();
+    let mut proofs: Vec<_> = local
+        .proofs_iter()?
+        .filter(|proof| own_ids.contains(&proof.content.author_id()))
+        .filter(|proof| since.map_or(true, |since| proof.content.date() >= since))
+        .collect();
+    proofs
+        .sort_by(|a, b| (a.content.date(), a.signature()).cmp(&(b.content.date(), b.signature())));
+    proofs.dedup_by(|a, b| a.signature() == b.signature());
+
+    Ok(proofs.iter().map(ToString::to_string).collect())
+}
+
+/// Check signatures of all proofs in `s`, reporting each of them
+///
+/// Fails if any of the proofs is malformed or not correctly signed.
+pub fn verify_proof_signatures(db: Option<&crev_lib::ProofDB>, s: &[u8]) -> Result<()> {
+    let mut invalid = 0;
+    let proofs = proof::Proof::parse_each(s)?;
+    if proofs.is_empty() {
+        bail!("No proofs found");
+    }
+    for (i, proof) in proofs.iter().enumerate() {
+        let proof = match proof {
+            Ok(proof) => proof,
+            Err(e) => {
+                eprintln!("Proof #{}: malformed: {}", i, e);
+                invalid += 1;
+                continue;
+            }
+        };
+        let author_id = proof.content.author_id();
+        let known_url = db.and_then(|db| db.lookup_url(&author_id));
+        let known_url = known_url.map_or("not in local proof database".into(), |url| {
+            format!("known as {}", url.url)
+        });
+        match proof.verify() {
+            Ok(()) => println!(
+                "Proof #{}: OK, signed by {} ({}) on {}",
+                i,
+                author_id,
+                known_url,
+                proof.content.date().to_rfc3339()
+            ),
+            Err(e) => {
+                println!(
+                    "Proof #{}: INVALID signature of {} ({}): {}",
+                    i, author_id, known_url, e
+                );
+                invalid += 1;
+            }
+        }
+    }
+
+    if invalid > 0 {
+        bail!("{} of {} proofs failed verification", invalid, proofs.len());
+    }
+    Ok(())
+}
+
 pub fn maybe_store(
     local: &Local,
     proof: &crev_data::proof::Proof,
     commit_msg: &str,
+    signer: Option<&crev_data::id::OwnId>,
     proof_create_opt: &opts::CommonProofCreate,
 ) -> Result<()> {
     if proof_create_opt.print_unsigned {
@@ -726,11 +1168,123 @@ pub fn maybe_store(
         local.insert(&proof)?;
 
         if !proof_create_opt.no_commit {
+            let signer = if proof_create_opt.sign_commits || local.sign_commits()? {
+                Some(signer.ok_or_else(|| format_err!("Can't sign the commit without an Id"))?)
+            } else {
+                None
+            };
             local
-                .proof_dir_commit(&commit_msg)
+                .proof_dir_commit(&commit_msg, signer)
                 .with_context(|_| format_err!("Could not not automatically commit"))?;
         }
     }
 
     Ok(())
 }
+
+/// A row of a Markdown table
+///
+/// Cells can't span multiple lines, nor contain unescaped `|`.
+pub fn markdown_row<S: AsRef<str>>(cells: &[S]) -> String {
+    let cells: Vec<_> = cells
+        .iter()
+        .map(|cell| cell.as_ref().replace('|', "\\|").replace('\n', " "))
+        .collect();
+    format!("| {} |", cells.join(" | "))
+}
+
+/// Header row of a Markdown table, with the separator row below it
+pub fn markdown_header(titles: &[&str]) -> String {
+    format!("{}\n|{}", markdown_row(titles), "---|".repeat(titles.len()))
+}
+
+/// Digest of a crate source, exactly as recorded in a review of it
+pub fn crate_digest(
+    name: &str,
+    version: Option<&Version>,
+    unrelated: UnrelatedOrDependency,
+) -> Result<crev_data::Digest> {
+    let repo = Repo::auto_open_cwd()?;
+    let crate_ = repo.find_crate(name, version, unrelated)?;
+    crev_lib::get_dir_digest(crate_.root(), &cargo_min_ignore_list().into())
+}
+
+/// Digest of a crate source, to record in a private note
+///
+/// Notes are kept without a digest when it can't be computed, eg. outside of a cargo project.
+/// Value of `source` for crates of the project in the current dir,
+/// or for crates.io outside of one
+pub fn current_registry_source() -> String {
+    Repo::auto_open_cwd()
+        .map(|repo| repo.registry_source().to_owned())
+        .unwrap_or_else(|_| PROJECT_SOURCE_CRATES_IO.to_owned())
+}
+
+pub fn crate_digest_for_note(name: &str, version: &Version) -> Option<crev_data::Digest> {
+    match crate_digest(name, Some(version), UnrelatedOrDependency::Unrelated) {
+        Ok(digest) => Some(digest),
+        Err(e) => {
+            eprintln!("Can't compute the digest of {} {}: {}", name, version, e);
+            None
+        }
+    }
+}
+
+/// Print a private note to stderr, away from any proofs on stdout
+pub fn print_note(note: &crev_lib::notes::Note, digest: Option<&crev_data::Digest>) {
+    eprintln!("{}", format_note(note, digest));
+}
+
+/// One line for a private note; `digest` is the current one of the crate, if known
+pub fn format_note(note: &crev_lib::notes::Note, digest: Option<&crev_data::Digest>) -> String {
+    format!(
+        "{} {} {}: {}{}",
+        note.created_at.to_rfc3339(),
+        note.name,
+        note.version
+            .as_ref()
+            .map_or_else(|| "*".to_owned(), ToString::to_string),
+        note.text,
+        if digest.map_or(false, |digest| note.is_stale(digest)) {
+            " (written about different content)"
+        } else {
+            ""
+        }
+    )
+}
+
+/// Number of single character insertions, deletions or substitutions to turn `a` into `b`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Up to five of `candidates` close to the mistyped `name`, closest first
+///
+/// `name` itself is never suggested.
+pub fn suggest_names<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let max_distance = (name.len() / 3).max(1);
+    let mut close: Vec<_> = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= max_distance || candidate.starts_with(name))
+        .collect();
+    close.sort();
+    close.dedup();
+    close
+        .into_iter()
+        .take(5)
+        .map(|(_, candidate)| candidate.to_owned())
+        .collect()
+}
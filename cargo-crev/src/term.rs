@@ -17,6 +17,15 @@ pub fn verification_status_color(s: &VerificationStatus) -> Option<color::Color>
     }
 }
 
+/// Color of the count of issues reported by trusted ids, by their highest severity
+pub fn issue_severity_color(severity: Option<crev_data::Level>) -> Option<color::Color> {
+    match severity {
+        Some(crev_data::Level::High) | Some(crev_data::Level::Medium) => Some(color::RED),
+        Some(_) => Some(color::YELLOW),
+        None => None,
+    }
+}
+
 pub fn known_owners_count_color(count: usize) -> Option<color::Color> {
     if count > 0 {
         Some(color::GREEN)
@@ -37,6 +46,7 @@ pub struct Term {
 fn output_to<O>(
     args: std::fmt::Arguments<'_>,
     color: Option<Color>,
+    bold: bool,
     term: &mut dyn term::Terminal<Output = O>,
     is_tty: bool,
 ) -> io::Result<()>
@@ -44,14 +54,18 @@ where
     O: Write,
 {
     let use_color = is_tty && term.supports_color();
+    let bold = use_color && bold && term.supports_attr(term::Attr::Bold);
     if use_color {
         if let Some(color) = color {
             term.fg(color)?
         }
     }
+    if bold {
+        term.attr(term::Attr::Bold)?;
+    }
     term.get_mut().write_fmt(args)?;
 
-    if use_color && color.is_some() {
+    if use_color && (color.is_some() || bold) {
         term.reset()?;
     }
 
@@ -79,6 +93,26 @@ impl Term {
             output_to(
                 fmt,
                 color,
+                false,
+                (&mut **term) as &mut dyn term::Terminal<Output = _>,
+                self.stdout_is_tty,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Like `print`, in bold
+    pub fn print_bold<C>(&mut self, fmt: Arguments<'_>, color: C) -> io::Result<()>
+    where
+        C: Into<Option<Color>>,
+    {
+        let color = color.into();
+
+        if let Some(ref mut term) = self.stdout {
+            output_to(
+                fmt,
+                color,
+                true,
                 (&mut **term) as &mut dyn term::Terminal<Output = _>,
                 self.stdout_is_tty,
             )?;
@@ -97,6 +131,7 @@ impl Term {
             output_to(
                 fmt,
                 color,
+                false,
                 (&mut **term) as &mut dyn term::Terminal<Output = _>,
                 self.stdout_is_tty,
             )?;
@@ -0,0 +1,1338 @@
+use super::*;
+
+#[test]
+fn completions_generate_for_each_shell() {
+    use structopt::clap::Shell;
+
+    for shell in &[Shell::Bash, Shell::Zsh, Shell::Fish] {
+        let mut script = vec![];
+        opts::gen_completions(*shell, &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("verify"), "{} completions", shell);
+        assert!(script.contains("cargo-crev"), "{} completions", shell);
+        assert!(!script.contains("_cargo()"), "{} completions", shell);
+    }
+}
+
+#[test]
+fn cli_config_fills_only_flags_not_given() {
+    let config = cli_config::CliConfig {
+        depth: Some(7),
+        low_cost: Some(2),
+        redundancy: Some(3),
+        understanding: Some(crev_data::Level::High),
+        skip_verified: Some(true),
+        ..Default::default()
+    };
+
+    let matches = opts::Opts::clap().get_matches_from([
+        "cargo",
+        "crev",
+        "verify",
+        "--depth",
+        "3",
+        "--redundancy",
+        "1",
+    ]);
+    let opts::MainCommand::Crev(mut command) = opts::Opts::from_clap(&matches).command;
+    config.apply(&mut command, &matches);
+
+    match command {
+        opts::Command::Verify(args) => {
+            assert_eq!(args.trust_params.depth, Some(3));
+            assert_eq!(args.trust_params.low_cost, Some(2));
+            assert_eq!(args.trust_params.high_cost, None);
+            assert_eq!(args.requirements.redundancy, 1);
+            assert_eq!(
+                args.requirements.understanding_level,
+                crev_data::Level::High
+            );
+            assert_eq!(args.requirements.thoroughness_level, crev_data::Level::None);
+            assert!(args.skip_verified);
+            assert!(!args.skip_known_owners);
+        }
+        command => panic!("parsed as {:?}", command),
+    }
+
+    let matches = opts::Opts::clap().get_matches_from([
+        "cargo",
+        "crev",
+        "review",
+        "--all-unreviewed",
+        "--depth",
+        "3",
+    ]);
+    let opts::MainCommand::Crev(mut command) = opts::Opts::from_clap(&matches).command;
+    config.apply(&mut command, &matches);
+
+    match command {
+        opts::Command::Review(args) => {
+            assert_eq!(args.trust_params.depth, Some(3));
+            assert_eq!(args.trust_params.low_cost, Some(2));
+            assert_eq!(args.requirements.redundancy, 3);
+        }
+        command => panic!("parsed as {:?}", command),
+    }
+}
+
+#[test]
+fn policy_overrides_cli_config_but_not_flags() {
+    let user_config = cli_config::CliConfig {
+        trust: Some(crev_data::Level::Low),
+        redundancy: Some(3),
+        understanding: Some(crev_data::Level::High),
+        ..Default::default()
+    };
+    let policy: policy::Policy = toml::from_str(
+        r#"
+trust = "high"
+redundancy = 2
+thoroughness = "low"
+fail-on = "medium"
+
+[[trusted-ids]]
+id = "FYlr8YoYGVvDwHQxqEIs89reKKDy-oWisoO0qXXEfHE"
+level = "high"
+"#,
+    )
+    .unwrap();
+    policy.validate().unwrap();
+    // Not fetched yet
+    assert!(policy.trust_roots(&crev_lib::ProofDB::new()).is_err());
+    assert_eq!(
+        policy.trusted_ids,
+        vec![policy::PinnedId {
+            id: "FYlr8YoYGVvDwHQxqEIs89reKKDy-oWisoO0qXXEfHE".into(),
+            level: crev_data::proof::trust::TrustLevel::High,
+        }]
+    );
+
+    let matches =
+        opts::Opts::clap().get_matches_from(["cargo", "crev", "verify", "--redundancy", "1"]);
+    let opts::MainCommand::Crev(mut command) = opts::Opts::from_clap(&matches).command;
+    user_config.apply(&mut command, &matches);
+    policy.apply(&mut command, &matches);
+
+    match command {
+        opts::Command::Verify(args) => {
+            assert_eq!(args.requirements.trust_level, crev_data::Level::High);
+            assert_eq!(args.requirements.redundancy, 1);
+            assert_eq!(
+                args.requirements.understanding_level,
+                crev_data::Level::High
+            );
+            assert_eq!(args.requirements.thoroughness_level, crev_data::Level::Low);
+            assert_eq!(args.deny_severity, Some(crev_data::Level::Medium));
+        }
+        command => panic!("parsed as {:?}", command),
+    }
+
+    let invalid: policy::Policy =
+        toml::from_str("[[trusted-ids]]\nid = \"not an id!\"\nlevel = \"low\"\n").unwrap();
+    assert!(invalid.validate().is_err());
+    assert!(toml::from_str::<policy::Policy>("unknown = 1").is_err());
+}
+
+#[test]
+fn policy_search_stops_at_workspace_root() -> Result<()> {
+    let tmp = tempdir::TempDir::new("crev-policy")?;
+    let root = tmp.path().join("workspace");
+    let member = root.join("member").join("src");
+    std::fs::create_dir_all(&member)?;
+
+    // Outside of the workspace, eg. in the home directory
+    std::fs::write(tmp.path().join(policy::POLICY_FILE_NAME), "")?;
+    assert_eq!(policy::Policy::find(&member, &root), None);
+
+    std::fs::write(root.join(policy::POLICY_FILE_NAME), "")?;
+    assert_eq!(
+        policy::Policy::find(&member, &root),
+        Some(root.join(policy::POLICY_FILE_NAME))
+    );
+    Ok(())
+}
+
+#[test]
+fn fail_loc_threshold_implies_loc_warnings() {
+    let verify_args = |args: &[&str]| {
+        let matches =
+            opts::Opts::clap().get_matches_from(["cargo", "crev", "verify"].iter().chain(args));
+        match opts::Opts::from_clap(&matches).command {
+            opts::MainCommand::Crev(opts::Command::Verify(args)) => args,
+            command => panic!("parsed as {:?}", command),
+        }
+    };
+
+    assert_eq!(verify_args(&[]).loc_warn_threshold(), None);
+    assert_eq!(
+        verify_args(&["--fail-loc-threshold", "5000"]).loc_warn_threshold(),
+        Some(5000)
+    );
+    let both = verify_args(&[
+        "--loc-warn-threshold",
+        "100",
+        "--fail-loc-threshold",
+        "5000",
+    ]);
+    assert_eq!(both.loc_warn_threshold(), Some(100));
+    assert_eq!(both.fail_loc_threshold, Some(5000));
+}
+
+#[test]
+fn crate_digest_verify_takes_a_base64_digest() {
+    let digest = crev_common::base64_encode(&[7; 32]);
+    let matches = opts::Opts::clap().get_matches_from([
+        "cargo",
+        "crev",
+        "crate",
+        "digest",
+        "foo",
+        "--independent",
+        "--verify",
+        &digest,
+    ]);
+    match opts::Opts::from_clap(&matches).command {
+        opts::MainCommand::Crev(opts::Command::Crate(opts::Crate::Digest(args))) => {
+            assert_eq!(args.name, "foo");
+            assert!(args.independent);
+            assert_eq!(args.verify, Some(crev_data::Digest::from_vec(vec![7; 32])));
+        }
+        command => panic!("parsed as {:?}", command),
+    }
+
+    assert!(opts::Opts::clap()
+        .get_matches_from_safe(["cargo", "crev", "crate", "digest", "foo", "--verify", "AAAA"])
+        .is_err());
+}
+
+#[test]
+fn huge_expirations_are_errors() {
+    let parse = |args: &[&str]| {
+        opts::Opts::clap()
+            .get_matches_from_safe(["cargo", "crev", "trust", "--expires"].iter().chain(args))
+            .map(|matches| opts::Opts::from_clap(&matches).command)
+    };
+    assert!(parse(&["90d"]).is_ok());
+    assert!(parse(&["99999999999999999d"]).is_err());
+    assert!(parse(&["9223372036854775807y"]).is_err());
+
+    let matches = opts::Opts::clap().get_matches_from([
+        "cargo",
+        "crev",
+        "trust",
+        "--expires-in",
+        "9223372036854775807",
+    ]);
+    match opts::Opts::from_clap(&matches).command {
+        opts::MainCommand::Crev(opts::Command::Trust(args)) => {
+            assert!(args.expiration().is_err())
+        }
+        command => panic!("parsed as {:?}", command),
+    }
+}
+
+#[test]
+fn signed_proofs_are_imported_with_repo_import() {
+    let matches = opts::Opts::clap().get_matches_from([
+        "cargo",
+        "crev",
+        "repo",
+        "import",
+        "proofs.crev",
+        "--allow-foreign",
+    ]);
+    match opts::Opts::from_clap(&matches).command {
+        opts::MainCommand::Crev(opts::Command::Repo(opts::Repo::Import(args))) => {
+            assert_eq!(args.path, std::path::Path::new("proofs.crev"));
+            assert!(args.allow_foreign);
+        }
+        command => panic!("parsed as {:?}", command),
+    }
+    assert!(opts::Opts::clap()
+        .get_matches_from_safe(["cargo", "crev", "import", "signed"])
+        .is_err());
+}
+
+#[test]
+fn crate_open_takes_an_optional_positional_version() {
+    let parse = |args: &[&str]| {
+        let matches = opts::Opts::clap()
+            .get_matches_from(["cargo", "crev", "crate", "open"].iter().chain(args.iter()));
+        match opts::Opts::from_clap(&matches).command {
+            opts::MainCommand::Crev(opts::Command::Crate(opts::Crate::Open(args))) => args,
+            command => panic!("parsed as {:?}", command),
+        }
+    };
+    let args = parse(&["foo"]);
+    assert_eq!(args.name, "foo");
+    assert_eq!(args.version, None);
+    assert_eq!(args.cmd, None);
+
+    let args = parse(&["foo", "1.2.3", "--cmd", "code --wait"]);
+    assert_eq!(args.version, Some(Version::parse("1.2.3").unwrap()));
+    assert_eq!(args.cmd.as_deref(), Some("code --wait"));
+}
+
+#[test]
+fn crate_open_leaves_the_source_dir_untouched() -> Result<()> {
+    let tmp_dir = tempdir::TempDir::new("crev-crate-open")?;
+    let local = Local::auto_create_at(
+        &tmp_dir.path().join("config"),
+        &tmp_dir.path().join("cache"),
+    )?;
+    let crate_root = tmp_dir.path().join("foo-1.2.3");
+    std::fs::create_dir_all(&crate_root)?;
+    std::fs::write(crate_root.join("Cargo.toml"), "[package]\n")?;
+    let list_files = || -> Result<Vec<_>> {
+        let mut files = std::fs::read_dir(&crate_root)?
+            .map(|entry| Ok(entry?.file_name()))
+            .collect::<Result<Vec<_>>>()?;
+        files.sort();
+        Ok(files)
+    };
+    let files = list_files()?;
+
+    let version = Version::parse("1.2.3").unwrap();
+    open_for_inspection(&local, "SOURCE", "foo", &version, &crate_root, None)?;
+    assert_eq!(list_files()?, files);
+    assert_eq!(
+        std::fs::read_to_string(crate_root.join("Cargo.toml"))?,
+        "[package]\n"
+    );
+    assert!(local
+        .read_review_activity("SOURCE", "foo", &version)?
+        .is_some());
+    assert!(local
+        .read_review_start("SOURCE", "foo", &version)?
+        .is_some());
+
+    if cfg!(unix) {
+        open_for_inspection(
+            &local,
+            "SOURCE",
+            "foo",
+            &version,
+            &crate_root,
+            Some("true".into()),
+        )?;
+        assert!(open_for_inspection(
+            &local,
+            "SOURCE",
+            "foo",
+            &version,
+            &crate_root,
+            Some("false".into())
+        )
+        .is_err());
+        assert_eq!(list_files()?, files);
+    }
+    Ok(())
+}
+
+#[test]
+fn dependency_kinds_prefer_normal_over_build_and_dev() {
+    use cargo::core::dependency::Kind;
+    use deps::DependencyKind;
+
+    // app -> (normal, dev) both, (build) cc, (dev) quickcheck
+    // cc -> jobserver; quickcheck -> rand, (dev) criterion
+    let graph = |pkg: &'static str| -> Vec<(&'static str, Vec<Kind>)> {
+        match pkg {
+            "app" => vec![
+                ("both", vec![Kind::Development, Kind::Normal]),
+                ("cc", vec![Kind::Build]),
+                ("quickcheck", vec![Kind::Development]),
+            ],
+            "cc" => vec![("jobserver", vec![Kind::Normal])],
+            "quickcheck" => vec![
+                ("rand", vec![Kind::Normal]),
+                ("criterion", vec![Kind::Development]),
+            ],
+            _ => vec![],
+        }
+    };
+    let kinds = deps::classify_dependencies(&["app"], graph);
+
+    assert_eq!(kinds["both"], DependencyKind::Normal);
+    assert_eq!(kinds["cc"], DependencyKind::Build);
+    assert_eq!(kinds["jobserver"], DependencyKind::Build);
+    assert_eq!(kinds["quickcheck"], DependencyKind::Dev);
+    assert_eq!(kinds["rand"], DependencyKind::Dev);
+    assert!(!kinds.contains_key("criterion"));
+
+    let verify = opts::Verify {
+        deps: vec!["normal".parse().unwrap(), "build".parse().unwrap()],
+        ..Default::default()
+    };
+    assert!(verify.includes_dep_kind(DependencyKind::Build));
+    assert!(!verify.includes_dep_kind(DependencyKind::Dev));
+    assert!(opts::Verify::default().includes_dep_kind(DependencyKind::Dev));
+}
+
+#[test]
+fn dependency_paths_through_the_resolve_graph() {
+    // app -> serde_json, log; serde_json -> serde, itoa; log -> serde;
+    // tool -> serde; serde -> serde_derive -> serde (a cycle)
+    let graph = |pkg: &'static str| -> Vec<&'static str> {
+        match pkg {
+            "app" => vec!["serde_json", "log"],
+            "serde_json" => vec!["serde", "itoa"],
+            "log" => vec!["serde"],
+            "tool" => vec!["serde"],
+            "serde" => vec!["serde_derive"],
+            "serde_derive" => vec!["serde"],
+            _ => vec![],
+        }
+    };
+
+    let shortest = deps::shortest_dependency_paths(&["app", "tool"], graph);
+    assert_eq!(shortest["app"], vec!["app"]);
+    assert_eq!(shortest["itoa"], vec!["itoa", "serde_json", "app"]);
+    assert_eq!(shortest["serde"], vec!["serde", "tool"]);
+    assert_eq!(
+        shortest["serde_derive"],
+        vec!["serde_derive", "serde", "tool"]
+    );
+    // Of equally short paths, the first dependency of `app` wins
+    assert_eq!(
+        deps::shortest_dependency_paths(&["app"], graph)["serde"],
+        vec!["serde", "serde_json", "app"]
+    );
+
+    assert_eq!(
+        deps::all_dependency_paths(&["app", "tool"], graph, "serde", 10),
+        vec![
+            vec!["serde", "tool"],
+            vec!["serde", "serde_json", "app"],
+            vec!["serde", "log", "app"],
+        ]
+    );
+    assert_eq!(
+        deps::all_dependency_paths(&["app", "tool"], graph, "itoa", 10),
+        vec![vec!["itoa", "serde_json", "app"]]
+    );
+    assert!(deps::all_dependency_paths(&["tool"], graph, "itoa", 10).is_empty());
+    assert_eq!(
+        deps::all_dependency_paths(&["app", "tool"], graph, "serde", 2).len(),
+        2
+    );
+
+    // A ladder of diamonds has 2^n paths; only the first few are walked
+    let ladder = |pkg: u32| -> Vec<u32> {
+        match pkg {
+            pkg if pkg >= 200 => vec![],
+            pkg if pkg % 2 == 0 => vec![pkg + 2, pkg + 3],
+            pkg => vec![pkg + 1, pkg + 2],
+        }
+    };
+    assert_eq!(deps::all_dependency_paths(&[0], ladder, 200, 5).len(), 5);
+}
+
+#[test]
+fn markdown_table_escapes_cells() {
+    assert_eq!(
+        shared::markdown_header(&["crate", "comment"]),
+        "| crate | comment |\n|---|---|"
+    );
+    assert_eq!(
+        shared::markdown_row(&["a|b", "two\nlines"]),
+        "| a\\|b | two lines |"
+    );
+    assert_eq!(
+        "md".parse::<opts::OutputFormat>().unwrap(),
+        opts::OutputFormat::Markdown
+    );
+}
+
+#[test]
+fn suggest_names_sorts_by_closeness() {
+    assert_eq!(shared::edit_distance("serd", "serde"), 1);
+    assert_eq!(shared::edit_distance("kitten", "sitting"), 3);
+    assert_eq!(shared::edit_distance("", "abc"), 3);
+
+    let candidates = ["serde_json", "serde", "rand", "sered", "syn"];
+    assert_eq!(
+        shared::suggest_names("serd", candidates.iter().cloned()),
+        vec!["serde", "sered", "serde_json"]
+    );
+    assert!(shared::suggest_names("tokio", candidates.iter().cloned()).is_empty());
+    assert_eq!(
+        shared::suggest_names("serde", candidates.iter().cloned()),
+        vec!["serde_json"]
+    );
+}
+
+#[test]
+fn crates_io_retry_delay_backs_off_exponentially() {
+    use std::time::Duration;
+
+    let policy = crates_io::RetryPolicy::default();
+    assert_eq!(policy.delay(0), Duration::from_millis(500));
+    assert_eq!(policy.delay(1), Duration::from_secs(1));
+    assert_eq!(policy.delay(2), Duration::from_secs(2));
+    assert_eq!(policy.delay(5), Duration::from_secs(10));
+    assert_eq!(policy.delay(100), Duration::from_secs(10));
+}
+
+#[test]
+fn sort_reviews_is_deterministic() {
+    use crev_data::{proof, OwnId};
+
+    let date = |s| chrono::DateTime::parse_from_rfc3339(s).unwrap();
+    let review = |id: &OwnId, date| {
+        proof::review::PackageBuilder::default()
+            .from(id.id.to_owned())
+            .date(date)
+            .package(proof::PackageInfo {
+                id: None,
+                source: "source".into(),
+                name: "name".into(),
+                version: semver::Version::parse("1.0.0").unwrap(),
+                digest: vec![0; 32],
+                digest_type: proof::default_digest_type(),
+                revision: "".into(),
+                revision_type: proof::default_revision_type(),
+            })
+            .build()
+            .unwrap()
+    };
+    let mut ids: Vec<_> = (0..3)
+        .map(|_| OwnId::generate_for_git_url("https://a"))
+        .collect();
+    ids.sort_by(|a, b| a.id.id.cmp(&b.id.id));
+    let old = date("2019-01-01T00:00:00Z");
+    let new = date("2019-06-01T00:00:00Z");
+    let reviews = vec![
+        review(&ids[2], new),
+        review(&ids[0], old),
+        review(&ids[1], new),
+    ];
+    let reviewers = |reviews: &[proof::review::Package]| -> Vec<crev_data::Id> {
+        reviews.iter().map(|r| r.from.id.clone()).collect()
+    };
+
+    let mut sorted = reviews.clone();
+    review::sort_reviews(&mut sorted, opts::ReviewSort::Date, false, |_| ());
+    assert_eq!(
+        reviewers(&sorted),
+        vec![
+            ids[1].id.id.clone(),
+            ids[2].id.id.clone(),
+            ids[0].id.id.clone()
+        ]
+    );
+
+    let mut reversed = reviews.clone();
+    review::sort_reviews(&mut reversed, opts::ReviewSort::Date, true, |_| ());
+    sorted.reverse();
+    assert_eq!(reviewers(&reversed), reviewers(&sorted));
+
+    let mut by_trust = reviews;
+    review::sort_reviews(&mut by_trust, opts::ReviewSort::Trust, false, |id| {
+        *id == ids[0].id.id
+    });
+    assert_eq!(
+        reviewers(&by_trust),
+        vec![
+            ids[0].id.id.clone(),
+            ids[1].id.id.clone(),
+            ids[2].id.id.clone()
+        ]
+    );
+}
+
+fn test_crate_info(name: &str, depth: usize) -> deps::CrateInfo {
+    let source_id = cargo::core::SourceId::for_path(std::path::Path::new("/crates")).unwrap();
+    let id = cargo::core::PackageId::new(name, "1.2.3", source_id).unwrap();
+    let root = cargo::core::PackageId::new("root", "0.1.0", source_id).unwrap();
+    deps::CrateInfo {
+        id,
+        root: "/crates".into(),
+        has_custom_build: name == "built",
+        kind: deps::DependencyKind::Build,
+        path: std::iter::once(id)
+            .chain(std::iter::repeat(root).take(depth))
+            .collect(),
+    }
+}
+
+fn test_crate_details() -> deps::CrateDetails {
+    use crate::deps::*;
+
+    CrateDetails {
+        digest: crev_data::Digest::from_vec(vec![0; 32]),
+        latest_trusted_version: Some(semver::Version::parse("1.2.0").unwrap()),
+        trust: crev_lib::VerificationStatus::Verified,
+        contributing_reviews: vec![],
+        reviews: ReviewCount {
+            version: 1,
+            total: 3,
+        },
+        downloads: None,
+        owners: Some(TrustCount {
+            trusted: 1,
+            total: 2,
+        }),
+        issues: TrustCount {
+            trusted: 0,
+            total: 1,
+        },
+        issues_severity: None,
+        loc: Some(1200),
+        unclean_digest: false,
+        verified: true,
+        geiger_count: None,
+        analyses: Default::default(),
+        has_notes: false,
+        stale_notes: false,
+        partial_coverage: None,
+        previously_reviewed: None,
+    }
+}
+
+#[test]
+fn machine_readable_rows_match_the_header() {
+    use crate::deps::{print_term, *};
+
+    let verified = CrateStats {
+        info: test_crate_info("built", 1),
+        details: Ok(Some(test_crate_details())),
+        durations: Default::default(),
+    };
+    let cells = print_term::machine_readable_cells(&verified);
+    assert_eq!(cells.len(), print_term::MACHINE_READABLE_COLUMNS.len());
+    assert_eq!(
+        cells.join("\t"),
+        "built\t1.2.3\tbuild\ttrue\tpass\t1/3\t\t1/2\t0/1\t\t1200\t\tfalse\t1.2.0\t\
+         AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\t"
+    );
+
+    let skipped = CrateStats {
+        info: test_crate_info("skipped", 1),
+        details: Ok(None),
+        durations: Default::default(),
+    };
+    let cells = print_term::machine_readable_cells(&skipped);
+    assert_eq!(cells.len(), print_term::MACHINE_READABLE_COLUMNS.len());
+    assert_eq!(cells[4], "skipped");
+}
+
+#[test]
+fn risk_score_weights_each_factor() {
+    use crate::deps::{risk::RiskModel, *};
+
+    let model = RiskModel {
+        loc_weight: 2.0,
+        has_build_script_weight: 5.0,
+        no_reviews_weight: 10.0,
+        open_issues_weight: 3.0,
+        download_count_factor: 1.0,
+    };
+    let stats = |name: &str, details: CrateDetails| CrateStats {
+        info: test_crate_info(name, 1),
+        details: Ok(Some(details)),
+        durations: Default::default(),
+    };
+    let reviewed = |loc| CrateDetails {
+        loc,
+        ..test_crate_details()
+    };
+    let score = |stats: &CrateStats| model.score(stats).unwrap();
+
+    // 1200 lines, reviewed, no trusted issues, unknown downloads
+    assert_eq!(score(&stats("plain", reviewed(Some(1200)))), 2.4);
+    assert_eq!(score(&stats("plain", reviewed(None))), 0.0);
+    assert_eq!(score(&stats("built", reviewed(None))), 5.0);
+    let unreviewed = CrateDetails {
+        reviews: ReviewCount {
+            version: 0,
+            total: 3,
+        },
+        ..reviewed(None)
+    };
+    assert_eq!(score(&stats("plain", unreviewed)), 10.0);
+    let with_issues = CrateDetails {
+        issues: TrustCount {
+            trusted: 2,
+            total: 5,
+        },
+        ..reviewed(None)
+    };
+    assert_eq!(score(&stats("plain", with_issues)), 6.0);
+
+    // Each order of magnitude of downloads takes one off, down to zero
+    let downloaded = |total, loc| CrateDetails {
+        downloads: Some(DownloadCount { version: 0, total }),
+        ..reviewed(Some(loc))
+    };
+    assert_eq!(score(&stats("plain", downloaded(0, 1000))), 2.0);
+    assert_eq!(score(&stats("plain", downloaded(9, 1000))), 1.0);
+    assert_eq!(score(&stats("plain", downloaded(999_999, 1000))), 0.0);
+
+    let skipped = CrateStats {
+        info: test_crate_info("skipped", 1),
+        details: Ok(None),
+        durations: Default::default(),
+    };
+    assert_eq!(model.score(&skipped), None);
+}
+
+#[test]
+fn coverage_counts_unknown_loc_separately() {
+    use crate::deps::*;
+
+    let stats = |name: &str, depth: usize, verified: bool, loc: Option<usize>| CrateStats {
+        info: test_crate_info(name, depth),
+        details: Ok(Some(CrateDetails {
+            verified,
+            loc,
+            ..test_crate_details()
+        })),
+        durations: Default::default(),
+    };
+    let deps = vec![
+        stats("a", 1, true, Some(100)),
+        stats("b", 1, false, Some(300)),
+        stats("c", 1, true, None),
+        stats("d", 2, true, Some(50)),
+        stats("e", 3, false, None),
+        CrateStats {
+            info: test_crate_info("f", 2),
+            details: Ok(None),
+            durations: Default::default(),
+        },
+    ];
+
+    let coverage = Coverage::from_deps(&deps);
+    assert_eq!(
+        coverage.direct,
+        CoverageBucket {
+            crates: 3,
+            verified_crates: 2,
+            loc: 400,
+            verified_loc: 100,
+            unknown_loc_crates: 1,
+        }
+    );
+    assert_eq!(
+        coverage.transitive,
+        CoverageBucket {
+            crates: 3,
+            verified_crates: 1,
+            loc: 50,
+            verified_loc: 50,
+            unknown_loc_crates: 2,
+        }
+    );
+    assert_eq!(coverage.total().crates, 6);
+    assert_eq!(coverage.total().verified_loc, 150);
+    assert_eq!(coverage.total().unknown_loc_crates, 3);
+}
+
+#[test]
+fn interactive_verify_sorts_and_filters_rows() {
+    use crate::deps::*;
+    use crate::tui::verify_screen::{visible_deps, SortKey, StatusFilter};
+    use crev_lib::VerificationStatus;
+
+    let stats =
+        |name: &str, trust: VerificationStatus, reviews: u64, loc: Option<usize>| CrateStats {
+            info: test_crate_info(name, 1),
+            details: Ok(Some(CrateDetails {
+                trust,
+                reviews: ReviewCount {
+                    version: reviews,
+                    total: reviews,
+                },
+                loc,
+                ..test_crate_details()
+            })),
+            durations: Default::default(),
+        };
+    let deps = vec![
+        stats("b", VerificationStatus::Verified, 2, Some(10)),
+        stats("d", VerificationStatus::Insufficient, 0, None),
+        stats("a", VerificationStatus::Negative, 1, Some(500)),
+        stats("c", VerificationStatus::Insufficient, 0, Some(30)),
+    ];
+    let names = |view: Vec<usize>| -> Vec<String> {
+        view.into_iter()
+            .map(|i| deps[i].info.id.name().to_string())
+            .collect()
+    };
+
+    let all = StatusFilter::All;
+    assert_eq!(
+        names(visible_deps(&deps, SortKey::Name, all, "")),
+        ["a", "b", "c", "d"]
+    );
+    assert_eq!(
+        names(visible_deps(&deps, SortKey::Trust, all, "")),
+        ["a", "c", "d", "b"]
+    );
+    assert_eq!(
+        names(visible_deps(&deps, SortKey::Loc, all, "")),
+        ["a", "c", "b", "d"]
+    );
+    assert_eq!(
+        names(visible_deps(&deps, SortKey::Reviews, all, "")),
+        ["c", "d", "a", "b"]
+    );
+
+    let by_name = |filter, pattern| names(visible_deps(&deps, SortKey::Name, filter, pattern));
+    assert_eq!(by_name(StatusFilter::NotVerified, ""), ["a", "c", "d"]);
+    assert_eq!(by_name(StatusFilter::Failing, ""), ["a"]);
+    assert_eq!(by_name(StatusFilter::Unreviewed, ""), ["c", "d"]);
+    assert_eq!(by_name(StatusFilter::Unreviewed, "d"), ["d"]);
+}
+
+#[test]
+fn timings_sum_phases_and_rank_slowest_crates() {
+    use crate::deps::*;
+    use std::time::Duration;
+
+    let stats = |name: &str, digest_ms: u64, loc_ms: u64| CrateStats {
+        info: test_crate_info(name, 1),
+        details: Ok(None),
+        durations: Durations {
+            digest: Duration::from_millis(digest_ms),
+            loc: Duration::from_millis(loc_ms),
+            ..Default::default()
+        },
+    };
+    let deps = vec![stats("a", 10, 300), stats("b", 50, 0), stats("c", 20, 100)];
+    let timings = Timings::from_deps(&deps, Duration::from_secs(1));
+
+    assert_eq!(timings.samples.len(), deps.len() * Phase::ALL.len());
+    assert_eq!(
+        timings.phase_total(Phase::Digest),
+        Duration::from_millis(80)
+    );
+    assert_eq!(
+        timings.phase_total(Phase::Total),
+        Duration::from_millis(480)
+    );
+    assert_eq!(timings.phase_total(Phase::CratesIo), Duration::default());
+
+    let slowest = |phase, n| -> Vec<String> {
+        timings
+            .slowest(phase, n)
+            .into_iter()
+            .map(|(id, _)| id.name().to_string())
+            .collect()
+    };
+    assert_eq!(slowest(Phase::Digest, 10), ["b", "c", "a"]);
+    assert_eq!(slowest(Phase::Total, 2), ["a", "c"]);
+}
+
+#[test]
+#[cfg(unix)]
+fn command_analyzers_run_in_the_crate_root() {
+    use crate::deps::analyze::*;
+
+    let command = |s: &str| s.parse::<opts::AnalyzerCommand>().unwrap();
+    assert!("no-command".parse::<opts::AnalyzerCommand>().is_err());
+    assert!("=true".parse::<opts::AnalyzerCommand>().is_err());
+    assert!(analyzers(&[command("loc=echo 1")]).is_err());
+    assert!(analyzers(&[command("a=true"), command("a=false")]).is_err());
+    assert!(analyzers(&[command("geiger=true")]).is_err());
+
+    let analyzers = analyzers(&[
+        command("manifest=test -f Cargo.toml && echo yes"),
+        command("answer=echo 42; echo ignored"),
+        command("broken=exit 3"),
+    ])
+    .unwrap();
+    let names: Vec<_> = analyzers.iter().map(|analyzer| analyzer.name()).collect();
+    assert_eq!(names, [LOC, "manifest", "answer", "broken"]);
+
+    let analyses = Analyses::run(
+        &analyzers[1..],
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")),
+    );
+    let custom: Vec<_> = analyses
+        .custom()
+        .map(|(name, output)| (name, output.summary.as_str(), output.count))
+        .collect();
+    assert_eq!(
+        custom,
+        [("manifest", "yes", None), ("answer", "42", Some(42))]
+    );
+    assert_eq!(analyses.count("answer"), Some(42));
+    assert!(analyses.get("broken").is_none());
+}
+
+#[test]
+fn digest_cache_runs_analyzers_once_per_digest() -> Result<()> {
+    use crate::deps::analyze::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    struct Counting(AtomicU64);
+
+    impl Analyzer for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn analyze(&self, _crate_root: &std::path::Path) -> Result<AnalyzerOutput> {
+            Ok(AnalyzerOutput::from_count(
+                self.0.fetch_add(1, Ordering::SeqCst) + 1,
+            ))
+        }
+    }
+
+    let tmp_dir = tempdir::TempDir::new("crev-digest-cache")?;
+    let cache = DigestCache::new(tmp_dir.path().to_owned());
+    let analyzer = Counting(AtomicU64::new(0));
+    let root = tmp_dir.path();
+    let digest_a = crev_data::Digest::from_vec(vec![1; 32]);
+    let digest_b = crev_data::Digest::from_vec(vec![2; 32]);
+
+    assert_eq!(cache.analyze(&analyzer, &digest_a, root)?.count, Some(1));
+    assert_eq!(cache.analyze(&analyzer, &digest_a, root)?.count, Some(1));
+    assert_eq!(cache.analyze(&analyzer, &digest_b, root)?.count, Some(2));
+    let reopened = DigestCache::new(tmp_dir.path().to_owned());
+    assert_eq!(reopened.analyze(&analyzer, &digest_a, root)?.count, Some(1));
+    assert_eq!(analyzer.0.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[test]
+fn audit_matches_advisories_to_affected_versions() {
+    use crate::audit::*;
+
+    let advisory = |id: &str, extra: &str| {
+        Advisory::parse(&format!(
+            "```toml\n\
+             [advisory]\n\
+             id = \"{}\"\n\
+             package = \"ammonia\"\n\
+             date = \"2019-04-27\"\n\
+             categories = [\"memory-corruption\"]\n\
+             {}\n\
+             [versions]\n\
+             patched = [\">= 2.1.0\"]\n\
+             unaffected = [\"< 1.0.0\"]\n\
+             ```\n\
+             \n\
+             # Incorrect handling of embedded SVG\n\
+             \n\
+             Description.\n",
+            id, extra
+        ))
+        .unwrap()
+    };
+    let version = |s| semver::Version::parse(s).unwrap();
+
+    let vulnerability = advisory("RUSTSEC-2019-0001", "");
+    assert_eq!(vulnerability.title, "Incorrect handling of embedded SVG");
+    assert!(vulnerability.is_vulnerability());
+    assert!(vulnerability.affects(&version("1.2.0")));
+    assert!(!vulnerability.affects(&version("0.9.0")));
+    assert!(!vulnerability.affects(&version("2.1.0")));
+
+    let unmaintained = advisory("RUSTSEC-2019-0002", "informational = \"unmaintained\"");
+    assert_eq!(unmaintained.kind(), "unmaintained");
+    assert!(!unmaintained.is_vulnerability());
+
+    let mut db = AdvisoryDb::default();
+    db.add(vulnerability);
+    db.add(unmaintained);
+    db.add(advisory("RUSTSEC-2019-0003", "withdrawn = \"2019-05-01\""));
+    let ids = |name, v| -> Vec<String> {
+        db.advisories_for(name, &version(v))
+            .into_iter()
+            .map(|advisory| advisory.metadata.id.clone())
+            .collect()
+    };
+    assert_eq!(
+        ids("ammonia", "1.2.0"),
+        ["RUSTSEC-2019-0001", "RUSTSEC-2019-0002"]
+    );
+    assert!(ids("ammonia", "2.1.0").is_empty());
+    assert!(ids("other", "1.2.0").is_empty());
+
+    assert!(Advisory::parse("# No metadata").is_err());
+}
+
+/// A `Repo` for an empty project, with a cargo home of its own
+fn test_repo(dir: &std::path::Path, cargo_config: &str, offline: bool) -> Result<Repo> {
+    let cargo_home = dir.join("cargo-home");
+    std::fs::create_dir_all(&cargo_home)?;
+    std::fs::write(cargo_home.join("config"), cargo_config)?;
+    let config =
+        cargo::util::config::Config::new(cargo::core::Shell::new(), dir.to_owned(), cargo_home);
+    Repo::open_with_config(dir.join("Cargo.toml"), config, offline)
+}
+
+#[test]
+fn crates_io_is_the_default_registry() -> Result<()> {
+    let tmp_dir = tempdir::TempDir::new("crev-repo")?;
+    let repo = test_repo(tmp_dir.path(), "", true)?;
+    assert_eq!(repo.registry_source(), PROJECT_SOURCE_CRATES_IO);
+    assert!(repo.registry_source_id()?.is_default_registry());
+    Ok(())
+}
+
+/// A `.crate` tarball with only a `Cargo.toml`
+fn test_crate_tarball(dir_name: &str, manifest: &str) -> Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        vec![],
+        flate2::Compression::default(),
+    ));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(
+        &mut header,
+        format!("{}/Cargo.toml", dir_name),
+        manifest.as_bytes(),
+    )?;
+    Ok(builder.into_inner()?.finish()?)
+}
+
+#[test]
+fn extracted_crates_are_checked_against_their_tarball() -> Result<()> {
+    use crate::extracted::*;
+    use std::fs;
+
+    let split = |dir_name| split_dir_name(dir_name).map(|(name, v)| (name, v.to_string()));
+    assert_eq!(split("foo-1.2.3"), Some(("foo", "1.2.3".to_owned())));
+    assert_eq!(
+        split("foo-bar-0.1.0-alpha.1"),
+        Some(("foo-bar", "0.1.0-alpha.1".to_owned()))
+    );
+    assert_eq!(split("foo-bar"), None);
+    assert_eq!(split("-1.2.3"), None);
+
+    let tmp_dir = tempdir::TempDir::new("crev-extracted")?;
+    let registry = tmp_dir.path().join("registry");
+    let root = registry.join("src/index-0123/foo-bar-1.2.3");
+    let manifest = "[package]\nname = \"foo-bar\"\nversion = \"1.2.3\"\n";
+    fs::create_dir_all(&root)?;
+    fs::write(root.join("Cargo.toml"), manifest)?;
+
+    assert!(ExtractedCrate::detect(tmp_dir.path())?.is_none());
+    assert!(ExtractedCrate::detect(&registry.join("src/index-0123"))?.is_none());
+    let extracted = ExtractedCrate::detect(&root)?.unwrap();
+    assert_eq!(extracted.name, "foo-bar");
+    assert_eq!(extracted.version, Version::parse("1.2.3")?);
+    assert_eq!(
+        extracted.tarball_path(),
+        registry.join("cache/index-0123/foo-bar-1.2.3.crate")
+    );
+
+    let other = registry.join("src/index-0123/other-0.1.0");
+    fs::create_dir_all(&other)?;
+    fs::write(other.join("Cargo.toml"), manifest)?;
+    assert!(ExtractedCrate::detect(&other).is_err());
+    fs::write(other.join("Cargo.toml"), "[workspace]\nmembers = []\n")?;
+    assert!(ExtractedCrate::detect(&other).is_err());
+
+    let tarball = test_crate_tarball("foo-bar-1.2.3", manifest)?;
+    fs::create_dir_all(extracted.tarball_path().parent().unwrap())?;
+    fs::write(extracted.tarball_path(), &tarball)?;
+
+    assert!(extracted.read_tarball("0000").is_err());
+    assert_eq!(extracted.read_tarball(&sha256_hex(&tarball))?, tarball);
+
+    let (_tmp_dir, clean_root) = extracted.unpack_tarball(&tarball)?;
+    let digest = |dir: &std::path::Path| {
+        crev_lib::get_recursive_digest_for_dir(dir, &cargo_min_ignore_list().into())
+    };
+    assert_eq!(digest(&clean_root)?, digest(&root)?);
+    fs::write(root.join("extra.rs"), "")?;
+    assert_ne!(digest(&clean_root)?, digest(&root)?);
+    Ok(())
+}
+
+#[test]
+fn inherited_reviews_summarize_the_source_changes() -> Result<()> {
+    use std::fs;
+
+    let tmp_dir = tempdir::TempDir::new("crev-inherit")?;
+    let old_root = tmp_dir.path().join("foo-1.0.0");
+    let new_root = tmp_dir.path().join("foo-1.0.1");
+    for root in &[&old_root, &new_root] {
+        fs::create_dir_all(root.join("src"))?;
+        fs::write(root.join("src/lib.rs"), "pub fn foo() {}\n")?;
+    }
+    assert_eq!(review::source_diff_stat(&old_root, &new_root), "no changes");
+
+    fs::write(
+        new_root.join("src/lib.rs"),
+        "pub fn foo() {}\npub fn bar() {}\n",
+    )?;
+    let stat = review::source_diff_stat(&old_root, &new_root);
+    assert!(stat.contains("src/lib.rs"), "{}", stat);
+    assert!(stat.contains("1 file changed, 1 insertion(+)"), "{}", stat);
+    Ok(())
+}
+
+#[test]
+fn extracted_crates_are_checked_against_the_registry_index() -> Result<()> {
+    use crate::extracted::*;
+    use std::fs;
+
+    let tmp_dir = tempdir::TempDir::new("crev-extracted")?;
+    let manifest = "[package]\nname = \"foo-bar\"\nversion = \"1.2.3\"\n";
+    let tarball = test_crate_tarball("foo-bar-1.2.3", manifest)?;
+
+    // A git registry index, with the checksum of the tarball
+    let index = tmp_dir.path().join("index");
+    fs::create_dir_all(index.join("fo/o-"))?;
+    fs::write(
+        index.join("config.json"),
+        r#"{"dl": "https://example.com/api/v1/crates"}"#,
+    )?;
+    fs::write(
+        index.join("fo/o-/foo-bar"),
+        format!(
+            r#"{{"name":"foo-bar","vers":"1.2.3","deps":[],"cksum":"{}","features":{{}},"yanked":false}}"#,
+            sha256_hex(&tarball)
+        ) + "\n",
+    )?;
+    let git_repo = git2::Repository::init(&index)?;
+    let mut git_index = git_repo.index()?;
+    git_index.add_all(["*"], git2::IndexAddOption::DEFAULT, None)?;
+    let tree = git_repo.find_tree(git_index.write_tree()?)?;
+    let signature = git2::Signature::now("test", "test@example.com")?;
+    git_repo.commit(Some("HEAD"), &signature, &signature, "index", &tree, &[])?;
+
+    let index_url = format!("file://{}", index.display());
+    let project = tmp_dir.path().join("project");
+    fs::create_dir_all(&project)?;
+    let repo = test_repo(
+        &project,
+        &format!("[crev]\nregistry = \"{}\"\n", index_url),
+        false,
+    )?;
+    assert_eq!(
+        repo.registry_checksum("foo-bar", &Version::parse("1.2.3")?)?,
+        Some(sha256_hex(&tarball))
+    );
+    assert_eq!(
+        repo.registry_checksum("foo-bar", &Version::parse("1.2.4")?)?,
+        None
+    );
+
+    let root = tmp_dir.path().join("registry/src/index-0123/foo-bar-1.2.3");
+    fs::create_dir_all(&root)?;
+    fs::write(root.join("Cargo.toml"), manifest)?;
+    let extracted = ExtractedCrate::detect(&root)?.unwrap();
+    fs::create_dir_all(extracted.tarball_path().parent().unwrap())?;
+    fs::write(extracted.tarball_path(), &tarball)?;
+    let (digest, _vcs) = extracted.check_clean_state(&repo, false)?;
+    assert_eq!(
+        digest,
+        crev_lib::get_recursive_digest_for_dir(&root, &cargo_min_ignore_list().into())?
+    );
+
+    // A tarball that doesn't match the index is never reviewed
+    fs::write(
+        extracted.tarball_path(),
+        test_crate_tarball("foo-bar-1.2.3", "")?,
+    )?;
+    assert!(extracted.check_clean_state(&repo, false).is_err());
+    Ok(())
+}
+
+#[test]
+fn review_keeps_id_as_an_alias_of_advisory_id() {
+    let matches = opts::Opts::clap().get_matches_from([
+        "cargo",
+        "crev",
+        "review",
+        "--advisory",
+        "--id",
+        "RUSTSEC-2019-0001",
+        "foo",
+    ]);
+    let opts = opts::Opts::from_clap(&matches);
+    assert_eq!(opts.id, None);
+    match opts.command {
+        opts::MainCommand::Crev(opts::Command::Review(args)) => {
+            assert_eq!(args.id.as_deref(), Some("RUSTSEC-2019-0001"));
+        }
+        opts::MainCommand::Crev(command) => panic!("parsed as {:?}", command),
+    }
+}
+
+#[test]
+fn deps_scope_parses_its_names() {
+    use deps::DepsScope;
+
+    assert_eq!(
+        "direct-only".parse::<DepsScope>().unwrap(),
+        DepsScope::DirectOnly
+    );
+    assert_eq!(
+        "direct".parse::<DepsScope>().unwrap(),
+        DepsScope::DirectOnly
+    );
+    assert_eq!(
+        "workspace".parse::<DepsScope>().unwrap(),
+        DepsScope::Workspace
+    );
+    assert_eq!("all".parse::<DepsScope>().unwrap(), DepsScope::All);
+    assert!("transitive".parse::<DepsScope>().is_err());
+    assert_eq!(DepsScope::default(), DepsScope::All);
+}
+
+#[test]
+fn deps_scope_limits_the_verified_packages() {
+    use deps::DepsScope;
+
+    // `app` and `member` are workspace members, `app` the current package
+    let deps = |pkg| match pkg {
+        "app" => vec!["lib-a", "member"],
+        "member" => vec!["lib-c"],
+        "lib-a" => vec!["lib-b"],
+        _ => vec![],
+    };
+    let members = ["app", "member"];
+    let in_scope = |scope, current| {
+        deps::packages_in_scope(scope, &members, current, deps).map(|pkgs| {
+            let mut pkgs: Vec<_> = pkgs.into_iter().collect();
+            pkgs.sort();
+            pkgs
+        })
+    };
+    assert_eq!(
+        in_scope(DepsScope::DirectOnly, Some("app")),
+        Some(vec!["lib-a", "member"])
+    );
+    // A virtual workspace has no current package
+    assert_eq!(
+        in_scope(DepsScope::DirectOnly, None),
+        Some(vec!["lib-a", "lib-c", "member"])
+    );
+    assert_eq!(
+        in_scope(DepsScope::Workspace, Some("app")),
+        Some(vec!["lib-a", "lib-c", "member"])
+    );
+    assert_eq!(in_scope(DepsScope::All, Some("app")), None);
+}
+
+#[test]
+fn clean_crate_digests_ignore_build_artifacts_only_when_shipped() -> Result<()> {
+    let tmp_dir = tempdir::TempDir::new("crev-clean-digests")?;
+    let root = tmp_dir.path();
+    std::fs::write(root.join("Cargo.toml"), "[package]\n")?;
+    std::fs::write(root.join(".cargo-ok"), "")?;
+    let (clean, compared) = clean_crate_digests(root)?;
+    assert_eq!(clean, compared);
+
+    std::fs::create_dir_all(root.join("target"))?;
+    std::fs::write(root.join("target/shipped"), "")?;
+    let (clean_shipped, compared_shipped) = clean_crate_digests(root)?;
+    assert_ne!(clean_shipped, compared_shipped);
+    assert_eq!(compared_shipped, compared);
+    Ok(())
+}
+
+#[test]
+fn locked_packages_are_read_from_git_history() -> Result<()> {
+    let tmp_dir = tempdir::TempDir::new("crev-locked")?;
+    let project = tmp_dir.path().join("project");
+    std::fs::create_dir_all(&project)?;
+    let lock = |version: &str| {
+        format!(
+            "[[package]]\nname = \"foo\"\nversion = \"{}\"\n\n[[package]]\nname = \"project\"\nversion = \"0.1.0\"\n",
+            version
+        )
+    };
+    std::fs::write(project.join("Cargo.lock"), lock("1.0.0"))?;
+    // The project is in a subdir of the git repository
+    let git_repo = git2::Repository::init(tmp_dir.path())?;
+    let mut git_index = git_repo.index()?;
+    git_index.add_path(std::path::Path::new("project/Cargo.lock"))?;
+    let tree = git_repo.find_tree(git_index.write_tree()?)?;
+    let signature = git2::Signature::now("test", "test@example.com")?;
+    git_repo.commit(Some("HEAD"), &signature, &signature, "lock", &tree, &[])?;
+    std::fs::write(project.join("Cargo.lock"), lock("2.0.0"))?;
+
+    let repo = test_repo(&project, "", true)?;
+    let packages = repo.get_locked_packages_at("HEAD")?;
+    assert_eq!(packages.len(), 2);
+    assert!(packages.contains(&("foo".to_owned(), Version::parse("1.0.0")?)));
+
+    // Revs are never taken for git options
+    assert!(repo.get_locked_packages_at("--output=/tmp/x").is_err());
+    assert!(repo.get_locked_packages_at("no-such-branch").is_err());
+    Ok(())
+}
+
+#[test]
+fn issue_args_take_an_optional_severity() {
+    let issue: opts::IssueArg = "RUSTSEC-2019-0001".parse().unwrap();
+    assert_eq!(issue.id, "RUSTSEC-2019-0001");
+    assert_eq!(issue.severity, crev_data::Level::Medium);
+
+    let issue: opts::IssueArg = "some:issue:high".parse().unwrap();
+    assert_eq!(issue.id, "some:issue");
+    assert_eq!(issue.severity, crev_data::Level::High);
+
+    assert!("some-issue:severe".parse::<opts::IssueArg>().is_err());
+    assert!(":low".parse::<opts::IssueArg>().is_err());
+}
+
+#[test]
+fn no_editor_requires_what_the_editor_would_fill_in() {
+    let content = |args: &[&str]| {
+        let matches = opts::Opts::clap().get_matches_from(
+            ["cargo", "crev", "review", "--no-editor"]
+                .iter()
+                .chain(args.iter())
+                .chain(["foo"].iter()),
+        );
+        match opts::Opts::from_clap(&matches).command {
+            opts::MainCommand::Crev(opts::Command::Review(args)) => args.content,
+            command => panic!("parsed as {:?}", command),
+        }
+    };
+    let issue = |id: Option<&str>| opts::IssueCommon {
+        id: id.map(str::to_owned),
+        ..Default::default()
+    };
+    let advisory = |id: Option<&str>| opts::AdviseCommon {
+        affected: Default::default(),
+        affected_versions: None,
+        severity: Default::default(),
+        id: id.map(str::to_owned),
+    };
+
+    let error = check_no_editor_args(None, None, &content(&[])).unwrap_err();
+    assert!(error.to_string().contains("--rating"), "{}", error);
+    check_no_editor_args(None, None, &content(&["--rating", "positive"])).unwrap();
+    check_no_editor_args(
+        None,
+        None,
+        &content(&["--rating", "negative", "--comment", "bad"]),
+    )
+    .unwrap();
+
+    let error = check_no_editor_args(Some(&issue(None)), None, &content(&[])).unwrap_err();
+    assert!(error.to_string().contains("--issues"), "{}", error);
+    check_no_editor_args(Some(&issue(Some("some-issue"))), None, &content(&[])).unwrap();
+    check_no_editor_args(
+        Some(&issue(None)),
+        None,
+        &content(&["--issues", "a:low", "--issues", "b"]),
+    )
+    .unwrap();
+
+    assert!(check_no_editor_args(None, Some(&advisory(None)), &content(&[])).is_err());
+    check_no_editor_args(
+        None,
+        Some(&advisory(Some("RUSTSEC-2019-0001"))),
+        &content(&[]),
+    )
+    .unwrap();
+
+    // Without `--no-editor`, the editor asks for all of it
+    let mut interactive = content(&[]);
+    interactive.no_editor = false;
+    check_no_editor_args(Some(&issue(None)), None, &interactive).unwrap();
+}
@@ -1,32 +1,85 @@
-mod verify_screen;
+mod review_screen;
+pub mod verify_screen;
 
 pub use crate::deps::{scan, DownloadCount, TrustCount};
-use crate::opts::Verify;
+use crate::deps::{CrateInfo, CrateStats};
+use crate::opts::{Review, Verify};
 use crate::prelude::*;
+use crate::repo::Repo;
+use crate::review::create_review_proof;
 use crate::shared::CommandExitStatus;
-use crossterm::{AlternateScreen, KeyEvent, TerminalCursor};
-use termimad::{Event, EventSource};
-use verify_screen::VerifyScreen;
+use crate::shared::IntegrityCheck;
+use crate::shared::UnrelatedOrDependency;
+use crev_data::OwnId;
+use crev_lib::TrustOrDistrust;
+use crossbeam::channel::{never, Receiver};
+use crossterm::{AlternateScreen, TerminalCursor};
+use review_screen::ReviewScreen;
+use structopt::StructOpt;
+use termimad::EventSource;
+use verify_screen::{Action, VerifyScreen};
 
-/// called in case of an --interactive execution
+/// Puts the terminal back in its normal state when dropped, including on errors
+struct TerminalGuard {
+    _alt_screen: AlternateScreen,
+    cursor: TerminalCursor,
+}
+
+impl TerminalGuard {
+    fn new() -> Result<Self> {
+        let alt_screen = AlternateScreen::to_alternate(true)?;
+        let cursor = TerminalCursor::new();
+        cursor.hide()?;
+        Ok(Self {
+            _alt_screen: alt_screen,
+            cursor,
+        })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // if we don't do this, the poor terminal is cursorless
+        let _ = self.cursor.show();
+    }
+}
+
+/// called in case of a `verify --interactive` execution
 ///
-/// Right now the --interactive is only possible for "verify" subcommand
-///  but this will hopefully change and the public function here would
-///  be a run_command
+/// Rows are added as the crates get scanned. The user can pick a crate
+/// to review, and gets back to the list with the status of that crate
+/// updated.
 pub fn verify_deps(args: Verify) -> Result<CommandExitStatus> {
-    let computer = scan::Scanner::new(&args)?;
+    if !atty::is(atty::Stream::Stdout) {
+        bail!("`--interactive` needs a terminal; drop it to print the table instead");
+    }
+    let mut computer = scan::Scanner::new(&args)?;
+    let mut screen = VerifyScreen::new(computer.total_crate_count())?;
+    let mut crate_stats_rx = computer.run();
+    let review_args = Review::from_iter(&["review"]);
 
-    let _alt_screen = AlternateScreen::to_alternate(true);
-    let cursor = TerminalCursor::new();
-    cursor.hide()?;
+    while let Some(info) = run_verify_screen(&computer, &mut screen, &mut crate_stats_rx)? {
+        review_dep(&info, &review_args, None)?;
+        // the new review is only in a freshly loaded proof db
+        computer.reload_db()?;
+        screen.update_dep(computer.scan_crate(info));
+    }
 
-    let mut screen = VerifyScreen::new(computer.total_crate_count())?;
+    Ok(CommandExitStatus::Successs)
+}
 
-    screen.update();
-    let crate_stats_rx = computer.run();
+/// Display the verify screen until the user quits, or picks a crate to review
+fn run_verify_screen(
+    computer: &scan::Scanner,
+    screen: &mut VerifyScreen<'_>,
+    crate_stats_rx: &mut Receiver<CrateStats>,
+) -> Result<Option<CrateInfo>> {
+    let _terminal = TerminalGuard::new()?;
+    screen.invalidate();
     let event_source = EventSource::new();
     let rx_user = event_source.receiver();
-    let mut crate_count = 0;
+    let mut crate_count = screen.computation_status();
+    let mut to_review = None;
 
     loop {
         screen.update();
@@ -41,16 +94,22 @@ pub fn verify_deps(args: Verify) -> Result<CommandExitStatus> {
                 } else {
                     // This happens on computation end (channel closed).
                     // We don't break because we let the user read the result.
+                    *crate_stats_rx = never();
                 }
             }
             recv(rx_user) -> user_event => {
                 if let Ok(user_event) = user_event {
-                    let quit = match user_event {
-                        Event::Key(KeyEvent::Ctrl('q')) => true,
-                        _ => {
-                            screen.apply_event(&user_event);
+                    let quit = match screen.apply_event(&user_event) {
+                        Action::None => false,
+                        Action::Quit => true,
+                        Action::ShowDetails(info) => {
+                            screen.show_details(computer.crate_reviews(&info));
                             false
                         }
+                        Action::Review(info) => {
+                            to_review = Some(info);
+                            true
+                        }
                     };
                     event_source.unblock(quit); // this will lead to channel closing
                 } else {
@@ -62,6 +121,61 @@ pub fn verify_deps(args: Verify) -> Result<CommandExitStatus> {
         }
     }
 
-    cursor.show()?; // if we don't do this, the poor terminal is cursorless
+    Ok(to_review)
+}
+
+/// Review a dependency from an interactive screen, then wait for the user
+///
+/// Failing to create the review isn't an error, the user gets back to the list.
+fn review_dep(info: &CrateInfo, args: &Review, id: Option<&OwnId>) -> Result<()> {
+    let name = info.id.name().to_string();
+    let version = info.id.version().to_owned();
+    if let Err(e) = create_review_proof(
+        &name,
+        Some(&version),
+        UnrelatedOrDependency::Dependency,
+        None,
+        None,
+        TrustOrDistrust::Trust,
+        &args.common_proof_create,
+        &None,
+        args.skip_activity_check,
+        &args.content,
+        args.json,
+        IntegrityCheck::from_no_fetch_flag(args.no_fetch),
+        id,
+    ) {
+        eprintln!("Error: {}", e);
+    }
+    eprintln!("Hit Enter to go back to the list");
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(())
+}
+
+/// called in case of a `review --interactive` execution
+///
+/// The user picks a dependency from the list, reviews it, and gets back
+/// to the list, with the status of that dependency updated.
+pub fn review_deps(args: &Review) -> Result<CommandExitStatus> {
+    let mut verify_args = Verify::from_iter(&["verify"]);
+    verify_args.trust_params = args.trust_params.clone();
+    verify_args.requirements = args.requirements.clone();
+    let mut scanner = scan::Scanner::new(&verify_args)?;
+    eprintln!("Scanning {} dependencies...", scanner.total_crate_count());
+    let deps = scanner.run().iter().collect();
+
+    let local = crev_lib::Local::auto_open()?;
+    let id = local.read_current_unlocked_id(&crev_common::read_passphrase)?;
+
+    let mut screen = ReviewScreen::new(Repo::auto_open_cwd()?.name().to_string(), deps);
+    while let Some(info) = screen.select()? {
+        review_dep(&info, args, Some(&id))?;
+
+        // the new review is only in a freshly loaded proof db
+        scanner.reload_db()?;
+        screen.update_dep(scanner.scan_crate(info));
+    }
+
     Ok(CommandExitStatus::Successs)
 }
@@ -0,0 +1,257 @@
+use crossterm::{
+    AlternateScreen, Attribute, ClearType, Color::*, KeyEvent, Terminal, TerminalCursor,
+};
+use std::io::{self, Write};
+use termimad::{gray, terminal_size, CompoundStyle, Event, EventSource};
+
+use super::verify_screen::u64_to_str;
+use crate::deps::{CrateInfo, CrateStats};
+use crate::prelude::*;
+use crev_lib::VerificationStatus;
+
+/// (title, width) of the columns, the crate name column is sized to fit
+const COLUMNS: &[(&str, usize)] = &[
+    ("version", 12),
+    ("trust", 6),
+    ("reviews", 9),
+    ("downloads", 13),
+    ("owners", 7),
+    ("issues", 7),
+    ("l.o.c.", 7),
+];
+
+/// How far a dependency is from being verified; the lowest go first
+pub(super) fn trust_gap(dep: &CrateStats) -> u8 {
+    match dep.details().map(|details| &details.trust) {
+        Some(VerificationStatus::Negative) => 0,
+        Some(VerificationStatus::Insufficient) => 1,
+        Some(VerificationStatus::Verified) => 2,
+        None => 3,
+    }
+}
+
+pub(super) fn pair(a: u64, b: u64) -> String {
+    format!("{}/{}", u64_to_str(a), u64_to_str(b))
+}
+
+/// Cells of a dependency row, after the crate name
+fn row_cells(dep: &CrateStats) -> Vec<String> {
+    let mut cells = vec![dep.info.id.version().to_string()];
+    if let Some(details) = dep.details() {
+        cells.push(
+            match details.trust {
+                VerificationStatus::Verified => "pass",
+                VerificationStatus::Insufficient => "none",
+                VerificationStatus::Negative => "fail",
+            }
+            .to_owned(),
+        );
+        cells.push(pair(details.reviews.version, details.reviews.total));
+        cells.push(
+            details
+                .downloads
+                .as_ref()
+                .map_or("-".to_owned(), |d| pair(d.version, d.total)),
+        );
+        cells.push(
+            details
+                .owners
+                .as_ref()
+                .map_or("-".to_owned(), |o| pair(o.trusted as u64, o.total as u64)),
+        );
+        cells.push(pair(
+            details.issues.trusted as u64,
+            details.issues.total as u64,
+        ));
+        cells.push(
+            details
+                .loc
+                .map_or("-".to_owned(), |loc| u64_to_str(loc as u64)),
+        );
+    } else {
+        cells.push("?".to_owned());
+        cells.resize(COLUMNS.len(), "".to_owned());
+    }
+    cells
+}
+
+/// List of dependencies to pick the next one to review from
+pub struct ReviewScreen {
+    title: String,
+    deps: Vec<CrateStats>,
+    selected: usize,
+    scroll: usize,
+    std: CompoundStyle,
+    header: CompoundStyle,
+    highlighted: CompoundStyle,
+    good: CompoundStyle,
+    bad: CompoundStyle,
+    none: CompoundStyle,
+}
+
+impl ReviewScreen {
+    pub fn new(title: String, mut deps: Vec<CrateStats>) -> Self {
+        deps.sort_by_key(|dep| {
+            (
+                trust_gap(dep),
+                dep.details()
+                    .and_then(|details| details.downloads.as_ref())
+                    .map_or(0, |downloads| downloads.total),
+            )
+        });
+        Self {
+            title,
+            deps,
+            selected: 0,
+            scroll: 0,
+            std: CompoundStyle::default(),
+            header: CompoundStyle::new(Some(gray(22)), None, vec![Attribute::Bold]),
+            highlighted: CompoundStyle::with_attr(Attribute::Reverse),
+            good: CompoundStyle::with_fg(Green),
+            bad: CompoundStyle::with_fgbg(White, Red),
+            none: CompoundStyle::with_fg(gray(10)),
+        }
+    }
+
+    /// Replace the stats of a dependency, eg. after it was reviewed
+    pub fn update_dep(&mut self, dep: CrateStats) {
+        if let Some(old) = self.deps.iter_mut().find(|old| old.info.id == dep.info.id) {
+            *old = dep;
+        }
+    }
+
+    /// Let the user pick a dependency; `None` if they quit instead
+    pub fn select(&mut self) -> Result<Option<CrateInfo>> {
+        if self.deps.is_empty() {
+            return Ok(None);
+        }
+        let _alt_screen = AlternateScreen::to_alternate(true)?;
+        let cursor = TerminalCursor::new();
+        cursor.hide()?;
+
+        let event_source = EventSource::new();
+        let rx_user = event_source.receiver();
+        let mut selected = None;
+        self.display()?;
+        // The channel closes once the event source released the terminal
+        while let Ok(user_event) = rx_user.recv() {
+            let quit = match user_event {
+                Event::Key(KeyEvent::Ctrl('q')) | Event::Key(KeyEvent::Char('q')) => true,
+                Event::Key(KeyEvent::Char('\n')) => {
+                    selected = Some(self.deps[self.selected].info.clone());
+                    true
+                }
+                _ => {
+                    self.apply_event(&user_event);
+                    false
+                }
+            };
+            if !quit {
+                self.display()?;
+            }
+            event_source.unblock(quit);
+        }
+
+        cursor.show()?;
+        Ok(selected)
+    }
+
+    fn list_height(&self) -> usize {
+        let (_, h) = terminal_size();
+        (h as usize).saturating_sub(3).max(1)
+    }
+
+    fn move_selection(&mut self, lines_count: i32) {
+        let selected = self.selected as i32 + lines_count;
+        self.selected = selected.max(0).min(self.deps.len() as i32 - 1) as usize;
+        let height = self.list_height();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    fn apply_event(&mut self, user_event: &Event) {
+        let page = self.list_height() as i32;
+        match user_event {
+            Event::Key(KeyEvent::Up) | Event::Key(KeyEvent::Char('k')) => self.move_selection(-1),
+            Event::Key(KeyEvent::Down) | Event::Key(KeyEvent::Char('j')) => self.move_selection(1),
+            Event::Key(KeyEvent::PageUp) => self.move_selection(-page),
+            Event::Key(KeyEvent::PageDown) => self.move_selection(page),
+            Event::Key(KeyEvent::Home) => self.move_selection(-(self.deps.len() as i32)),
+            Event::Key(KeyEvent::End) => self.move_selection(self.deps.len() as i32),
+            Event::Wheel(lines_count) => self.move_selection(*lines_count),
+            _ => {}
+        }
+    }
+
+    fn display(&self) -> io::Result<()> {
+        let terminal = Terminal::new();
+        let cursor = TerminalCursor::new();
+        let name_width = self
+            .deps
+            .iter()
+            .map(|dep| dep.info.id.name().len())
+            .max()
+            .unwrap_or(0)
+            .max(5)
+            .min(40);
+
+        terminal.clear(ClearType::All)?;
+        cursor.goto(0, 0)?;
+        print!(
+            "{}",
+            self.header.apply_to(format!("crev : {}", &self.title))
+        );
+        cursor.goto(0, 1)?;
+        print!(
+            "{}",
+            self.header.apply_to(format!("{:<1$}", "crate", name_width))
+        );
+        for (title, width) in COLUMNS {
+            print!("{}", self.header.apply_to(format!(" {:>1$}", title, width)));
+        }
+
+        let height = self.list_height();
+        for (y, (i, dep)) in self
+            .deps
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(height)
+            .enumerate()
+        {
+            cursor.goto(0, 2 + y as u16)?;
+            let highlighted = i == self.selected;
+            let std = if highlighted {
+                &self.highlighted
+            } else {
+                &self.std
+            };
+            let name: String = dep.info.id.name().chars().take(name_width).collect();
+            print!("{}", std.apply_to(format!("{:<1$}", name, name_width)));
+            for (col_idx, (cell, (_, width))) in row_cells(dep).into_iter().zip(COLUMNS).enumerate()
+            {
+                let style = match (col_idx, cell.as_str()) {
+                    _ if highlighted => &self.highlighted,
+                    (1, "pass") => &self.good,
+                    (1, "fail") => &self.bad,
+                    (1, _) => &self.none,
+                    _ => &self.std,
+                };
+                print!("{}", style.apply_to(format!(" {:>1$}", cell, width)));
+            }
+        }
+
+        let (_, h) = terminal_size();
+        cursor.goto(0, h.saturating_sub(1))?;
+        print!(
+            "{}",
+            self.none.apply_to(
+                "Hit Enter to review the selected crate, Up/Down or PageUp/PageDown to move, q to quit"
+            )
+        );
+        io::stdout().flush()
+    }
+}
@@ -1,12 +1,12 @@
-use crossterm::{Attribute, ClearType, Color::*, KeyEvent, Terminal};
-use termimad::{
-    ansi, gray, terminal_size, Alignment, Area, CompoundStyle, Event, InputField, ListView,
-    ListViewCell, ListViewColumn, MadSkin,
-};
+use crossterm::{Attribute, ClearType, Color::*, KeyEvent, Terminal, TerminalCursor};
+use std::io::{self, Write};
+use termimad::{ansi, gray, terminal_size, Area, CompoundStyle, Event, InputField, MadSkin};
 
-use crate::deps::{latest_trusted_version_string, CrateDetails, CrateStats, Progress};
+use super::review_screen::{pair, trust_gap};
+use crate::deps::{latest_trusted_version_string, CrateInfo, CrateStats, Progress};
 use crate::prelude::*;
 use crate::repo::Repo;
+use crev_data::proof::review::Package as PackageReview;
 use crev_lib::VerificationStatus;
 
 /// the styles that can be applied to cells of the dep list
@@ -16,6 +16,8 @@ struct DepTableSkin {
     medium: CompoundStyle,
     good: CompoundStyle,
     none: CompoundStyle,
+    header: CompoundStyle,
+    highlighted: CompoundStyle,
 }
 
 impl Default for DepTableSkin {
@@ -26,17 +28,317 @@ impl Default for DepTableSkin {
             medium: CompoundStyle::with_fg(Yellow),
             good: CompoundStyle::with_fg(Green),
             none: CompoundStyle::with_fg(gray(10)),
+            header: CompoundStyle::new(Some(gray(22)), None, vec![Attribute::Bold]),
+            highlighted: CompoundStyle::with_attr(Attribute::Reverse),
         }
     }
 }
 
-fn list_skin() -> MadSkin {
-    let mut skin = MadSkin::default();
-    skin.table.align = Alignment::Center;
-    skin.bold.set_fg(gray(22));
-    skin.italic.set_fg(ansi(153));
-    skin.headers[0].compound_style = CompoundStyle::with_attr(Attribute::Bold);
-    skin
+/// (title, width) of the columns, the crate name column is sized to fit
+const COLUMNS: &[(&str, usize)] = &[
+    ("version", 12),
+    ("trust", 6),
+    ("last trusted", 12),
+    ("reviews", 9),
+    ("downloads", 13),
+    ("owners", 7),
+    ("issues", 7),
+    ("l.o.c.", 7),
+];
+
+/// Cells of a dependency row after the crate name, with their style
+fn row_cells<'s>(dep: &CrateStats, ts: &'s DepTableSkin) -> Vec<(String, &'s CompoundStyle)> {
+    let mut cells = vec![(dep.info.id.version().to_string(), &ts.std)];
+    let details = match dep.details() {
+        Some(details) => details,
+        None => {
+            cells.push(("?".to_owned(), &ts.medium));
+            cells.resize(COLUMNS.len(), ("".to_owned(), &ts.std));
+            return cells;
+        }
+    };
+    cells.push(match details.trust {
+        _ if details.unclean_digest => ("dirty".to_owned(), &ts.medium),
+        VerificationStatus::Verified => ("pass".to_owned(), &ts.good),
+        VerificationStatus::Insufficient => ("none".to_owned(), &ts.none),
+        VerificationStatus::Negative => ("fail".to_owned(), &ts.bad),
+    });
+    cells.push((
+        latest_trusted_version_string(dep.info.id.version(), &details.latest_trusted_version),
+        &ts.std,
+    ));
+    cells.push((
+        pair(details.reviews.version, details.reviews.total),
+        &ts.std,
+    ));
+    cells.push(match &details.downloads {
+        Some(downloads) => (
+            pair(downloads.version, downloads.total),
+            if downloads.version < 1000 {
+                &ts.medium
+            } else {
+                &ts.std
+            },
+        ),
+        None => ("".to_owned(), &ts.std),
+    });
+    cells.push(match &details.owners {
+        Some(owners) => (
+            pair(owners.trusted as u64, owners.total as u64),
+            if owners.trusted > 0 {
+                &ts.good
+            } else {
+                &ts.std
+            },
+        ),
+        None => ("".to_owned(), &ts.std),
+    });
+    cells.push((
+        pair(details.issues.trusted as u64, details.issues.total as u64),
+        if details.issues.trusted > 0 {
+            &ts.bad
+        } else if details.issues.total > 0 {
+            &ts.medium
+        } else {
+            &ts.std
+        },
+    ));
+    cells.push((
+        details
+            .loc
+            .map_or("".to_owned(), |loc| u64_to_str(loc as u64)),
+        &ts.std,
+    ));
+    cells
+}
+
+/// Lines of the details pane of a dependency
+fn details_lines(dep: &CrateStats, reviews: &[(PackageReview, bool)]) -> Vec<String> {
+    let info = &dep.info;
+    let via: Vec<_> = info
+        .path
+        .iter()
+        .rev()
+        .map(|id| id.name().to_string())
+        .collect();
+    let mut lines = vec![
+        format!("{} {}", info.id.name(), info.id.version()),
+        format!("{} dependency, via {}", info.kind, via.join(" > ")),
+        "".to_owned(),
+    ];
+    let details = match dep.details() {
+        Some(details) => details,
+        None => {
+            lines.push("Not scanned yet".to_owned());
+            return lines;
+        }
+    };
+    lines.push(format!(
+        "trust: {}{}",
+        match details.trust {
+            VerificationStatus::Verified => "pass",
+            VerificationStatus::Insufficient => "none",
+            VerificationStatus::Negative => "fail",
+        },
+        if details.unclean_digest {
+            " (the local copy was modified)"
+        } else {
+            ""
+        },
+    ));
+    lines.push(format!(
+        "last trusted version: {}",
+        details
+            .latest_trusted_version
+            .as_ref()
+            .map_or("none".to_owned(), ToString::to_string)
+    ));
+    lines.push(format!(
+        "reviews: {} of this version, {} in total",
+        details.reviews.version, details.reviews.total
+    ));
+    lines.push(format!(
+        "issues: {} reported by trusted ids, {} in total{}",
+        details.issues.trusted,
+        details.issues.total,
+        details
+            .issues_severity
+            .map_or("".to_owned(), |severity| format!(
+                ", up to {} severity",
+                severity
+            )),
+    ));
+    lines.push(match &details.owners {
+        Some(owners) => format!(
+            "owners: {} trusted, {} in total",
+            owners.trusted, owners.total
+        ),
+        None => "owners: unknown".to_owned(),
+    });
+    if let Some(downloads) = &details.downloads {
+        lines.push(format!(
+            "downloads: {} of this version, {} in total",
+            downloads.version, downloads.total
+        ));
+    }
+    if let Some(loc) = details.loc {
+        lines.push(format!("lines of code: {}", loc));
+    }
+    if let Some(geiger_count) = details.geiger_count {
+        lines.push(format!("unsafe expressions: {}", geiger_count));
+    }
+    for (analyzer, output) in details.analyses.custom() {
+        lines.push(format!("{}: {}", analyzer, output.summary));
+    }
+    lines.push("".to_owned());
+    if reviews.is_empty() {
+        lines.push("No review of this version".to_owned());
+    }
+    for (review, trusted) in reviews {
+        lines.push(format!(
+            "{:9} {:8} {} {}/{} {}",
+            if *trusted { "trusted" } else { "untrusted" },
+            review.review.rating,
+            review.date.format("%Y-%m-%d"),
+            review.review.thoroughness,
+            review.review.understanding,
+            review.from.url.url,
+        ));
+        if !review.issues.is_empty() {
+            lines.push(format!(
+                "          reports {} issue(s)",
+                review.issues.len()
+            ));
+        }
+    }
+    lines
+}
+
+/// Order of the rows of the dependency list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    /// Least trusted first
+    Trust,
+    /// Biggest first
+    Loc,
+    /// Least reviewed first
+    Reviews,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Name => SortKey::Trust,
+            SortKey::Trust => SortKey::Loc,
+            SortKey::Loc => SortKey::Reviews,
+            SortKey::Reviews => SortKey::Name,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Trust => "trust",
+            SortKey::Loc => "l.o.c.",
+            SortKey::Reviews => "reviews",
+        }
+    }
+}
+
+/// Which rows of the dependency list are displayed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusFilter {
+    All,
+    NotVerified,
+    /// Failed verification, modified locally or with issues reported by trusted ids
+    Failing,
+    /// No review of the used version
+    Unreviewed,
+}
+
+impl StatusFilter {
+    fn next(self) -> Self {
+        match self {
+            StatusFilter::All => StatusFilter::NotVerified,
+            StatusFilter::NotVerified => StatusFilter::Failing,
+            StatusFilter::Failing => StatusFilter::Unreviewed,
+            StatusFilter::Unreviewed => StatusFilter::All,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            StatusFilter::All => "all",
+            StatusFilter::NotVerified => "not verified",
+            StatusFilter::Failing => "failing",
+            StatusFilter::Unreviewed => "unreviewed",
+        }
+    }
+
+    pub fn accepts(self, dep: &CrateStats) -> bool {
+        let details = match dep.details() {
+            Some(details) => details,
+            None => return self == StatusFilter::All,
+        };
+        match self {
+            StatusFilter::All => true,
+            StatusFilter::NotVerified => match details.trust {
+                VerificationStatus::Verified => details.unclean_digest,
+                _ => true,
+            },
+            StatusFilter::Failing => match details.trust {
+                VerificationStatus::Negative => true,
+                _ => details.unclean_digest || details.issues.trusted > 0,
+            },
+            StatusFilter::Unreviewed => details.reviews.version == 0,
+        }
+    }
+}
+
+/// Indices in `deps` of the rows to display, in display order
+pub fn visible_deps(
+    deps: &[CrateStats],
+    sort: SortKey,
+    filter: StatusFilter,
+    pattern: &str,
+) -> Vec<usize> {
+    let mut view: Vec<usize> = (0..deps.len())
+        .filter(|&i| filter.accepts(&deps[i]) && deps[i].info.id.name().contains(pattern))
+        .collect();
+    let sort_key = |dep: &CrateStats| -> (u8, std::cmp::Reverse<Option<usize>>, u64) {
+        let details = dep.details();
+        match sort {
+            SortKey::Name => Default::default(),
+            SortKey::Trust => (trust_gap(dep), Default::default(), 0),
+            SortKey::Loc => (
+                0,
+                std::cmp::Reverse(details.and_then(|details| details.loc)),
+                0,
+            ),
+            SortKey::Reviews => (
+                0,
+                Default::default(),
+                details.map_or(u64::max_value(), |details| details.reviews.total),
+            ),
+        }
+    };
+    view.sort_by(|&a, &b| {
+        let (a, b) = (&deps[a], &deps[b]);
+        sort_key(a)
+            .cmp(&sort_key(b))
+            .then_with(|| a.info.id.name().cmp(&b.info.id.name()))
+            .then_with(|| a.info.id.version().cmp(b.info.id.version()))
+    });
+    view
+}
+
+/// What the user asked for, beyond moving around the screen
+pub enum Action {
+    None,
+    Quit,
+    ShowDetails(CrateInfo),
+    Review(CrateInfo),
 }
 
 /// The whole screen
@@ -47,9 +349,21 @@ pub struct VerifyScreen<'t> {
     status_area: Area,
     status_skin: MadSkin,
     input_field: InputField,
+    /// Keystrokes go to the name filter
+    editing_pattern: bool,
     hint_area: Area,
-    list_view: ListView<'t, CrateStats>,
     skin: &'t MadSkin,
+    table_skin: &'t DepTableSkin,
+    deps: Vec<CrateStats>,
+    /// Indices in `deps` of the displayed rows
+    view: Vec<usize>,
+    sort: SortKey,
+    filter: StatusFilter,
+    /// Index in `view` of the selected row
+    selected: usize,
+    scroll: usize,
+    /// Reviews of the selected dependency, when its details are displayed
+    details: Option<Vec<(PackageReview, bool)>>,
     last_dimensions: (u16, u16),
     progress: crate::deps::Progress,
 }
@@ -72,7 +386,7 @@ impl<'t> VerifyScreen<'t> {
     pub fn new(total_crate_count: usize) -> Result<Self> {
         lazy_static! {
             static ref TS: DepTableSkin = DepTableSkin::default();
-            static ref SKIN: MadSkin = list_skin();
+            static ref SKIN: MadSkin = MadSkin::default();
         }
 
         let mut status_skin = MadSkin::default();
@@ -83,214 +397,6 @@ impl<'t> VerifyScreen<'t> {
         title_skin.headers[0].compound_style =
             CompoundStyle::new(Some(gray(22)), None, vec![Attribute::Bold]);
 
-        let columns = vec![
-            ListViewColumn::new(
-                "crate",
-                10,
-                80,
-                Box::new(|dep: &CrateStats| {
-                    ListViewCell::new(dep.info.id.name().to_string(), &TS.std)
-                }),
-            )
-            .with_align(Alignment::Left),
-            ListViewColumn::new(
-                "version",
-                9,
-                13,
-                Box::new(|dep: &CrateStats| {
-                    ListViewCell::new(dep.info.id.version().to_string(), &TS.std)
-                }),
-            )
-            .with_align(Alignment::Right),
-            ListViewColumn::new(
-                "trust",
-                6,
-                6,
-                Box::new(|dep: &CrateStats| {
-                    if let Some(details) = dep.details() {
-                        match details.trust {
-                            VerificationStatus::Verified => {
-                                ListViewCell::new("pass".to_owned(), &TS.good)
-                            }
-                            VerificationStatus::Insufficient => {
-                                ListViewCell::new("none".to_owned(), &TS.none)
-                            }
-                            VerificationStatus::Negative => {
-                                ListViewCell::new("fail".to_owned(), &TS.bad)
-                            }
-                        }
-                    } else {
-                        ListViewCell::new("?".to_string(), &TS.medium)
-                    }
-                }),
-            ),
-            ListViewColumn::new(
-                "last trusted",
-                12,
-                16,
-                Box::new(|dep: &CrateStats| {
-                    ListViewCell::new(
-                        dep.details().map_or("?".to_owned(), |cdep| {
-                            latest_trusted_version_string(
-                                &dep.info.id.version(),
-                                &cdep.latest_trusted_version,
-                            )
-                        }),
-                        &TS.std,
-                    )
-                }),
-            )
-            .with_align(Alignment::Right),
-            ListViewColumn::new(
-                "reviews",
-                3,
-                3,
-                Box::new(|dep: &CrateStats| {
-                    ListViewCell::new(
-                        dep.details()
-                            .map_or("?".to_owned(), |cdep| u64_to_str(cdep.reviews.version)),
-                        &TS.std,
-                    )
-                }),
-            )
-            .with_align(Alignment::Center),
-            ListViewColumn::new(
-                "reviews",
-                3,
-                3,
-                Box::new(|dep: &CrateStats| {
-                    ListViewCell::new(
-                        dep.details()
-                            .map_or("?".to_owned(), |cdep| u64_to_str(cdep.reviews.total)),
-                        &TS.std,
-                    )
-                }),
-            )
-            .with_align(Alignment::Center),
-            ListViewColumn::new(
-                "downloads",
-                6,
-                6,
-                Box::new(|dep: &CrateStats| {
-                    if let Some(CrateDetails {
-                        downloads: Some(downloads),
-                        ..
-                    }) = dep.details()
-                    {
-                        ListViewCell::new(
-                            u64_to_str(downloads.version),
-                            if downloads.version < 1000 {
-                                &TS.medium
-                            } else {
-                                &TS.std
-                            },
-                        )
-                    } else {
-                        ListViewCell::new("".to_string(), &TS.std)
-                    }
-                }),
-            )
-            .with_align(Alignment::Right),
-            ListViewColumn::new(
-                "downloads",
-                6,
-                6,
-                Box::new(|dep: &CrateStats| {
-                    if let Some(CrateDetails {
-                        downloads: Some(downloads),
-                        ..
-                    }) = dep.details()
-                    {
-                        ListViewCell::new(
-                            u64_to_str(downloads.total),
-                            if downloads.total < 1000 {
-                                &TS.medium
-                            } else {
-                                &TS.std
-                            },
-                        )
-                    } else {
-                        ListViewCell::new("".to_string(), &TS.std)
-                    }
-                }),
-            )
-            .with_align(Alignment::Right),
-            ListViewColumn::new(
-                "owners",
-                2,
-                2,
-                Box::new(|dep: &CrateStats| match dep.details() {
-                    Some(CrateDetails {
-                        owners: Some(owners),
-                        ..
-                    }) if owners.trusted > 0 => {
-                        ListViewCell::new(format!("{}", owners.trusted), &TS.good)
-                    }
-                    _ => ListViewCell::new("".to_owned(), &TS.std),
-                }),
-            )
-            .with_align(Alignment::Right),
-            ListViewColumn::new(
-                "owners",
-                3,
-                3,
-                Box::new(|dep: &CrateStats| {
-                    ListViewCell::new(
-                        match dep.details() {
-                            Some(CrateDetails {
-                                owners: Some(owners),
-                                ..
-                            }) if owners.total > 0 => format!("{}", owners.total),
-                            _ => "".to_owned(),
-                        },
-                        &TS.std,
-                    )
-                }),
-            )
-            .with_align(Alignment::Right),
-            ListViewColumn::new(
-                "issues",
-                2,
-                2,
-                Box::new(|dep: &CrateStats| match dep.details() {
-                    Some(CrateDetails { issues, .. }) if issues.trusted > 0 => {
-                        ListViewCell::new(format!("{}", issues.trusted), &TS.bad)
-                    }
-                    _ => ListViewCell::new("".to_owned(), &TS.std),
-                }),
-            )
-            .with_align(Alignment::Right),
-            ListViewColumn::new(
-                "issues",
-                3,
-                3,
-                Box::new(|dep: &CrateStats| match dep.details() {
-                    Some(CrateDetails { issues, .. }) if issues.total > 0 => {
-                        ListViewCell::new(format!("{}", issues.total), &TS.medium)
-                    }
-                    _ => ListViewCell::new("".to_owned(), &TS.std),
-                }),
-            )
-            .with_align(Alignment::Right),
-            ListViewColumn::new(
-                "l.o.c.",
-                6,
-                6,
-                Box::new(|dep: &CrateStats| {
-                    ListViewCell::new(
-                        match dep.details() {
-                            Some(CrateDetails { loc: Some(loc), .. }) => u64_to_str(*loc as u64),
-                            _ => "".to_string(),
-                        },
-                        &TS.std,
-                    )
-                }),
-            )
-            .with_align(Alignment::Right),
-        ];
-
-        let list_view = ListView::new(Area::new(0, 1, 10, 10), columns, &SKIN);
-
         let repo = Repo::auto_open_cwd()?; // TODO not extra clean
         let title = repo.name().to_string();
         let mut screen = Self {
@@ -299,10 +405,18 @@ impl<'t> VerifyScreen<'t> {
             title_skin,
             status_area: Area::new(0, 2, 10, 1),
             input_field: InputField::new(Area::new(0, 3, 10, 1)),
+            editing_pattern: false,
             hint_area: Area::new(0, 3, 10, 1),
-            list_view,
             skin: &SKIN,
+            table_skin: &TS,
             status_skin,
+            deps: vec![],
+            view: vec![],
+            sort: SortKey::Name,
+            filter: StatusFilter::All,
+            selected: 0,
+            scroll: 0,
+            details: None,
             last_dimensions: (0, 0),
             progress: Progress {
                 done: 0,
@@ -312,12 +426,49 @@ impl<'t> VerifyScreen<'t> {
         screen.resize();
         Ok(screen)
     }
+    /// Number of crates scanned so far
+    pub fn computation_status(&self) -> usize {
+        self.progress.done
+    }
     pub fn set_computation_status(&mut self, done: usize) {
         self.progress.done = done;
         assert!(self.progress.is_valid());
     }
     pub fn add_dep(&mut self, dep: CrateStats) {
-        self.list_view.add_row(dep);
+        self.deps.push(dep);
+        self.refresh_view();
+    }
+    /// Replace the stats of a dependency, eg. after it was reviewed
+    pub fn update_dep(&mut self, dep: CrateStats) {
+        if let Some(old) = self.deps.iter_mut().find(|old| old.info.id == dep.info.id) {
+            *old = dep;
+        }
+        self.refresh_view();
+    }
+    /// Display the details of the selected dependency, with its reviews
+    pub fn show_details(&mut self, reviews: Vec<(PackageReview, bool)>) {
+        self.details = Some(reviews);
+    }
+    fn selected_dep(&self) -> Option<&CrateStats> {
+        self.view.get(self.selected).map(|&i| &self.deps[i])
+    }
+    /// Recompute the displayed rows, keeping the same dependency selected
+    fn refresh_view(&mut self) {
+        let selected_id = self.selected_dep().map(|dep| dep.info.id);
+        self.view = visible_deps(
+            &self.deps,
+            self.sort,
+            self.filter,
+            &self.input_field.get_content(),
+        );
+        self.selected = selected_id
+            .and_then(|id| self.view.iter().position(|&i| self.deps[i].info.id == id))
+            .unwrap_or(0);
+        self.move_selection(0);
+    }
+    /// Make the next update redraw the whole screen
+    pub fn invalidate(&mut self) {
+        self.last_dimensions = (0, 0);
     }
     pub fn resize(&mut self) {
         let (w, h) = terminal_size();
@@ -327,32 +478,109 @@ impl<'t> VerifyScreen<'t> {
         Terminal::new().clear(ClearType::All).unwrap();
         self.last_dimensions = (w, h);
         self.title_area.width = w;
-        self.list_view.area.width = w;
-        self.list_view.area.height = h - 4;
-        self.list_view.update_dimensions();
         self.status_area.top = h - 3;
         self.status_area.width = w;
         self.input_field.change_area(0, h - 2, w / 2);
         self.hint_area.top = h - 2;
-        self.hint_area.left = self.input_field.area.width;
-        self.hint_area.width = w - self.hint_area.left;
+        self.hint_area.width = w;
+        self.move_selection(0);
+    }
+    fn list_height(&self) -> usize {
+        (self.last_dimensions.1 as usize).saturating_sub(5).max(1)
+    }
+    fn move_selection(&mut self, lines_count: i32) {
+        let selected = self.selected as i32 + lines_count;
+        self.selected = selected.min(self.view.len() as i32 - 1).max(0) as usize;
+        let height = self.list_height();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
     }
     fn update_title(&self) {
         self.title_skin
             .write_in_area(&format!("# *crev* : {}", &self.title), &self.title_area)
             .unwrap();
     }
-    fn update_list_view(&mut self) {
-        if !self.progress.is_complete() {
-            self.skin
-                .write_in_area(
-                    &format!("\n*preparing table... You may quit at any time with ctrl-q*"),
-                    &self.list_view.area,
-                )
-                .unwrap();
-        } else {
-            self.list_view.display().unwrap();
+    fn update_list_view(&self) -> io::Result<()> {
+        let terminal = Terminal::new();
+        let cursor = TerminalCursor::new();
+        let ts = self.table_skin;
+        let width = self.last_dimensions.0 as usize;
+        let name_width = self
+            .deps
+            .iter()
+            .map(|dep| dep.info.id.name().len())
+            .max()
+            .unwrap_or(0)
+            .max(5)
+            .min(40);
+        // only the columns fitting in the terminal
+        let mut row_width = name_width;
+        let columns_count = COLUMNS
+            .iter()
+            .take_while(|(_, column_width)| {
+                row_width += column_width + 1;
+                row_width < width
+            })
+            .count();
+
+        cursor.goto(0, 1)?;
+        print!(
+            "{}",
+            ts.header.apply_to(format!("{:<1$}", "crate", name_width))
+        );
+        for (title, column_width) in &COLUMNS[..columns_count] {
+            print!(
+                "{}",
+                ts.header.apply_to(format!(" {:>1$}", title, column_width))
+            );
+        }
+        terminal.clear(ClearType::UntilNewLine)?;
+
+        let height = self.list_height();
+        let details = match (&self.details, self.selected_dep()) {
+            (Some(reviews), Some(dep)) => Some(details_lines(dep, reviews)),
+            _ => None,
+        };
+        for y in 0..height {
+            cursor.goto(0, 2 + y as u16)?;
+            if let Some(details) = &details {
+                if let Some(line) = details.get(y) {
+                    let line: String = line.chars().take(width).collect();
+                    print!("{}", ts.std.apply_to(line));
+                }
+            } else if let Some(&i) = self.view.get(self.scroll + y) {
+                let dep = &self.deps[i];
+                let highlighted = self.scroll + y == self.selected;
+                let name: String = dep.info.id.name().chars().take(name_width).collect();
+                let std = if highlighted {
+                    &ts.highlighted
+                } else {
+                    &ts.std
+                };
+                print!("{}", std.apply_to(format!("{:<1$}", name, name_width)));
+                for ((cell, style), (_, column_width)) in row_cells(dep, ts)
+                    .into_iter()
+                    .zip(&COLUMNS[..columns_count])
+                {
+                    let style = if highlighted { &ts.highlighted } else { style };
+                    print!("{}", style.apply_to(format!(" {:>1$}", cell, column_width)));
+                }
+            } else if y == 0 && self.view.is_empty() {
+                print!(
+                    "{}",
+                    ts.none.apply_to(if self.deps.is_empty() {
+                        "Waiting for the first crates to be scanned..."
+                    } else {
+                        "No dependency matches the filters"
+                    })
+                );
+            }
+            terminal.clear(ClearType::UntilNewLine)?;
         }
+        io::stdout().flush()
     }
 
     fn all_deps_ready(&self) -> bool {
@@ -368,11 +596,13 @@ impl<'t> VerifyScreen<'t> {
                 self.progress.done, self.progress.total
             )
         };
-        let (displayed, total) = self.list_view.row_counts();
-        if displayed < total {
+        status.push_str(&format!(" - sorted by *{}*", self.sort.label()));
+        if self.view.len() < self.deps.len() {
             status.push_str(&format!(
-                " - **Filtered list** displays *{}* / *{}*. Hit `<esc>` to show all",
-                displayed, total
+                " - **Filtered list** ({}) displays *{}* / *{}*. Hit `<esc>` to show all",
+                self.filter.label(),
+                self.view.len(),
+                self.deps.len()
             ));
         }
         self.status_skin
@@ -380,65 +610,108 @@ impl<'t> VerifyScreen<'t> {
             .unwrap();
     }
     fn update_input(&self) {
-        self.input_field.display();
+        if self.editing_pattern {
+            self.input_field.display();
+        }
     }
     fn update_hint(&self) {
+        let left = if self.editing_pattern {
+            self.input_field.area.width
+        } else {
+            0
+        };
+        let hint_area = Area::new(left, self.hint_area.top, self.hint_area.width - left, 1);
         self.skin
             .write_in_area(
-                if self.all_deps_ready() {
-                    "Hit *ctrl-q* to quit, *PageUp* or *PageDown* to scroll"
+                if self.editing_pattern {
+                    "Type a crate name, *enter* to keep the filter"
+                } else if self.details.is_some() {
+                    "*enter* or *esc* back to the list, *r* review the crate"
                 } else {
-                    "Hit *ctrl-q* to quit"
+                    "*enter* details, *r* review, *s* sort, *f* filter, */* search, *q* quit"
                 },
-                &self.hint_area,
+                &hint_area,
             )
             .unwrap();
     }
     pub fn update(&mut self) {
         self.resize();
         self.update_title();
-        self.update_list_view();
+        self.update_list_view().unwrap();
         self.update_status();
         self.update_input();
         self.update_hint();
     }
-    #[allow(dead_code)]
-    pub fn try_scroll_lines(&mut self, lines_count: i32) {
-        self.list_view.try_scroll_lines(lines_count);
-    }
-    /// set the scroll amount.
-    /// pages_count can be negative
-    pub fn try_scroll_pages(&mut self, pages_count: i32) {
-        self.list_view.try_scroll_pages(pages_count);
+    fn selected_info(&self) -> Option<CrateInfo> {
+        self.selected_dep().map(|dep| dep.info.clone())
     }
     /// handle a user event
-    pub fn apply_event(&mut self, user_event: &Event) {
+    pub fn apply_event(&mut self, user_event: &Event) -> Action {
+        if self.editing_pattern {
+            match user_event {
+                Event::Key(KeyEvent::Char('\n')) => self.editing_pattern = false,
+                Event::Key(KeyEvent::Esc) => {
+                    self.editing_pattern = false;
+                    self.input_field.set_content("");
+                    self.refresh_view();
+                }
+                _ => {
+                    if self.input_field.apply_event(user_event) {
+                        self.refresh_view();
+                    }
+                }
+            }
+            return Action::None;
+        }
+        if self.details.is_some() {
+            match user_event {
+                Event::Key(KeyEvent::Ctrl('q')) => return Action::Quit,
+                Event::Key(KeyEvent::Char('r')) => {
+                    return self.selected_info().map_or(Action::None, Action::Review)
+                }
+                Event::Key(KeyEvent::Char('\n'))
+                | Event::Key(KeyEvent::Char('q'))
+                | Event::Key(KeyEvent::Esc) => self.details = None,
+                _ => {}
+            }
+            return Action::None;
+        }
+        let page = self.list_height() as i32;
         match user_event {
-            Event::Key(KeyEvent::PageUp) => {
-                self.try_scroll_pages(-1);
+            Event::Key(KeyEvent::Ctrl('q')) | Event::Key(KeyEvent::Char('q')) => {
+                return Action::Quit
             }
-            Event::Key(KeyEvent::PageDown) => {
-                self.try_scroll_pages(1);
+            Event::Key(KeyEvent::Char('\n')) => {
+                return self
+                    .selected_info()
+                    .map_or(Action::None, Action::ShowDetails)
             }
-            Event::Wheel(lines_count) => {
-                self.try_scroll_lines(*lines_count);
+            Event::Key(KeyEvent::Char('r')) => {
+                return self.selected_info().map_or(Action::None, Action::Review)
             }
+            Event::Key(KeyEvent::Char('s')) => {
+                self.sort = self.sort.next();
+                self.refresh_view();
+            }
+            Event::Key(KeyEvent::Char('f')) => {
+                self.filter = self.filter.next();
+                self.refresh_view();
+            }
+            Event::Key(KeyEvent::Char('/')) => self.editing_pattern = true,
             Event::Key(KeyEvent::Esc) => {
                 self.input_field.set_content("");
-                self.list_view.remove_filter();
-            }
-            _ => {
-                if self.input_field.apply_event(user_event) {
-                    let pattern = self.input_field.get_content();
-                    if pattern.len() > 0 {
-                        self.list_view.set_filter(Box::new(move |dep: &CrateStats| {
-                            dep.info.id.name().contains(&pattern)
-                        }));
-                    } else {
-                        self.list_view.remove_filter();
-                    }
-                }
+                self.filter = StatusFilter::All;
+                self.refresh_view();
             }
+            Event::Key(KeyEvent::Up) | Event::Key(KeyEvent::Char('k')) => self.move_selection(-1),
+            Event::Key(KeyEvent::Down) | Event::Key(KeyEvent::Char('j')) => self.move_selection(1),
+            Event::Key(KeyEvent::PageUp) => self.move_selection(-page),
+            Event::Key(KeyEvent::PageDown) => self.move_selection(page),
+            Event::Key(KeyEvent::Home) => self.move_selection(-(self.view.len() as i32)),
+            Event::Key(KeyEvent::End) => self.move_selection(self.view.len() as i32),
+            Event::Wheel(lines_count) => self.move_selection(*lines_count),
+            _ => {}
         }
+        Action::None
     }
 }
@@ -57,7 +57,7 @@ fn run_on_deps<'a>(
     let _alt_screen = AlternateScreen::to_alternate(true);
     let local = crev_lib::Local::auto_create_or_open()?;
     let db = local.load_db()?;
-    let ignore_list = cargo_min_ignore_list();
+    let digest_options = cargo_min_ignore_list().into();
     let trust_set = if let Some(for_id) = local.get_for_id_from_str_opt(args.for_id.as_deref())? {
         db.calculate_trust_set(&for_id, &args.trust_params.clone().into())
     } else {
@@ -104,7 +104,7 @@ fn run_on_deps<'a>(
         let crate_version = crate_id.version();
         let crate_root = crate_.root();
 
-        let digest = crev_lib::get_dir_digest(&crate_root, &ignore_list)?;
+        let digest = crev_lib::get_dir_digest(&crate_root, &digest_options)?;
 
         if !is_digest_clean(&db, &crate_name, &crate_version, &digest) {
             unclean_digests.insert(
@@ -0,0 +1,127 @@
+//! Checking that a crate published on crates.io matches its git repository
+
+use crate::opts;
+use crate::prelude::*;
+use crate::repo::Repo;
+use crate::shared::*;
+use crate::term;
+use crev_lib::util::git;
+use std::path::{Path, PathBuf};
+
+/// Directory of the `name` package in a checked out repository
+fn find_package_dir(root: &Path, name: &str) -> Result<Option<PathBuf>> {
+    for entry in walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let file_name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(file_name.starts_with('.') || file_name == "target")
+        })
+    {
+        let entry = entry?;
+        if entry.file_name() != "Cargo.toml" || !entry.file_type().is_file() {
+            continue;
+        }
+        let manifest: toml::Value =
+            match toml::from_str(&crev_common::read_file_to_string(entry.path())?) {
+                Ok(manifest) => manifest,
+                Err(_) => continue,
+            };
+        let package_name = manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str());
+        if package_name == Some(name) {
+            return Ok(entry.path().parent().map(ToOwned::to_owned));
+        }
+    }
+    Ok(None)
+}
+
+/// Compare the packaged source of a crate with the tagged source in its repository
+///
+/// The packaged source must be a subset of the tagged one: every file
+/// must also be in git, with the same content. Files `cargo package`
+/// generates are skipped, except the original `Cargo.toml` kept as
+/// `Cargo.toml.orig`.
+pub fn verify_crate_against_git(args: &opts::CrateVerifyGit) -> Result<CommandExitStatus> {
+    let repo = Repo::auto_open_cwd()?;
+    let pkg = repo.find_crate(
+        &args.name,
+        args.version.as_ref(),
+        UnrelatedOrDependency::Unrelated,
+    )?;
+    let version = pkg.version();
+    let crate_root = pkg.root();
+    let url = pkg
+        .manifest()
+        .metadata()
+        .repository
+        .clone()
+        .ok_or_else(|| format_err!("{} {} doesn't specify its `repository`", args.name, version))?;
+
+    let packaged_from =
+        VcsInfoJson::read_from_crate_dir(crate_root)?.and_then(|vcs| vcs.get_git_revision());
+    let tags = git::list_remote_tags(&url)?;
+    let (rev, rev_name) = match git::find_version_tag(&tags, &args.name, version) {
+        Some((tag, commit)) => {
+            if let Some(packaged_from) = packaged_from.as_ref() {
+                if packaged_from != commit {
+                    eprintln!(
+                        "WARNING: Tag {} points to {}, but the crate was packaged from {}",
+                        tag, commit, packaged_from
+                    );
+                }
+            }
+            (
+                format!("refs/tags/{}", tag),
+                format!("tag {} (commit {})", tag, commit),
+            )
+        }
+        None => match packaged_from {
+            Some(commit) => {
+                eprintln!(
+                    "No tag found for version {}; using commit {} the crate was packaged from",
+                    version, commit
+                );
+                (commit.clone(), format!("commit {}", commit))
+            }
+            None => bail!("No tag found for version {} in {}", version, url),
+        },
+    };
+
+    let tmp_dir = tempdir::TempDir::new("crev-verify-git")?;
+    let checkout_dir = tmp_dir.path().join("checkout");
+    git::shallow_checkout_git_rev(&url, &rev, &checkout_dir)?;
+    let git_pkg_dir = find_package_dir(&checkout_dir, &args.name)?
+        .ok_or_else(|| format_err!("No package named {} in {} at {}", args.name, url, rev_name))?;
+
+    let report = crev_lib::compare_package_to_repo(crate_root, &checkout_dir, &git_pkg_dir)?;
+
+    println!("{} {} from {} at {}", args.name, version, url, rev_name);
+    println!("{:>6} identical files", report.identical);
+    let mut term = term::Term::new();
+    for (paths, status) in &[
+        (&report.only_in_package, "not in git"),
+        (&report.different, "modified"),
+    ] {
+        for path in paths.iter() {
+            term.print(format_args!("{:10}", status), ::term::color::RED)?;
+            println!(" {}", path.display());
+        }
+    }
+    println!(
+        "{:>6} files in git are not packaged",
+        report.only_in_repo.len()
+    );
+
+    if report.is_faithful() {
+        println!("{} {} matches {}", args.name, version, rev_name);
+        Ok(CommandExitStatus::Successs)
+    } else {
+        println!(
+            "{} {} is NOT a faithful subset of {}",
+            args.name, version, rev_name
+        );
+        Ok(CommandExitStatus::VerificationFailed)
+    }
+}
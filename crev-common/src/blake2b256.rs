@@ -1,4 +1,5 @@
-use digest::{self, VariableOutput};
+use digest::{self, FixedOutput, Input, VariableOutput};
+use std::path::Path;
 
 #[derive(Debug, Clone)]
 pub struct Blake2b256(blake2::VarBlake2b);
@@ -34,3 +35,26 @@ impl digest::Reset for Blake2b256 {
         self.0.reset()
     }
 }
+
+/// Blake2b256 digest of data fed in chunks, eg. from several files
+#[derive(Debug, Clone, Default)]
+pub struct IncrementalHasher(Blake2b256);
+
+impl IncrementalHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.input(data)
+    }
+
+    /// Feed the whole content of a file
+    pub fn update_file(&mut self, path: &Path) -> crate::Result<()> {
+        crate::read_file_to_digest_input(path, &mut self.0)
+    }
+
+    pub fn finalise(self) -> Vec<u8> {
+        self.0.fixed_result().to_vec()
+    }
+}
@@ -0,0 +1,119 @@
+//! Errors of `crev-common` functions
+
+use std::{fmt, io, path::PathBuf};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Parsing YAML failed
+    YamlDe(serde_yaml::Error),
+    /// Serializing to YAML failed
+    YamlSer(serde_yaml::Error),
+    /// Parsing TOML failed
+    TomlDe(toml::de::Error),
+    /// Serializing to TOML failed
+    TomlSer(toml::ser::Error),
+    Base64(base64::DecodeError),
+    /// A path that can't be written to, eg. the root directory
+    InvalidPath(PathBuf),
+    /// Another process is still holding a `FileLock`
+    LockTimeout(PathBuf),
+    /// A versioned document that isn't a mapping, or with an invalid version
+    InvalidVersion(String),
+    /// A versioned document written by a newer crev
+    UnsupportedVersion {
+        version: u64,
+        supported: u64,
+    },
+    /// A date that can't be parsed, even leniently
+    InvalidDate(String),
+    /// A file longer than allowed by `read_file_to_string_limited`
+    FileTooLarge {
+        path: PathBuf,
+        max_bytes: u64,
+    },
+    /// The user chose not to try again
+    Cancelled,
+    /// Error reading or parsing the file at `path`
+    InFile {
+        path: PathBuf,
+        error: Box<Error>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::YamlDe(e) => write!(f, "Invalid YAML: {}", e),
+            Error::YamlSer(e) => write!(f, "Can't serialize to YAML: {}", e),
+            Error::TomlDe(e) => write!(f, "Invalid TOML: {}", e),
+            Error::TomlSer(e) => write!(f, "Can't serialize to TOML: {}", e),
+            Error::Base64(e) => write!(f, "Invalid base64: {}", e),
+            Error::InvalidPath(path) => write!(f, "Invalid path `{}`", path.display()),
+            Error::LockTimeout(path) => write!(f, "{} is locked", path.display()),
+            Error::InvalidVersion(msg) => f.write_str(msg),
+            Error::UnsupportedVersion { version, supported } => write!(
+                f,
+                "Written by a newer crev (format version {}, this crev supports up to {}); please upgrade",
+                version, supported
+            ),
+            Error::InvalidDate(date) => write!(f, "Invalid date `{}`", date),
+            Error::FileTooLarge { path, max_bytes } => write!(
+                f,
+                "File too large: {} is over the limit of {} bytes",
+                path.display(),
+                max_bytes
+            ),
+            Error::Cancelled => f.write_str("Canceled by the user"),
+            Error::InFile { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::YamlDe(e) | Error::YamlSer(e) => Some(e),
+            Error::TomlDe(e) => Some(e),
+            Error::TomlSer(e) => Some(e),
+            Error::Base64(e) => Some(e),
+            Error::InFile { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Parsing is the more common failure; serialization errors are wrapped in `YamlSer` explicitly
+impl From<serde_yaml::Error> for Error {
+    fn from(e: serde_yaml::Error) -> Self {
+        Error::YamlDe(e)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::TomlDe(e)
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(e: toml::ser::Error) -> Self {
+        Error::TomlSer(e)
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(e: base64::DecodeError) -> Self {
+        Error::Base64(e)
+    }
+}
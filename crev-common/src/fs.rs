@@ -1,10 +1,15 @@
+use crate::{Error, Result};
+use fs2::FileExt;
 use std::{
     fs,
+    io::{self, Read},
     path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
 };
 
 /// Move dir content from `from` dir to `to` dir
-pub fn move_dir_content(from: &Path, to: &Path) -> std::io::Result<()> {
+pub fn move_dir_content(from: &Path, to: &Path) -> Result<()> {
     fs::create_dir_all(&to)?;
 
     for entry in fs::read_dir(&from)? {
@@ -19,8 +24,266 @@ pub fn move_dir_content(from: &Path, to: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Content of a dir moved aside, moved back on drop unless `commit`ed
+///
+/// Entries are moved one by one, instead of the whole dir, so that
+/// processes with `dir` as their current directory are not confused.
+pub struct DirSwapGuard {
+    dir: PathBuf,
+    aside: PathBuf,
+    committed: bool,
+}
+
+impl DirSwapGuard {
+    /// Move the content of `dir` to `aside`, which must not exist
+    pub fn new(dir: &Path, aside: &Path) -> Result<Self> {
+        if aside.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", aside.display()),
+            )
+            .into());
+        }
+        if let Err(e) = move_dir_content(dir, aside) {
+            // move back whatever was moved so far
+            let _ = move_dir_content(aside, dir);
+            let _ = fs::remove_dir(aside);
+            return Err(e);
+        }
+        Ok(DirSwapGuard {
+            dir: dir.to_owned(),
+            aside: aside.to_owned(),
+            committed: false,
+        })
+    }
+
+    pub fn aside_path(&self) -> &Path {
+        &self.aside
+    }
+
+    /// Keep the new content of `dir`, and the old one in the aside dir
+    pub fn commit(mut self) -> PathBuf {
+        self.committed = true;
+        self.aside.clone()
+    }
+
+    fn restore(&self) -> Result<()> {
+        if self.dir.is_dir() {
+            for entry in fs::read_dir(&self.dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                if entry.file_type()?.is_dir() {
+                    fs::remove_dir_all(&path)?;
+                } else {
+                    fs::remove_file(&path)?;
+                }
+            }
+        }
+        move_dir_content(&self.aside, &self.dir)?;
+        Ok(fs::remove_dir(&self.aside)?)
+    }
+}
+
+impl Drop for DirSwapGuard {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = self.restore();
+        }
+    }
+}
+
+/// Join an untrusted relative path to `base`, making sure it stays inside of it
+///
+/// `untrusted` can't be absolute, or have `..` components. The existing
+/// part of the result is canonicalized, and must be inside of the
+/// canonicalized `base`, so symlinks can't lead out of it either.
+pub fn safe_join(base: &Path, untrusted: &Path) -> Result<PathBuf> {
+    use std::path::Component;
+
+    let is_plain = untrusted
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !is_plain {
+        return Err(Error::InvalidPath(untrusted.to_owned()));
+    }
+    let joined = base.join(untrusted);
+
+    if let Ok(canonical_base) = base.canonicalize() {
+        // the deepest existing part decides where symlinks lead
+        if let Some(existing) = joined
+            .ancestors()
+            .find(|path| path.symlink_metadata().is_ok())
+        {
+            if !existing.canonicalize()?.starts_with(canonical_base) {
+                return Err(Error::InvalidPath(untrusted.to_owned()));
+            }
+        }
+    }
+    Ok(joined)
+}
+
+/// Directories `find_files_matching` doesn't look into: git metadata and build output
+pub const PRUNED_DIRS: &[&str] = &[".git", "target"];
+
+/// Is `path` a directory that shouldn't be searched, see `PRUNED_DIRS`
+pub fn is_pruned_dir(path: &Path) -> bool {
+    path.file_name().map_or(false, |name| {
+        PRUNED_DIRS.iter().any(|pruned| name == *pruned)
+    }) && path.is_dir()
+}
+
+/// All entries (files, directories and symlinks) under `dir`, and `dir` itself first
+///
+/// Directories are walked recursively, and symlinks are not followed.
+/// Entries for which `skip` is true are left out, along with everything
+/// in them.
+pub fn walk_dir<'a>(
+    dir: &Path,
+    mut skip: impl FnMut(&Path) -> bool + 'a,
+) -> impl Iterator<Item = io::Result<walkdir::DirEntry>> + 'a {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(move |entry| entry.depth() == 0 || !skip(entry.path()))
+        .map(|entry| entry.map_err(io::Error::from))
+}
+
+/// All files under `dir` for which `predicate` is true, sorted
+///
+/// Directories are walked recursively, except for `PRUNED_DIRS`, and
+/// symlinks are not followed.
+pub fn find_files_matching(
+    dir: &Path,
+    predicate: impl Fn(&Path) -> bool,
+) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in walk_dir(dir, is_pruned_dir) {
+        let entry = entry?;
+        if entry.file_type().is_file() && predicate(entry.path()) {
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// All files under `dir` with extension `ext` (without the leading `.`), sorted
+pub fn find_files_with_extension(dir: &Path, ext: &str) -> io::Result<Vec<PathBuf>> {
+    find_files_matching(dir, |path| path.extension().map_or(false, |e| e == ext))
+}
+
 pub fn append_to_path(path: PathBuf, ext: &str) -> PathBuf {
     let mut path = path.into_os_string();
     path.push(ext);
     path.into()
 }
+
+/// Path next to `path`, with a random suffix
+pub fn random_sibling_path(path: &Path, kind: &str) -> PathBuf {
+    append_to_path(
+        path.to_owned(),
+        &format!(".{}-{}", kind, crate::rand::secure_token(6)),
+    )
+}
+
+/// Replace `dest` dir with a new one, filled by `f`
+///
+/// `f` is given a temporary directory next to `dest`, which then takes
+/// place of `dest`. If `f` fails, `dest` is left untouched.
+///
+/// An existing `dest` is first moved aside, as directories can't be renamed
+/// over non-empty (or on Windows - any) existing directories. If
+/// moving the new directory in place fails, the old one is restored.
+pub fn atomic_write_dir<F>(dest: &Path, f: F) -> Result<()>
+where
+    F: FnOnce(&Path) -> Result<()>,
+{
+    let tmp_path = random_sibling_path(dest, "tmp");
+    fs::create_dir_all(&tmp_path)?;
+    if let Err(e) = f(&tmp_path) {
+        let _ = fs::remove_dir_all(&tmp_path);
+        return Err(e);
+    }
+
+    if !dest.exists() {
+        return Ok(fs::rename(&tmp_path, dest)?);
+    }
+
+    let old_path = random_sibling_path(dest, "old");
+    fs::rename(dest, &old_path)?;
+    if let Err(e) = fs::rename(&tmp_path, dest) {
+        fs::rename(&old_path, dest)?;
+        let _ = fs::remove_dir_all(&tmp_path);
+        return Err(e.into());
+    }
+    Ok(fs::remove_dir_all(&old_path)?)
+}
+
+/// Advisory lock on `<path>.lock`, released on drop
+///
+/// Only guards against other users of `FileLock`, eg. other crev processes.
+pub struct FileLock {
+    file: fs::File,
+}
+
+impl FileLock {
+    fn open(path: &Path) -> io::Result<fs::File> {
+        fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(append_to_path(path.to_owned(), ".lock"))
+    }
+
+    /// Lock `path` for reading, waiting for any writer to finish
+    pub fn shared(path: &Path) -> Result<Self> {
+        let file = Self::open(path)?;
+        file.lock_shared()?;
+        Ok(FileLock { file })
+    }
+
+    /// Lock `path` for writing, waiting for all readers and writers to finish
+    pub fn exclusive(path: &Path) -> Result<Self> {
+        let file = Self::open(path)?;
+        file.lock_exclusive()?;
+        Ok(FileLock { file })
+    }
+
+    /// Like `exclusive`, but fail with `Error::LockTimeout` if still locked after `timeout`
+    pub fn exclusive_with_timeout(path: &Path, timeout: Duration) -> Result<Self> {
+        let file = Self::open(path)?;
+        let start = Instant::now();
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => return Ok(FileLock { file }),
+                Err(ref e) if e.kind() == fs2::lock_contended_error().kind() => {
+                    if timeout <= start.elapsed() {
+                        return Err(Error::LockTimeout(path.to_owned()));
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// Read a file, holding a shared `FileLock` on it
+///
+/// The lock is returned to allow keeping it while the content is used.
+/// Drop it before writing the file back with `store_str_to_file_with_lock`:
+/// the exclusive lock would wait for it forever, as it's taken through
+/// another file handle.
+pub fn read_file_with_lock(path: &Path) -> Result<(String, FileLock)> {
+    let lock = FileLock::shared(path)?;
+    let mut file = fs::File::open(path)?;
+    let mut res = String::new();
+    file.read_to_string(&mut res)?;
+
+    Ok((res, lock))
+}
@@ -2,18 +2,19 @@
 
 pub mod blake2b256;
 pub mod convert;
+mod error;
 pub mod fs;
 pub mod rand;
 pub mod serde;
 
-pub use crate::blake2b256::Blake2b256;
+pub use crate::{
+    blake2b256::{Blake2b256, IncrementalHasher},
+    error::{Error, Result},
+};
 
 use blake2;
 use chrono;
-use failure::bail;
 
-use blake2::{digest::FixedOutput, Digest};
-use failure::format_err;
 use rpassword;
 use rprompt;
 use std::{
@@ -23,25 +24,64 @@ use std::{
 };
 
 /// Now with a fixed offset of the current system timezone
+///
+/// Truncated to a second, so it reads the same throughout the second.
 pub fn now() -> chrono::DateTime<chrono::offset::FixedOffset> {
     let date = chrono::offset::Local::now();
-    date.with_timezone(&date.offset())
+    truncate_to_seconds(date.with_timezone(&date.offset()))
+}
+
+fn truncate_to_seconds(
+    date: chrono::DateTime<chrono::offset::FixedOffset>,
+) -> chrono::DateTime<chrono::offset::FixedOffset> {
+    use chrono::Timelike;
+    date.with_nanosecond(0).unwrap_or(date)
+}
+
+/// Parse an RFC 3339 date, also accepting some common deviations
+///
+/// Both `Z` and `+hh:mm` offsets are accepted, in any case, with a space
+/// instead of `T`, and with the seconds left out. Fractions of a second
+/// are dropped.
+///
+/// Meant for dates typed by users; proofs must have strict RFC 3339 dates.
+pub fn parse_rfc3339_lenient(s: &str) -> Result<chrono::DateTime<chrono::offset::FixedOffset>> {
+    const FORMATS: &[&str] = &[
+        "%Y-%m-%dT%H:%M:%S%.f%:z",
+        "%Y-%m-%d %H:%M:%S%.f%:z",
+        "%Y-%m-%dT%H:%M%:z",
+        "%Y-%m-%d %H:%M%:z",
+    ];
+    let trimmed = s.trim();
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(trimmed) {
+        return Ok(truncate_to_seconds(date));
+    }
+    let mut normalized = trimmed.to_uppercase();
+    if normalized.ends_with('Z') {
+        normalized.pop();
+        normalized.push_str("+00:00");
+    }
+    FORMATS
+        .iter()
+        .find_map(|format| chrono::DateTime::parse_from_str(&normalized, format).ok())
+        .map(truncate_to_seconds)
+        .ok_or_else(|| Error::InvalidDate(trimmed.to_owned()))
 }
 
 pub fn blake2b256sum(bytes: &[u8]) -> Vec<u8> {
-    let mut hasher = Blake2b256::new();
-    hasher.input(bytes);
-    hasher.fixed_result().to_vec()
+    let mut hasher = IncrementalHasher::new();
+    hasher.update(bytes);
+    hasher.finalise()
 }
 
-pub fn blake2b256sum_file(path: &Path) -> io::Result<Vec<u8>> {
-    let mut hasher = Blake2b256::new();
-    read_file_to_digest_input(path, &mut hasher)?;
-    Ok(hasher.fixed_result().to_vec())
+pub fn blake2b256sum_file(path: &Path) -> Result<Vec<u8>> {
+    let mut hasher = IncrementalHasher::new();
+    hasher.update_file(path)?;
+    Ok(hasher.finalise())
 }
 
-pub fn base64_decode<T: ?Sized + AsRef<[u8]>>(input: &T) -> Result<Vec<u8>, base64::DecodeError> {
-    base64::decode_config(input, base64::URL_SAFE_NO_PAD)
+pub fn base64_decode<T: ?Sized + AsRef<[u8]>>(input: &T) -> Result<Vec<u8>> {
+    Ok(base64::decode_config(input, base64::URL_SAFE_NO_PAD)?)
 }
 
 pub fn base64_encode<T: ?Sized + AsRef<[u8]>>(input: &T) -> String {
@@ -105,7 +145,7 @@ pub fn is_vec_empty<T>(t: &Vec<T>) -> bool {
 pub fn read_file_to_digest_input(
     path: &Path,
     input: &mut impl blake2::digest::Input,
-) -> io::Result<()> {
+) -> Result<()> {
     let file = std::fs::File::open(path)?;
 
     let mut reader = io::BufReader::new(file);
@@ -125,15 +165,15 @@ pub fn read_file_to_digest_input(
     Ok(())
 }
 
-pub fn try_again_or_cancel() -> common_failures::Result<()> {
+pub fn try_again_or_cancel() -> Result<()> {
     if !yes_or_no_was_y("Try again (y/n) ")? {
-        bail!("Canceled by the user");
+        return Err(Error::Cancelled);
     }
 
     Ok(())
 }
 
-pub fn yes_or_no_was_y(msg: &str) -> io::Result<bool> {
+pub fn yes_or_no_was_y(msg: &str) -> Result<bool> {
     loop {
         let reply = rprompt::prompt_reply_stderr(msg)?;
 
@@ -145,16 +185,38 @@ pub fn yes_or_no_was_y(msg: &str) -> io::Result<bool> {
     }
 }
 
-pub fn read_passphrase() -> io::Result<String> {
+/// Ask until the reply is one of `choices`, and return it
+pub fn read_choice(msg: &str, choices: &[&str]) -> Result<String> {
+    loop {
+        let reply = rprompt::prompt_reply_stderr(msg)?;
+        let reply = reply.trim().to_lowercase();
+        if choices.contains(&reply.as_str()) {
+            return Ok(reply);
+        }
+    }
+}
+
+/// Ask for a line of text, falling back to `default` on an empty reply
+pub fn read_line_or(msg: &str, default: &str) -> Result<String> {
+    let reply = rprompt::prompt_reply_stderr(msg)?;
+    let reply = reply.trim();
+    Ok(if reply.is_empty() {
+        default.to_owned()
+    } else {
+        reply.to_owned()
+    })
+}
+
+pub fn read_passphrase() -> Result<String> {
     if let Ok(pass) = env::var("CREV_PASSPHRASE") {
         eprint!("Using passphrase set in CREV_PASSPHRASE\n");
         return Ok(pass);
     }
     eprint!("Enter passphrase to unlock: ");
-    rpassword::read_password()
+    Ok(rpassword::read_password()?)
 }
 
-pub fn read_new_passphrase() -> io::Result<String> {
+pub fn read_new_passphrase() -> Result<String> {
     if let Ok(pass) = env::var("CREV_PASSPHRASE") {
         eprint!("Using passphrase set in CREV_PASSPHRASE\n");
         return Ok(pass);
@@ -171,7 +233,7 @@ pub fn read_new_passphrase() -> io::Result<String> {
     }
 }
 
-pub fn read_file_to_string(path: &Path) -> io::Result<String> {
+pub fn read_file_to_string(path: &Path) -> Result<String> {
     let mut file = std::fs::File::open(&path)?;
     let mut res = String::new();
     file.read_to_string(&mut res)?;
@@ -179,20 +241,38 @@ pub fn read_file_to_string(path: &Path) -> io::Result<String> {
     Ok(res)
 }
 
-pub fn save_to_yaml_file<T>(path: &Path, t: &T) -> common_failures::Result<()>
+/// Like `read_file_to_string`, but for files that may be arbitrarily large
+///
+/// Reading stops as soon as the file turns out to be longer than
+/// `max_bytes`, with `Error::FileTooLarge`.
+pub fn read_file_to_string_limited(path: &Path, max_bytes: u64) -> Result<String> {
+    let file = std::fs::File::open(path)?;
+    let mut bytes = vec![];
+    file.take(max_bytes.saturating_add(1))
+        .read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > max_bytes {
+        return Err(Error::FileTooLarge {
+            path: path.to_owned(),
+            max_bytes,
+        });
+    }
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e).into())
+}
+
+pub fn save_to_yaml_file<T>(path: &Path, t: &T) -> Result<()>
 where
     T: ::serde::Serialize,
 {
     std::fs::create_dir_all(
         path.parent()
-            .ok_or_else(|| format_err!("Can't save to root path"))?,
+            .ok_or_else(|| Error::InvalidPath(path.to_owned()))?,
     )?;
-    let text = serde_yaml::to_string(t)?;
+    let text = serde_yaml::to_string(t).map_err(Error::YamlSer)?;
     store_str_to_file(&path, &text)?;
     Ok(())
 }
 
-pub fn read_from_yaml_file<T>(path: &Path) -> common_failures::Result<T>
+pub fn read_from_yaml_file<T>(path: &Path) -> Result<T>
 where
     T: ::serde::de::DeserializeOwned,
 {
@@ -201,25 +281,162 @@ where
     Ok(serde_yaml::from_str(&text)?)
 }
 
-pub fn store_str_to_file(path: &Path, s: &str) -> io::Result<()> {
+/// Top-level key with the schema version in files written by `save_versioned`
+pub const VERSION_KEY: &str = "version";
+
+/// Like `save_to_yaml_file`, but with a top-level `version` key
+///
+/// `t` must serialize to a mapping.
+pub fn save_versioned<T>(path: &Path, t: &T, version: u64) -> Result<()>
+where
+    T: ::serde::Serialize,
+{
+    std::fs::create_dir_all(
+        path.parent()
+            .ok_or_else(|| Error::InvalidPath(path.to_owned()))?,
+    )?;
+    let text = to_versioned_yaml_string(t, version)?;
+    store_str_to_file(path, &text)?;
+    Ok(())
+}
+
+/// Read a file written by `save_versioned`, migrating it if needed
+///
+/// See `from_versioned_yaml_str`.
+pub fn load_versioned<T, F>(path: &Path, current_version: u64, migrate: F) -> Result<T>
+where
+    T: ::serde::de::DeserializeOwned,
+    F: Fn(u64, serde_yaml::Value) -> Result<serde_yaml::Value>,
+{
+    read_file_to_string(path)
+        .and_then(|text| from_versioned_yaml_str(&text, current_version, migrate))
+        .map_err(|error| Error::InFile {
+            path: path.to_owned(),
+            error: Box::new(error),
+        })
+}
+
+pub fn to_versioned_yaml_string<T>(t: &T, version: u64) -> Result<String>
+where
+    T: ::serde::Serialize,
+{
+    let mut value = serde_yaml::to_value(t).map_err(Error::YamlSer)?;
+    match value {
+        serde_yaml::Value::Mapping(ref mut mapping) => {
+            mapping.insert(VERSION_KEY.into(), version.into());
+        }
+        _ => {
+            return Err(Error::InvalidVersion(
+                "Only mappings can be versioned".into(),
+            ))
+        }
+    }
+    serde_yaml::to_string(&value).map_err(Error::YamlSer)
+}
+
+/// Parse a versioned YAML document
+///
+/// Documents without a `version` key are version 1. Older documents are
+/// upgraded by calling `migrate(version, document)` for every version up to
+/// `current_version`; it should return the document in the format of
+/// `version + 1`. The `version` key is removed before `migrate` is called.
+pub fn from_versioned_yaml_str<T, F>(s: &str, current_version: u64, migrate: F) -> Result<T>
+where
+    T: ::serde::de::DeserializeOwned,
+    F: Fn(u64, serde_yaml::Value) -> Result<serde_yaml::Value>,
+{
+    let mut value: serde_yaml::Value = serde_yaml::from_str(s)?;
+    let version = match value {
+        serde_yaml::Value::Mapping(ref mut mapping) => match mapping.remove(&VERSION_KEY.into()) {
+            None => 1,
+            Some(version) => version.as_u64().ok_or_else(|| {
+                Error::InvalidVersion(format!("Invalid `{}`: {:?}", VERSION_KEY, version))
+            })?,
+        },
+        _ => 1,
+    };
+    if version > current_version {
+        return Err(Error::UnsupportedVersion {
+            version,
+            supported: current_version,
+        });
+    }
+    for version in version..current_version {
+        value = migrate(version, value)?;
+    }
+
+    Ok(serde_yaml::from_value(value)?)
+}
+
+pub fn save_to_toml_file<T>(path: &Path, t: &T) -> Result<()>
+where
+    T: ::serde::Serialize,
+{
+    std::fs::create_dir_all(
+        path.parent()
+            .ok_or_else(|| Error::InvalidPath(path.to_owned()))?,
+    )?;
+    let text = to_toml_string(t)?;
+    store_str_to_file(path, &text)?;
+    Ok(())
+}
+
+pub fn read_from_toml_file<T>(path: &Path) -> Result<T>
+where
+    T: ::serde::de::DeserializeOwned,
+{
+    let text = read_file_to_string(path)?;
+
+    Ok(toml::from_str(&text)?)
+}
+
+/// Serialize to TOML
+///
+/// Unlike `toml::to_string` this doesn't fail on structs that have
+/// table fields before plain value fields.
+pub fn to_toml_string<T>(t: &T) -> Result<String>
+where
+    T: ::serde::Serialize,
+{
+    Ok(toml::to_string(&toml::Value::try_from(t)?)?)
+}
+
+/// Write `s` to `path`, replacing its content at once
+///
+/// `s` is written to a uniquely named file next to `path` first, so
+/// neither readers nor concurrent writers see a partially written file.
+pub fn store_str_to_file(path: &Path, s: &str) -> Result<()> {
     std::fs::create_dir_all(path.parent().expect("Not a root path"))?;
-    let tmp_path = path.with_extension("tmp");
-    let mut file = std::fs::File::create(&tmp_path)?;
-    file.write_all(&s.as_bytes())?;
-    file.flush()?;
-    drop(file);
-    std::fs::rename(tmp_path, path)?;
+    let tmp_path = fs::random_sibling_path(path, "tmp");
+    let written = std::fs::write(&tmp_path, s).and_then(|()| std::fs::rename(&tmp_path, path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
     Ok(())
 }
 
-pub fn store_to_file_with<E, F>(path: &Path, f: F) -> io::Result<Result<(), E>>
+/// Like `store_str_to_file`, holding an exclusive `FileLock` on `path`
+///
+/// Only for files crev manages itself, as it leaves a `.lock` file next
+/// to `path`. See `fs::read_file_with_lock`.
+pub fn store_str_to_file_with_lock(path: &Path, s: &str) -> Result<()> {
+    std::fs::create_dir_all(path.parent().expect("Not a root path"))?;
+    let _lock = fs::FileLock::exclusive(path)?;
+    store_str_to_file(path, s)
+}
+
+pub fn store_to_file_with<E, F>(path: &Path, f: F) -> Result<std::result::Result<(), E>>
 where
-    F: Fn(&mut dyn io::Write) -> Result<(), E>,
+    F: Fn(&mut dyn io::Write) -> std::result::Result<(), E>,
 {
     std::fs::create_dir_all(path.parent().expect("Not a root path"))?;
-    let tmp_path = path.with_extension("tmp");
+    // Unique name, so concurrent writers don't clobber each other's temporary file
+    let tmp_path = fs::random_sibling_path(path, "tmp");
     let mut file = std::fs::File::create(&tmp_path)?;
     if let Err(e) = f(&mut file) {
+        drop(file);
+        let _ = std::fs::remove_file(&tmp_path);
         return Ok(Err(e));
     }
     file.flush()?;
@@ -228,3 +445,6 @@ where
     std::fs::rename(tmp_path, path)?;
     Ok(Ok(()))
 }
+
+#[cfg(test)]
+mod tests;
@@ -1,8 +1,74 @@
-use rand::{self, Rng};
+use rand::{self, CryptoRng, Rng, RngCore};
+
+/// Source of randomness used by crev
+///
+/// Only cryptographically secure generators qualify.
+pub trait CrevRng: RngCore + CryptoRng {}
+
+impl CrevRng for rand::rngs::OsRng {}
+
+/// Default random number generator, backed by the OS
+pub fn os_rng() -> rand::rngs::OsRng {
+    rand::rngs::OsRng::new().expect("OS random number generator not available")
+}
+
+/// Deterministic generator, for reproducible tests
+///
+/// Only available in tests, so no build of crev can use it.
+#[cfg(test)]
+pub struct SeededRng(rand::rngs::StdRng);
+
+#[cfg(test)]
+impl SeededRng {
+    pub fn from_seed(seed: u64) -> Self {
+        use rand::SeedableRng;
+        SeededRng(rand::rngs::StdRng::seed_from_u64(seed))
+    }
+}
+
+#[cfg(test)]
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+impl CryptoRng for SeededRng {}
+
+#[cfg(test)]
+impl CrevRng for SeededRng {}
+
+/// URL-safe base64 encoded token of `length` random bytes from the OS
+///
+/// Panics if the OS random number generator fails, as nothing sensible
+/// can be done without one.
+pub fn secure_token(length: usize) -> String {
+    let mut bytes = vec![0; length];
+    os_rng()
+        .try_fill_bytes(&mut bytes)
+        .expect("OS random number generator failed");
+    crate::base64_encode(&bytes)
+}
 
 pub fn random_vec(len: usize) -> Vec<u8> {
-    rand::thread_rng()
-        .sample_iter(&rand::distributions::Standard)
+    random_vec_with(&mut os_rng(), len)
+}
+
+pub fn random_vec_with(rng: &mut impl CrevRng, len: usize) -> Vec<u8> {
+    rng.sample_iter(&rand::distributions::Standard)
         .take(len)
         .collect()
 }
@@ -86,6 +86,36 @@ where
     serializer.serialize_str(&key.to_rfc3339())
 }
 
+pub fn from_opt_rfc3339_fixed<'d, D>(
+    deserializer: D,
+) -> Result<Option<chrono::DateTime<FixedOffset>>, D::Error>
+where
+    D: serde::Deserializer<'d>,
+{
+    use self::serde::de::Error;
+    Option::<String>::deserialize(deserializer).and_then(|string| {
+        string
+            .map(|string| {
+                DateTime::<FixedOffset>::parse_from_rfc3339(&string)
+                    .map_err(|err| Error::custom(err.to_string()))
+            })
+            .transpose()
+    })
+}
+
+pub fn as_opt_rfc3339_fixed<S>(
+    key: &Option<chrono::DateTime<FixedOffset>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match key {
+        Some(key) => serializer.serialize_some(&key.to_rfc3339()),
+        None => serializer.serialize_none(),
+    }
+}
+
 impl MyTryFromBytes for Vec<u8> {
     type Err = io::Error;
     fn try_from(slice: &[u8]) -> Result<Self, Self::Err> {
@@ -103,6 +133,20 @@ pub fn write_as_headerless_yaml<T: self::serde::Serialize>(
 ) -> fmt::Result {
     // TODO: Don't serialize to string, and instead serialize to writer
     let yaml_document = serde_yaml::to_string(t).map_err(|_| fmt::Error)?;
+    write_headerless(&yaml_document, f)
+}
+
+/// Like `write_as_headerless_yaml`, with the keys ordered as in `to_canonical_yaml_string`
+pub fn write_as_canonical_headerless_yaml<T: self::serde::Serialize>(
+    t: &T,
+    key_order: &[&str],
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    let yaml_document = to_canonical_yaml_string(t, key_order).map_err(|_| fmt::Error)?;
+    write_headerless(&yaml_document, f)
+}
+
+fn write_headerless(yaml_document: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     let mut lines = yaml_document.lines();
     let dropped_header = lines.next();
     assert_eq!(dropped_header, Some("---"));
@@ -113,3 +157,58 @@ pub fn write_as_headerless_yaml<T: self::serde::Serialize>(
     }
     Ok(())
 }
+
+/// Serialize `t` to YAML, with the keys of all mappings in a fixed order
+///
+/// Keys are ordered by their position in `key_order`, and keys not in
+/// it come after, sorted. This way the output doesn't depend on the
+/// declaration order of struct fields, nor on the order of maps.
+pub fn to_canonical_yaml_string<T: self::serde::Serialize>(
+    t: &T,
+    key_order: &[&str],
+) -> Result<String, serde_yaml::Error> {
+    serde_yaml::to_string(&to_canonical_yaml_value(t, key_order)?)
+}
+
+/// `t` as a YAML value, with the keys ordered as in `to_canonical_yaml_string`
+pub fn to_canonical_yaml_value<T: self::serde::Serialize>(
+    t: &T,
+    key_order: &[&str],
+) -> Result<serde_yaml::Value, serde_yaml::Error> {
+    Ok(canonicalize_yaml_value(serde_yaml::to_value(t)?, key_order))
+}
+
+fn canonicalize_yaml_value(value: serde_yaml::Value, key_order: &[&str]) -> serde_yaml::Value {
+    use serde_yaml::Value;
+
+    match value {
+        Value::Mapping(mapping) => {
+            let mut entries: Vec<_> = mapping
+                .into_iter()
+                .map(|(key, value)| (key, canonicalize_yaml_value(value, key_order)))
+                .collect();
+            entries.sort_by(|(a, _), (b, _)| {
+                canonical_key_rank(a, key_order).cmp(&canonical_key_rank(b, key_order))
+            });
+            Value::Mapping(entries.into_iter().collect())
+        }
+        Value::Sequence(sequence) => Value::Sequence(
+            sequence
+                .into_iter()
+                .map(|value| canonicalize_yaml_value(value, key_order))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+fn canonical_key_rank<'a>(
+    key: &'a serde_yaml::Value,
+    key_order: &[&str],
+) -> (usize, Option<&'a str>) {
+    let key = key.as_str();
+    let position = key
+        .and_then(|key| key_order.iter().position(|ordered| *ordered == key))
+        .unwrap_or(key_order.len());
+    (position, key)
+}
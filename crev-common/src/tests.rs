@@ -0,0 +1,397 @@
+use super::rand::*;
+use super::*;
+use ::serde::{Deserialize, Serialize};
+
+#[test]
+fn seeded_rng_is_deterministic() {
+    let mut a = SeededRng::from_seed(7);
+    let mut b = SeededRng::from_seed(7);
+    assert_eq!(random_vec_with(&mut a, 64), random_vec_with(&mut b, 64));
+    assert_eq!(random_vec_with(&mut a, 16), random_vec_with(&mut b, 16));
+
+    let mut c = SeededRng::from_seed(8);
+    assert_ne!(
+        random_vec_with(&mut SeededRng::from_seed(7), 64),
+        random_vec_with(&mut c, 64)
+    );
+}
+
+#[test]
+fn secure_token_is_url_safe_and_unique() {
+    let token = secure_token(16);
+    assert_eq!(base64_decode(&token).unwrap().len(), 16);
+    assert!(token
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    assert_ne!(token, secure_token(16));
+    assert_eq!(secure_token(0), "");
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct ConfigV2 {
+    name: String,
+    urls: Vec<String>,
+}
+
+// v1 had a single `url` instead of a list
+fn migrate_config(version: u64, mut value: serde_yaml::Value) -> crate::Result<serde_yaml::Value> {
+    assert_eq!(version, 1);
+    let mapping = value.as_mapping_mut().unwrap();
+    let url = mapping.remove(&"url".into()).unwrap();
+    mapping.insert("urls".into(), serde_yaml::Value::Sequence(vec![url]));
+    Ok(value)
+}
+
+#[test]
+fn versioned_yaml_migrates_old_versions() -> common_failures::Result<()> {
+    let expected = ConfigV2 {
+        name: "a".into(),
+        urls: vec!["https://a".into()],
+    };
+
+    let v1 = "version: 1\nname: a\nurl: \"https://a\"\n";
+    let config: ConfigV2 = from_versioned_yaml_str(v1, 2, migrate_config)?;
+    assert_eq!(config, expected);
+
+    // Files without a version are version 1
+    let unversioned = "name: a\nurl: \"https://a\"\n";
+    let config: ConfigV2 = from_versioned_yaml_str(unversioned, 2, migrate_config)?;
+    assert_eq!(config, expected);
+
+    let v2 = to_versioned_yaml_string(&expected, 2)?;
+    let config: ConfigV2 = from_versioned_yaml_str(&v2, 2, |_, _| panic!("not needed"))?;
+    assert_eq!(config, expected);
+
+    Ok(())
+}
+
+#[test]
+fn versioned_yaml_rejects_newer_versions() -> common_failures::Result<()> {
+    let v3 = "version: 3\nname: a\nurls: []\n";
+    let err = from_versioned_yaml_str::<ConfigV2, _>(v3, 2, migrate_config).unwrap_err();
+    assert!(err.to_string().contains("newer crev"));
+
+    // Errors of a file tell which file it is
+    let dir = tempdir::TempDir::new("crev-versioned")?;
+    let path = dir.path().join("config.yaml");
+    std::fs::write(&path, v3)?;
+    match load_versioned::<ConfigV2, _>(&path, 2, migrate_config) {
+        Err(Error::InFile {
+            path: err_path,
+            error,
+        }) => {
+            assert_eq!(err_path, path);
+            assert!(matches!(
+                *error,
+                Error::UnsupportedVersion { version: 3, .. }
+            ));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    let err = load_versioned::<ConfigV2, _>(&path, 2, migrate_config).unwrap_err();
+    assert!(err.to_string().starts_with(&path.display().to_string()));
+    Ok(())
+}
+
+#[test]
+fn errors_can_be_matched_on() {
+    let v3 = "version: 3\nname: a\nurls: []\n";
+    match from_versioned_yaml_str::<ConfigV2, _>(v3, 2, migrate_config) {
+        Err(Error::UnsupportedVersion {
+            version: 3,
+            supported: 2,
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    match read_from_yaml_file::<ConfigV2>(std::path::Path::new("/nonexistent/config.yaml")) {
+        Err(Error::Io(ref e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    match from_versioned_yaml_str::<ConfigV2, _>("name: [", 2, migrate_config) {
+        Err(Error::YamlDe(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    match save_to_yaml_file(std::path::Path::new("/"), &()) {
+        Err(Error::InvalidPath(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn dir_swap_guard_restores_dir_on_failure() -> common_failures::Result<()> {
+    let tmp = tempdir::TempDir::new("crev-common")?;
+    let dir = tmp.path().join("pkg");
+    let aside = tmp.path().join("pkg.crev.reviewed");
+    std::fs::create_dir_all(dir.join("src"))?;
+    std::fs::write(dir.join("src").join("lib.rs"), "reviewed")?;
+
+    let fail_to_download = |dir: &std::path::Path| -> common_failures::Result<()> {
+        let _guard = fs::DirSwapGuard::new(dir, &aside)?;
+        assert!(!dir.join("src").exists());
+        // partially downloaded copy
+        std::fs::write(dir.join("Cargo.toml"), "partial")?;
+        failure::bail!("download failed")
+    };
+    assert!(fail_to_download(&dir).is_err());
+
+    assert_eq!(
+        std::fs::read_to_string(dir.join("src").join("lib.rs"))?,
+        "reviewed"
+    );
+    assert!(!dir.join("Cargo.toml").exists());
+    assert!(!aside.exists());
+
+    Ok(())
+}
+
+#[test]
+fn dir_swap_guard_keeps_both_dirs_on_commit() -> common_failures::Result<()> {
+    let tmp = tempdir::TempDir::new("crev-common")?;
+    let dir = tmp.path().join("pkg");
+    let aside = tmp.path().join("pkg.crev.reviewed");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("lib.rs"), "reviewed")?;
+
+    let guard = fs::DirSwapGuard::new(&dir, &aside)?;
+    std::fs::write(dir.join("lib.rs"), "fresh")?;
+    assert_eq!(guard.commit(), aside);
+
+    assert_eq!(std::fs::read_to_string(dir.join("lib.rs"))?, "fresh");
+    assert_eq!(std::fs::read_to_string(aside.join("lib.rs"))?, "reviewed");
+    assert!(fs::DirSwapGuard::new(&dir, &aside).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn find_files_with_extension_walks_subdirs_in_order() -> common_failures::Result<()> {
+    let tmp = tempdir::TempDir::new("crev-common")?;
+    let dir = tmp.path();
+    std::fs::create_dir_all(dir.join("b").join("c.crev"))?;
+    std::fs::create_dir_all(dir.join(".git"))?;
+    std::fs::create_dir_all(dir.join("b").join("target"))?;
+    for file in &[
+        "z.crev",
+        "a.txt",
+        "b/x.crev",
+        "b/c.crev/y.crev",
+        "b/crev",
+        ".git/HEAD.crev",
+        "b/target/w.crev",
+    ] {
+        std::fs::write(dir.join(file), "")?;
+    }
+
+    assert_eq!(
+        fs::find_files_with_extension(dir, "crev")?,
+        vec![
+            dir.join("b/c.crev/y.crev"),
+            dir.join("b/x.crev"),
+            dir.join("z.crev")
+        ]
+    );
+    assert_eq!(
+        fs::find_files_matching(dir, |path| path.ends_with("b/crev"))?,
+        vec![dir.join("b/crev")]
+    );
+    assert!(fs::find_files_with_extension(&dir.join("missing"), "crev").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn canonical_yaml_orders_keys_on_all_levels() -> common_failures::Result<()> {
+    #[derive(Serialize)]
+    struct Inner {
+        b: u8,
+        zeta: u8,
+        a: u8,
+    }
+    #[derive(Serialize)]
+    struct Outer {
+        extra: std::collections::HashMap<&'static str, u8>,
+        items: Vec<Inner>,
+        a: u8,
+    }
+
+    let mut extra = std::collections::HashMap::new();
+    extra.insert("y", 1);
+    extra.insert("x", 2);
+    let outer = Outer {
+        extra,
+        items: vec![Inner {
+            b: 1,
+            zeta: 2,
+            a: 3,
+        }],
+        a: 4,
+    };
+    assert_eq!(
+        serde::to_canonical_yaml_string(&outer, &["a", "items", "b"])?,
+        "---\na: 4\nitems:\n  - a: 3\n    b: 1\n    zeta: 2\nextra:\n  x: 2\n  y: 1"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn incremental_hasher_matches_single_digest() -> common_failures::Result<()> {
+    let tmp = tempdir::TempDir::new("crev-common")?;
+    let path = tmp.path().join("src.rs");
+    std::fs::write(&path, "fn main() {}\n")?;
+
+    let mut hasher = IncrementalHasher::new();
+    hasher.update(b"metadata\n");
+    hasher.update_file(&path)?;
+    assert_eq!(
+        hasher.finalise(),
+        blake2b256sum(b"metadata\nfn main() {}\n")
+    );
+
+    assert_eq!(blake2b256sum_file(&path)?, blake2b256sum(b"fn main() {}\n"));
+    Ok(())
+}
+
+#[test]
+fn parse_rfc3339_lenient_accepts_common_variants() {
+    let expected = parse_rfc3339_lenient("2019-10-01T12:30:00+00:00").unwrap();
+    for s in &[
+        "2019-10-01T12:30:00Z",
+        "2019-10-01t12:30:00z",
+        "2019-10-01 12:30:00Z",
+        " 2019-10-01T12:30Z\n",
+        "2019-10-01T14:30:00+02:00",
+        "2019-10-01T14:30+02:00",
+    ] {
+        assert_eq!(parse_rfc3339_lenient(s).unwrap(), expected, "{}", s);
+    }
+    assert_eq!(
+        parse_rfc3339_lenient("2019-10-01T12:30:00.5Z").unwrap(),
+        expected
+    );
+    assert_eq!(
+        parse_rfc3339_lenient("2019-10-01 12:30:00.999+00:00").unwrap(),
+        expected
+    );
+
+    match parse_rfc3339_lenient("2019-10-01") {
+        Err(Error::InvalidDate(ref date)) if date == "2019-10-01" => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    for s in &[
+        "2019-10-01T12:0ééZ",
+        "2019-10-01T12:é0Z",
+        "2019-10-01T12:30:00Zé",
+    ] {
+        assert!(parse_rfc3339_lenient(s).is_err(), "{}", s);
+    }
+}
+
+#[test]
+fn now_is_truncated_to_seconds() {
+    assert_eq!(now().timestamp_subsec_nanos(), 0);
+}
+
+#[derive(Deserialize)]
+struct Dated {
+    #[serde(deserialize_with = "crate::serde::from_rfc3339_fixed")]
+    date: chrono::DateTime<chrono::offset::FixedOffset>,
+}
+
+#[test]
+fn serialized_dates_must_be_strict_rfc3339() {
+    let date = serde_yaml::from_str::<Dated>("date: \"2019-10-01T12:30:00.5+02:00\"")
+        .unwrap()
+        .date;
+    assert_eq!(date.timestamp_subsec_millis(), 500);
+    for s in &["2019-10-01 12:30Z", "2019-10-01T12:30+02:00"] {
+        assert!(
+            serde_yaml::from_str::<Dated>(&format!("date: \"{}\"", s)).is_err(),
+            "{}",
+            s
+        );
+    }
+}
+
+#[test]
+fn safe_join_stays_inside_base() -> common_failures::Result<()> {
+    use std::path::Path;
+
+    let tmp = tempdir::TempDir::new("crev-common")?;
+    let base = tmp.path().join("base");
+    std::fs::create_dir_all(base.join("a"))?;
+
+    assert_eq!(
+        fs::safe_join(&base, Path::new("a/b/c"))?,
+        base.join("a/b/c")
+    );
+    assert_eq!(fs::safe_join(&base, Path::new("./a"))?, base.join("./a"));
+
+    for untrusted in &["../../etc/passwd", "a/../../b", "/etc/passwd"] {
+        match fs::safe_join(&base, Path::new(untrusted)) {
+            Err(Error::InvalidPath(_)) => {}
+            other => panic!("{}: unexpected {:?}", untrusted, other),
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        std::fs::create_dir_all(tmp.path().join("outside"))?;
+        std::os::unix::fs::symlink(tmp.path().join("outside"), base.join("link"))?;
+        std::os::unix::fs::symlink(base.join("a"), base.join("inner-link"))?;
+        assert!(fs::safe_join(&base, Path::new("link")).is_err());
+        assert!(fs::safe_join(&base, Path::new("link/new-file")).is_err());
+        assert!(fs::safe_join(&base, Path::new("inner-link/new-file")).is_ok());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn read_file_to_string_limited_stops_at_the_limit() -> common_failures::Result<()> {
+    let dir = tempdir::TempDir::new("crev-read-limited")?;
+    let path = dir.path().join("file");
+    std::fs::write(&path, "0123456789")?;
+
+    assert_eq!(read_file_to_string_limited(&path, 10)?, "0123456789");
+    match read_file_to_string_limited(&path, 9) {
+        Err(Error::FileTooLarge { max_bytes: 9, .. }) => {}
+        other => panic!("unexpected: {:?}", other),
+    }
+    // Ordinary IO errors stay IO errors
+    match read_file_to_string_limited(&dir.path().join("missing"), 9) {
+        Err(Error::Io(_)) => {}
+        other => panic!("unexpected: {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn store_str_to_file_leaves_nothing_else_behind() -> common_failures::Result<()> {
+    let dir = tempdir::TempDir::new("crev-store")?;
+    let path = dir.path().join("sub").join("file.txt");
+    let entries = || -> std::io::Result<Vec<_>> {
+        std::fs::read_dir(path.parent().unwrap())?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect()
+    };
+
+    store_str_to_file(&path, "first")?;
+    store_str_to_file(&path, "second")?;
+    assert_eq!(std::fs::read_to_string(&path)?, "second");
+    assert_eq!(entries()?, vec!["file.txt"]);
+
+    // Writers that lock keep their lock file, readers can lock it afterwards
+    store_str_to_file_with_lock(&path, "third")?;
+    let (content, _lock) = fs::read_file_with_lock(&path)?;
+    assert_eq!(content, "third");
+    let mut entries = entries()?;
+    entries.sort();
+    assert_eq!(entries, vec!["file.txt", "file.txt.lock"]);
+
+    Ok(())
+}
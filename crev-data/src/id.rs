@@ -1,15 +1,26 @@
 use crate::{proof, Result, Url};
 use crev_common::{
     self,
+    rand::CrevRng,
     serde::{as_base64, from_base64},
 };
 use derive_builder::Builder;
 use ed25519_dalek::{self, PublicKey, SecretKey};
 use failure::format_err;
-use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+/// Git trailer of proof repository commits, signing their tree
+///
+/// See `OwnId::sign_commit_message`.
+pub const COMMIT_SIGNATURE_TRAILER: &str = "Crev-Signature";
+
+/// What is signed for a commit: only its tree, as the rest of it can't be
+/// known before it's created
+fn commit_signature_payload(tree_id: &str) -> Vec<u8> {
+    format!("crev commit tree {}", tree_id).into_bytes()
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum IdType {
     #[serde(rename = "crev")]
@@ -65,6 +76,18 @@ impl Id {
             Id::Crev { id } => id.clone(),
         }
     }
+
+    /// Check the `COMMIT_SIGNATURE_TRAILER` of this id in a commit message
+    ///
+    /// `tree_id` is the (hex) id of the tree of the commit.
+    pub fn verify_commit_message(&self, commit_msg: &str, tree_id: &str) -> Result<()> {
+        let prefix = format!("{}: {} ", COMMIT_SIGNATURE_TRAILER, self);
+        let signature = commit_msg
+            .lines()
+            .find_map(|line| line.strip_prefix(prefix.as_str()))
+            .ok_or_else(|| format_err!("Not signed by {}", self))?;
+        self.verify_signature(&commit_signature_payload(tree_id), signature.trim())
+    }
 }
 
 impl fmt::Display for Id {
@@ -170,6 +193,19 @@ impl OwnId {
         self.keypair.sign(msg).to_bytes().to_vec()
     }
 
+    /// `commit_msg` with a `COMMIT_SIGNATURE_TRAILER` signing `tree_id`
+    ///
+    /// See `Id::verify_commit_message`.
+    pub fn sign_commit_message(&self, commit_msg: &str, tree_id: &str) -> String {
+        format!(
+            "{}\n\n{}: {} {}\n",
+            commit_msg.trim_end(),
+            COMMIT_SIGNATURE_TRAILER,
+            self.id.id,
+            crev_common::base64_encode(&self.sign(&commit_signature_payload(tree_id)))
+        )
+    }
+
     pub fn type_as_string(&self) -> String {
         "crev".into()
     }
@@ -183,8 +219,12 @@ impl OwnId {
     }
 
     pub fn generate(url: Url) -> Self {
-        let mut csprng: OsRng = OsRng::new().unwrap();
-        let keypair = ed25519_dalek::Keypair::generate(&mut csprng);
+        Self::generate_with_rng(url, &mut crev_common::rand::os_rng())
+    }
+
+    /// Like `generate`, with the keypair drawn from `rng`
+    pub fn generate_with_rng(url: Url, rng: &mut impl CrevRng) -> Self {
+        let keypair = ed25519_dalek::Keypair::generate(rng);
         Self {
             id: PubId::new_from_pubkey(keypair.public.as_bytes().to_vec(), url),
             keypair,
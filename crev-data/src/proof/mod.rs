@@ -24,6 +24,70 @@ use crate::Result;
 
 pub type Date = chrono::DateTime<FixedOffset>;
 
+/// Order of the keys in serialized proofs, on every level of nesting
+///
+/// Signatures cover the serialized bytes, so reordering struct fields must
+/// not change them. New keys go where they should appear; keys missing
+/// here come last, sorted.
+pub const CANONICAL_FIELD_ORDER: &[&str] = &[
+    "id-type",
+    "id",
+    "url",
+    "source",
+    "name",
+    "version",
+    "revision",
+    "revision-type",
+    "date",
+    "from",
+    "ids",
+    "trust",
+    "expires",
+    "package",
+    "package-diff-base",
+    "thoroughness",
+    "understanding",
+    "rating",
+    "review",
+    "scope-of-review",
+    "api",
+    "implementation",
+    "tests",
+    "build-script",
+    "issues",
+    "advisories",
+    "severity",
+    "range",
+    "affected",
+    "comment",
+    "files",
+    "path",
+    "digest",
+    "digest_type",
+    "digest-type",
+    "build-log",
+    "review-duration-minutes",
+];
+
+/// How to treat proofs dated further into the future than clock skew explains
+#[derive(Debug, Clone, Copy)]
+pub struct DatePolicy {
+    /// How far into the future a proof date is still accepted
+    pub max_skew: chrono::Duration,
+    /// Use the current time for proofs dated too far into the future,
+    /// instead of rejecting them
+    pub clamp: bool,
+}
+
+impl Default for DatePolicy {
+    fn default() -> Self {
+        Self {
+            max_skew: chrono::Duration::days(1),
+            clamp: false,
+        }
+    }
+}
+
 pub trait ContentCommon {
     fn date(&self) -> &Date;
     fn set_date(&mut self, date: &Date);
@@ -147,6 +211,21 @@ impl Content {
         Ok(())
     }
 
+    /// Check the date isn't too far after `now`, as allowed by `policy`
+    ///
+    /// Returns `true` if the date was clamped to `now`.
+    pub fn check_date(&mut self, now: &Date, policy: &DatePolicy) -> Result<bool> {
+        let latest = now.checked_add_signed(policy.max_skew);
+        if latest.map_or(true, |latest| *self.date() <= latest) {
+            return Ok(false);
+        }
+        if !policy.clamp {
+            bail!("Proof dated {} is in the future", self.date().to_rfc3339());
+        }
+        self.set_date(now);
+        Ok(true)
+    }
+
     pub fn parse(s: &str, type_: ProofType) -> Result<Content> {
         Ok(match type_ {
             ProofType::Code => review::Code::parse(&s)?.into(),
@@ -415,6 +494,15 @@ impl Proof {
         Ok(v)
     }
 
+    /// Like `parse`, but with a separate result for every proof, so
+    /// a single malformed proof doesn't prevent reading the others
+    pub fn parse_each(reader: impl io::Read) -> Result<Vec<Result<Self>>> {
+        Ok(Serialized::parse(reader)?
+            .iter()
+            .map(Serialized::to_parsed)
+            .collect())
+    }
+
     pub fn signature(&self) -> &str {
         self.signature.trim()
     }
@@ -144,7 +144,11 @@ impl CodeDraft {
 
 impl fmt::Display for Code {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        crev_common::serde::write_as_headerless_yaml(self, f)
+        crev_common::serde::write_as_canonical_headerless_yaml(
+            self,
+            proof::CANONICAL_FIELD_ORDER,
+            f,
+        )
     }
 }
 
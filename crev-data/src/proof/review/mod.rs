@@ -1,7 +1,8 @@
 use crate::level::Level;
 use derive_builder::Builder;
+use failure::Fail;
 use serde::{Deserialize, Serialize};
-use std::default::Default;
+use std::{default::Default, fmt};
 
 pub mod code;
 pub mod package;
@@ -28,6 +29,36 @@ impl Default for Rating {
     }
 }
 
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use self::Rating::*;
+        f.pad(match self {
+            Negative => "negative",
+            Neutral => "neutral",
+            Positive => "positive",
+            Strong => "strong",
+        })
+    }
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "Can't convert string to Rating")]
+pub struct FromStrErr;
+
+impl std::str::FromStr for Rating {
+    type Err = FromStrErr;
+
+    fn from_str(s: &str) -> std::result::Result<Rating, FromStrErr> {
+        Ok(match s {
+            "negative" | "dangerous" => Rating::Negative,
+            "neutral" => Rating::Neutral,
+            "positive" => Rating::Positive,
+            "strong" => Rating::Strong,
+            _ => return Err(FromStrErr),
+        })
+    }
+}
+
 /// Information about review result
 #[derive(Clone, Debug, Serialize, Deserialize, Builder, PartialEq, Eq)]
 pub struct Review {
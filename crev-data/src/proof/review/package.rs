@@ -2,12 +2,12 @@ use crate::{id, proof, Level, Result};
 use chrono::{self, prelude::*};
 use crev_common::{
     self,
-    serde::{as_rfc3339_fixed, from_rfc3339_fixed},
+    serde::{as_base64, as_rfc3339_fixed, from_base64, from_rfc3339_fixed},
 };
 use crev_common::{is_equal_default, is_vec_empty};
 use derive_builder::Builder;
 use failure::bail;
-use semver::Version;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use serde_yaml;
 use std::{default::Default, fmt, mem};
@@ -46,6 +46,10 @@ pub struct Package {
     #[serde(default = "Default::default", skip_serializing_if = "is_equal_default")]
     pub review: super::Review,
     #[builder(default = "Default::default()")]
+    #[serde(skip_serializing_if = "Option::is_none", default = "Default::default")]
+    #[serde(rename = "scope-of-review")]
+    pub scope_of_review: Option<ReviewScope>,
+    #[builder(default = "Default::default()")]
     #[serde(skip_serializing_if = "is_vec_empty", default = "Default::default")]
     pub issues: Vec<Issue>,
     #[builder(default = "Default::default()")]
@@ -54,12 +58,22 @@ pub struct Package {
     #[serde(skip_serializing_if = "String::is_empty", default = "Default::default")]
     #[builder(default = "Default::default()")]
     pub comment: String,
+    #[builder(default = "Default::default()")]
+    #[serde(skip_serializing_if = "Option::is_none", default = "Default::default")]
+    #[serde(rename = "build-log")]
+    pub build_log: Option<BuildLog>,
+    /// How long the review took, in minutes
+    #[builder(default = "Default::default()")]
+    #[serde(skip_serializing_if = "Option::is_none", default = "Default::default")]
+    #[serde(rename = "review-duration-minutes")]
+    pub review_duration_minutes: Option<u64>,
 }
 
 impl Package {
     pub fn apply_draft(&self, draft: PackageDraft) -> Package {
         let mut copy = self.clone();
         copy.review = draft.review;
+        copy.scope_of_review = draft.scope_of_review;
         copy.comment = draft.comment;
         copy.advisories = draft.advisories;
         copy.issues = draft.issues;
@@ -71,6 +85,9 @@ impl Package {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PackageDraft {
     review: super::Review,
+    #[serde(skip_serializing_if = "Option::is_none", default = "Default::default")]
+    #[serde(rename = "scope-of-review")]
+    pub scope_of_review: Option<ReviewScope>,
     #[serde(default = "Default::default", skip_serializing_if = "is_vec_empty")]
     pub advisories: Vec<Advisory>,
     #[serde(default = "Default::default", skip_serializing_if = "is_vec_empty")]
@@ -83,6 +100,7 @@ impl From<Package> for PackageDraft {
     fn from(package: Package) -> Self {
         PackageDraft {
             review: package.review,
+            scope_of_review: package.scope_of_review,
             advisories: package.advisories,
             issues: package.issues,
             comment: package.comment,
@@ -159,6 +177,13 @@ impl Package {
         proof::Content::from(self).sign_by(id)
     }
 
+    /// Was only the public API reviewed, without the implementation
+    pub fn is_api_only(&self) -> bool {
+        self.scope_of_review
+            .as_ref()
+            .map_or(false, ReviewScope::is_api_only)
+    }
+
     pub fn is_advisory_for(&self, version: &Version) -> bool {
         for advisory in &self.advisories {
             if advisory.is_for_version_when_reported_in_version(version, &self.package.version) {
@@ -193,7 +218,11 @@ impl fmt::Display for Package {
         let mut comment = String::new();
         mem::swap(&mut comment, &mut clone.comment);
 
-        crev_common::serde::write_as_headerless_yaml(&clone, f)?;
+        crev_common::serde::write_as_canonical_headerless_yaml(
+            &clone,
+            proof::CANONICAL_FIELD_ORDER,
+            f,
+        )?;
         write_comment(comment.as_str(), f)
     }
 }
@@ -210,6 +239,65 @@ impl fmt::Display for PackageDraft {
     }
 }
 
+/// Parts of the package that were actually reviewed
+///
+/// Reviews without it are considered to cover everything, and so are
+/// the parts left out of it.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ReviewScope {
+    pub api: bool,
+    pub implementation: bool,
+    pub tests: bool,
+    pub build_script: bool,
+}
+
+impl Default for ReviewScope {
+    fn default() -> Self {
+        ReviewScope::full()
+    }
+}
+
+impl ReviewScope {
+    pub fn full() -> Self {
+        Self {
+            api: true,
+            implementation: true,
+            tests: true,
+            build_script: true,
+        }
+    }
+
+    pub fn api_only() -> Self {
+        Self {
+            api: true,
+            implementation: false,
+            tests: false,
+            build_script: false,
+        }
+    }
+
+    /// Was the implementation left out of the review
+    ///
+    /// A scope with nothing at all in it is treated the same way: such
+    /// a review can only flag the package, not vouch for it.
+    pub fn is_api_only(&self) -> bool {
+        !self.implementation
+    }
+}
+
+/// Log of building the package, attached by the reviewer
+///
+/// Documents that the package was built (running its build script),
+/// not only read.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BuildLog {
+    /// Path of the copy of the log, relative to the proof repository
+    pub path: String,
+    #[serde(serialize_with = "as_base64", deserialize_with = "from_base64")]
+    pub digest: Vec<u8>,
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "kebab-case")]
 pub enum VersionRange {
@@ -275,6 +363,11 @@ pub struct Advisory {
     )]
     pub range: VersionRange,
 
+    /// Exact affected versions; takes precedence over `range`
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affected: Option<VersionReq>,
+
     #[builder(default)]
     #[serde(default = "Default::default")]
     pub comment: String,
@@ -294,6 +387,7 @@ impl Default for Advisory {
         Self {
             ids: vec![],
             range: VersionRange::default(),
+            affected: None,
             severity: Default::default(),
             comment: "".to_string(),
         }
@@ -306,6 +400,9 @@ impl Advisory {
         for_version: &Version,
         in_pkg_version: &Version,
     ) -> bool {
+        if let Some(ref affected) = self.affected {
+            return affected.matches(for_version);
+        }
         if for_version < in_pkg_version {
             match self.range {
                 VersionRange::All => return true,
@@ -349,6 +446,11 @@ pub struct Issue {
     )]
     pub range: VersionRange,
 
+    /// Exact affected versions; takes precedence over `range`
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affected: Option<VersionReq>,
+
     #[builder(default)]
     #[serde(default = "Default::default")]
     pub comment: String,
@@ -359,6 +461,7 @@ impl Issue {
         Self {
             id,
             range: Default::default(),
+            affected: None,
             severity: Default::default(),
             comment: Default::default(),
         }
@@ -367,6 +470,7 @@ impl Issue {
         Self {
             id,
             range: Default::default(),
+            affected: None,
             severity,
             comment: Default::default(),
         }
@@ -376,6 +480,9 @@ impl Issue {
         for_version: &Version,
         in_pkg_version: &Version,
     ) -> bool {
+        if let Some(ref affected) = self.affected {
+            return affected.matches(for_version);
+        }
         if for_version >= in_pkg_version {
             match self.range {
                 VersionRange::All => return true,
@@ -2,7 +2,7 @@ use crate::{id, proof, Level, Result};
 use chrono::{self, prelude::*};
 use crev_common::{
     self,
-    serde::{as_rfc3339_fixed, from_rfc3339_fixed},
+    serde::{as_opt_rfc3339_fixed, as_rfc3339_fixed, from_opt_rfc3339_fixed, from_rfc3339_fixed},
 };
 use derive_builder::Builder;
 use failure::bail;
@@ -89,12 +89,25 @@ pub struct Trust {
     pub ids: Vec<crate::PubId>,
     #[builder(default = "Default::default()")]
     pub trust: TrustLevel,
+    /// After this date the proof doesn't count anymore
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default = "Default::default",
+        serialize_with = "as_opt_rfc3339_fixed",
+        deserialize_with = "from_opt_rfc3339_fixed"
+    )]
+    #[builder(default = "Default::default()")]
+    pub expires: Option<chrono::DateTime<FixedOffset>>,
     #[serde(skip_serializing_if = "String::is_empty", default = "Default::default")]
     #[builder(default = "Default::default()")]
     comment: String,
 }
 
 impl Trust {
+    pub fn expires_utc(&self) -> Option<chrono::DateTime<Utc>> {
+        self.expires.map(|date| date.with_timezone(&Utc))
+    }
+
     pub fn apply_draft(&self, draft: TrustDraft) -> Trust {
         let mut copy = self.clone();
         copy.trust = draft.trust;
@@ -122,7 +135,11 @@ impl From<Trust> for TrustDraft {
 
 impl fmt::Display for Trust {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        crev_common::serde::write_as_headerless_yaml(self, f)
+        crev_common::serde::write_as_canonical_headerless_yaml(
+            self,
+            proof::CANONICAL_FIELD_ORDER,
+            f,
+        )
     }
 }
 
@@ -74,10 +74,8 @@ sig2
     Ok(())
 }
 
-pub fn generate_id_and_proof() -> Result<(OwnId, Proof)> {
-    let id = OwnId::generate(Url::new_git("https://mypage.com/trust.git".into()));
-
-    let package_info = proof::PackageInfo {
+fn test_package_info() -> proof::PackageInfo {
+    proof::PackageInfo {
         id: None,
         source: "SOURCE_ID".to_owned(),
         name: "name".into(),
@@ -86,7 +84,13 @@ pub fn generate_id_and_proof() -> Result<(OwnId, Proof)> {
         digest_type: proof::default_digest_type(),
         revision: "".into(),
         revision_type: proof::default_revision_type(),
-    };
+    }
+}
+
+pub fn generate_id_and_proof() -> Result<(OwnId, Proof)> {
+    let id = OwnId::generate(Url::new_git("https://mypage.com/trust.git".into()));
+
+    let package_info = test_package_info();
     let review = proof::review::CodeBuilder::default()
         .from(id.id.to_owned())
         .package(package_info)
@@ -121,6 +125,84 @@ pub fn sign_proof_review() -> Result<()> {
     Ok(())
 }
 
+// See also the `proof_roundtrip` fuzz target
+#[test]
+pub fn proof_content_roundtrip() -> Result<()> {
+    let (_id, proof) = generate_id_and_proof()?;
+
+    let serialized = proof.content.to_string();
+    let reparsed = proof::Content::parse(&serialized, proof.content.proof_type())?;
+    assert_eq!(serialized, reparsed.to_string());
+
+    Ok(())
+}
+
+#[test]
+pub fn trust_expiration_roundtrip() -> Result<()> {
+    let id = OwnId::generate(Url::new_git("https://mypage.com/trust.git".into()));
+    let mut trust = id
+        .id
+        .create_trust_proof(vec![&id.id], proof::trust::TrustLevel::High)?;
+
+    let content: proof::Content = trust.clone().into();
+    assert!(!content.to_string().contains("expires"));
+    let reparsed = proof::Content::parse(&content.to_string(), content.proof_type())?;
+    assert_eq!(content.to_string(), reparsed.to_string());
+
+    trust.expires = Some(crev_common::now());
+    let content: proof::Content = trust.into();
+    assert!(content.to_string().contains("expires"));
+    let reparsed = proof::Content::parse(&content.to_string(), content.proof_type())?;
+    assert_eq!(content.to_string(), reparsed.to_string());
+
+    Ok(())
+}
+
+#[test]
+pub fn review_duration_roundtrip() -> Result<()> {
+    let id = OwnId::generate(Url::new_git("https://mypage.com/trust.git".into()));
+    let package_info = test_package_info();
+    let mut review = proof::review::PackageBuilder::default()
+        .from(id.id.to_owned())
+        .package(package_info)
+        .build()
+        .map_err(|e| format_err!("{}", e))?;
+
+    let content: proof::Content = review.clone().into();
+    assert!(!content.to_string().contains("review-duration-minutes"));
+
+    review.review_duration_minutes = Some(42);
+    let content: proof::Content = review.into();
+    assert!(content.to_string().contains("review-duration-minutes: 42"));
+    let reparsed = proof::Content::parse(&content.to_string(), content.proof_type())?;
+    assert_eq!(content.to_string(), reparsed.to_string());
+
+    Ok(())
+}
+
+#[test]
+pub fn review_scope_defaults_missing_parts_to_reviewed() -> Result<()> {
+    use proof::review::package::ReviewScope;
+
+    let scope: ReviewScope = serde_yaml::from_str("implementation: false")?;
+    assert!(scope.api && scope.tests && scope.build_script);
+    assert!(scope.is_api_only());
+    assert!(ReviewScope::api_only().is_api_only());
+
+    let scope: ReviewScope = serde_yaml::from_str("tests: false")?;
+    assert!(scope.api && scope.implementation && scope.build_script);
+    assert!(!scope.is_api_only());
+    assert!(!ReviewScope::full().is_api_only());
+
+    // Nothing in scope can't vouch for the implementation either
+    let scope: ReviewScope = serde_yaml::from_str(
+        "api: false\nimplementation: false\ntests: false\nbuild-script: false",
+    )?;
+    assert!(scope.is_api_only());
+
+    Ok(())
+}
+
 #[test]
 pub fn verify_works() -> Result<()> {
     let (_id, mut proof) = generate_id_and_proof()?;
@@ -131,3 +213,143 @@ pub fn verify_works() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+pub fn parse_each_reports_malformed_proofs() -> Result<()> {
+    let (_id, proof) = generate_id_and_proof()?;
+    let s = format!(
+        r#"{}
+-----BEGIN CODE REVIEW-----
+foo
+-----BEGIN CODE REVIEW SIGNATURE-----
+sig
+-----END CODE REVIEW-----
+"#,
+        proof
+    );
+
+    let proofs = Proof::parse_each(s.as_bytes())?;
+    assert_eq!(proofs.len(), 2);
+    assert_eq!(proofs[0].as_ref().unwrap().signature(), proof.signature());
+    assert!(proofs[1].is_err());
+    Ok(())
+}
+
+#[test]
+pub fn commit_message_signature_roundtrip() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let tree = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+    let msg = a.sign_commit_message("Add review\n", tree);
+    assert!(msg.starts_with("Add review\n\nCrev-Signature: "));
+    a.id.id.verify_commit_message(&msg, tree)?;
+    assert!(b.id.id.verify_commit_message(&msg, tree).is_err());
+    assert!(a
+        .id
+        .id
+        .verify_commit_message(&msg, "0000000000000000000000000000000000000000")
+        .is_err());
+    assert!(a.id.id.verify_commit_message("Add review", tree).is_err());
+
+    Ok(())
+}
+
+fn golden_id() -> Result<OwnId> {
+    OwnId::new(
+        Url::new_git("https://example.com/crev-proofs".into()),
+        vec![7; 32],
+    )
+}
+
+fn golden_date() -> proof::Date {
+    chrono::DateTime::parse_from_rfc3339("2019-06-01T12:00:00+02:00").unwrap()
+}
+
+fn golden_package_info() -> proof::PackageInfo {
+    proof::PackageInfo {
+        id: None,
+        source: "https://crates.io".into(),
+        name: "golden".into(),
+        version: Version::parse("1.2.3").unwrap(),
+        digest: vec![1; 32],
+        digest_type: proof::default_digest_type(),
+        revision: "0123456789abcdef".into(),
+        revision_type: proof::default_revision_type(),
+    }
+}
+
+fn golden_package_review() -> Result<Proof> {
+    let id = golden_id()?;
+    proof::review::PackageBuilder::default()
+        .date(golden_date())
+        .from(id.id.clone())
+        .package(golden_package_info())
+        .review(proof::review::Review::new_positive())
+        .scope_of_review(Some(proof::review::package::ReviewScope {
+            api: true,
+            implementation: true,
+            tests: false,
+            build_script: true,
+        }))
+        .issues(vec![proof::review::package::Issue::new_with_severity(
+            "GOLDEN-1".into(),
+            crate::Level::High,
+        )])
+        .advisories(vec![proof::review::package::Advisory {
+            ids: vec!["GOLDEN-0".into()],
+            comment: "fixed".into(),
+            ..Default::default()
+        }])
+        .comment("Looks fine.\nMostly.".into())
+        .build_log(Some(proof::review::package::BuildLog {
+            digest: vec![2; 32],
+            path: "build-logs/golden.log".into(),
+        }))
+        .review_duration_minutes(Some(30))
+        .build()
+        .map_err(|e| format_err!("{}", e))?
+        .sign_by(&id)
+}
+
+fn golden_trust() -> Result<Proof> {
+    let id = golden_id()?;
+    let other = crate::PubId::new_crevid_from_base64(
+        "FYlr8YoYGVvDwHQxqEIs89reKKDy-oWisoO0qXXEfHE",
+        Url::new_git("https://example.com/other".into()),
+    )?;
+    proof::TrustBuilder::default()
+        .date(golden_date())
+        .from(id.id.clone())
+        .ids(vec![other])
+        .trust(proof::TrustLevel::High)
+        .expires(Some(golden_date() + chrono::Duration::days(365)))
+        .comment("Colleague".into())
+        .build()
+        .map_err(|e| format_err!("{}", e))?
+        .sign_by(&id)
+}
+
+#[test]
+pub fn golden_package_review_proof() -> Result<()> {
+    let golden = include_str!("../rc/golden/package-review.crev");
+    assert_eq!(golden_package_review()?.to_string(), golden);
+
+    let parsed = Proof::parse(golden.as_bytes())?;
+    assert_eq!(parsed.len(), 1);
+    parsed[0].verify()?;
+    assert_eq!(parsed[0].content.to_string(), parsed[0].body);
+    Ok(())
+}
+
+#[test]
+pub fn golden_trust_proof() -> Result<()> {
+    let golden = include_str!("../rc/golden/trust.crev");
+    assert_eq!(golden_trust()?.to_string(), golden);
+
+    let parsed = Proof::parse(golden.as_bytes())?;
+    assert_eq!(parsed.len(), 1);
+    parsed[0].verify()?;
+    assert_eq!(parsed[0].content.to_string(), parsed[0].body);
+    Ok(())
+}
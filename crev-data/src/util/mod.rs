@@ -1,9 +1,10 @@
-use rand::{self, Rng};
+use crev_common::rand::{self, CrevRng};
 
 pub fn random_id_str() -> String {
-    let project_id: Vec<u8> = rand::thread_rng()
-        .sample_iter(&rand::distributions::Standard)
-        .take(32)
-        .collect();
-    crev_common::base64_encode(&project_id)
+    random_id_str_with(&mut rand::os_rng())
+}
+
+/// Like `random_id_str`, with the id drawn from `rng`
+pub fn random_id_str_with(rng: &mut impl CrevRng) -> String {
+    crev_common::base64_encode(&rand::random_vec_with(rng, 32))
 }
@@ -69,3 +69,21 @@ impl ReviewActivity {
         }
     }
 }
+
+/// Start of a review session, timing a review with `review --timed`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewStart {
+    #[serde(
+        serialize_with = "as_rfc3339_fixed",
+        deserialize_with = "from_rfc3339_fixed"
+    )]
+    pub timestamp: Date,
+}
+
+impl ReviewStart {
+    pub fn now() -> Self {
+        Self {
+            timestamp: crev_common::now(),
+        }
+    }
+}
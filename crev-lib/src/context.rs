@@ -0,0 +1,147 @@
+//! High-level API for checking packages against the local crev setup
+//!
+//! Meant for tools that need verification results programmatically;
+//! nothing here reads from the terminal or asks for a passphrase.
+use crate::{
+    prelude::*,
+    proofdb::{TrustDistanceParams, TrustSet},
+    Local, ProofDB, VerificationRequirements,
+};
+use crev_data::{proof::review, Digest, Id};
+use semver::Version;
+use std::{cell::RefCell, collections::HashMap, path::Path};
+
+/// Verification result of a single package
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PackageVerification {
+    /// Enough trusted reviews satisfy the requirements
+    Verified,
+    /// Trusted reviews exist, but more are needed to satisfy the requirements
+    Insufficient { reviews_needed: u64 },
+    /// Trusted ids reviewed the package negatively
+    Flagged { by: Vec<Id> },
+    /// No trusted ids reviewed the package
+    Unknown,
+}
+
+impl PackageVerification {
+    pub fn is_verified(&self) -> bool {
+        *self == PackageVerification::Verified
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct CacheKey {
+    source: String,
+    name: String,
+    version: Version,
+    digest: Option<Vec<u8>>,
+    requirements: VerificationRequirements,
+}
+
+/// Proof database and trust set of an id, loaded once and reused
+pub struct CrevContext {
+    db: ProofDB,
+    trust_set: TrustSet,
+    cache: RefCell<HashMap<CacheKey, PackageVerification>>,
+}
+
+impl CrevContext {
+    /// Load the default local configuration, for the current id
+    pub fn auto_open(trust_params: &TrustDistanceParams) -> Result<Self> {
+        Self::from_local(&Local::auto_open()?, trust_params)
+    }
+
+    /// Load the configuration stored in custom directories, for the current id
+    pub fn auto_open_at(
+        root_path: &Path,
+        cache_path: &Path,
+        trust_params: &TrustDistanceParams,
+    ) -> Result<Self> {
+        Self::from_local(&Local::auto_open_at(root_path, cache_path)?, trust_params)
+    }
+
+    pub fn from_local(local: &Local, trust_params: &TrustDistanceParams) -> Result<Self> {
+        let db = local.load_db()?;
+        let for_id = local.get_current_userid()?;
+        Ok(Self::new(db, &for_id, trust_params))
+    }
+
+    pub fn new(db: ProofDB, for_id: &Id, trust_params: &TrustDistanceParams) -> Self {
+        let trust_set = db.calculate_trust_set(for_id, trust_params);
+        Self {
+            db,
+            trust_set,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn db(&self) -> &ProofDB {
+        &self.db
+    }
+
+    pub fn trust_set(&self) -> &TrustSet {
+        &self.trust_set
+    }
+
+    /// Verify a package version
+    ///
+    /// If `digest` is given, only reviews of this exact content count,
+    /// otherwise all reviews of the version do.
+    pub fn verify_package(
+        &self,
+        source: &str,
+        name: &str,
+        version: &Version,
+        digest: Option<&Digest>,
+        requirements: &VerificationRequirements,
+    ) -> PackageVerification {
+        let key = CacheKey {
+            source: source.to_owned(),
+            name: name.to_owned(),
+            version: version.clone(),
+            digest: digest.map(|d| d.as_slice().to_vec()),
+            requirements: requirements.clone(),
+        };
+        if let Some(cached) = self.cache.borrow().get(&key) {
+            return cached.clone();
+        }
+
+        let reviews: Vec<review::Package> = if let Some(digest) = digest {
+            self.db
+                .get_package_reviews_by_digest(digest)
+                .filter(|review| {
+                    review.package.source == source
+                        && review.package.name == name
+                        && &review.package.version == version
+                })
+                .collect()
+        } else {
+            self.db
+                .get_pkg_reviews_for_version(source, name, version)
+                .cloned()
+                .collect()
+        };
+
+        let trusted_reviews =
+            self.db
+                .get_trusted_reviews(reviews.into_iter(), &self.trust_set, requirements);
+
+        let verification = if !trusted_reviews.negative_by.is_empty() {
+            PackageVerification::Flagged {
+                by: trusted_reviews.negative_by,
+            }
+        } else if trusted_reviews.positive >= requirements.redundancy {
+            PackageVerification::Verified
+        } else if trusted_reviews.reviewers == 0 {
+            PackageVerification::Unknown
+        } else {
+            PackageVerification::Insufficient {
+                reviews_needed: requirements.redundancy - trusted_reviews.positive,
+            }
+        };
+
+        self.cache.borrow_mut().insert(key, verification.clone());
+        verification
+    }
+}
@@ -1,6 +1,6 @@
 use crate::prelude::*;
 use argon2::{self, Config};
-use crev_common::rand::random_vec;
+use crev_common::rand::{self, random_vec_with, CrevRng};
 use crev_common::serde::{as_base64, from_base64};
 use crev_data::id::{OwnId, PubId};
 use failure::{bail, format_err};
@@ -11,7 +11,7 @@ use serde_yaml;
 use std::{self, fmt, io::Write, path::Path};
 
 const CURRENT_LOCKED_ID_SERIALIZATION_VERSION: i64 = -1;
-pub type PassphraseFn<'a> = &'a dyn Fn() -> std::io::Result<String>;
+pub type PassphraseFn<'a> = &'a dyn Fn() -> crev_common::Result<String>;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PassConfig {
@@ -61,6 +61,15 @@ impl std::str::FromStr for LockedId {
 
 impl LockedId {
     pub fn from_own_id(own_id: &OwnId, passphrase: &str) -> Result<LockedId> {
+        Self::from_own_id_with_rng(own_id, passphrase, &mut rand::os_rng())
+    }
+
+    /// Like `from_own_id`, with the salt and nonce drawn from `rng`
+    pub fn from_own_id_with_rng(
+        own_id: &OwnId,
+        passphrase: &str,
+        rng: &mut impl CrevRng,
+    ) -> Result<LockedId> {
         use miscreant::aead::Aead;
 
         let config = Config {
@@ -78,12 +87,12 @@ impl LockedId {
             secret: &[],
         };
 
-        let pwsalt = random_vec(32);
+        let pwsalt = random_vec_with(rng, 32);
         let pwhash = argon2::hash_raw(passphrase.as_bytes(), &pwsalt, &config)?;
 
         let mut siv = miscreant::aead::Aes256SivAead::new(&pwhash);
 
-        let seal_nonce = random_vec(32);
+        let seal_nonce = random_vec_with(rng, 32);
 
         Ok(LockedId {
             version: CURRENT_LOCKED_ID_SERIALIZATION_VERSION,
@@ -113,7 +122,8 @@ impl LockedId {
     pub fn save_to(&self, path: &Path) -> Result<()> {
         let mut file = std::fs::OpenOptions::new()
             .create(true)
-            .append(true)
+            .write(true)
+            .truncate(true)
             .open(path)?;
 
         // it is not terribly important for this file to be readable
@@ -132,6 +142,17 @@ impl LockedId {
         Ok(serde_yaml::from_reader(&file)?)
     }
 
+    /// Read a key file, either YAML (as stored in the config directory) or TOML
+    pub fn read_from_file(path: &Path) -> Result<Self> {
+        let s = crev_common::read_file_to_string(path)?;
+        match serde_yaml::from_str(&s) {
+            Ok(id) => Ok(id),
+            Err(yaml_err) => toml::from_str(&s).map_err(|_| {
+                format_err!("`{}` is not a valid Id file: {}", path.display(), yaml_err)
+            }),
+        }
+    }
+
     pub fn to_unlocked(&self, passphrase: &str) -> Result<OwnId> {
         let LockedId {
             ref version,
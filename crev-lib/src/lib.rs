@@ -1,8 +1,10 @@
 #![type_length_limit = "10709970"]
 
 pub mod activity;
+pub mod context;
 pub mod id;
 pub mod local;
+pub mod notes;
 pub(crate) mod prelude;
 pub mod proof;
 pub mod proofdb;
@@ -13,15 +15,17 @@ pub mod util;
 use crate::{prelude::*, proofdb::TrustSet};
 use crev_data::Digest;
 use failure::format_err;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashMap, HashSet},
     fmt,
     path::{Path, PathBuf},
 };
 
 pub use self::local::Local;
 pub use crate::proofdb::{ProofDB, TrustDistanceParams};
-pub use activity::{ReviewActivity, ReviewMode};
+pub use activity::{ReviewActivity, ReviewMode, ReviewStart};
+pub use context::{CrevContext, PackageVerification};
 
 /// Trait representing a place that can keep proofs
 ///
@@ -64,7 +68,7 @@ impl TrustOrDistrust {
 }
 
 /// Verification requirements
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct VerificationRequirements {
     pub trust_level: crev_data::Level,
     pub understanding: crev_data::Level,
@@ -72,6 +76,17 @@ pub struct VerificationRequirements {
     pub redundancy: u64,
 }
 
+impl Default for VerificationRequirements {
+    fn default() -> Self {
+        VerificationRequirements {
+            trust_level: crev_data::Level::Low,
+            understanding: crev_data::Level::None,
+            thoroughness: crev_data::Level::None,
+            redundancy: 1,
+        }
+    }
+}
+
 /// Result of verification
 ///
 /// Not named `Result` to avoid confusion with `Result` type.
@@ -101,68 +116,108 @@ impl fmt::Display for VerificationStatus {
     }
 }
 
-pub fn dir_or_git_repo_verify<H1>(
+/// Which files of a directory are taken into account when digesting it
+///
+/// Paths in the ignore list are relative to the directory root;
+/// ignoring a directory excludes everything inside it as well.
+#[derive(Clone, Debug, Default)]
+pub struct DigestOptions {
+    ignore_list: HashSet<PathBuf>,
+}
+
+impl DigestOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ignore(mut self, rel_path: impl Into<PathBuf>) -> Self {
+        self.ignore_list.insert(rel_path.into());
+        self
+    }
+
+    pub fn ignore_list(&self) -> &HashSet<PathBuf> {
+        &self.ignore_list
+    }
+
+    /// Is `relative_path` (relative to the digested directory) included
+    pub fn should_include(&self, relative_path: &Path) -> bool {
+        !relative_path
+            .ancestors()
+            .any(|path| self.ignore_list.contains(path))
+    }
+
+    /// All included entries (files, directories and symlinks) under `root_path`
+    ///
+    /// Returned paths are relative to `root_path`.
+    pub fn included_paths(&self, root_path: &Path) -> Result<HashSet<PathBuf>> {
+        let mut paths = HashSet::new();
+        for entry in crev_common::fs::walk_dir(root_path, |path| {
+            !self.should_include(path.strip_prefix(root_path).unwrap_or(path))
+        }) {
+            let entry = entry?;
+            let path = entry.path().strip_prefix(root_path)?;
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+            paths.insert(path.to_owned());
+        }
+        Ok(paths)
+    }
+}
+
+impl<H> From<HashSet<PathBuf, H>> for DigestOptions
+where
+    H: std::hash::BuildHasher,
+{
+    fn from(ignore_list: HashSet<PathBuf, H>) -> Self {
+        Self {
+            ignore_list: ignore_list.into_iter().collect(),
+        }
+    }
+}
+
+pub fn dir_or_git_repo_verify(
     path: &Path,
-    ignore_list: &HashSet<PathBuf, H1>,
+    options: &DigestOptions,
     db: &ProofDB,
     trusted_set: &TrustSet,
     requirements: &VerificationRequirements,
-) -> Result<crate::VerificationStatus>
-where
-    H1: std::hash::BuildHasher + std::default::Default,
-{
+) -> Result<crate::VerificationStatus> {
     let digest = if path.join(".git").exists() {
-        get_recursive_digest_for_git_dir(path, ignore_list)?
+        get_recursive_digest_for_git_dir(path, options)?
     } else {
-        Digest::from_vec(crev_recursive_digest::get_recursive_digest_for_dir::<
-            crev_common::Blake2b256,
-            H1,
-        >(path, ignore_list)?)
+        get_dir_digest(path, options)?
     };
 
     Ok(db.verify_package_digest(&digest, trusted_set, requirements))
 }
 
-pub fn dir_verify<H1>(
+pub fn dir_verify(
     path: &Path,
-    ignore_list: &HashSet<PathBuf, H1>,
+    options: &DigestOptions,
     db: &ProofDB,
     trusted_set: &TrustSet,
     requirements: &VerificationRequirements,
-) -> Result<crate::VerificationStatus>
-where
-    H1: std::hash::BuildHasher + std::default::Default,
-{
-    let digest = Digest::from_vec(crev_recursive_digest::get_recursive_digest_for_dir::<
-        crev_common::Blake2b256,
-        H1,
-    >(path, ignore_list)?);
+) -> Result<crate::VerificationStatus> {
+    let digest = get_dir_digest(path, options)?;
     Ok(db.verify_package_digest(&digest, trusted_set, requirements))
 }
 
-pub fn get_dir_digest<H1>(path: &Path, ignore_list: &HashSet<PathBuf, H1>) -> Result<Digest>
-where
-    H1: std::hash::BuildHasher + std::default::Default,
-{
-    Ok(Digest::from_vec(
-        crev_recursive_digest::get_recursive_digest_for_dir::<crev_common::Blake2b256, H1>(
-            path,
-            ignore_list,
-        )?,
-    ))
+pub fn get_dir_digest(path: &Path, options: &DigestOptions) -> Result<Digest> {
+    Ok(Digest::from_vec(get_recursive_digest_for_paths(
+        path,
+        options.included_paths(path)?,
+    )?))
 }
 
-pub fn get_recursive_digest_for_git_dir<H>(
+pub fn get_recursive_digest_for_git_dir(
     root_path: &Path,
-    ignore_list: &HashSet<PathBuf, H>,
-) -> Result<Digest>
-where
-    H: std::hash::BuildHasher + std::default::Default,
-{
+    options: &DigestOptions,
+) -> Result<Digest> {
     let git_repo = git2::Repository::open(root_path)?;
 
     let mut status_opts = git2::StatusOptions::new();
-    let mut paths = HashSet::default();
+    let mut paths = HashSet::new();
 
     status_opts.include_unmodified(true);
     status_opts.include_untracked(false);
@@ -172,18 +227,16 @@ where
                 .path()
                 .ok_or_else(|| format_err!("Git entry without a path"))?,
         );
-        if ignore_list.contains(&entry_path) {
+        if !options.should_include(&entry_path) {
             continue;
         };
 
         paths.insert(entry_path);
     }
 
-    Ok(Digest::from_vec(
-        crev_recursive_digest::get_recursive_digest_for_paths::<crev_common::Blake2b256, H>(
-            root_path, paths,
-        )?,
-    ))
+    Ok(Digest::from_vec(get_recursive_digest_for_paths(
+        root_path, paths,
+    )?))
 }
 
 pub fn get_recursive_digest_for_paths<H>(
@@ -199,19 +252,206 @@ where
     >(root_path, paths)?)
 }
 
-pub fn get_recursive_digest_for_dir<H>(
+pub fn get_recursive_digest_for_dir(root_path: &Path, options: &DigestOptions) -> Result<Digest> {
+    get_dir_digest(root_path, options)
+}
+
+/// Files of a directory covered by trusted code reviews
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartialReviewCoverage {
+    /// Files whose current content was reviewed
+    pub reviewed_files: usize,
+    pub total_files: usize,
+}
+
+/// Check which files of a directory match the digests in `reviewed_files`
+///
+/// See `ProofDB::get_trusted_reviewed_files`.
+pub fn dir_partial_review_coverage(
+    path: &Path,
+    options: &DigestOptions,
+    reviewed_files: &HashMap<PathBuf, HashSet<Vec<u8>>>,
+) -> Result<PartialReviewCoverage> {
+    let files = get_file_digests_for_dir(path, options)?;
+    Ok(PartialReviewCoverage {
+        reviewed_files: files
+            .iter()
+            .filter(|(path, (digest, _))| {
+                reviewed_files
+                    .get(*path)
+                    .map_or(false, |digests| digests.contains(digest))
+            })
+            .count(),
+        total_files: files.len(),
+    })
+}
+
+/// A file that differs between two directories compared with `diff_dir_digests`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DirDiffEntry {
+    pub path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub size_a: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub size_b: Option<u64>,
+}
+
+/// Per-file differences between two directories
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DirDiffReport {
+    pub only_in_a: Vec<DirDiffEntry>,
+    pub only_in_b: Vec<DirDiffEntry>,
+    pub different: Vec<DirDiffEntry>,
+    /// Number of files that are the same in both
+    pub identical: usize,
+}
+
+impl DirDiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.different.is_empty()
+    }
+}
+
+/// Digest and size of every file (and symlink) in `root_path`
+fn get_file_digests_for_dir(
     root_path: &Path,
-    rel_path_ignore_list: &HashSet<PathBuf, H>,
-) -> Result<Digest>
-where
-    H: std::hash::BuildHasher,
-{
-    Ok(Digest::from_vec(
-        crev_recursive_digest::get_recursive_digest_for_dir::<crev_common::Blake2b256, H>(
-            root_path,
-            rel_path_ignore_list,
-        )?,
-    ))
+    options: &DigestOptions,
+) -> Result<BTreeMap<PathBuf, (Vec<u8>, u64)>> {
+    let mut files = BTreeMap::new();
+    for entry in crev_common::fs::walk_dir(root_path, |path| {
+        !options.should_include(path.strip_prefix(root_path).unwrap_or(path))
+    }) {
+        let entry = entry?;
+        let file_type = entry.file_type();
+        let digest = if file_type.is_symlink() {
+            crev_common::blake2b256sum(entry.path().read_link()?.to_string_lossy().as_bytes())
+        } else if file_type.is_file() {
+            crev_common::blake2b256sum_file(entry.path())?
+        } else {
+            continue;
+        };
+        let path = entry.path().strip_prefix(root_path)?.to_owned();
+        files.insert(path, (digest, entry.metadata()?.len()));
+    }
+    Ok(files)
+}
+
+/// Compare two directories file by file
+///
+/// Useful to explain why the recursive digests of two directories
+/// are different.
+pub fn diff_dir_digests(a: &Path, b: &Path, options: &DigestOptions) -> Result<DirDiffReport> {
+    let files_a = get_file_digests_for_dir(a, options)?;
+    let mut files_b = get_file_digests_for_dir(b, options)?;
+
+    let mut report = DirDiffReport::default();
+    for (path, (digest_a, size_a)) in files_a {
+        match files_b.remove(&path) {
+            None => report.only_in_a.push(DirDiffEntry {
+                path,
+                size_a: Some(size_a),
+                size_b: None,
+            }),
+            Some((digest_b, size_b)) => {
+                if digest_a != digest_b {
+                    report.different.push(DirDiffEntry {
+                        path,
+                        size_a: Some(size_a),
+                        size_b: Some(size_b),
+                    })
+                } else {
+                    report.identical += 1;
+                }
+            }
+        }
+    }
+    report.only_in_b = files_b
+        .into_iter()
+        .map(|(path, (_, size_b))| DirDiffEntry {
+            path,
+            size_a: None,
+            size_b: Some(size_b),
+        })
+        .collect();
+
+    Ok(report)
+}
+
+/// Files that `cargo package` generates or rewrites, so they are not expected to match
+pub const PACKAGING_GENERATED_FILES: &[&str] = &[
+    ".cargo-ok",
+    ".cargo_vcs_info.json",
+    "Cargo.toml",
+    "Cargo.toml.orig",
+    "Cargo.lock",
+];
+
+/// How the packaged source of a crate compares to its source repository
+#[derive(Clone, Debug, Default)]
+pub struct ProvenanceReport {
+    /// Packaged files that are the same in the repository
+    pub identical: usize,
+    /// Packaged files with a different content in the repository
+    pub different: Vec<PathBuf>,
+    /// Packaged files that are not in the repository
+    pub only_in_package: Vec<PathBuf>,
+    /// Files of the repository that were not packaged
+    pub only_in_repo: Vec<PathBuf>,
+}
+
+impl ProvenanceReport {
+    /// Is every packaged file also in the repository, with the same content
+    pub fn is_faithful(&self) -> bool {
+        self.different.is_empty() && self.only_in_package.is_empty()
+    }
+}
+
+fn same_file_content(a: &Path, b: &Path) -> Result<bool> {
+    Ok(b.is_file() && crev_common::blake2b256sum_file(a)? == crev_common::blake2b256sum_file(b)?)
+}
+
+/// Compare the packaged source of a crate with its directory in a checkout of its repository
+///
+/// Files `cargo package` generates are skipped, except the original
+/// `Cargo.toml` kept as `Cargo.toml.orig`. Packaged files from outside of
+/// the package directory (eg. `readme = "../README.md"`) are looked up
+/// relative to `repo_root`.
+pub fn compare_package_to_repo(
+    package_dir: &Path,
+    repo_root: &Path,
+    repo_package_dir: &Path,
+) -> Result<ProvenanceReport> {
+    let mut options = DigestOptions::new().ignore(".git").ignore("target");
+    for path in PACKAGING_GENERATED_FILES {
+        options = options.ignore(*path);
+    }
+    let diff = diff_dir_digests(package_dir, repo_package_dir, &options)?;
+
+    let mut report = ProvenanceReport {
+        identical: diff.identical,
+        different: diff.different.into_iter().map(|entry| entry.path).collect(),
+        only_in_repo: diff.only_in_b.into_iter().map(|entry| entry.path).collect(),
+        ..Default::default()
+    };
+    for entry in diff.only_in_a {
+        if same_file_content(&package_dir.join(&entry.path), &repo_root.join(&entry.path))? {
+            report.identical += 1;
+        } else {
+            report.only_in_package.push(entry.path);
+        }
+    }
+    let manifest_orig = package_dir.join("Cargo.toml.orig");
+    if manifest_orig.exists() {
+        if same_file_content(&manifest_orig, &repo_package_dir.join("Cargo.toml"))? {
+            report.identical += 1;
+        } else {
+            report.different.push(PathBuf::from("Cargo.toml.orig"));
+        }
+    }
+
+    Ok(report)
 }
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
-use crate::activity::ReviewActivity;
+use crate::activity::{ReviewActivity, ReviewStart};
 use crate::{
     id::{self, LockedId, PassphraseFn},
+    notes::Notes,
     prelude::*,
     util, ProofDB, ProofStore,
 };
@@ -22,17 +23,26 @@ use resiter::*;
 use serde::{Deserialize, Serialize};
 use serde_yaml;
 use std::{
-    cell::RefCell,
-    collections::HashSet,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet, HashSet},
     ffi::OsString,
-    fs,
+    fmt, fs,
     io::{BufRead, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    time::Duration,
 };
 
 const CURRENT_USER_CONFIG_SERIALIZATION_VERSION: i64 = -1;
 
+/// How long to wait for other crev processes to release the proof repository
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Environment variable with an Id (or its prefix) to use instead of the current one
+///
+/// Applied by `Local::auto_open`; see `Local::set_id_override`.
+pub const CREV_ID_ENV: &str = "CREV_ID";
+
 fn generete_salt() -> Vec<u8> {
     crev_common::rand::random_vec(32)
 }
@@ -75,6 +85,39 @@ pub struct UserConfig {
         default = "Option::default"
     )]
     pub open_cmd: Option<String>,
+
+    /// Seconds to wait for other crev processes to release the proof repository
+    #[serde(
+        rename = "lock-timeout",
+        skip_serializing_if = "Option::is_none",
+        default = "Option::default"
+    )]
+    pub lock_timeout: Option<u64>,
+
+    /// Hours a proof can be dated into the future, to allow for clock skew
+    #[serde(
+        rename = "max-date-skew",
+        skip_serializing_if = "Option::is_none",
+        default = "Option::default"
+    )]
+    pub max_date_skew: Option<u64>,
+
+    /// Use the current time for proofs dated too far into the future,
+    /// instead of ignoring them
+    #[serde(
+        rename = "clamp-future-dates",
+        skip_serializing_if = "std::ops::Not::not",
+        default
+    )]
+    pub clamp_future_dates: bool,
+
+    /// Sign commits of the local proof repository with the current Id
+    #[serde(
+        rename = "sign-commits",
+        skip_serializing_if = "std::ops::Not::not",
+        default
+    )]
+    pub sign_commits: bool,
 }
 
 impl Default for UserConfig {
@@ -84,6 +127,10 @@ impl Default for UserConfig {
             current_id: None,
             host_salt: generete_salt(),
             open_cmd: None,
+            lock_timeout: None,
+            max_date_skew: None,
+            clamp_future_dates: false,
+            sign_commits: false,
         }
     }
 }
@@ -98,10 +145,10 @@ impl UserConfig {
     }
 
     pub fn edit_iteractively(&self) -> Result<Self> {
-        let mut text = serde_yaml::to_string(self)?;
+        let mut text = crev_common::to_toml_string(self)?;
         loop {
             text = util::edit_text_iteractively(&text)?;
-            match serde_yaml::from_str(&text) {
+            match toml::from_str(&text) {
                 Err(e) => {
                     eprintln!("There was an error parsing content: {}", e);
                     crev_common::try_again_or_cancel()?;
@@ -112,6 +159,62 @@ impl UserConfig {
     }
 }
 
+/// A proof that was skipped, or loaded with a different date
+#[derive(Debug, Clone)]
+pub struct LoadWarning {
+    pub path: PathBuf,
+    pub problem: String,
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.problem)
+    }
+}
+
+/// Problems with individual proofs, found while loading them
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    pub warnings: Vec<LoadWarning>,
+}
+
+impl LoadReport {
+    fn warn(&mut self, path: &Path, problem: String) {
+        self.warnings.push(LoadWarning {
+            path: path.to_owned(),
+            problem,
+        });
+    }
+
+    pub fn print(&self) {
+        for warning in &self.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+}
+
+/// Proof file that failed to parse or verify
+#[derive(Debug, Clone)]
+pub struct BrokenProofFile {
+    pub path: PathBuf,
+    pub problem: String,
+}
+
+/// Exclusive access to the local proof repository, see `Local::lock_proof_dir`
+pub struct ProofDirLock<'a> {
+    local: &'a Local,
+}
+
+impl<'a> Drop for ProofDirLock<'a> {
+    fn drop(&mut self) {
+        let depth = self.local.proof_dir_lock_depth.get() - 1;
+        self.local.proof_dir_lock_depth.set(depth);
+        if depth == 0 {
+            *self.local.proof_dir_lock.borrow_mut() = None;
+        }
+    }
+}
+
 /// Local config stored in `~/.config/crev`
 ///
 /// This managed IDs, local proof repository, etc.
@@ -119,7 +222,14 @@ pub struct Local {
     root_path: PathBuf,
     cache_path: PathBuf,
     cur_url: RefCell<Option<Url>>,
+    id_override: RefCell<Option<Id>>,
     user_config: RefCell<Option<UserConfig>>,
+    fetch_full_history: Cell<bool>,
+    verify_fetched_signatures: Cell<bool>,
+    require_signed_commits: Cell<bool>,
+    lock_timeout: Cell<Duration>,
+    proof_dir_lock: RefCell<Option<crev_common::fs::FileLock>>,
+    proof_dir_lock_depth: Cell<usize>,
 }
 
 impl Local {
@@ -127,13 +237,89 @@ impl Local {
     fn new() -> Result<Self> {
         let proj_dir = ProjectDirs::from("", "", "crev")
             .expect("no valid home directory path could be retrieved from the operating system");
-        let root_path = proj_dir.config_dir().into();
-        let cache_path = proj_dir.cache_dir().into();
-        Ok(Self {
-            root_path,
-            cache_path,
+        Ok(Self::new_at(proj_dir.config_dir(), proj_dir.cache_dir()))
+    }
+
+    fn new_at(root_path: &Path, cache_path: &Path) -> Self {
+        Self {
+            root_path: root_path.into(),
+            cache_path: cache_path.into(),
             cur_url: RefCell::new(None),
+            id_override: RefCell::new(None),
             user_config: RefCell::new(None),
+            fetch_full_history: Cell::new(false),
+            verify_fetched_signatures: Cell::new(false),
+            require_signed_commits: Cell::new(false),
+            lock_timeout: Cell::new(DEFAULT_LOCK_TIMEOUT),
+            proof_dir_lock: RefCell::new(None),
+            proof_dir_lock_depth: Cell::new(0),
+        }
+    }
+
+    /// Clone proof repositories with their full history when fetching
+    ///
+    /// By default new clones contain only the latest commit of the default branch.
+    pub fn set_fetch_full_history(&self, full: bool) {
+        self.fetch_full_history.set(full);
+    }
+
+    /// Check proof files of proof repositories right after fetching them
+    ///
+    /// Files with a malformed proof, or one not signed by the Id it claims
+    /// to be from, are moved to `QUARANTINE_DIR` of the fetched repository.
+    pub fn set_verify_fetched_signatures(&self, verify: bool) {
+        self.verify_fetched_signatures.set(verify);
+    }
+
+    /// Don't use fetched proof repositories unless their latest commits are
+    /// signed by their owner
+    ///
+    /// Otherwise only inconsistently signed commits are warned about;
+    /// see `CommitSignatureReport::is_suspicious`.
+    pub fn set_require_signed_commits(&self, require: bool) {
+        self.require_signed_commits.set(require);
+    }
+
+    /// How long to wait for other crev processes in `lock_proof_dir`
+    ///
+    /// Defaults to `lock-timeout` of the user config, or 30 seconds.
+    pub fn set_lock_timeout(&self, timeout: Duration) {
+        self.lock_timeout.set(timeout);
+    }
+
+    /// Lock the local proof repository against changes by other crev processes
+    ///
+    /// Held until the returned guard is dropped. Locking again, while
+    /// this `Local` is already holding the lock, doesn't wait.
+    pub fn lock_proof_dir(&self) -> Result<ProofDirLock<'_>> {
+        if self.proof_dir_lock_depth.get() == 0 {
+            fs::create_dir_all(&self.root_path)?;
+            let lock = self.lock_path(&self.user_proofs_path())?;
+            *self.proof_dir_lock.borrow_mut() = Some(lock);
+        }
+        self.proof_dir_lock_depth
+            .set(self.proof_dir_lock_depth.get() + 1);
+        Ok(ProofDirLock { local: self })
+    }
+
+    /// Lock the cached copy of the proof repository at `url` against other crev processes
+    ///
+    /// Unlike `lock_proof_dir`, it must not be locked again while held.
+    pub fn lock_remote_cache(&self, url: &str) -> Result<crev_common::fs::FileLock> {
+        let path = self.get_remote_git_cache_path(url);
+        fs::create_dir_all(path.parent().expect("Not a root dir"))?;
+        self.lock_path(&path)
+    }
+
+    fn lock_path(&self, path: &Path) -> Result<crev_common::fs::FileLock> {
+        let timeout = self.lock_timeout.get();
+        crev_common::fs::FileLock::exclusive_with_timeout(path, timeout).map_err(|e| match e {
+            crev_common::Error::LockTimeout(_) => format_err!(
+                "Another crev process is running; gave up waiting for it after {}s (lock file: {}.lock)",
+                timeout.as_secs_f32(),
+                path.display()
+            ),
+            _ => e.into(),
         })
     }
 
@@ -142,23 +328,47 @@ impl Local {
     }
 
     pub fn auto_open() -> Result<Self> {
-        let repo = Self::new()?;
+        let repo = Self::open(Self::new()?)?;
+        if let Ok(id_str) = std::env::var(CREV_ID_ENV) {
+            let id = repo.resolve_id_prefix(&id_str)?;
+            repo.set_id_override(Some(id));
+        }
+        Ok(repo)
+    }
+
+    /// Like `auto_open`, but with config and cache in custom directories
+    pub fn auto_open_at(root_path: &Path, cache_path: &Path) -> Result<Self> {
+        Self::open(Self::new_at(root_path, cache_path))
+    }
+
+    fn open(repo: Self) -> Result<Self> {
         fs::create_dir_all(&repo.cache_remotes_path())?;
-        if !repo.root_path.exists() || !repo.user_config_path().exists() {
+        if !repo.root_path.exists() || !repo.user_config_exists() {
             bail!("User config not-initialized. Use `crev id new` to generate CrevID.");
         }
 
-        *repo.user_config.borrow_mut() = Some(repo.load_user_config()?);
+        let config = repo.load_user_config()?;
+        if let Some(secs) = config.lock_timeout {
+            repo.set_lock_timeout(Duration::from_secs(secs));
+        }
+        *repo.user_config.borrow_mut() = Some(config);
         Ok(repo)
     }
 
     pub fn auto_create() -> Result<Self> {
-        let repo = Self::new()?;
+        Self::create(Self::new()?)
+    }
+
+    /// Like `auto_create`, but with config and cache in custom directories
+    pub fn auto_create_at(root_path: &Path, cache_path: &Path) -> Result<Self> {
+        Self::create(Self::new_at(root_path, cache_path))
+    }
+
+    fn create(repo: Self) -> Result<Self> {
         fs::create_dir_all(&repo.root_path)?;
         fs::create_dir_all(&repo.cache_remotes_path())?;
 
-        let config_path = repo.user_config_path();
-        if config_path.exists() {
+        if repo.user_config_exists() {
             bail!("User config already exists");
         }
         let config: UserConfig = default();
@@ -169,8 +379,7 @@ impl Local {
 
     pub fn auto_create_or_open() -> Result<Self> {
         let repo = Self::new()?;
-        let config_path = repo.user_config_path();
-        if config_path.exists() {
+        if repo.user_config_exists() {
             Self::auto_open()
         } else {
             Self::auto_create()
@@ -178,11 +387,11 @@ impl Local {
     }
 
     pub fn read_current_id(&self) -> Result<crev_data::Id> {
-        Ok(self.load_user_config()?.get_current_userid()?.to_owned())
+        self.get_current_userid()
     }
 
     pub fn read_current_id_opt(&self) -> Result<Option<crev_data::Id>> {
-        Ok(self.load_user_config()?.get_current_userid_opt().cloned())
+        self.get_current_userid_opt()
     }
 
     /// Calculate `for_id` that is used in a lot of operations
@@ -201,6 +410,41 @@ impl Local {
             .ok_or_else(|| format_err!("Id not specified and current id not set"))
     }
 
+    /// Use `id` instead of the current Id of the user config
+    ///
+    /// Only affects this `Local`: proofs get signed by `id` and stored in
+    /// the proof repository of its url. `None` goes back to the current Id.
+    pub fn set_id_override(&self, id: Option<Id>) {
+        *self.cur_url.borrow_mut() = None;
+        *self.id_override.borrow_mut() = id;
+    }
+
+    /// Find the one local Id starting with `prefix`
+    pub fn resolve_id_prefix(&self, prefix: &str) -> Result<Id> {
+        if prefix.is_empty() {
+            bail!("Id prefix must not be empty");
+        }
+        let mut matching: Vec<Id> = self
+            .list_ids()?
+            .into_iter()
+            .map(|pub_id| pub_id.id)
+            .filter(|id| id.to_string().starts_with(prefix))
+            .collect();
+        match matching.len() {
+            0 => bail!("No local Id matches `{}`", prefix),
+            1 => Ok(matching.remove(0)),
+            _ => bail!(
+                "Id prefix `{}` is ambiguous; it matches: {}",
+                prefix,
+                matching
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
     pub fn save_current_id(&self, id: &Id) -> Result<()> {
         let path = self.id_path(id);
         if !path.exists() {
@@ -233,7 +477,7 @@ impl Local {
         self.root_path.join("proofs")
     }
 
-    fn id_path(&self, id: &Id) -> PathBuf {
+    pub(crate) fn id_path(&self, id: &Id) -> PathBuf {
         match id {
             Id::Crev { id } => self
                 .user_ids_path()
@@ -253,9 +497,88 @@ impl Local {
     }
 
     fn user_config_path(&self) -> PathBuf {
+        self.user_dir_path().join("config.toml")
+    }
+
+    /// Path of the user config written by older versions
+    fn legacy_user_config_path(&self) -> PathBuf {
         self.user_dir_path().join("config.yaml")
     }
 
+    fn user_config_exists(&self) -> bool {
+        self.user_config_path().exists() || self.legacy_user_config_path().exists()
+    }
+
+    /// Path of the defaults of command line flags
+    pub fn cli_config_path(&self) -> PathBuf {
+        self.user_dir_path().join("cli.yaml")
+    }
+
+    /// Load the defaults of command line flags
+    ///
+    /// `T::default()` if there's no config file yet.
+    pub fn load_cli_config<T>(&self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned + Default,
+    {
+        let path = self.cli_config_path();
+        if !path.exists() {
+            return Ok(T::default());
+        }
+        Ok(crev_common::read_from_yaml_file(&path)
+            .with_context(|_| format!("Can't read {}", path.display()))?)
+    }
+
+    pub fn store_cli_config<T: Serialize>(&self, config: &T) -> Result<()> {
+        Ok(crev_common::save_to_yaml_file(
+            &self.cli_config_path(),
+            config,
+        )?)
+    }
+
+    /// Edit the defaults of command line flags in a text editor
+    ///
+    /// The file is saved only once it parses, and passes `validate`.
+    pub fn edit_cli_config<T>(&self, validate: impl Fn(&T) -> Result<()>) -> Result<()>
+    where
+        T: serde::de::DeserializeOwned + Serialize + Default,
+    {
+        let path = self.cli_config_path();
+        let mut text = if path.exists() {
+            crev_common::read_file_to_string(&path)?
+        } else {
+            serde_yaml::to_string(&T::default())?
+        };
+        loop {
+            text = util::edit_text_iteractively(&text)?;
+            match serde_yaml::from_str(&text)
+                .map_err(failure::Error::from)
+                .and_then(|config: T| validate(&config))
+            {
+                Err(e) => {
+                    eprintln!("There was an error parsing content: {}", e);
+                    crev_common::try_again_or_cancel()?;
+                }
+                Ok(()) => break,
+            }
+        }
+        crev_common::store_str_to_file(&path, &text)?;
+        Ok(())
+    }
+
+    /// Path of private notes; outside of the proof repository
+    fn notes_path(&self) -> PathBuf {
+        self.user_dir_path().join("notes.toml")
+    }
+
+    pub fn load_notes(&self) -> Result<Notes> {
+        Notes::read_from_toml_file(&self.notes_path())
+    }
+
+    pub fn store_notes(&self, notes: &Notes) -> Result<()> {
+        notes.save_to_toml_file(&self.notes_path())
+    }
+
     pub fn cache_remotes_path(&self) -> PathBuf {
         self.cache_path.join("remotes")
     }
@@ -292,6 +615,63 @@ impl Local {
         Ok(())
     }
 
+    fn cache_review_start_path(
+        &self,
+        source: &str,
+        name: &str,
+        version: &semver::Version,
+    ) -> PathBuf {
+        self.cache_activity_path()
+            .join("review-start")
+            .join(sanitize_name(source))
+            .join(sanitize_name(name))
+            .join(sanitize_name(&version.to_string()))
+            .with_extension("yaml")
+    }
+
+    /// Note that a review session of the package starts now
+    pub fn record_review_start(
+        &self,
+        source: &str,
+        name: &str,
+        version: &semver::Version,
+    ) -> Result<()> {
+        let path = self.cache_review_start_path(source, name, version);
+        crev_common::save_to_yaml_file(&path, &ReviewStart::now())?;
+        Ok(())
+    }
+
+    /// Start of the review session of the package, if one was recorded
+    pub fn read_review_start(
+        &self,
+        source: &str,
+        name: &str,
+        version: &semver::Version,
+    ) -> Result<Option<ReviewStart>> {
+        let path = self.cache_review_start_path(source, name, version);
+        if path.exists() {
+            Ok(Some(crev_common::read_from_yaml_file(&path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// End the review session of the package
+    ///
+    /// Each session times one review, so the next one has to start anew.
+    pub fn clear_review_start(
+        &self,
+        source: &str,
+        name: &str,
+        version: &semver::Version,
+    ) -> Result<()> {
+        let path = self.cache_review_start_path(source, name, version);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
     pub fn read_review_activity(
         &self,
         source: &str,
@@ -307,20 +687,56 @@ impl Local {
         }
     }
 
+    /// How to treat proofs dated in the future, as set in the user config
+    ///
+    /// Fails if `max-date-skew` is too large to be a duration.
+    pub fn date_policy(&self) -> Result<proof::DatePolicy> {
+        let mut policy = proof::DatePolicy::default();
+        if let Ok(config) = self.load_user_config() {
+            if let Some(hours) = config.max_date_skew {
+                const MILLIS_PER_HOUR: i64 = 60 * 60 * 1000;
+                policy.max_skew = Some(hours)
+                    .filter(|&hours| hours <= (i64::max_value() / MILLIS_PER_HOUR) as u64)
+                    .map(|hours| chrono::Duration::milliseconds(hours as i64 * MILLIS_PER_HOUR))
+                    .ok_or_else(|| format_err!("max-date-skew of {} hours is too large", hours))?;
+            }
+            policy.clamp = config.clamp_future_dates;
+        }
+        Ok(policy)
+    }
+
+    /// Load the user config
+    ///
+    /// A YAML config of older versions is rewritten as TOML.
     pub fn load_user_config(&self) -> Result<UserConfig> {
         let path = self.user_config_path();
+        let legacy_path = self.legacy_user_config_path();
+
+        if !path.exists() && legacy_path.exists() {
+            let config: UserConfig = {
+                let (config_str, _lock) = crev_common::fs::read_file_with_lock(&legacy_path)?;
+                serde_yaml::from_str(&config_str)?
+            };
+            self.store_user_config(&config)?;
+            return Ok(config);
+        }
 
-        let config_str = crev_common::read_file_to_string(&path)?;
+        let (config_str, _lock) = crev_common::fs::read_file_with_lock(&path)?;
 
-        Ok(serde_yaml::from_str(&config_str)?)
+        Ok(toml::from_str(&config_str)?)
     }
 
     pub fn store_user_config(&self, config: &UserConfig) -> Result<()> {
         let path = self.user_config_path();
 
-        let config_str = serde_yaml::to_string(&config)?;
+        let config_str = crev_common::to_toml_string(&config)?;
 
-        util::store_str_to_file(&path, &config_str)?;
+        crev_common::store_str_to_file_with_lock(&path, &config_str)?;
+
+        let legacy_path = self.legacy_user_config_path();
+        if legacy_path.exists() {
+            fs::remove_file(&legacy_path)?;
+        }
 
         *self.user_config.borrow_mut() = Some(config.clone());
         Ok(())
@@ -332,6 +748,9 @@ impl Local {
     }
 
     pub fn get_current_userid_opt(&self) -> Result<Option<Id>> {
+        if let Some(id) = self.id_override.borrow().clone() {
+            return Ok(Some(id));
+        }
         let config = self.load_user_config()?;
         Ok(config.current_id)
     }
@@ -366,26 +785,24 @@ impl Local {
             .ok_or_else(|| format_err!("Current Id not set"))
     }
 
+    /// Like `read_current_unlocked_id`, but for `id`, if given
+    pub fn read_current_or_unlocked_id(
+        &self,
+        id: Option<&Id>,
+        passphrase_callback: PassphraseFn<'_>,
+    ) -> Result<OwnId> {
+        match id {
+            Some(id) => self.read_unlocked_id(id, passphrase_callback),
+            None => self.read_current_unlocked_id(passphrase_callback),
+        }
+    }
+
     pub fn read_unlocked_id(
         &self,
         id: &Id,
         passphrase_callback: PassphraseFn<'_>,
     ) -> Result<OwnId> {
-        let locked = self.read_locked_id(id)?;
-        let mut i = 0;
-        loop {
-            let passphrase = passphrase_callback()?;
-            match locked.to_unlocked(&passphrase) {
-                Ok(o) => return Ok(o),
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    if i == 5 {
-                        return Err(e);
-                    }
-                }
-            }
-            i += 1;
-        }
+        unlock_with_retries(&self.read_locked_id(id)?, passphrase_callback)
     }
 
     pub fn save_locked_id(&self, id: &id::LockedId) -> Result<()> {
@@ -400,24 +817,12 @@ impl Local {
         git_https_url: &str,
         use_https_push: bool,
     ) -> Result<()> {
-        let push_url = if use_https_push {
-            git_https_url.to_string()
-        } else {
-            match util::git::https_to_git_url(git_https_url) {
-                Some(git_url) => git_url,
-                None => {
-                    eprintln!("Could not deduce `ssh` push url. Call:");
-                    eprintln!("cargo crev git remote set-url --push origin <url>");
-                    eprintln!("manually, after id is generated.");
-                    eprintln!("");
-                    git_https_url.to_string()
-                }
-            }
-        };
+        let push_url = git_push_url(git_https_url, use_https_push);
 
         let proof_dir =
             self.get_proofs_dir_path_for_url(&Url::new_git(git_https_url.to_owned()))?;
 
+        let _lock = self.lock_proof_dir()?;
         if proof_dir.exists() {
             eprintln!(
                 "Proof directory `{}` already exists. Will not clone.",
@@ -428,7 +833,7 @@ impl Local {
 
         self.ensure_proofs_root_exists()?;
 
-        match git2::Repository::clone(git_https_url, &proof_dir) {
+        match util::git::clone_git_repo(git_https_url, &proof_dir) {
             Ok(repo) => {
                 eprintln!("{} cloned to {}", git_https_url, proof_dir.display());
                 repo.remote_set_url("origin", &push_url)?;
@@ -441,6 +846,104 @@ impl Local {
         Ok(())
     }
 
+    /// Change the url of the current id
+    ///
+    /// The locked id is not signed, so only its url needs updating. Existing
+    /// proofs keep the old url; new proofs carry the new one, which is how
+    /// others learn about the change.
+    ///
+    /// With `migrate_proofs` the existing proof repository is moved to the
+    /// new url, otherwise the new url is cloned.
+    pub fn change_current_id_url(&self, new_url: &str, migrate_proofs: bool) -> Result<()> {
+        let mut locked_id = self.read_current_locked_id()?;
+        let id = locked_id.to_pubid().id;
+        let old_url = locked_id.url.clone();
+        let new_url = Url::new_git(new_url.to_owned());
+        if old_url == new_url {
+            bail!("Current Id already uses {}", new_url.url);
+        }
+        if let Some(other) = self
+            .list_ids()?
+            .into_iter()
+            .find(|other| other.id != id && other.url == new_url)
+        {
+            bail!("{} is already used by Id {}", new_url.url, other.id);
+        }
+
+        let old_dir = self.get_proofs_dir_path_for_url(&old_url)?;
+        let new_dir = self.get_proofs_dir_path_for_url(&new_url)?;
+        let _lock = self.lock_proof_dir()?;
+        if new_dir.exists() {
+            if let Some(author) = proofs_iter_for_path(new_dir.clone(), self.date_policy()?)
+                .map(|proof| proof.content.author_id())
+                .find(|author| *author != id)
+            {
+                bail!(
+                    "Proof directory `{}` already contains proofs of a different Id {}",
+                    new_dir.display(),
+                    author
+                );
+            }
+            if migrate_proofs {
+                bail!(
+                    "Proof directory `{}` already exists. Will not migrate proofs.",
+                    new_dir.display()
+                );
+            }
+        } else if migrate_proofs && old_dir.exists() {
+            fs::rename(&old_dir, &new_dir)?;
+            let repo = git2::Repository::open(&new_dir)?;
+            // fetching keeps working anonymously over https
+            repo.remote_set_url("origin", &new_url.url)?;
+            repo.remote_set_pushurl("origin", Some(&git_push_url(&new_url.url, false)))?;
+            eprintln!("{} moved to {}", old_dir.display(), new_dir.display());
+        } else {
+            self.clone_proof_dir_from_git(&new_url.url, false)?;
+        }
+
+        locked_id.url = new_url.clone();
+        self.save_locked_id(&locked_id)?;
+        *self.cur_url.borrow_mut() = Some(new_url);
+        Ok(())
+    }
+
+    /// Re-encrypt the current Id with a new passphrase
+    ///
+    /// The key pair stays the same, so all proofs signed with it remain valid.
+    pub fn change_current_id_passphrase(
+        &self,
+        passphrase_callback: PassphraseFn<'_>,
+        new_passphrase_callback: PassphraseFn<'_>,
+    ) -> Result<()> {
+        let old_passphrase = std::cell::RefCell::new(String::new());
+        let own_id = self.read_current_unlocked_id(&|| {
+            let passphrase = passphrase_callback()?;
+            *old_passphrase.borrow_mut() = passphrase.clone();
+            Ok(passphrase)
+        })?;
+        let new_passphrase = new_passphrase_callback()?;
+        if new_passphrase == *old_passphrase.borrow() {
+            bail!("The new passphrase is the same as the old one (unset CREV_PASSPHRASE to be asked for a new one)");
+        }
+        let locked_id = LockedId::from_own_id(&own_id, &new_passphrase)?;
+
+        // The id file is replaced, never written in place
+        let path = self.id_path(&own_id.id.id);
+        let _lock = crev_common::fs::FileLock::exclusive(&path)?;
+        let tmp_path = crev_common::fs::random_sibling_path(&path, "tmp");
+        let written = util::create_new_600_file(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(locked_id.to_string().as_bytes())?;
+                file.sync_data()
+            })
+            .and_then(|()| fs::rename(&tmp_path, &path));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
     pub fn init_repo_readme_using_template(&self) -> Result<()> {
         const README_MARKER_V0: &str = "CREV_README_MARKER_V0";
 
@@ -512,22 +1015,28 @@ impl Local {
     pub fn build_trust_proof(
         &self,
         from_id: &PubId,
-        id_strings: Vec<String>,
+        ids: Vec<(String, Option<String>)>,
         trust_or_distrust: crate::TrustOrDistrust,
+        expires: Option<chrono::DateTime<chrono::FixedOffset>>,
     ) -> Result<proof::Content> {
-        if id_strings.is_empty() {
+        if ids.is_empty() {
             bail!("No ids given.");
         }
 
         let mut db = crate::ProofDB::new();
         db.import_from_iter(self.proofs_iter()?);
-        db.import_from_iter(proofs_iter_for_path(self.cache_remotes_path()));
+        db.import_from_iter(proofs_iter_for_path(
+            self.cache_remotes_path(),
+            self.date_policy()?,
+        ));
         let mut pub_ids = vec![];
 
-        for id_string in id_strings {
+        for (id_string, url) in ids {
             let id = Id::crevid_from_str(&id_string)?;
 
-            if let Some(url) = db.lookup_url(&id) {
+            if let Some(url) = url {
+                pub_ids.push(PubId::new(id, Url::new_git(url)));
+            } else if let Some(url) = db.lookup_url(&id) {
                 pub_ids.push(PubId::new(id, url.to_owned()));
             } else {
                 bail!(
@@ -537,7 +1046,7 @@ impl Local {
             }
         }
 
-        let trust = from_id.create_trust_proof(
+        let mut trust = from_id.create_trust_proof(
             &pub_ids,
             if trust_or_distrust.is_trust() {
                 TrustLevel::Medium
@@ -545,6 +1054,7 @@ impl Local {
                 TrustLevel::Distrust
             },
         )?;
+        trust.expires = expires;
 
         // TODO: Look up previous trust proof?
         Ok(util::edit_proof_content_iteractively(
@@ -556,9 +1066,9 @@ impl Local {
 
     pub fn fetch_url(&self, url: &str) -> Result<()> {
         let mut db = self.load_db()?;
-        if let Some(dir) = self.fetch_proof_repo_import_and_print_counts(url, &mut db) {
+        if let Some(dir) = self.fetch_proof_repo_import_and_print_counts(url, None, &mut db) {
             let mut db = ProofDB::new();
-            db.import_from_iter(proofs_iter_for_path(dir));
+            db.import_from_iter(proofs_iter_for_path(dir, self.date_policy()?));
             eprintln!("Found proofs from:");
             for (id, count) in db.all_author_ids() {
                 println!("{:>8} {}", count, id);
@@ -571,15 +1081,16 @@ impl Local {
         let mut already_fetched = HashSet::new();
         let mut db = crate::ProofDB::new();
         db.import_from_iter(self.proofs_iter()?);
-        db.import_from_iter(proofs_iter_for_path(self.cache_remotes_path()));
-        let user_config = self.load_user_config()?;
-        let user_id = user_config.get_current_userid()?;
+        db.import_from_iter(proofs_iter_for_path(
+            self.cache_remotes_path(),
+            self.date_policy()?,
+        ));
+        let user_id = self.get_current_userid()?;
 
         let mut something_was_fetched = true;
         while something_was_fetched {
             something_was_fetched = false;
-            let trust_set =
-                db.calculate_trust_set(user_config.get_current_userid()?, &trust_params);
+            let trust_set = db.calculate_trust_set(&user_id, &trust_params);
 
             for id in trust_set.trusted_ids() {
                 if already_fetched.contains(id) {
@@ -587,10 +1098,10 @@ impl Local {
                 } else {
                     already_fetched.insert(id.to_owned());
                 }
-                if user_id == id {
+                if &user_id == id {
                     continue;
                 } else if let Some(url) = db.lookup_url(id).cloned() {
-                    self.fetch_proof_repo_import_and_print_counts(&url.url, &mut db);
+                    self.fetch_proof_repo_import_and_print_counts(&url.url, Some(id), &mut db);
                 } else {
                     eprintln!("No URL for {}", id);
                 }
@@ -599,14 +1110,43 @@ impl Local {
         Ok(())
     }
 
+    /// Fetch only proof repositories of ids that reviewed a given package
+    ///
+    /// Reviewers are found using the local (possibly stale) proof db.
+    pub fn fetch_for_package(&self, source: &str, name: &str) -> Result<()> {
+        let mut db = self.load_db()?;
+        let user_id = self.get_current_userid_opt()?;
+
+        let reviewers: BTreeSet<Id> = db
+            .get_package_reviews_for_package(source, Some(name), None)
+            .map(|review| review.from.id.clone())
+            .collect();
+        if reviewers.is_empty() {
+            eprintln!("No known reviews of {}", name);
+        }
+
+        let mut fetched_urls = HashSet::new();
+        for id in &reviewers {
+            if user_id.as_ref() == Some(id) {
+                continue;
+            } else if let Some(url) = db.lookup_url(id).cloned() {
+                if fetched_urls.insert(url.url.clone()) {
+                    self.fetch_proof_repo_import_and_print_counts(&url.url, Some(id), &mut db);
+                }
+            } else {
+                eprintln!("No URL for {}", id);
+            }
+        }
+        Ok(())
+    }
+
     fn fetch_all_ids_recursively(
         &self,
         mut already_fetched_urls: HashSet<String>,
         db: &mut ProofDB,
     ) -> Result<()> {
         let mut already_fetched = HashSet::new();
-        let user_config = self.load_user_config()?;
-        let user_id = user_config.get_current_userid_opt();
+        let user_id = self.get_current_userid_opt()?;
 
         let mut something_was_fetched = true;
         while something_was_fetched {
@@ -618,7 +1158,7 @@ impl Local {
                 } else {
                     already_fetched.insert(id.to_owned());
                 }
-                if user_id == Some(id) {
+                if user_id.as_ref() == Some(id) {
                     continue;
                 } else if let Some(url) = db.lookup_url(id).cloned() {
                     let url = url.url;
@@ -628,7 +1168,7 @@ impl Local {
                     } else {
                         already_fetched_urls.insert(url.clone());
                     }
-                    self.fetch_proof_repo_import_and_print_counts(&url, db);
+                    self.fetch_proof_repo_import_and_print_counts(&url, Some(id), db);
                 } else {
                     eprintln!("No URL for {}", id);
                 }
@@ -645,32 +1185,225 @@ impl Local {
 
     /// Fetch a git proof repository
     ///
-    /// Returns url where it was cloned/fetched
+    /// Returns url where it was cloned/fetched. Other crev processes are
+    /// kept away only with `lock_remote_cache`.
     pub fn fetch_remote_git(&self, url: &str) -> Result<PathBuf> {
         let dir = self.get_remote_git_cache_path(url);
 
         if dir.exists() {
             let repo = git2::Repository::open(&dir)?;
-            util::git::fetch_and_checkout_git_repo(&repo)?
+            if repo.is_shallow() {
+                util::git::shallow_fetch_and_checkout_git_repo(&dir)?
+            } else {
+                util::git::fetch_and_checkout_git_repo(&repo)?
+            }
+        } else if self.fetch_full_history.get() {
+            util::git::clone_git_repo(url, &dir)?;
         } else {
-            git2::Repository::clone(url, &dir)?;
+            util::git::shallow_clone_git_repo(url, &dir)?;
         }
 
         Ok(dir)
     }
 
+    /// Kind of the proof repository at `url`
+    ///
+    /// Unless the URL tells, a repository fetched before keeps its kind,
+    /// and a new one is served over HTTPS if it has a `HTTPS_MANIFEST_FILE`.
+    /// Only a missing manifest makes it a git repository; failing to
+    /// download or parse it is an error, so a short outage doesn't
+    /// change the kind of the repository.
+    pub fn proof_repo_kind(&self, url: &str) -> Result<ProofRepoKind> {
+        if let Some(kind) = ProofRepoKind::from_url(url) {
+            return Ok(kind);
+        }
+        let base = url.trim_end_matches('/').to_owned();
+        let dir = self.get_remote_git_cache_path(url);
+        if dir.join(HTTPS_STATE_FILE).exists() {
+            return Ok(ProofRepoKind::HttpsDir(base));
+        } else if dir.exists() {
+            return Ok(ProofRepoKind::Git);
+        }
+        let manifest_url = format!("{}/{}", base, HTTPS_MANIFEST_FILE);
+        let client = util::http::client()?;
+        match util::http::conditional_get(&client, &manifest_url, &default(), MAX_PROOF_FILE_SIZE)?
+        {
+            util::http::Fetched::Modified { body, .. } => {
+                parse_https_manifest(&String::from_utf8_lossy(&body))
+                    .with_context(|_| format!("Invalid manifest {}", manifest_url))?;
+                Ok(ProofRepoKind::HttpsDir(base))
+            }
+            util::http::Fetched::NotFound => Ok(ProofRepoKind::Git),
+            util::http::Fetched::NotModified => {
+                bail!("Unexpected 304 Not Modified for {}", manifest_url)
+            }
+        }
+    }
+
+    /// Fetch a proof repository served over HTTPS
+    ///
+    /// Only files that changed since the previous fetch are downloaded,
+    /// and files no longer published are removed. Like `fetch_remote_git`,
+    /// it doesn't lock the cache.
+    pub fn fetch_remote_https(&self, url: &str, kind: &ProofRepoKind) -> Result<PathBuf> {
+        if !is_https_url(url) {
+            bail!("Proof repositories served as files must use https: {}", url);
+        }
+        let dir = self.get_remote_git_cache_path(url);
+        let state_path = dir.join(HTTPS_STATE_FILE);
+        let mut state: HttpsRemoteState = if state_path.exists() {
+            crev_common::read_from_yaml_file(&state_path)?
+        } else {
+            default()
+        };
+        state.url = url.to_owned();
+
+        let client = util::http::client()?;
+        let files: Vec<(String, String)> = match kind {
+            ProofRepoKind::Git => bail!("{} is a git repository", url),
+            ProofRepoKind::HttpsBundle => vec![(HTTPS_BUNDLE_FILE.to_owned(), url.to_owned())],
+            ProofRepoKind::HttpsDir(base) => {
+                let manifest = fetch_https_file(
+                    &client,
+                    &dir,
+                    &mut state,
+                    HTTPS_MANIFEST_FILE,
+                    &format!("{}/{}", base, HTTPS_MANIFEST_FILE),
+                )?;
+                parse_https_manifest(&String::from_utf8(manifest)?)?
+                    .into_iter()
+                    .map(|path| {
+                        let file_url = format!("{}/{}", base, path);
+                        (path, file_url)
+                    })
+                    .collect()
+            }
+        };
+        for (path, file_url) in &files {
+            fetch_https_file(&client, &dir, &mut state, path, file_url)?;
+        }
+
+        let published: HashSet<&str> = files
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .chain(Some(HTTPS_MANIFEST_FILE))
+            .collect();
+        let unpublished: Vec<String> = state
+            .files
+            .keys()
+            .filter(|path| !published.contains(path.as_str()))
+            .cloned()
+            .collect();
+        for path in unpublished {
+            state.files.remove(&path);
+            let path = dir.join(&path);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        crev_common::save_to_yaml_file(&state_path, &state)?;
+        Ok(dir)
+    }
+
+    /// Fetch the proof repository at `url` and import its proofs into `db`
+    ///
+    /// `owner` is the id `url` was found for, if any. The commits of the
+    /// repository are checked against it or, without one, against the ids
+    /// that `db` already resolves to `url`. Ids claiming `url` in proofs
+    /// of the fetched repository itself are never trusted as its owner.
     pub fn fetch_proof_repo_import_and_print_counts(
         &self,
         url: &str,
+        owner: Option<&Id>,
         db: &mut ProofDB,
     ) -> Option<PathBuf> {
         let prev_pkg_review_count = db.unique_package_review_proof_count();
         let prev_trust_count = db.unique_trust_proof_count();
+        let owners: Vec<Id> = match owner {
+            Some(owner) => vec![owner.clone()],
+            None => db
+                .all_known_ids()
+                .into_iter()
+                .filter(|id| db.lookup_url(id).map_or(false, |known| known.url == url))
+                .collect(),
+        };
 
         eprint!("Fetching {}... ", url);
-        match self.fetch_remote_git(url) {
+        // Held until the fetched proofs are checked and loaded
+        let _lock = match self.lock_remote_cache(url) {
+            Ok(lock) => lock,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return None;
+            }
+        };
+        let kind = match self.proof_repo_kind(url) {
+            Ok(kind) => kind,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return None;
+            }
+        };
+        // Only the commits fetched now are reported; when signed commits are
+        // required, the cached history doesn't vouch for anything
+        let previous_head = if self.require_signed_commits.get() {
+            None
+        } else {
+            git2::Repository::open(self.get_remote_git_cache_path(url))
+                .and_then(|repo| Ok(repo.head()?.peel_to_commit()?.id()))
+                .ok()
+        };
+        let fetched = match kind {
+            ProofRepoKind::Git => self.fetch_remote_git(url),
+            _ => self.fetch_remote_https(url, &kind),
+        };
+        match fetched {
             Ok(dir) => {
-                db.import_from_iter(proofs_iter_for_path(dir.clone()));
+                // without git, nothing but the signatures vouches for the files
+                let quarantined =
+                    if self.verify_fetched_signatures.get() || kind != ProofRepoKind::Git {
+                        match quarantine_broken_proof_files(&dir) {
+                            Ok(quarantined) => quarantined,
+                            Err(e) => {
+                                eprintln!("Error: {}", e);
+                                return None;
+                            }
+                        }
+                    } else {
+                        vec![]
+                    };
+                let policy = match self.date_policy() {
+                    Ok(policy) => policy,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        return None;
+                    }
+                };
+                let mut report = LoadReport::default();
+                let proofs = load_proofs_for_path(dir.clone(), &policy, &mut report);
+                let commits = if kind == ProofRepoKind::Git {
+                    check_commit_signatures(&dir, &owners, previous_head).unwrap_or_else(|e| {
+                        CommitSignatureReport {
+                            problems: vec![format!("can't check commits: {}", e)],
+                            ..default()
+                        }
+                    })
+                } else {
+                    CommitSignatureReport {
+                        problems: vec!["served over HTTPS, without commits to check".to_owned()],
+                        ..default()
+                    }
+                };
+                if self.require_signed_commits.get() && !commits.is_fully_signed() {
+                    eprintln!("Error: commits not signed by the owner of the repository");
+                    commits.print();
+                    // Not to be loaded from the cache later
+                    if let Err(e) = fs::remove_dir_all(&dir) {
+                        eprintln!("Can't remove {}: {}", dir.display(), e);
+                    }
+                    return None;
+                }
+                db.import_from_iter(proofs.into_iter());
 
                 eprint!("OK");
 
@@ -685,6 +1418,13 @@ impl Local {
                     eprint!("; {} new package reviews", new_pkg_review_count);
                 }
                 eprintln!("");
+                for broken in &quarantined {
+                    eprintln!("Quarantined {}: {}", broken.path.display(), broken.problem);
+                }
+                if commits.is_suspicious() {
+                    commits.print();
+                }
+                report.print();
                 Some(dir)
             }
             Err(e) => {
@@ -700,7 +1440,7 @@ impl Local {
 
         // Temporarily hardcode `dpc`'s proof-repo url
         let dpc_url = "https://github.com/dpc/crev-proofs";
-        self.fetch_proof_repo_import_and_print_counts(dpc_url, &mut db);
+        self.fetch_proof_repo_import_and_print_counts(dpc_url, None, &mut db);
         fetched_urls.insert(dpc_url.to_owned());
 
         for entry in fs::read_dir(self.cache_remotes_path())? {
@@ -708,6 +1448,21 @@ impl Local {
             if !path.is_dir() {
                 continue;
             }
+            if path.join(HTTPS_STATE_FILE).exists() {
+                match crev_common::read_from_yaml_file::<HttpsRemoteState>(
+                    &path.join(HTTPS_STATE_FILE),
+                ) {
+                    Ok(state) => {
+                        if fetched_urls.insert(state.url.clone()) {
+                            self.fetch_proof_repo_import_and_print_counts(
+                                &state.url, None, &mut db,
+                            );
+                        }
+                    }
+                    Err(e) => eprintln!("ERR: {} {}", path.display(), e),
+                }
+                continue;
+            }
 
             let repo = git2::Repository::open(&path);
             if repo.is_err() {
@@ -729,7 +1484,7 @@ impl Local {
                 Ok(url) => {
                     if !fetched_urls.contains(&url) {
                         fetched_urls.insert(url.clone());
-                        self.fetch_proof_repo_import_and_print_counts(&url, &mut db);
+                        self.fetch_proof_repo_import_and_print_counts(&url, None, &mut db);
                     }
                 }
                 Err(e) => {
@@ -743,13 +1498,67 @@ impl Local {
         Ok(())
     }
 
-    pub fn run_git(&self, args: Vec<OsString>) -> Result<std::process::ExitStatus> {
-        let orig_dir = std::env::current_dir()?;
+    /// Path of the current local proof repository, cloned first if needed
+    fn ensure_proof_dir_cloned(&self) -> Result<PathBuf> {
         let proof_dir_path = self.get_proofs_dir_path()?;
         if !proof_dir_path.exists() {
             let id = self.read_current_locked_id()?;
             self.clone_proof_dir_from_git(&id.url.url, false)?;
         }
+        Ok(proof_dir_path)
+    }
+
+    fn open_proof_dir_git_repo(&self) -> Result<git2::Repository> {
+        Ok(git2::Repository::open(self.ensure_proof_dir_cloned()?)?)
+    }
+
+    /// Tracked files of the local proof repository changed since the last commit
+    pub fn proof_dir_changes(&self) -> Result<Vec<PathBuf>> {
+        util::git::changed_paths(&self.open_proof_dir_git_repo()?)
+    }
+
+    /// Commit all changes of the local proof repository
+    ///
+    /// The commit is signed by `signer`, if given (see `sign-commits`
+    /// of the user config). Returns `false` if there was nothing to commit.
+    pub fn proof_dir_commit_all(&self, commit_msg: &str, signer: Option<&OwnId>) -> Result<bool> {
+        let _lock = self.lock_proof_dir()?;
+        util::git::commit_all_with(&self.open_proof_dir_git_repo()?, |tree_id| {
+            signed_commit_message(commit_msg, tree_id, signer)
+        })
+    }
+
+    /// Whether commits of the local proof repository are signed by default
+    pub fn sign_commits(&self) -> Result<bool> {
+        Ok(self.load_user_config()?.sign_commits)
+    }
+
+    /// Pull the upstream of the local proof repository, merging it with local commits
+    ///
+    /// `signer` is only asked for the id signing the merge commit when
+    /// one is needed (see `sign-commits` of the user config).
+    pub fn proof_dir_pull(&self, signer: impl FnOnce() -> Result<Option<OwnId>>) -> Result<()> {
+        let _lock = self.lock_proof_dir()?;
+        util::git::pull(&self.open_proof_dir_git_repo()?, |tree_id| {
+            Ok(signed_commit_message(
+                "Merge upstream proofs",
+                tree_id,
+                signer()?.as_ref(),
+            ))
+        })
+    }
+
+    /// Push the local proof repository to its upstream
+    pub fn proof_dir_push(&self) -> Result<()> {
+        let _lock = self.lock_proof_dir()?;
+        util::git::push(&self.open_proof_dir_git_repo()?)
+    }
+
+    /// Run the `git` binary in the local proof repository
+    pub fn run_git(&self, args: Vec<OsString>) -> Result<std::process::ExitStatus> {
+        let orig_dir = std::env::current_dir()?;
+        let _lock = self.lock_proof_dir()?;
+        let proof_dir_path = self.ensure_proof_dir_cloned()?;
 
         std::env::set_current_dir(proof_dir_path)
             .with_context(|_| "Trying to change dir to the current local proof repo")?;
@@ -767,6 +1576,7 @@ impl Local {
     }
 
     pub fn edit_readme(&self) -> Result<()> {
+        let _lock = self.lock_proof_dir()?;
         util::edit_file(&self.get_proofs_dir_path()?.join("README.md"))?;
         self.proof_dir_git_add_path(&PathBuf::from("README.md"))?;
         Ok(())
@@ -786,18 +1596,84 @@ impl Local {
         Ok(())
     }
 
+    /// Find proof files in the local proof repository that fail to parse or verify
+    pub fn find_broken_proof_files(&self) -> Result<Vec<BrokenProofFile>> {
+        let mut broken = vec![];
+        for path in proof_file_paths(self.get_proofs_dir_path()?) {
+            let path = path?;
+            if let Some(problem) = check_proof_file(&path) {
+                broken.push(BrokenProofFile { path, problem });
+            }
+        }
+        Ok(broken)
+    }
+
+    /// Move a proof file into the `.broken` dir of the proof repository
+    ///
+    /// Proofs in there are not loaded anymore, nor committed. Returns the new path.
+    pub fn quarantine_proof_file(&self, path: &Path) -> Result<PathBuf> {
+        let proofs_dir = self.get_proofs_dir_path()?;
+        let _lock = self.lock_proof_dir()?;
+        quarantine_file(&proofs_dir, BROKEN_PROOFS_DIR, path)
+    }
+
+    /// Copy a build log of a package into the proof repository, and stage it
+    ///
+    /// Returns the log attachment for a review of the package.
+    pub fn store_build_log(
+        &self,
+        log_path: &Path,
+        name: &str,
+        version: &semver::Version,
+    ) -> Result<proof::review::package::BuildLog> {
+        let digest = crev_common::blake2b256sum_file(log_path)?;
+        let rel_path = PathBuf::from(BUILD_LOGS_DIR).join(name).join(format!(
+            "{}-{}.log",
+            version,
+            crev_common::base64_encode(&digest[..8])
+        ));
+        let proofs_dir = self.get_proofs_dir_path()?;
+        let path = proofs_dir.join(&rel_path);
+        let _lock = self.lock_proof_dir()?;
+        fs::create_dir_all(path.parent().expect("Not a root dir"))?;
+        fs::copy(log_path, &path)?;
+        self.proof_dir_git_add_path(&rel_path)?;
+
+        Ok(proof::review::package::BuildLog {
+            digest,
+            path: rel_path.to_string_lossy().replace('\\', "/"),
+        })
+    }
+
     /// Create a new proofdb, and populate it with local repo
     /// and cache content.
     pub fn load_db(&self) -> Result<crate::ProofDB> {
-        let mut db = crate::ProofDB::new();
-        db.import_from_iter(self.proofs_iter()?);
-        db.import_from_iter(proofs_iter_for_path(self.cache_remotes_path()));
+        let (db, report) = self.load_db_with_report()?;
+        report.print();
 
         Ok(db)
     }
 
+    /// Like `load_db`, but returns the problems with individual proofs
+    /// instead of printing them
+    pub fn load_db_with_report(&self) -> Result<(crate::ProofDB, LoadReport)> {
+        let policy = self.date_policy()?;
+        let mut report = LoadReport::default();
+        let mut db = crate::ProofDB::new();
+        for path in self
+            .get_proofs_dir_path_opt()?
+            .into_iter()
+            .chain(std::iter::once(self.cache_remotes_path()))
+        {
+            db.import_from_iter(load_proofs_for_path(path, &policy, &mut report).into_iter());
+        }
+
+        Ok((db, report))
+    }
+
     pub fn proof_dir_git_add_path(&self, rel_path: &Path) -> Result<()> {
         let proof_dir = self.get_proofs_dir_path()?;
+        let _lock = self.lock_proof_dir()?;
         let repo = git2::Repository::open(&proof_dir)?;
         let mut index = repo.index()?;
 
@@ -806,8 +1682,10 @@ impl Local {
         Ok(())
     }
 
-    pub fn proof_dir_commit(&self, commit_msg: &str) -> Result<()> {
+    /// Commit the staged changes of the local proof repository, signed by `signer`, if given
+    pub fn proof_dir_commit(&self, commit_msg: &str, signer: Option<&OwnId>) -> Result<()> {
         let proof_dir = self.get_proofs_dir_path()?;
+        let _lock = self.lock_proof_dir()?;
         let repo = git2::Repository::open(&proof_dir)?;
         let mut index = repo.index()?;
         let tree_id = index.write_tree()?;
@@ -820,7 +1698,7 @@ impl Local {
             Some("HEAD"),
             &signature,
             &signature,
-            commit_msg,
+            &signed_commit_message(commit_msg, tree_id, signer),
             &tree,
             &[&head],
         )?;
@@ -883,6 +1761,21 @@ impl Local {
         Ok(())
     }
 
+    /// All Ids seen in the local and all fetched proof repositories, with their urls
+    ///
+    /// Unlike a trust set, this includes Ids that are not trusted in any way.
+    pub fn list_all_known_ids(&self) -> Result<Vec<(Id, Option<String>)>> {
+        let db = self.load_db()?;
+        Ok(db
+            .all_known_ids()
+            .into_iter()
+            .map(|id| {
+                let url = db.lookup_url(&id).map(|url| url.url.clone());
+                (id, url)
+            })
+            .collect())
+    }
+
     pub fn list_own_ids(&self) -> Result<()> {
         for id in self.list_ids()? {
             println!("{} {}", id.id, id.url.url);
@@ -920,6 +1813,20 @@ impl Local {
         self.save_locked_id(&id)?;
         Ok(id.to_pubid())
     }
+
+    /// Import a locked id from a key file
+    ///
+    /// The id is only saved once the passphrase is known to unlock it.
+    pub fn import_locked_id_file(
+        &self,
+        path: &Path,
+        passphrase_callback: PassphraseFn<'_>,
+    ) -> Result<PubId> {
+        let id = LockedId::read_from_file(path)?;
+        unlock_with_retries(&id, passphrase_callback)?;
+        self.save_locked_id(&id)?;
+        Ok(id.to_pubid())
+    }
 }
 
 impl ProofStore for Local {
@@ -935,6 +1842,7 @@ impl ProofStore for Local {
         );
         let path = self.get_proofs_dir_path()?.join(&rel_store_path);
 
+        let _lock = self.lock_proof_dir()?;
         fs::create_dir_all(path.parent().expect("Not a root dir"))?;
         let mut file = fs::OpenOptions::new()
             .append(true)
@@ -953,42 +1861,491 @@ impl ProofStore for Local {
     }
 
     fn proofs_iter(&self) -> Result<Box<dyn Iterator<Item = proof::Proof>>> {
+        let policy = self.date_policy()?;
         Ok(Box::new(
             self.get_proofs_dir_path_opt()?
                 .into_iter()
-                .flat_map(proofs_iter_for_path),
+                .flat_map(move |path| proofs_iter_for_path(path, policy)),
         ))
     }
 }
 
-fn proofs_iter_for_path(path: PathBuf) -> impl Iterator<Item = proof::Proof> {
+/// Name of the dir in the proof repository with quarantined proof files
+pub const BROKEN_PROOFS_DIR: &str = ".broken";
+
+/// Proof files larger than this are not loaded, as they can come from anyone
+pub const MAX_PROOF_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Dir of a fetched proof repository with proof files that failed verification
+pub const QUARANTINE_DIR: &str = ".crev/quarantine";
+
+/// Name of the dir in the proof repository with build logs attached to reviews
+pub const BUILD_LOGS_DIR: &str = "build-logs";
+
+/// File of a proof repository served over HTTPS, listing its proof files
+pub const HTTPS_MANIFEST_FILE: &str = "crev-manifest.txt";
+
+/// Where a proof repository served as a single proof file is saved in the cache
+const HTTPS_BUNDLE_FILE: &str = "bundle.crev";
+
+/// File in the cache of a proof repository fetched over HTTPS, with the state of its files
+const HTTPS_STATE_FILE: &str = ".crev/https-state.yaml";
+
+/// Hosts of git repositories, never serving proof repositories as static files
+const GIT_HOSTS: &[&str] = &["github.com", "gitlab.com", "bitbucket.org"];
+
+/// How a proof repository is published
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofRepoKind {
+    Git,
+    /// Static directory with a `HTTPS_MANIFEST_FILE` listing its proof files, at this base URL
+    HttpsDir(String),
+    /// Single proof file, eg. `https://example.com/proofs.crev`
+    HttpsBundle,
+}
+
+/// Whether proof files can be downloaded from `url`
+///
+/// Files aren't signed as a whole, so over plain http a network attacker
+/// could drop or roll back proofs unnoticed. Tests serve them from a
+/// local plain http server.
+fn is_https_url(url: &str) -> bool {
+    url.starts_with("https://") || (cfg!(test) && url.starts_with("http://"))
+}
+
+impl ProofRepoKind {
+    /// Kind of the repository, when its URL is enough to tell
+    ///
+    /// Plain `http://` URLs aren't probed for a manifest, so they're git
+    /// repositories unless they point at proof files, which are then
+    /// refused by `Local::fetch_remote_https`.
+    pub fn from_url(url: &str) -> Option<Self> {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Some(ProofRepoKind::Git);
+        }
+        let url = url.trim_end_matches('/');
+        let host = url.split('/').nth(2).unwrap_or("");
+        let manifest_suffix = format!("/{}", HTTPS_MANIFEST_FILE);
+        if url.ends_with(".crev") {
+            Some(ProofRepoKind::HttpsBundle)
+        } else if url.ends_with(&manifest_suffix) {
+            Some(ProofRepoKind::HttpsDir(
+                url[..url.len() - manifest_suffix.len()].to_owned(),
+            ))
+        } else if url.ends_with(".git") || GIT_HOSTS.contains(&host) || !is_https_url(url) {
+            Some(ProofRepoKind::Git)
+        } else {
+            None
+        }
+    }
+}
+
+/// Paths of the proof files listed in a `HTTPS_MANIFEST_FILE`
+///
+/// One path, relative to the manifest, per line; blank lines and lines
+/// starting with `#` are ignored.
+pub fn parse_https_manifest(text: &str) -> Result<Vec<String>> {
+    use std::path::Component;
+
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let path = Path::new(line);
+            let relative = path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+            if !relative || line.contains('\\') || path.extension() != Some("crev".as_ref()) {
+                bail!("Invalid proof file in the manifest: `{}`", line);
+            }
+            Ok(line.to_owned())
+        })
+        .collect()
+}
+
+/// Content of `HTTPS_STATE_FILE`
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+struct HttpsRemoteState {
+    url: String,
+    /// Validators of the downloaded files, by their path in the cache
+    files: BTreeMap<String, util::http::Validators>,
+}
+
+/// Download a file of a proof repository served over HTTPS to `dir`, unless
+/// it didn't change, and return its content
+fn fetch_https_file(
+    client: &reqwest::Client,
+    dir: &Path,
+    state: &mut HttpsRemoteState,
+    path: &str,
+    url: &str,
+) -> Result<Vec<u8>> {
+    use util::http::Fetched;
+
+    let local_path = crev_common::fs::safe_join(dir, Path::new(path))?;
+    // quarantined files are downloaded again
+    let validators = if local_path.exists() {
+        state.files.get(path).cloned().unwrap_or_default()
+    } else {
+        default()
+    };
+    match util::http::conditional_get(client, url, &validators, MAX_PROOF_FILE_SIZE)? {
+        Fetched::NotModified => Ok(fs::read(&local_path)?),
+        Fetched::NotFound => bail!("{} not found", url),
+        Fetched::Modified { body, validators } => {
+            fs::create_dir_all(local_path.parent().expect("Not a root dir"))?;
+            fs::write(&local_path, &body)?;
+            state.files.insert(path.to_owned(), validators);
+            Ok(body)
+        }
+    }
+}
+
+/// Parse a list of ids to trust, eg. from `trust --from-file`
+///
+/// Every line holds a public id, optionally followed by the URL of its
+/// proof repository. Empty lines and `#` comments are skipped. All lines
+/// are checked, so that nothing gets trusted if any of them is malformed.
+pub fn parse_trust_id_list(content: &str) -> Result<Vec<(String, Option<String>)>> {
+    let mut ids = vec![];
+    let mut errors = vec![];
+    for (line_idx, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let id = fields.next().unwrap_or("");
+        let url = fields.next();
+        if fields.next().is_some() {
+            errors.push(format!("line {}: expected `<id> [<url>]`", line_idx + 1));
+        } else if let Err(e) = Id::crevid_from_str(id) {
+            errors.push(format!("line {}: invalid id `{}`: {}", line_idx + 1, id, e));
+        } else {
+            ids.push((id.to_owned(), url.map(ToOwned::to_owned)));
+        }
+    }
+    if !errors.is_empty() {
+        bail!("Malformed id list:\n{}", errors.join("\n"));
+    }
+    Ok(ids)
+}
+
+fn unlock_with_retries(locked: &LockedId, passphrase_callback: PassphraseFn<'_>) -> Result<OwnId> {
+    let mut i = 0;
+    loop {
+        let passphrase = passphrase_callback()?;
+        match locked.to_unlocked(&passphrase) {
+            Ok(o) => return Ok(o),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                if i == 5 {
+                    return Err(e);
+                }
+            }
+        }
+        i += 1;
+    }
+}
+
+/// Url to push to a proof repository cloned from `git_https_url`
+fn git_push_url(git_https_url: &str, use_https_push: bool) -> String {
+    if use_https_push {
+        git_https_url.to_string()
+    } else {
+        match util::git::https_to_git_url(git_https_url) {
+            Some(git_url) => git_url,
+            None => {
+                eprintln!("Could not deduce `ssh` push url. Call:");
+                eprintln!("cargo crev git remote set-url --push origin <url>");
+                eprintln!("manually, after id is generated.");
+                eprintln!("");
+                git_https_url.to_string()
+            }
+        }
+    }
+}
+
+/// Paths of all proof files under `path`, except quarantined ones
+fn proof_file_paths(path: PathBuf) -> impl Iterator<Item = Result<PathBuf>> {
     use std::ffi::OsStr;
-    let file_iter = walkdir::WalkDir::new(path)
-        .into_iter()
-        .map_err(|e| format_err!("Error iterating local ProofStore: {:?}", e))
-        .filter_map_ok(|entry| {
-            let path = entry.path();
-            if !path.is_file() {
-                return None;
+    crev_common::fs::walk_dir(&path, |path| {
+        path.file_name() == Some(BROKEN_PROOFS_DIR.as_ref())
+            || path.ends_with(QUARANTINE_DIR)
+            || crev_common::fs::is_pruned_dir(path)
+    })
+    .map_err(|e| format_err!("Error iterating local ProofStore: {:?}", e))
+    .filter_map_ok(|entry| {
+        let path = entry.path();
+        if !path.is_file() {
+            return None;
+        }
+
+        let osext_match: &OsStr = "crev".as_ref();
+        match path.extension() {
+            Some(osext) if osext == osext_match => Some(path.to_owned()),
+            _ => None,
+        }
+    })
+}
+
+fn proofs_iter_for_path(
+    path: PathBuf,
+    policy: proof::DatePolicy,
+) -> impl Iterator<Item = proof::Proof> {
+    let mut report = LoadReport::default();
+    let proofs = load_proofs_for_path(path, &policy, &mut report);
+    report.print();
+
+    proofs.into_iter()
+}
+
+/// Valid proofs in `path`, with the ones skipped or changed noted in `report`
+fn load_proofs_for_path(
+    path: PathBuf,
+    policy: &proof::DatePolicy,
+    report: &mut LoadReport,
+) -> Vec<proof::Proof> {
+    let now = crev_common::now();
+    let mut proofs = vec![];
+    for file_path in proof_file_paths(path.clone()) {
+        let file_path = match file_path {
+            Ok(file_path) => file_path,
+            Err(e) => {
+                report.warn(&path, e.to_string());
+                continue;
             }
+        };
+        let file_proofs =
+            match crev_common::read_file_to_string_limited(&file_path, MAX_PROOF_FILE_SIZE)
+                .map_err(failure::Error::from)
+                .and_then(|content| proof::Proof::parse(content.as_bytes()))
+            {
+                Ok(file_proofs) => file_proofs,
+                Err(e) => {
+                    report.warn(&file_path, e.to_string());
+                    continue;
+                }
+            };
+        for mut proof in file_proofs {
+            let result = proof
+                .verify()
+                .and_then(|()| proof.content.check_date(&now, policy));
+            match result {
+                Ok(false) => {}
+                Ok(true) => report.warn(
+                    &file_path,
+                    format!(
+                        "proof by {} was dated in the future; using the current time",
+                        proof.content.author_id()
+                    ),
+                ),
+                Err(e) => {
+                    report.warn(
+                        &file_path,
+                        format!("proof by {} skipped: {}", proof.content.author_id(), e),
+                    );
+                    continue;
+                }
+            }
+            proofs.push(proof);
+        }
+    }
+    proofs
+}
+
+/// Move a file of the proof repository at `repo_dir` into its `quarantine_dir`
+///
+/// The dir is excluded from git locally, so it's never committed.
+/// Returns the new path.
+fn quarantine_file(repo_dir: &Path, quarantine_dir: &str, path: &Path) -> Result<PathBuf> {
+    if let Ok(repo) = git2::Repository::open(repo_dir) {
+        util::git::exclude_locally(&repo, &format!("/{}/", quarantine_dir))?;
+    }
+    let new_path = repo_dir
+        .join(quarantine_dir)
+        .join(path.strip_prefix(repo_dir)?);
+    fs::create_dir_all(new_path.parent().expect("Not a root dir"))?;
+    fs::rename(path, &new_path)?;
+    Ok(new_path)
+}
+
+/// Move proof files of a fetched repository that fail to parse or verify
+/// into its `QUARANTINE_DIR`
+///
+/// Returns the quarantined files, with their new paths.
+pub fn quarantine_broken_proof_files(repo_dir: &Path) -> Result<Vec<BrokenProofFile>> {
+    let paths: Vec<PathBuf> = proof_file_paths(repo_dir.to_owned()).collect::<Result<_>>()?;
+    let mut quarantined = vec![];
+    for path in paths {
+        if let Some(problem) = check_proof_file(&path) {
+            quarantined.push(BrokenProofFile {
+                path: quarantine_file(repo_dir, QUARANTINE_DIR, &path)?,
+                problem,
+            });
+        }
+    }
+    Ok(quarantined)
+}
+
+/// Result of checking the signatures of the fetched commits of a proof repository
+#[derive(Debug, Clone, Default)]
+pub struct CommitSignatureReport {
+    pub signed: usize,
+    pub unsigned: usize,
+    pub invalid: usize,
+    pub problems: Vec<String>,
+}
 
-            let osext_match: &OsStr = "crev".as_ref();
-            match path.extension() {
-                Some(osext) if osext == osext_match => Some(path.to_owned()),
-                _ => None,
+impl CommitSignatureReport {
+    /// The owner signed the commits since they started signing, `HEAD` included
+    pub fn is_fully_signed(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Repositories that never signed their commits are not suspicious,
+    /// but ones that stopped signing or have forged signatures are
+    pub fn is_suspicious(&self) -> bool {
+        self.invalid > 0 || (self.signed > 0 && !self.problems.is_empty())
+    }
+
+    pub fn print(&self) {
+        for problem in &self.problems {
+            eprintln!("Warning: {}", problem);
+        }
+    }
+}
+
+/// Check that the commits in `repo_dir` after `since` are signed by one of `owners`
+///
+/// All the parents of merges are followed. Unsigned commits older than the
+/// first one signed by an owner are tolerated, but `HEAD` must be signed
+/// or have such a commit in its history. Commits reachable from `since`
+/// count only for that, and are not reported. Without `since`, all the
+/// commits that were fetched are checked.
+pub fn check_commit_signatures(
+    repo_dir: &Path,
+    owners: &[Id],
+    since: Option<git2::Oid>,
+) -> Result<CommitSignatureReport> {
+    let repo = git2::Repository::open(repo_dir)?;
+    let head = repo.head()?.peel_to_commit()?.id();
+
+    let mut report = CommitSignatureReport::default();
+    if owners.is_empty() {
+        report
+            .problems
+            .push("no known id owns the repository".to_owned());
+        return Ok(report);
+    }
+    let checked_before: HashSet<git2::Oid> = match since {
+        Some(since) => commits_parents_first(&repo, since)?
+            .iter()
+            .map(git2::Commit::id)
+            .collect(),
+        None => HashSet::new(),
+    };
+    // Signed by an owner, or descending from such a commit
+    let mut signing_started = HashSet::new();
+    for commit in commits_parents_first(&repo, head)? {
+        let after_signed = commit
+            .parent_ids()
+            .any(|parent_id| signing_started.contains(&parent_id));
+        let short_id = commit.id().to_string()[..8].to_owned();
+        let msg = commit.message().unwrap_or("");
+        let is_signed = msg
+            .lines()
+            .any(|line| line.starts_with(crev_data::id::COMMIT_SIGNATURE_TRAILER));
+        let tree_id = commit.tree_id().to_string();
+        let is_owner_signed = is_signed
+            && owners
+                .iter()
+                .any(|id| id.verify_commit_message(msg, &tree_id).is_ok());
+        if is_owner_signed || after_signed {
+            signing_started.insert(commit.id());
+        }
+        if checked_before.contains(&commit.id()) {
+            continue;
+        }
+        if is_owner_signed {
+            report.signed += 1;
+        } else if is_signed {
+            report.invalid += 1;
+            report.problems.push(format!(
+                "commit {} is not signed by the owner of the repository",
+                short_id
+            ));
+        } else {
+            report.unsigned += 1;
+            if after_signed {
+                report
+                    .problems
+                    .push(format!("commit {} is not signed", short_id));
             }
-        });
+        }
+    }
+    if !signing_started.contains(&head) {
+        report
+            .problems
+            .push("no commit is signed by the owner of the repository".to_owned());
+    }
+    Ok(report)
+}
 
-    let proofs_iter = file_iter
-        .and_then_ok(|path| Ok(proof::Proof::parse_from(&path)?))
-        .flatten_ok()
-        .and_then_ok(|proof| {
-            proof.verify()?;
-            Ok(proof)
-        })
-        .on_err(|e| {
-            eprintln!("Failed processing a proof: {}", e);
-        });
+/// All the commits reachable from `tip`, each one after its parents
+fn commits_parents_first(repo: &git2::Repository, tip: git2::Oid) -> Result<Vec<git2::Commit<'_>>> {
+    let is_shallow = repo.is_shallow();
+    let mut commits = vec![];
+    let mut visited = HashSet::new();
+    // Commits to visit, and ones with all their parents already listed
+    let mut stack = vec![(tip, false)];
+    while let Some((id, parents_listed)) = stack.pop() {
+        if parents_listed {
+            commits.push(repo.find_commit(id)?);
+            continue;
+        }
+        if !visited.insert(id) {
+            continue;
+        }
+        let commit = match repo.find_commit(id) {
+            Ok(commit) => commit,
+            // Shallow clones end with commits whose parents are missing
+            Err(ref e) if is_shallow && e.code() == git2::ErrorCode::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        stack.push((id, true));
+        stack.extend(commit.parent_ids().map(|parent_id| (parent_id, false)));
+    }
+    Ok(commits)
+}
 
-    proofs_iter.oks()
+fn signed_commit_message(commit_msg: &str, tree_id: git2::Oid, signer: Option<&OwnId>) -> String {
+    match signer {
+        Some(signer) => signer.sign_commit_message(commit_msg, &tree_id.to_string()),
+        None => commit_msg.to_owned(),
+    }
+}
+
+/// Find the first problem with proofs in a file, if any
+fn check_proof_file(path: &Path) -> Option<String> {
+    let content = match crev_common::read_file_to_string_limited(path, MAX_PROOF_FILE_SIZE) {
+        Ok(content) => content,
+        Err(e) => return Some(format!("can't read: {}", e)),
+    };
+    let proofs = match proof::Proof::parse_each(content.as_bytes()) {
+        Ok(proofs) => proofs,
+        Err(e) => return Some(format!("malformed: {}", e)),
+    };
+    for (i, proof) in proofs.into_iter().enumerate() {
+        match proof {
+            Err(e) => return Some(format!("proof #{}: malformed: {}", i, e)),
+            Ok(proof) => {
+                if let Err(e) = proof.verify() {
+                    return Some(format!("proof #{}: invalid signature: {}", i, e));
+                }
+            }
+        }
+    }
+    None
 }
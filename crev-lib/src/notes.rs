@@ -0,0 +1,124 @@
+//! Private notes about packages
+//!
+//! Notes are kept in a local file outside of the proof repository,
+//! and are never published.
+//!
+//! A note can record the digest of the package it was written about,
+//! to tell whether it still applies to a package, and to find it
+//! for the same content under another version.
+use crate::prelude::*;
+use crev_common::{
+    self,
+    serde::{as_rfc3339_fixed, from_rfc3339_fixed},
+};
+use crev_data::Digest;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub type Date = chrono::DateTime<chrono::FixedOffset>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Note {
+    pub source: String,
+    pub name: String,
+    /// `None` means the note is about all versions
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<Version>,
+    /// Base64 digest of the package the note was written about
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub digest: Option<String>,
+    pub text: String,
+    #[serde(
+        serialize_with = "as_rfc3339_fixed",
+        deserialize_with = "from_rfc3339_fixed"
+    )]
+    pub created_at: Date,
+}
+
+impl Note {
+    pub fn new(
+        source: &str,
+        name: &str,
+        version: Option<&Version>,
+        digest: Option<&Digest>,
+        text: String,
+    ) -> Self {
+        Self {
+            source: source.to_owned(),
+            name: name.to_owned(),
+            version: version.cloned(),
+            digest: digest.map(ToString::to_string),
+            text,
+            created_at: crev_common::now(),
+        }
+    }
+
+    /// Is the note about the given package, or all versions of it
+    pub fn is_for(&self, source: &str, name: &str, version: Option<&Version>) -> bool {
+        self.source == source
+            && self.name == name
+            && (self.version.is_none() || version.is_none() || self.version.as_ref() == version)
+    }
+
+    /// Was the note written about a package with different content than `digest`
+    ///
+    /// Notes without a digest are never stale.
+    pub fn is_stale(&self, digest: &Digest) -> bool {
+        self.digest
+            .as_ref()
+            .map_or(false, |own| *own != digest.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Notes {
+    #[serde(rename = "note", default)]
+    pub notes: Vec<Note>,
+}
+
+impl Notes {
+    pub fn read_from_toml_file(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(crev_common::read_from_toml_file(path)?)
+    }
+
+    pub fn save_to_toml_file(&self, path: &Path) -> Result<()> {
+        Ok(crev_common::save_to_toml_file(path, self)?)
+    }
+
+    pub fn add(&mut self, note: Note) {
+        self.notes.push(note);
+    }
+
+    pub fn get_for<'a>(
+        &'a self,
+        source: &'a str,
+        name: &'a str,
+        version: Option<&'a Version>,
+    ) -> impl Iterator<Item = &'a Note> + 'a {
+        self.notes
+            .iter()
+            .filter(move |note| note.is_for(source, name, version))
+    }
+
+    /// Like `get_for`, and also notes about other versions with the same `digest`
+    pub fn get_for_package<'a>(
+        &'a self,
+        source: &'a str,
+        name: &'a str,
+        version: &'a Version,
+        digest: &Digest,
+    ) -> impl Iterator<Item = &'a Note> + 'a {
+        let digest = digest.to_string();
+        self.notes.iter().filter(move |note| {
+            note.is_for(source, name, Some(version))
+                || (note.source == source
+                    && note.name == name
+                    && note.digest.as_ref() == Some(&digest))
+        })
+    }
+}
@@ -4,7 +4,7 @@ use crev_data::{
     self,
     proof::{
         self,
-        review::{self, Rating},
+        review::{self, Common as _, Rating},
         trust::TrustLevel,
         Content, ContentCommon,
     },
@@ -12,7 +12,12 @@ use crev_data::{
 };
 use default::default;
 use semver::Version;
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    path::PathBuf,
+};
 
 /// A `T` with a timestamp
 ///
@@ -42,14 +47,30 @@ impl<T> Timestamped<T> {
 
 pub type Signature = String;
 type TimestampedUrl = Timestamped<Url>;
-type TimestampedTrustLevel = Timestamped<TrustLevel>;
+type TimestampedTrustLevel = Timestamped<ExpiringTrustLevel>;
 type TimestampedReview = Timestamped<review::Review>;
 
+/// Trust level given by a trust proof, until it expires
+#[derive(Copy, Clone, Debug)]
+struct ExpiringTrustLevel {
+    level: TrustLevel,
+    expires: Option<DateTime<Utc>>,
+}
+
+impl ExpiringTrustLevel {
+    fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires.map_or(false, |expires| expires <= now)
+    }
+}
+
 impl From<proof::Trust> for TimestampedTrustLevel {
     fn from(trust: proof::Trust) -> Self {
         TimestampedTrustLevel {
             date: trust.date().with_timezone(&Utc),
-            value: trust.trust,
+            value: ExpiringTrustLevel {
+                level: trust.trust,
+                expires: trust.expires_utc(),
+            },
         }
     }
 }
@@ -118,6 +139,7 @@ where
 
 pub type Source = String;
 pub type Name = String;
+type CodeReviews = HashMap<Signature, review::Code>;
 
 /// In memory database tracking information from proofs
 ///
@@ -142,6 +164,9 @@ pub struct ProofDB {
 
     // pkg_review_id by package information, nicely grouped
     package_reviews: BTreeMap<Source, BTreeMap<Name, BTreeMap<Version, HashSet<PkgReviewId>>>>,
+
+    // code reviews (of some files of a package) by package information
+    code_reviews: BTreeMap<Source, BTreeMap<Name, BTreeMap<Version, CodeReviews>>>,
 }
 
 impl Default for ProofDB {
@@ -154,12 +179,14 @@ impl Default for ProofDB {
             package_review_signatures_by_pkg_review_id: default(),
             package_review_by_signature: default(),
             package_reviews: default(),
+            code_reviews: default(),
         }
     }
 }
 
 #[derive(Default, Debug)]
 pub struct IssueDetails {
+    /// Highest severity the issue was reported with
     pub severity: Level,
     /// Reviews that reported a given issue by `issues` field
     pub issues: HashSet<PkgReviewId>,
@@ -223,6 +250,57 @@ impl ProofDB {
             })
     }
 
+    /// Code reviews of some files of a package version
+    pub fn get_code_reviews_for_version<'a, 'b, 'c: 'a, 'd: 'a>(
+        &'a self,
+        source: &'b str,
+        name: &'c str,
+        version: &'d Version,
+    ) -> impl Iterator<Item = &'a proof::review::Code> {
+        self.code_reviews
+            .get(source)
+            .into_iter()
+            .flat_map(move |map| map.get(name))
+            .flat_map(move |map| map.get(version))
+            .flat_map(HashMap::values)
+    }
+
+    /// Files of a package version vouched for by trusted code reviews
+    ///
+    /// Returns the digests each file was reviewed at. Code reviews cover only
+    /// some files, so they never count towards verifying the whole package.
+    pub fn get_trusted_reviewed_files(
+        &self,
+        source: &str,
+        name: &str,
+        version: &Version,
+        trust_set: &TrustSet,
+        requirements: &VerificationRequirements,
+    ) -> HashMap<PathBuf, HashSet<Vec<u8>>> {
+        let mut files: HashMap<PathBuf, HashSet<Vec<u8>>> = HashMap::new();
+        for code_review in self.get_code_reviews_for_version(source, name, version) {
+            let review = code_review.review();
+            let trusted = trust_set
+                .get_effective_trust_level(&code_review.from.id)
+                .map_or(false, |level| {
+                    TrustLevel::from(requirements.trust_level) <= level
+                });
+            if trusted
+                && Rating::Neutral <= review.rating
+                && requirements.thoroughness <= review.thoroughness
+                && requirements.understanding <= review.understanding
+            {
+                for file in &code_review.files {
+                    files
+                        .entry(file.path.clone())
+                        .or_default()
+                        .insert(file.digest.clone());
+                }
+            }
+        }
+        files
+    }
+
     pub fn get_pkg_reviews_gte_version<'a, 'b, 'c: 'a, 'd: 'a>(
         &'a self,
         source: &'b str,
@@ -393,10 +471,11 @@ impl ProofDB {
         // Here we track all the reported isue by issue id
         let mut issue_reports_by_id: HashMap<String, IssueDetails> = HashMap::new();
 
-        // First we go through all the reports in previous versions with `issues` fields and collect these.
-        // Easy.
+        // First we go through all the reports with `issues` fields and collect these.
+        // Easy. Usually only previous versions matter, but issues can also
+        // list affected versions explicitly.
         for (review, issue) in self
-            .get_pkg_reviews_lte_version(source, name, queried_version)
+            .get_pkg_reviews_for_name(source, name)
             .filter(|review| {
                 if let Some(effective) = trust_set.get_effective_trust_level(&review.from.id) {
                     effective >= trust_level_required
@@ -464,14 +543,8 @@ impl ProofDB {
                     issue_marker.issues = issues
                         .into_iter()
                         .filter(|pkg_review_id| {
-                            let signature = &self
-                                .package_review_signatures_by_pkg_review_id
-                                .get(pkg_review_id)
-                                .expect("review for this signature")
-                                .value;
                             let issue_review = self
-                                .package_review_by_signature
-                                .get(signature)
+                                .get_pkg_review_by_pkg_review_id(pkg_review_id)
                                 .expect("review for this pkg_review_id");
                             !advisory.is_for_version_when_reported_in_version(
                                 &issue_review.package.version,
@@ -486,6 +559,31 @@ impl ProofDB {
         issue_reports_by_id
             .into_iter()
             .filter(|(_id, markers)| !markers.issues.is_empty() || !markers.advisories.is_empty())
+            .map(|(id, mut markers)| {
+                markers.severity = markers
+                    .issues
+                    .iter()
+                    .chain(markers.advisories.iter())
+                    .map(|pkg_review_id| {
+                        let review = self
+                            .get_pkg_review_by_pkg_review_id(pkg_review_id)
+                            .expect("review for this pkg_review_id");
+                        let issues = review
+                            .issues
+                            .iter()
+                            .filter(|issue| issue.id == id)
+                            .map(|issue| issue.severity);
+                        let advisories = review
+                            .advisories
+                            .iter()
+                            .filter(|advisory| advisory.ids.contains(&id))
+                            .map(|advisory| advisory.severity);
+                        issues.chain(advisories).max().unwrap_or_default()
+                    })
+                    .max()
+                    .unwrap_or_default();
+                (id, markers)
+            })
             .collect()
     }
 
@@ -554,12 +652,19 @@ impl ProofDB {
             .fold(0, |count, (_id, set)| count + set.len())
     }
 
-    fn add_code_review(&mut self, review: &review::Code) {
+    fn add_code_review(&mut self, review: &review::Code, signature: &str) {
         let from = &review.from;
         self.record_url_from_from_field(&review.date_utc(), &from);
-        for _file in &review.files {
-            // not implemented right now; just ignore
-        }
+
+        self.code_reviews
+            .entry(review.package.source.clone())
+            .or_default()
+            .entry(review.package.name.clone())
+            .or_default()
+            .entry(review.package.version.clone())
+            .or_default()
+            .entry(signature.to_owned())
+            .or_insert_with(|| review.to_owned());
     }
 
     fn add_package_review(&mut self, review: &review::Package, signature: &str) {
@@ -622,6 +727,71 @@ impl ProofDB {
         }
     }
 
+    /// Package reviews matching `query`, with the effective trust level of their authors
+    ///
+    /// The trust level is `None` for authors outside of `trust_set`, and
+    /// these reviews only match a query without `min_trust_level`.
+    pub fn query_reviews<'a, 'q: 'a>(
+        &'a self,
+        query: ReviewQuery<'q>,
+        trust_set: &'a TrustSet,
+    ) -> impl Iterator<Item = QueriedReview<'a>> + 'a {
+        self.filter_package_reviews(query)
+            .map(move |review| QueriedReview {
+                review,
+                effective_trust: trust_set.get_effective_trust_level(&review.from.id),
+            })
+            .filter(move |queried| {
+                query.min_trust_level.map_or(true, |min_trust_level| {
+                    queried
+                        .effective_trust
+                        .map_or(false, |level| min_trust_level <= level)
+                })
+            })
+    }
+
+    /// Package reviews matching `query`, except for `min_trust_level`
+    fn filter_package_reviews<'a, 'q: 'a>(
+        &'a self,
+        query: ReviewQuery<'q>,
+    ) -> impl Iterator<Item = &'a proof::review::Package> + 'a {
+        let by_name: Box<dyn Iterator<Item = &'a BTreeMap<Name, _>>> = match query.source {
+            Some(source) => Box::new(self.package_reviews.get(source).into_iter()),
+            None => Box::new(self.package_reviews.values()),
+        };
+        by_name
+            .flat_map(
+                move |by_name| -> Box<dyn Iterator<Item = &'a BTreeMap<Version, _>>> {
+                    match query.name {
+                        Some(name) => Box::new(by_name.get(name).into_iter()),
+                        None => Box::new(by_name.values()),
+                    }
+                },
+            )
+            .flat_map(
+                move |by_version| -> Box<dyn Iterator<Item = &'a HashSet<PkgReviewId>>> {
+                    match query.version {
+                        Some(version) => Box::new(by_version.get(version).into_iter()),
+                        None => Box::new(by_version.values()),
+                    }
+                },
+            )
+            .flatten()
+            .map(move |pkg_review_id| {
+                self.get_pkg_review_by_pkg_review_id(pkg_review_id)
+                    .expect("exists")
+            })
+            .filter(move |review| {
+                query.reviewer_id.map_or(true, |id| review.from.id == *id)
+                    && query
+                        .since_date
+                        .map_or(true, |since_date| since_date <= review.date_utc())
+                    && query
+                        .until_date
+                        .map_or(true, |until_date| review.date_utc() < until_date)
+            })
+    }
+
     pub fn get_package_reviews_for_package_sorted<'a, 'b, 'c: 'a, 'd: 'a>(
         &'a self,
         source: &'b str,
@@ -638,8 +808,21 @@ impl ProofDB {
         proofs
     }
 
-    fn add_trust_raw(&mut self, from: &Id, to: &Id, date: DateTime<Utc>, trust: TrustLevel) {
-        let tl = TimestampedTrustLevel { value: trust, date };
+    fn add_trust_raw(
+        &mut self,
+        from: &Id,
+        to: &Id,
+        date: DateTime<Utc>,
+        trust: TrustLevel,
+        expires: Option<DateTime<Utc>>,
+    ) {
+        let tl = TimestampedTrustLevel {
+            value: ExpiringTrustLevel {
+                level: trust,
+                expires,
+            },
+            date,
+        };
         self.trust_id_to_id
             .entry(from.to_owned())
             .or_insert_with(HashMap::new)
@@ -652,7 +835,13 @@ impl ProofDB {
         let from = &trust.from;
         self.record_url_from_from_field(&trust.date_utc(), &from);
         for to in &trust.ids {
-            self.add_trust_raw(&from.id, &to.id, trust.date_utc(), trust.trust);
+            self.add_trust_raw(
+                &from.id,
+                &to.id,
+                trust.date_utc(),
+                trust.trust,
+                trust.expires_utc(),
+            );
         }
         for to in &trust.ids {
             self.record_url_from_to_field(&trust.date_utc(), &to)
@@ -681,6 +870,15 @@ impl ProofDB {
         res
     }
 
+    /// Get all Ids that authored a package review (with review count)
+    pub fn all_package_review_author_ids(&self) -> BTreeMap<Id, usize> {
+        let mut res = BTreeMap::new();
+        for uniq_rev in self.package_review_signatures_by_pkg_review_id.keys() {
+            *res.entry(uniq_rev.from.clone()).or_default() += 1;
+        }
+        res
+    }
+
     pub fn get_package_review_by_signature<'a>(
         &'a self,
         signature: &str,
@@ -688,10 +886,18 @@ impl ProofDB {
         self.package_review_by_signature.get(signature)
     }
 
+    /// All reviews of packages with `digest`, whatever their name or version
     pub fn get_package_reviews_by_digest<'a>(
         &'a self,
         digest: &Digest,
     ) -> impl Iterator<Item = review::Package> + 'a {
+        self.package_reviews_by_digest(digest).cloned()
+    }
+
+    fn package_reviews_by_digest<'a>(
+        &'a self,
+        digest: &Digest,
+    ) -> impl Iterator<Item = &'a review::Package> + 'a {
         self.package_review_signatures_by_package_digest
             .get(digest.as_slice())
             .into_iter()
@@ -699,7 +905,7 @@ impl ProofDB {
                 unique_reviews
                     .iter()
                     .map(move |(_unique_review, signature)| {
-                        self.package_review_by_signature[&signature.value].clone()
+                        &self.package_review_by_signature[&signature.value]
                     })
             })
     }
@@ -710,41 +916,150 @@ impl ProofDB {
         trust_set: &TrustSet,
         requirements: &VerificationRequirements,
     ) -> VerificationStatus {
-        let reviews: HashMap<Id, review::Package> = self
-            .get_package_reviews_by_digest(digest)
-            .map(|review| (review.from.id.clone(), review))
+        let trusted_reviews = self.get_trusted_reviews(
+            self.package_reviews_by_digest(digest),
+            trust_set,
+            requirements,
+        );
+
+        if !trusted_reviews.negative_by.is_empty() {
+            VerificationStatus::Negative
+        } else if trusted_reviews.positive >= requirements.redundancy {
+            VerificationStatus::Verified
+        } else {
+            VerificationStatus::Insufficient
+        }
+    }
+
+    /// Like `verify_package_digest`, along with the reviews the status is based on
+    ///
+    /// These are the reviews by trusted ids that count towards verification
+    /// or flag the package, most trusted reviewers first.
+    pub fn verify_package_digest_with_details(
+        &self,
+        digest: &Digest,
+        trust_set: &TrustSet,
+        requirements: &VerificationRequirements,
+    ) -> VerificationDetails {
+        let status = self.verify_package_digest(digest, trust_set, requirements);
+        let reviews: HashMap<&Id, &review::Package> = self
+            .package_reviews_by_digest(digest)
+            .map(|review| (&review.from.id, review))
+            .collect();
+        let mut reviews: Vec<_> = reviews
+            .values()
+            .filter(|review| {
+                matches!(
+                    get_review_verdict(review, trust_set, requirements),
+                    ReviewVerdict::Counted | ReviewVerdict::Negative
+                )
+            })
+            .filter_map(|review| {
+                Some(ContributingReview {
+                    id: review.from.id.clone(),
+                    trust_level: trust_set.get_effective_trust_level(&review.from.id)?,
+                    date: review.date,
+                    rating: review.review.rating.clone(),
+                })
+            })
+            .collect();
+        reviews.sort_by(|a, b| {
+            b.trust_level
+                .cmp(&a.trust_level)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        VerificationDetails { status, reviews }
+    }
+
+    /// Sort out `reviews` made by ids in the `trust_set`
+    pub fn get_trusted_reviews<R: Borrow<review::Package>>(
+        &self,
+        reviews: impl Iterator<Item = R>,
+        trust_set: &TrustSet,
+        requirements: &VerificationRequirements,
+    ) -> TrustedReviews {
+        let reviews: HashMap<Id, R> = reviews
+            .map(|review| (review.borrow().from.id.clone(), review))
             .collect();
         // Faster somehow maybe?
         let reviews_by: HashSet<Id, _> = reviews.keys().cloned().collect();
         let trusted_ids: HashSet<_> = trust_set.trusted_ids().cloned().collect();
         let matching_reviewers = trusted_ids.intersection(&reviews_by);
-        let mut trust_count = 0;
-        let mut negative_count = 0;
+        let mut trusted_reviews = TrustedReviews::default();
         for matching_reviewer in matching_reviewers {
-            let review = &reviews[matching_reviewer].review;
-            if Rating::Neutral <= review.rating
-                && requirements.thoroughness <= review.thoroughness
-                && requirements.understanding <= review.understanding
-            {
-                if TrustLevel::from(requirements.trust_level)
-                    <= trust_set
-                        .get_effective_trust_level(matching_reviewer)
-                        .expect("Id should have been there")
-                {
-                    trust_count += 1;
+            trusted_reviews.reviewers += 1;
+            match get_review_verdict(reviews[matching_reviewer].borrow(), trust_set, requirements) {
+                ReviewVerdict::Counted => trusted_reviews.positive += 1,
+                ReviewVerdict::Negative => {
+                    trusted_reviews.negative_by.push(matching_reviewer.clone())
                 }
-            } else if review.rating <= Rating::Negative {
-                negative_count += 1;
+                _ => {}
             }
         }
+        trusted_reviews.negative_by.sort();
 
-        if negative_count > 0 {
-            VerificationStatus::Negative
-        } else if trust_count >= requirements.redundancy {
-            VerificationStatus::Verified
-        } else {
-            VerificationStatus::Insufficient
+        trusted_reviews
+    }
+
+    /// Explain how each review of `digest` counts towards its verification
+    ///
+    /// Reviews by trusted ids go first, closest reviewers first.
+    pub fn explain_package_digest(
+        &self,
+        digest: &Digest,
+        trust_set: &TrustSet,
+        requirements: &VerificationRequirements,
+    ) -> Vec<ReviewExplanation> {
+        let reviews: HashMap<Id, review::Package> = self
+            .get_package_reviews_by_digest(digest)
+            .map(|review| (review.from.id.clone(), review))
+            .collect();
+        let mut explanations: Vec<_> = reviews
+            .into_iter()
+            .map(|(id, review)| ReviewExplanation {
+                distance: trust_set.get_distance(&id),
+                effective_trust: trust_set.get_effective_trust_level(&id),
+                verdict: get_review_verdict(&review, trust_set, requirements),
+                review,
+            })
+            .collect();
+        explanations.sort_by(|a, b| {
+            (a.distance.is_none(), a.distance, &a.review.from.id).cmp(&(
+                b.distance.is_none(),
+                b.distance,
+                &b.review.from.id,
+            ))
+        });
+        explanations
+    }
+
+    /// Calculate how much code is covered by trusted reviews
+    ///
+    /// `packages` are digests of packages along with their lines of code.
+    pub fn get_coverage_stats<'a>(
+        &self,
+        trust_set: &TrustSet,
+        requirements: &VerificationRequirements,
+        packages: impl Iterator<Item = (&'a Digest, u64)>,
+    ) -> CoverageStats {
+        let mut stats = CoverageStats::default();
+        for (digest, loc) in packages {
+            if self
+                .verify_package_digest(digest, trust_set, requirements)
+                .is_verified()
+            {
+                stats.trusted_loc += loc;
+            } else {
+                stats.untrusted_loc += loc;
+            }
         }
+        let total_loc = stats.trusted_loc + stats.untrusted_loc;
+        stats.coverage_fraction = if total_loc == 0 {
+            1.0
+        } else {
+            stats.trusted_loc as f64 / total_loc as f64
+        };
+        stats
     }
 
     pub fn find_latest_trusted_version(
@@ -767,6 +1082,26 @@ impl ProofDB {
             .map(|review| review.package.version.clone())
     }
 
+    /// Newest version below `below` reviewed positively by any of the ids `reviewed_by` accepts
+    ///
+    /// Used to point out what changed since a package was last reviewed.
+    pub fn find_previously_reviewed_version(
+        &self,
+        source: &str,
+        name: &str,
+        below: &Version,
+        reviewed_by: impl Fn(&Id) -> bool,
+    ) -> Option<Version> {
+        self.get_pkg_reviews_for_name(source, name)
+            .filter(|review| {
+                review.package.version < *below
+                    && Rating::Positive <= review.review.rating
+                    && reviewed_by(&review.from.id)
+            })
+            .map(|review| review.package.version.clone())
+            .max()
+    }
+
     fn record_url_from_to_field(&mut self, date: &DateTime<Utc>, to: &crev_data::PubId) {
         self.url_by_id_secondary
             .entry(to.id.clone())
@@ -793,7 +1128,7 @@ impl ProofDB {
             .verify()
             .expect("All proofs were supposed to be valid here");
         match proof.content {
-            Content::Code(ref review) => self.add_code_review(&review),
+            Content::Code(ref review) => self.add_code_review(&review, &proof.signature),
             Content::Package(ref review) => self.add_package_review(&review, &proof.signature),
             Content::Trust(ref trust) => self.add_trust(&trust),
         }
@@ -805,9 +1140,18 @@ impl ProofDB {
         }
     }
 
-    fn get_trust_list_of_id(&self, id: &Id) -> impl Iterator<Item = (TrustLevel, &Id)> {
+    /// Trust given by `id`, except for expired trust proofs
+    fn get_trust_list_of_id(
+        &self,
+        id: &Id,
+        now: DateTime<Utc>,
+    ) -> impl Iterator<Item = (TrustLevel, &Id)> {
         if let Some(map) = self.trust_id_to_id.get(id) {
-            Some(map.iter().map(|(id, trust)| (trust.value, id)))
+            Some(
+                map.iter()
+                    .filter(move |(_id, trust)| !trust.value.is_expired_at(now))
+                    .map(|(id, trust)| (trust.value.level, id)),
+            )
         } else {
             None
         }
@@ -815,14 +1159,55 @@ impl ProofDB {
         .flatten()
     }
 
+    /// Trust proofs by `id` that expire before `date` (or already did), soonest first
+    ///
+    /// Only the most recent proof for each trusted id is considered.
+    pub fn get_trust_expiring_before(
+        &self,
+        id: &Id,
+        date: DateTime<Utc>,
+    ) -> Vec<(&Id, TrustLevel, DateTime<Utc>)> {
+        let mut expiring: Vec<_> = self
+            .trust_id_to_id
+            .get(id)
+            .into_iter()
+            .flat_map(|map| map.iter())
+            .filter_map(|(id, trust)| {
+                let expires = trust.value.expires?;
+                if expires <= date {
+                    Some((id, trust.value.level, expires))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        expiring.sort_by(|a, b| (a.2, a.0).cmp(&(b.2, b.0)));
+        expiring
+    }
+
     pub fn calculate_trust_set(&self, for_id: &Id, params: &TrustDistanceParams) -> TrustSet {
+        self.calculate_trust_set_with_roots(for_id, &[], params)
+    }
+
+    /// Like `calculate_trust_set`, with `roots` trusted by `for_id` at the given levels
+    ///
+    /// Roots are at distance 0, as if they were `for_id` itself, so Ids they
+    /// trust are reached as if `for_id` trusted them directly.
+    pub fn calculate_trust_set_with_roots(
+        &self,
+        for_id: &Id,
+        roots: &[(Id, TrustLevel)],
+        params: &TrustDistanceParams,
+    ) -> TrustSet {
+        let now = crev_common::now().with_timezone(&Utc);
         let mut distrusted = HashMap::new();
 
         // We keep retrying the whole thing, with more and more
         // distrusted Ids
         loop {
             let prev_distrusted_len = distrusted.len();
-            let trust_set = self.calculate_trust_set_internal(for_id, params, distrusted);
+            let trust_set =
+                self.calculate_trust_set_internal(for_id, roots, params, distrusted, now);
             if trust_set.distrusted.len() <= prev_distrusted_len {
                 return trust_set;
             }
@@ -836,8 +1221,10 @@ impl ProofDB {
     fn calculate_trust_set_internal(
         &self,
         for_id: &Id,
+        roots: &[(Id, TrustLevel)],
         params: &TrustDistanceParams,
         distrusted: HashMap<Id, HashSet<Id>>,
+        now: DateTime<Utc>,
     ) -> TrustSet {
         #[derive(PartialOrd, Ord, Eq, PartialEq, Clone, Debug)]
         struct Visit {
@@ -854,11 +1241,22 @@ impl ProofDB {
             id: for_id.clone(),
         });
         visited.record_trusted_id(for_id.clone(), for_id.clone(), 0, TrustLevel::High);
+        for (id, level) in roots {
+            if *level <= TrustLevel::None || visited.distrusted.contains_key(id) {
+                continue;
+            }
+            if visited.record_trusted_id(id.clone(), for_id.clone(), 0, *level) {
+                pending.insert(Visit {
+                    distance: 0,
+                    id: id.clone(),
+                });
+            }
+        }
 
         while let Some(current) = pending.iter().next().cloned() {
             pending.remove(&current);
 
-            for (level, candidate_id) in self.get_trust_list_of_id(&&current.id) {
+            for (level, candidate_id) in self.get_trust_list_of_id(&current.id, now) {
                 if level == TrustLevel::Distrust {
                     visited
                         .distrusted
@@ -926,11 +1324,149 @@ impl ProofDB {
 #[derive(Debug, Clone)]
 struct TrustedIdDetails {
     distance: u64,
+    // the referer `distance` was reached through
+    closest_referer: Id,
     // effective, global trust from the root of the WoT
     effective_trust: TrustLevel,
     referers: HashMap<Id, TrustLevel>,
 }
 
+/// Lines of code of packages, split by whether they are verified
+///
+/// See `ProofDB::get_coverage_stats`.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct CoverageStats {
+    pub trusted_loc: u64,
+    pub untrusted_loc: u64,
+    /// `trusted_loc` out of all lines; `1.0` if there are none
+    pub coverage_fraction: f64,
+}
+
+/// How a single package review counts towards verification
+fn get_review_verdict(
+    pkg_review: &review::Package,
+    trust_set: &TrustSet,
+    requirements: &VerificationRequirements,
+) -> ReviewVerdict {
+    let effective_trust = match trust_set.get_effective_trust_level(&pkg_review.from.id) {
+        Some(level) => level,
+        None => return ReviewVerdict::Untrusted,
+    };
+    let review = &pkg_review.review;
+    if review.rating <= Rating::Negative {
+        ReviewVerdict::Negative
+    } else if pkg_review.is_api_only() {
+        // API-only reviews can still flag a package, but don't
+        // vouch for an implementation that was not looked at
+        ReviewVerdict::ApiOnly
+    } else if requirements.thoroughness > review.thoroughness
+        || requirements.understanding > review.understanding
+    {
+        ReviewVerdict::RequirementsNotMet
+    } else if TrustLevel::from(requirements.trust_level) > effective_trust {
+        ReviewVerdict::TrustLevelTooLow
+    } else {
+        ReviewVerdict::Counted
+    }
+}
+
+/// How a package review counts towards verification
+///
+/// See `ProofDB::explain_package_digest`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReviewVerdict {
+    /// Counts towards the required number of reviews
+    Counted,
+    /// Flags the package
+    Negative,
+    /// The reviewer is not in the trust set
+    Untrusted,
+    /// The effective trust level of the reviewer is lower than required
+    TrustLevelTooLow,
+    /// The review is less thorough, or shows less understanding than required
+    RequirementsNotMet,
+    /// API-only reviews don't vouch for the implementation
+    ApiOnly,
+}
+
+impl fmt::Display for ReviewVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReviewVerdict::Counted => "counted",
+            ReviewVerdict::Negative => "negative",
+            ReviewVerdict::Untrusted => "reviewer not trusted",
+            ReviewVerdict::TrustLevelTooLow => "trust level too low",
+            ReviewVerdict::RequirementsNotMet => "thoroughness or understanding too low",
+            ReviewVerdict::ApiOnly => "API-only review",
+        })
+    }
+}
+
+/// Filters of `ProofDB::query_reviews`; fields left `None` match any review
+#[derive(Default, Debug, Clone, Copy)]
+pub struct ReviewQuery<'a> {
+    pub source: Option<&'a str>,
+    pub name: Option<&'a str>,
+    pub version: Option<&'a Version>,
+    pub reviewer_id: Option<&'a Id>,
+    /// Minimum effective trust level of the reviewer
+    pub min_trust_level: Option<TrustLevel>,
+    /// Reviews created at or after this date
+    pub since_date: Option<DateTime<Utc>>,
+    /// Reviews created before this date
+    pub until_date: Option<DateTime<Utc>>,
+}
+
+/// A package review returned by `ProofDB::query_reviews`
+#[derive(Debug, Clone, Copy)]
+pub struct QueriedReview<'a> {
+    pub review: &'a review::Package,
+    /// Effective trust level of the reviewer, if trusted
+    pub effective_trust: Option<TrustLevel>,
+}
+
+/// A package review along with how it counts towards verification
+#[derive(Clone, Debug)]
+pub struct ReviewExplanation {
+    pub review: review::Package,
+    /// Distance of the reviewer from the root of the trust set, if trusted
+    pub distance: Option<u64>,
+    pub effective_trust: Option<TrustLevel>,
+    pub verdict: ReviewVerdict,
+}
+
+/// A review by a trusted id that a verification status is based on
+#[derive(Clone, Debug)]
+pub struct ContributingReview {
+    pub id: Id,
+    /// Effective trust level of the reviewer
+    pub trust_level: TrustLevel,
+    pub date: crev_data::proof::Date,
+    pub rating: Rating,
+}
+
+/// Verification status of a package, with the reviews it is based on
+///
+/// See `ProofDB::verify_package_digest_with_details`.
+#[derive(Clone, Debug)]
+pub struct VerificationDetails {
+    pub status: VerificationStatus,
+    pub reviews: Vec<ContributingReview>,
+}
+
+/// Package reviews made by trusted ids
+///
+/// See `ProofDB::get_trusted_reviews`.
+#[derive(Default, Debug, Clone)]
+pub struct TrustedReviews {
+    /// Number of trusted ids that reviewed the package
+    pub reviewers: u64,
+    /// Number of reviews satisfying the verification requirements
+    pub positive: u64,
+    /// Trusted ids that reviewed the package negatively
+    pub negative_by: Vec<Id>,
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct TrustSet {
     trusted: HashMap<Id, TrustedIdDetails>,
@@ -970,9 +1506,10 @@ impl TrustSet {
         match self.trusted.entry(subject) {
             Entry::Vacant(entry) => {
                 let mut referers = HashMap::default();
-                referers.insert(referer, effective_trust);
+                referers.insert(referer.clone(), effective_trust);
                 entry.insert(TrustedIdDetails {
                     distance,
+                    closest_referer: referer,
                     effective_trust,
                     referers,
                 });
@@ -983,6 +1520,7 @@ impl TrustSet {
                 let details = entry.get_mut();
                 if details.distance > distance {
                     details.distance = distance;
+                    details.closest_referer = referer.clone();
                     changed = true;
                 }
                 if details.effective_trust < effective_trust {
@@ -1010,6 +1548,26 @@ impl TrustSet {
     pub fn get_effective_trust_level(&self, id: &Id) -> Option<TrustLevel> {
         self.trusted.get(id).map(|details| details.effective_trust)
     }
+
+    /// Number of trust hops from the root of the trust set
+    pub fn get_distance(&self, id: &Id) -> Option<u64> {
+        self.trusted.get(id).map(|details| details.distance)
+    }
+
+    /// Ids through which `id` is trusted, from the root of the trust set to `id` itself
+    ///
+    /// This is the path the distance of `id` was calculated along.
+    pub fn get_trust_chain(&self, id: &Id) -> Option<Vec<Id>> {
+        let mut chain = vec![id.clone()];
+        let mut current = self.trusted.get(id)?;
+        // Only the root refers to itself
+        while !chain.contains(&current.closest_referer) {
+            chain.push(current.closest_referer.clone());
+            current = &self.trusted[&current.closest_referer];
+        }
+        chain.reverse();
+        Some(chain)
+    }
 }
 
 pub struct TrustDistanceParams {
@@ -1,4 +1,6 @@
-use crate::{id::PassphraseFn, local::Local, prelude::*, proofdb::TrustSet, util, ProofStore};
+use crate::{
+    id::PassphraseFn, local::Local, prelude::*, proofdb::TrustSet, util, DigestOptions, ProofStore,
+};
 use crev_common::convert::OptionDeref;
 use crev_data::{proof, Digest};
 use failure::{bail, format_err, Fail};
@@ -6,7 +8,6 @@ use git2;
 use serde::{Deserialize, Serialize};
 use serde_yaml;
 use std::{
-    collections::HashSet,
     fs,
     io::Write,
     path::{Path, PathBuf},
@@ -174,8 +175,8 @@ impl Repo {
         } else {
             TrustSet::default()
         };
-        let ignore_list = HashSet::new();
-        let digest = crate::get_recursive_digest_for_git_dir(&self.root_dir, &ignore_list)?;
+        let digest =
+            crate::get_recursive_digest_for_git_dir(&self.root_dir, &DigestOptions::new())?;
         Ok(db.verify_package_digest(&digest, &trust_set, requirements))
     }
 
@@ -184,10 +185,9 @@ impl Repo {
             bail!("Git repository is not in a clean state");
         }
 
-        let ignore_list = HashSet::new();
         Ok(crate::get_recursive_digest_for_git_dir(
             &self.root_dir,
-            &ignore_list,
+            &DigestOptions::new(),
         )?)
     }
 
@@ -251,8 +251,8 @@ impl Repo {
         let local = Local::auto_open()?;
         let _revision = self.read_revision()?;
 
-        let ignore_list = HashSet::new();
-        let _digest = crate::get_recursive_digest_for_git_dir(&self.root_dir, &ignore_list)?;
+        let _digest =
+            crate::get_recursive_digest_for_git_dir(&self.root_dir, &DigestOptions::new())?;
         let id = local.read_current_unlocked_id(passphrase_callback)?;
 
         let review = proof::review::PackageBuilder::default()
@@ -1,12 +1,35 @@
 use super::*;
 
+use crev_data::proof::review::Rating;
 use crev_data::{proof::trust::TrustLevel, Digest, OwnId};
 use default::default;
+use proofdb::ReviewVerdict;
 use semver::Version;
 use std::str::FromStr;
 
+mod context;
+mod fetch;
 mod issues;
 
+/// Package info of `name` `version` from `source`, with `digest`
+fn package_info(
+    source: &str,
+    name: &str,
+    version: &str,
+    digest: &[u8],
+) -> crev_data::proof::PackageInfo {
+    crev_data::proof::PackageInfo {
+        id: None,
+        source: source.into(),
+        name: name.into(),
+        version: Version::parse(version).unwrap(),
+        digest: digest.to_vec(),
+        digest_type: crev_data::proof::default_digest_type(),
+        revision: "".into(),
+        revision_type: crev_data::proof::default_revision_type(),
+    }
+}
+
 // Basic liftime of an `LockedId`:
 //
 // * generate
@@ -150,6 +173,150 @@ fn proofdb_distance() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn proofdb_trust_chain() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let c = OwnId::generate_for_git_url("https://c");
+    let d = OwnId::generate_for_git_url("https://d");
+
+    let distance_params = TrustDistanceParams {
+        high_trust_distance: 1,
+        medium_trust_distance: 10,
+        low_trust_distance: 100,
+        max_distance: 1000,
+    };
+
+    let a_to_b = a.create_signed_trust_proof(vec![b.as_pubid()], TrustLevel::High)?;
+    let b_to_cd =
+        b.create_signed_trust_proof(vec![c.as_pubid(), d.as_pubid()], TrustLevel::Medium)?;
+    let c_to_d = c.create_signed_trust_proof(vec![d.as_pubid()], TrustLevel::Low)?;
+
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(vec![a_to_b, b_to_cd, c_to_d].into_iter());
+    let trust_set = trustdb.calculate_trust_set(a.as_ref(), &distance_params);
+
+    assert_eq!(
+        trust_set.get_trust_chain(a.as_ref()),
+        Some(vec![a.id.id.clone()])
+    );
+    assert_eq!(trust_set.get_distance(d.as_ref()), Some(11));
+    assert_eq!(
+        trust_set.get_trust_chain(d.as_ref()),
+        Some(vec![a.id.id.clone(), b.id.id.clone(), d.id.id.clone()])
+    );
+    assert_eq!(
+        trust_set.get_trust_chain(&OwnId::generate_for_git_url("https://e").id.id),
+        None
+    );
+
+    // The chain is the one the distance is calculated along,
+    // not the one through the closest referer
+    let e = OwnId::generate_for_git_url("https://e");
+    let a_to_c = a.create_signed_trust_proof(vec![c.as_pubid()], TrustLevel::Medium)?;
+    let b_to_e = b.create_signed_trust_proof(vec![e.as_pubid()], TrustLevel::Low)?;
+    let c_to_e = c.create_signed_trust_proof(vec![e.as_pubid()], TrustLevel::High)?;
+    trustdb.import_from_iter(vec![a_to_c, b_to_e, c_to_e].into_iter());
+    let trust_set = trustdb.calculate_trust_set(a.as_ref(), &distance_params);
+    assert_eq!(trust_set.get_distance(c.as_ref()), Some(10));
+    assert_eq!(trust_set.get_distance(e.as_ref()), Some(11));
+    assert_eq!(
+        trust_set.get_trust_chain(e.as_ref()),
+        Some(vec![a.id.id.clone(), c.id.id.clone(), e.id.id.clone()])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn proofdb_trust_set_with_roots() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let c = OwnId::generate_for_git_url("https://c");
+    let d = OwnId::generate_for_git_url("https://d");
+
+    let distance_params = TrustDistanceParams {
+        high_trust_distance: 1,
+        medium_trust_distance: 10,
+        low_trust_distance: 100,
+        max_distance: 10,
+    };
+
+    let b_to_c = b.create_signed_trust_proof(vec![c.as_pubid()], TrustLevel::High)?;
+    let a_to_d = a.create_signed_trust_proof(vec![d.as_pubid()], TrustLevel::Distrust)?;
+
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(vec![b_to_c, a_to_d].into_iter());
+
+    let trust_set = trustdb.calculate_trust_set(a.as_ref(), &distance_params);
+    assert_eq!(trust_set.get_distance(b.as_ref()), None);
+
+    let roots = vec![
+        (b.id.id.clone(), TrustLevel::Medium),
+        (d.id.id.clone(), TrustLevel::High),
+    ];
+    let trust_set = trustdb.calculate_trust_set_with_roots(a.as_ref(), &roots, &distance_params);
+    assert_eq!(trust_set.get_distance(b.as_ref()), Some(0));
+    assert_eq!(
+        trust_set.get_effective_trust_level(b.as_ref()),
+        Some(TrustLevel::Medium)
+    );
+    // Trust of a root is capped at its own level
+    assert_eq!(trust_set.get_distance(c.as_ref()), Some(1));
+    assert_eq!(
+        trust_set.get_effective_trust_level(c.as_ref()),
+        Some(TrustLevel::Medium)
+    );
+    assert_eq!(
+        trust_set.get_trust_chain(c.as_ref()),
+        Some(vec![a.id.id.clone(), b.id.id.clone(), c.id.id.clone()])
+    );
+    // Distrust still wins
+    assert_eq!(trust_set.get_distance(d.as_ref()), None);
+
+    Ok(())
+}
+
+// Reviews are found by the digest of the content, whatever
+// crate name and version they claim it to be
+#[test]
+fn package_reviews_by_digest_ignore_name() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let package = |name: &str, digest: u8| package_info("source", name, "1.0.0", &[digest; 32]);
+
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(
+        vec![
+            a.as_pubid()
+                .create_package_review_proof(package("foo", 1), default(), "".into())?
+                .sign_by(&a)?,
+            b.as_pubid()
+                .create_package_review_proof(package("foo-vendored", 1), default(), "".into())?
+                .sign_by(&b)?,
+            b.as_pubid()
+                .create_package_review_proof(package("foo", 2), default(), "".into())?
+                .sign_by(&b)?,
+        ]
+        .into_iter(),
+    );
+
+    let mut names: Vec<_> = trustdb
+        .get_package_reviews_by_digest(&Digest::from_vec(vec![1; 32]))
+        .map(|review| review.package.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["foo", "foo-vendored"]);
+    assert_eq!(
+        trustdb
+            .get_package_reviews_by_digest(&Digest::from_vec(vec![3; 32]))
+            .count(),
+        0
+    );
+
+    Ok(())
+}
+
 // A subsequent review of exactly same package version
 // is supposed to overwrite the previous one, and it
 // should be visible in all the user-facing stats, listings
@@ -158,25 +325,15 @@ fn proofdb_distance() -> Result<()> {
 fn overwritting_reviews() -> Result<()> {
     let a = OwnId::generate_for_git_url("https://a");
     let digest = vec![0; 32];
-    let package = crev_data::proof::PackageInfo {
-        id: None,
-        source: "source".into(),
-        name: "name".into(),
-        version: Version::parse("1.0.0").unwrap(),
-        digest: digest.clone(),
-        digest_type: crev_data::proof::default_digest_type(),
-        revision: "".into(),
-        revision_type: crev_data::proof::default_revision_type(),
-    };
+    let package = package_info("source", "name", "1.0.0", &digest);
 
     let proof1 = a
         .as_pubid()
         .create_package_review_proof(package.clone(), default(), "a".into())?
         .sign_by(&a)?;
     // it's lame, but oh well... ; we need to make sure there's a time delay between
-    // the two proofs
-    #[allow(deprecated)]
-    std::thread::sleep_ms(1);
+    // the two proofs, and dates are only precise to a second
+    std::thread::sleep(std::time::Duration::from_secs(1));
     let proof2 = a
         .as_pubid()
         .create_package_review_proof(package.clone(), default(), "b".into())?
@@ -222,6 +379,144 @@ fn overwritting_reviews() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn proofdb_previously_reviewed_version() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let review = |id: &OwnId, version: &str, review: crev_data::Review| -> Result<_> {
+        let package = package_info("source", "name", version, &[0; 32]);
+        id.as_pubid()
+            .create_package_review_proof(package, review, "".into())?
+            .sign_by(id)
+    };
+
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(
+        vec![
+            review(&a, "1.0.0", crev_data::Review::new_positive())?,
+            review(&a, "1.1.0", crev_data::Review::new_negative())?,
+            review(&b, "1.2.0", crev_data::Review::new_positive())?,
+            review(&a, "2.0.0", crev_data::Review::new_positive())?,
+        ]
+        .into_iter(),
+    );
+
+    let below = Version::parse("1.2.3").unwrap();
+    let previous = |reviewed_by: &dyn Fn(&crev_data::Id) -> bool| -> Option<String> {
+        trustdb
+            .find_previously_reviewed_version("source", "name", &below, reviewed_by)
+            .map(|version| version.to_string())
+    };
+    assert_eq!(previous(&|id| *id == a.id.id), Some("1.0.0".into()));
+    assert_eq!(previous(&|_| true), Some("1.2.0".into()));
+    assert_eq!(previous(&|_| false), None);
+
+    Ok(())
+}
+
+#[test]
+fn proofdb_query_reviews() -> Result<()> {
+    use chrono::{TimeZone, Utc};
+
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let c = OwnId::generate_for_git_url("https://c");
+    let d = OwnId::generate_for_git_url("https://d");
+    let review = |id: &OwnId, source: &str, name: &str, version: &str, year| -> Result<_> {
+        let package = package_info(source, name, version, &[0; 32]);
+        let mut review = id.as_pubid().create_package_review_proof(
+            package,
+            crev_data::Review::new_positive(),
+            format!("{} {}", name, version),
+        )?;
+        review.date = Utc.ymd(year, 1, 1).and_hms(0, 0, 0).into();
+        review.sign_by(id)
+    };
+
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(
+        vec![
+            a.create_signed_trust_proof(vec![b.as_pubid()], TrustLevel::High)?,
+            a.create_signed_trust_proof(vec![c.as_pubid()], TrustLevel::Low)?,
+            review(&a, "s1", "x", "1.0.0", 2018)?,
+            review(&b, "s1", "x", "2.0.0", 2019)?,
+            review(&c, "s1", "y", "1.0.0", 2019)?,
+            review(&d, "s2", "x", "1.0.0", 2018)?,
+        ]
+        .into_iter(),
+    );
+    let trust_set = trustdb.calculate_trust_set(a.as_ref(), &default());
+
+    let comments = |query: proofdb::ReviewQuery<'_>| -> Vec<String> {
+        let mut comments: Vec<_> = trustdb
+            .query_reviews(query, &trust_set)
+            .map(|queried| {
+                format!(
+                    "{}/{:?}",
+                    queried.review.comment,
+                    queried.effective_trust.map(|level| level.to_string())
+                )
+            })
+            .collect();
+        comments.sort();
+        comments
+    };
+
+    assert_eq!(comments(default()).len(), 4);
+    assert_eq!(
+        comments(proofdb::ReviewQuery {
+            source: Some("s2"),
+            ..default()
+        }),
+        vec!["x 1.0.0/None"]
+    );
+    assert_eq!(
+        comments(proofdb::ReviewQuery {
+            name: Some("y"),
+            ..default()
+        }),
+        vec!["y 1.0.0/Some(\"low\")"]
+    );
+    assert_eq!(
+        comments(proofdb::ReviewQuery {
+            version: Some(&Version::parse("2.0.0").unwrap()),
+            ..default()
+        }),
+        vec!["x 2.0.0/Some(\"high\")"]
+    );
+    assert_eq!(
+        comments(proofdb::ReviewQuery {
+            reviewer_id: Some(&a.id.id),
+            ..default()
+        }),
+        vec!["x 1.0.0/Some(\"high\")"]
+    );
+    assert_eq!(
+        comments(proofdb::ReviewQuery {
+            min_trust_level: Some(TrustLevel::Medium),
+            ..default()
+        }),
+        vec!["x 1.0.0/Some(\"high\")", "x 2.0.0/Some(\"high\")"]
+    );
+    assert_eq!(
+        comments(proofdb::ReviewQuery {
+            since_date: Some(Utc.ymd(2019, 1, 1).and_hms(0, 0, 0)),
+            ..default()
+        }),
+        vec!["x 2.0.0/Some(\"high\")", "y 1.0.0/Some(\"low\")"]
+    );
+    assert_eq!(
+        comments(proofdb::ReviewQuery {
+            source: Some("s1"),
+            until_date: Some(Utc.ymd(2019, 1, 1).and_hms(0, 0, 0)),
+            ..default()
+        }),
+        vec!["x 1.0.0/Some(\"high\")"]
+    );
+
+    Ok(())
+}
+
 #[test]
 fn proofdb_distrust() -> Result<()> {
     let a = OwnId::generate_for_git_url("https://a");
@@ -277,3 +572,793 @@ fn proofdb_distrust() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn proofdb_ignores_expired_trust() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let c = OwnId::generate_for_git_url("https://c");
+
+    let now = crev_common::now();
+    let mut a_to_b =
+        a.id.create_trust_proof(vec![b.as_pubid()], TrustLevel::High)?;
+    a_to_b.expires = Some(now - chrono::Duration::days(1));
+    let mut a_to_c =
+        a.id.create_trust_proof(vec![c.as_pubid()], TrustLevel::High)?;
+    a_to_c.expires = Some(now + chrono::Duration::days(10));
+
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(vec![a_to_b.sign_by(&a)?, a_to_c.sign_by(&a)?].into_iter());
+
+    let trust_set: HashSet<_> = trustdb
+        .calculate_trust_set(a.as_ref(), &default())
+        .trusted_ids()
+        .cloned()
+        .collect();
+
+    assert!(!trust_set.contains(b.as_ref()));
+    assert!(trust_set.contains(c.as_ref()));
+
+    let expiring = trustdb.get_trust_expiring_before(a.as_ref(), now.with_timezone(&chrono::Utc));
+    assert_eq!(expiring.len(), 1);
+    assert_eq!(expiring[0].0, b.as_ref());
+    let expiring = trustdb.get_trust_expiring_before(
+        a.as_ref(),
+        (now + chrono::Duration::days(30)).with_timezone(&chrono::Utc),
+    );
+    assert_eq!(expiring.len(), 2);
+    assert_eq!(expiring[1].0, c.as_ref());
+
+    Ok(())
+}
+
+// Ids reachable only through an expired trust proof are not trusted,
+// even if the proofs further down the path are still valid.
+#[test]
+fn proofdb_expired_trust_was_only_path() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let c = OwnId::generate_for_git_url("https://c");
+    let d = OwnId::generate_for_git_url("https://d");
+
+    let mut a_to_b =
+        a.id.create_trust_proof(vec![b.as_pubid()], TrustLevel::High)?;
+    a_to_b.expires = Some(crev_common::now() - chrono::Duration::seconds(1));
+    let b_to_c = b.create_signed_trust_proof(vec![c.as_pubid()], TrustLevel::High)?;
+    let a_to_d = a.create_signed_trust_proof(vec![d.as_pubid()], TrustLevel::High)?;
+
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(vec![a_to_b.sign_by(&a)?, b_to_c, a_to_d].into_iter());
+
+    let trust_set = trustdb.calculate_trust_set(a.as_ref(), &default());
+    assert!(!trust_set.contains_trusted(b.as_ref()));
+    assert!(!trust_set.contains_trusted(c.as_ref()));
+    assert!(trust_set.contains_trusted(d.as_ref()));
+
+    Ok(())
+}
+
+#[test]
+fn parse_trust_id_list_is_all_or_nothing() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a").id.id.to_string();
+    let b = OwnId::generate_for_git_url("https://b").id.id.to_string();
+
+    let ids = local::parse_trust_id_list(&format!(
+        "# friends\n{}\n\n  {} https://github.com/b/crev-proofs # b\n",
+        a, b
+    ))?;
+    assert_eq!(
+        ids,
+        vec![
+            (a.clone(), None),
+            (
+                b.clone(),
+                Some("https://github.com/b/crev-proofs".to_owned())
+            ),
+        ]
+    );
+
+    let err = local::parse_trust_id_list(&format!("{}\nnot-an-id!\n{} url extra\n", a, b))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("line 2"));
+    assert!(err.contains("line 3"));
+    Ok(())
+}
+
+// API-only reviews should not verify a package on their own,
+// but negative ones still count.
+#[test]
+fn api_only_reviews() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let digest = vec![0; 32];
+    let package = package_info("source", "name", "1.0.0", &digest);
+
+    let distance_params = TrustDistanceParams {
+        high_trust_distance: 1,
+        medium_trust_distance: 10,
+        low_trust_distance: 100,
+        max_distance: 10000,
+    };
+    let requirements = VerificationRequirements {
+        trust_level: crev_data::Level::None,
+        understanding: crev_data::Level::None,
+        thoroughness: crev_data::Level::None,
+        redundancy: 1,
+    };
+
+    let verify = |review: crev_data::proof::review::Package| -> Result<VerificationStatus> {
+        let mut trustdb = ProofDB::new();
+        trustdb.import_from_iter(vec![review.sign_by(&a)?].into_iter());
+        let trust_set = trustdb.calculate_trust_set(a.as_ref(), &distance_params);
+        Ok(trustdb.verify_package_digest(
+            &Digest::from_vec(digest.clone()),
+            &trust_set,
+            &requirements,
+        ))
+    };
+
+    let mut review = a.as_pubid().create_package_review_proof(
+        package.clone(),
+        crev_data::Review::new_positive(),
+        "".into(),
+    )?;
+    assert_eq!(verify(review.clone())?, VerificationStatus::Verified);
+
+    review.scope_of_review = Some(crev_data::proof::review::ReviewScope::api_only());
+    assert_eq!(verify(review.clone())?, VerificationStatus::Insufficient);
+
+    review.review = crev_data::Review::new_negative();
+    assert_eq!(verify(review.clone())?, VerificationStatus::Negative);
+
+    Ok(())
+}
+
+// Every review of a digest is explained, trusted reviewers first
+#[test]
+fn explain_package_digest() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let c = OwnId::generate_for_git_url("https://c");
+    let d = OwnId::generate_for_git_url("https://d");
+    let digest = vec![0; 32];
+    let package = package_info("source", "name", "1.0.0", &digest);
+    let review = |id: &OwnId, review| -> Result<crev_data::proof::Proof> {
+        id.as_pubid()
+            .create_package_review_proof(package.clone(), review, "".into())?
+            .sign_by(id)
+    };
+
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(
+        vec![
+            a.create_signed_trust_proof(vec![b.as_pubid()], TrustLevel::Low)?,
+            a.create_signed_trust_proof(vec![d.as_pubid()], TrustLevel::High)?,
+            review(&a, crev_data::Review::new_positive())?,
+            review(&b, crev_data::Review::new_positive())?,
+            review(&c, crev_data::Review::new_positive())?,
+            review(&d, crev_data::Review::new_negative())?,
+        ]
+        .into_iter(),
+    );
+    let trust_set = trustdb.calculate_trust_set(a.as_ref(), &default());
+    let requirements = VerificationRequirements {
+        trust_level: crev_data::Level::Medium,
+        understanding: crev_data::Level::None,
+        thoroughness: crev_data::Level::None,
+        redundancy: 1,
+    };
+
+    let digest = Digest::from_vec(digest);
+    let explanations = trustdb.explain_package_digest(&digest, &trust_set, &requirements);
+    let verdict = |id: &OwnId| {
+        explanations
+            .iter()
+            .find(|e| e.review.from.id == id.id.id)
+            .map(|e| e.verdict)
+    };
+    assert_eq!(explanations.len(), 4);
+    assert_eq!(explanations[0].distance, Some(0));
+    assert_eq!(explanations[3].review.from.id, c.id.id);
+    assert_eq!(explanations[3].distance, None);
+    assert_eq!(verdict(&a), Some(ReviewVerdict::Counted));
+    assert_eq!(verdict(&b), Some(ReviewVerdict::TrustLevelTooLow));
+    assert_eq!(verdict(&c), Some(ReviewVerdict::Untrusted));
+    assert_eq!(verdict(&d), Some(ReviewVerdict::Negative));
+
+    // Only the reviews that counted or flagged the package contributed to its status
+    let details = trustdb.verify_package_digest_with_details(&digest, &trust_set, &requirements);
+    assert_eq!(
+        details.status,
+        trustdb.verify_package_digest(&digest, &trust_set, &requirements)
+    );
+    assert_eq!(details.status, VerificationStatus::Negative);
+    let reviewers: Vec<_> = details
+        .reviews
+        .iter()
+        .map(|r| (r.id.clone(), r.trust_level, r.rating.clone()))
+        .collect();
+    let mut expected = vec![
+        (a.id.id.clone(), TrustLevel::High, Rating::Positive),
+        (d.id.id.clone(), TrustLevel::High, Rating::Negative),
+    ];
+    expected.sort_by(|x, y| x.0.cmp(&y.0));
+    assert_eq!(reviewers, expected);
+
+    Ok(())
+}
+
+// Code reviews of some files report partial coverage,
+// but never verify the whole package
+#[test]
+fn partial_code_reviews() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let dir = tempdir::TempDir::new("crev-partial")?;
+    std::fs::create_dir_all(dir.path().join("src"))?;
+    std::fs::write(dir.path().join("src/lib.rs"), "mod parser;")?;
+    std::fs::write(dir.path().join("src/parser.rs"), "fn parse() {}")?;
+    std::fs::write(dir.path().join("Cargo.toml"), "[package]")?;
+
+    let digest_options = DigestOptions::new();
+    let digest = get_dir_digest(dir.path(), &digest_options)?;
+    let package = package_info("source", "name", "1.0.0", digest.as_slice());
+    let file = |path: &str, content: &[u8]| crev_data::proof::review::code::File {
+        path: path.into(),
+        digest: crev_common::blake2b256sum(content),
+        digest_type: crev_data::proof::default_digest_type(),
+    };
+    let code_review = |id: &OwnId, files| -> Result<crev_data::proof::Proof> {
+        crev_data::proof::review::CodeBuilder::default()
+            .from(id.id.to_owned())
+            .package(package.clone())
+            .review(crev_data::Review::new_positive())
+            .files(files)
+            .build()
+            .map_err(|e| format_err!("{}", e))?
+            .sign_by(id)
+    };
+
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(
+        vec![
+            a.create_signed_trust_proof(vec![b.as_pubid()], TrustLevel::High)?,
+            code_review(
+                &b,
+                vec![
+                    file("src/lib.rs", b"mod parser;"),
+                    file("src/parser.rs", b"outdated"),
+                ],
+            )?,
+        ]
+        .into_iter(),
+    );
+    let trust_set = trustdb.calculate_trust_set(a.as_ref(), &default());
+    let requirements = VerificationRequirements {
+        trust_level: crev_data::Level::None,
+        understanding: crev_data::Level::None,
+        thoroughness: crev_data::Level::None,
+        redundancy: 1,
+    };
+    let version = Version::parse("1.0.0").unwrap();
+
+    assert_eq!(
+        trustdb.verify_package_digest(&digest, &trust_set, &requirements),
+        VerificationStatus::Insufficient
+    );
+
+    let reviewed_files =
+        trustdb.get_trusted_reviewed_files("source", "name", &version, &trust_set, &requirements);
+    assert_eq!(reviewed_files.len(), 2);
+    assert_eq!(
+        dir_partial_review_coverage(dir.path(), &digest_options, &reviewed_files)?,
+        PartialReviewCoverage {
+            reviewed_files: 1,
+            total_files: 3,
+        }
+    );
+
+    // Reviews by untrusted ids don't count
+    let c = OwnId::generate_for_git_url("https://c");
+    trustdb.import_from_iter(
+        vec![code_review(&c, vec![file("Cargo.toml", b"[package]")])?].into_iter(),
+    );
+    assert_eq!(
+        trustdb
+            .get_trusted_reviewed_files("source", "name", &version, &trust_set, &requirements)
+            .len(),
+        2
+    );
+
+    Ok(())
+}
+
+#[test]
+fn coverage_stats() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let reviewed_digest = Digest::from_vec(vec![0; 32]);
+    let unreviewed_digest = Digest::from_vec(vec![1; 32]);
+    let package = package_info("source", "name", "1.0.0", reviewed_digest.as_slice());
+    let requirements = VerificationRequirements {
+        trust_level: crev_data::Level::None,
+        understanding: crev_data::Level::None,
+        thoroughness: crev_data::Level::None,
+        redundancy: 1,
+    };
+
+    let review = a
+        .as_pubid()
+        .create_package_review_proof(package, crev_data::Review::new_positive(), "".into())?
+        .sign_by(&a)?;
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(vec![review].into_iter());
+    let trust_set = trustdb.calculate_trust_set(a.as_ref(), &default());
+
+    let stats = trustdb.get_coverage_stats(
+        &trust_set,
+        &requirements,
+        vec![(&reviewed_digest, 300), (&unreviewed_digest, 100)].into_iter(),
+    );
+    assert_eq!(stats.trusted_loc, 300);
+    assert_eq!(stats.untrusted_loc, 100);
+    assert_eq!(stats.coverage_fraction, 0.75);
+
+    let stats = trustdb.get_coverage_stats(&trust_set, &requirements, vec![].into_iter());
+    assert_eq!(stats.coverage_fraction, 1.0);
+
+    Ok(())
+}
+
+#[test]
+fn diff_dir_digests_reports_changed_files() -> Result<()> {
+    let a = tempdir::TempDir::new("crev-diff-a")?;
+    let b = tempdir::TempDir::new("crev-diff-b")?;
+    std::fs::write(a.path().join("same"), "same")?;
+    std::fs::write(b.path().join("same"), "same")?;
+    std::fs::write(a.path().join("changed"), "a")?;
+    std::fs::write(b.path().join("changed"), "bb")?;
+    std::fs::write(a.path().join("removed"), "a")?;
+    std::fs::create_dir(b.path().join("target"))?;
+    std::fs::write(b.path().join("target").join("ignored"), "b")?;
+    std::fs::write(b.path().join("added"), "b")?;
+
+    let mut ignore_list = HashSet::new();
+    ignore_list.insert(PathBuf::from("target"));
+    let options = ignore_list.into();
+    let report = diff_dir_digests(a.path(), b.path(), &options)?;
+
+    let paths = |entries: &[DirDiffEntry]| -> Vec<PathBuf> {
+        entries.iter().map(|e| e.path.clone()).collect()
+    };
+    assert_eq!(paths(&report.only_in_a), vec![PathBuf::from("removed")]);
+    assert_eq!(paths(&report.only_in_b), vec![PathBuf::from("added")]);
+    assert_eq!(paths(&report.different), vec![PathBuf::from("changed")]);
+    assert_eq!(report.different[0].size_a, Some(1));
+    assert_eq!(report.different[0].size_b, Some(2));
+
+    assert!(diff_dir_digests(a.path(), a.path(), &options)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn compare_package_to_repo_skips_generated_files() -> Result<()> {
+    let package = tempdir::TempDir::new("crev-provenance-pkg")?;
+    let repo = tempdir::TempDir::new("crev-provenance-repo")?;
+    let repo_pkg = repo.path().join("foo");
+    let write = |dir: &Path, path: &str, content: &str| -> Result<()> {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, content)?;
+        Ok(())
+    };
+
+    write(package.path(), "src/lib.rs", "lib")?;
+    write(&repo_pkg, "src/lib.rs", "lib")?;
+    // Normalized by `cargo package`, the original is kept as `Cargo.toml.orig`
+    write(package.path(), "Cargo.toml", "normalized")?;
+    write(package.path(), "Cargo.toml.orig", "manifest")?;
+    write(&repo_pkg, "Cargo.toml", "manifest")?;
+    write(package.path(), ".cargo_vcs_info.json", "{}")?;
+    // Copied from outside of the package directory
+    write(package.path(), "README.md", "readme")?;
+    write(repo.path(), "README.md", "readme")?;
+    write(&repo_pkg, "tests/test.rs", "test")?;
+    write(&repo_pkg, ".git/HEAD", "ref")?;
+
+    let report = crate::compare_package_to_repo(package.path(), repo.path(), &repo_pkg)?;
+    assert!(report.is_faithful(), "{:?}", report);
+    assert_eq!(report.identical, 3);
+    assert_eq!(report.only_in_repo, vec![PathBuf::from("tests/test.rs")]);
+
+    write(package.path(), "src/lib.rs", "injected")?;
+    write(package.path(), "build.rs", "injected")?;
+    write(package.path(), "Cargo.toml.orig", "changed")?;
+    let report = crate::compare_package_to_repo(package.path(), repo.path(), &repo_pkg)?;
+    assert!(!report.is_faithful());
+    assert_eq!(report.identical, 1);
+    assert_eq!(
+        report.different,
+        vec![
+            PathBuf::from("src/lib.rs"),
+            PathBuf::from("Cargo.toml.orig")
+        ]
+    );
+    assert_eq!(report.only_in_package, vec![PathBuf::from("build.rs")]);
+
+    Ok(())
+}
+
+#[test]
+fn digest_options_ignore_paths_and_their_contents() -> Result<()> {
+    let options = DigestOptions::new().ignore("target").ignore("src/gen.rs");
+    assert!(options.should_include(Path::new("src/lib.rs")));
+    assert!(!options.should_include(Path::new("src/gen.rs")));
+    assert!(!options.should_include(Path::new("target")));
+    assert!(!options.should_include(Path::new("target/debug/build")));
+    assert!(options.should_include(Path::new("targets")));
+
+    let dir = tempdir::TempDir::new("crev-digest")?;
+    std::fs::create_dir_all(dir.path().join("src"))?;
+    std::fs::write(dir.path().join("src/lib.rs"), "mod gen;")?;
+    let digest = get_dir_digest(dir.path(), &options)?;
+
+    std::fs::write(dir.path().join("src/gen.rs"), "")?;
+    std::fs::create_dir_all(dir.path().join("target/debug"))?;
+    std::fs::write(dir.path().join("target/debug/out"), "")?;
+    assert_eq!(get_dir_digest(dir.path(), &options)?, digest);
+    assert_ne!(get_dir_digest(dir.path(), &DigestOptions::new())?, digest);
+
+    Ok(())
+}
+
+#[test]
+fn notes_roundtrip_and_lookup() -> Result<()> {
+    use crate::notes::{Note, Notes};
+
+    let dir = tempdir::TempDir::new("crev-notes")?;
+    let path = dir.path().join("notes.toml");
+    let v1 = Version::parse("1.0.0").unwrap();
+    let v2 = Version::parse("2.0.0").unwrap();
+
+    let mut notes = Notes::read_from_toml_file(&path)?;
+    notes.add(Note::new("source", "a", None, None, "all versions".into()));
+    notes.add(Note::new("source", "b", Some(&v1), None, "only v1".into()));
+    notes.save_to_toml_file(&path)?;
+
+    let notes = Notes::read_from_toml_file(&path)?;
+    assert_eq!(notes.get_for("source", "a", Some(&v2)).count(), 1);
+    assert_eq!(notes.get_for("source", "b", Some(&v1)).count(), 1);
+    assert_eq!(notes.get_for("source", "b", Some(&v2)).count(), 0);
+    assert_eq!(notes.get_for("source", "b", None).count(), 1);
+    assert_eq!(notes.get_for("other", "a", None).count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn notes_are_keyed_by_digest() -> Result<()> {
+    use crate::notes::{Note, Notes};
+
+    let dir = tempdir::TempDir::new("crev-notes")?;
+    let path = dir.path().join("notes.toml");
+    let v1 = Version::parse("1.0.0").unwrap();
+    let v2 = Version::parse("1.0.1").unwrap();
+    let reviewed = Digest::from_vec(vec![1; 32]);
+    let modified = Digest::from_vec(vec![2; 32]);
+
+    let mut notes = Notes::default();
+    notes.add(Note::new(
+        "source",
+        "a",
+        Some(&v1),
+        Some(&reviewed),
+        "ok".into(),
+    ));
+    notes.add(Note::new("source", "a", None, None, "any".into()));
+    notes.save_to_toml_file(&path)?;
+    let notes = Notes::read_from_toml_file(&path)?;
+
+    // a version bump with the same content keeps the note
+    let found: Vec<_> = notes
+        .get_for_package("source", "a", &v2, &reviewed)
+        .map(|note| (note.text.as_str(), note.is_stale(&reviewed)))
+        .collect();
+    assert_eq!(found, vec![("ok", false), ("any", false)]);
+
+    // the note no longer applies to modified content of the same version
+    let found: Vec<_> = notes
+        .get_for_package("source", "a", &v1, &modified)
+        .map(|note| (note.text.as_str(), note.is_stale(&modified)))
+        .collect();
+    assert_eq!(found, vec![("ok", true), ("any", false)]);
+
+    assert_eq!(
+        notes.get_for_package("source", "a", &v2, &modified).count(),
+        1
+    );
+
+    Ok(())
+}
+
+#[test]
+fn legacy_yaml_user_config_is_migrated_to_toml() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-config")?;
+    let root = dir.path().join("config");
+    let cache = dir.path().join("cache");
+    let id = OwnId::generate_for_git_url("https://a");
+
+    let local = Local::auto_create_at(&root, &cache)?;
+    local.save_locked_id(&id::LockedId::from_own_id(&id, "")?)?;
+    local.save_current_id(id.as_ref())?;
+    let config = local.load_user_config()?;
+    let toml_path = root.join("config.toml");
+    let yaml_path = root.join("config.yaml");
+    assert!(toml_path.exists());
+
+    std::fs::remove_file(&toml_path)?;
+    crev_common::save_to_yaml_file(&yaml_path, &config)?;
+
+    let local = Local::auto_open_at(&root, &cache)?;
+    assert_eq!(local.get_current_userid()?, id.id.id);
+    assert!(toml_path.exists());
+    assert!(!yaml_path.exists());
+
+    let migrated: local::UserConfig = crev_common::read_from_toml_file(&toml_path)?;
+    assert_eq!(migrated.current_id, config.current_id);
+
+    Ok(())
+}
+
+#[test]
+fn change_current_id_passphrase() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-passphrase")?;
+    let local = Local::auto_create_at(&dir.path().join("config"), &dir.path().join("cache"))?;
+    let a = OwnId::generate_for_git_url("https://a");
+    local.save_locked_id(&id::LockedId::from_own_id(&a, "old")?)?;
+    local.save_current_id(a.as_ref())?;
+
+    // eg. both read from CREV_PASSPHRASE
+    assert!(local
+        .change_current_id_passphrase(&|| Ok("old".into()), &|| Ok("old".into()))
+        .is_err());
+    local.change_current_id_passphrase(&|| Ok("old".into()), &|| Ok("new".into()))?;
+
+    let locked = local.read_current_locked_id()?;
+    assert!(locked.to_unlocked("old").is_err());
+    let unlocked = locked.to_unlocked("new")?;
+    assert_eq!(unlocked.id.id, a.id.id);
+    assert_eq!(
+        unlocked.keypair.secret.as_bytes(),
+        a.keypair.secret.as_bytes()
+    );
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let path = local.id_path(&a.id.id);
+        assert_eq!(std::fs::metadata(path)?.permissions().mode() & 0o777, 0o600);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn import_locked_id_file() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-import-id")?;
+    let local = Local::auto_create_at(&dir.path().join("config"), &dir.path().join("cache"))?;
+    let a = OwnId::generate_for_git_url("https://a");
+    let locked = id::LockedId::from_own_id(&a, "pass")?;
+    let key_file = dir.path().join("a.toml");
+    std::fs::write(&key_file, toml::to_string(&locked)?)?;
+
+    // Nothing is saved if the passphrase doesn't unlock the key
+    assert!(local
+        .import_locked_id_file(&key_file, &|| Ok("wrong".into()))
+        .is_err());
+    assert!(local.read_locked_id(&a.id.id).is_err());
+
+    let imported = local.import_locked_id_file(&key_file, &|| Ok("pass".into()))?;
+    assert_eq!(imported.id, a.id.id);
+    local.save_current_id(&imported.id)?;
+    assert_eq!(local.read_current_locked_id()?.url.url, "https://a");
+
+    Ok(())
+}
+
+#[test]
+fn change_current_id_url() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-set-url")?;
+    let local = Local::auto_create_at(&dir.path().join("config"), &dir.path().join("cache"))?;
+    let a = OwnId::generate_for_git_url("https://a");
+    local.save_locked_id(&id::LockedId::from_own_id(&a, "")?)?;
+    local.save_current_id(a.as_ref())?;
+
+    let old_dir = local.get_proofs_dir_path()?;
+    git2::Repository::init(&old_dir)?;
+    std::fs::write(
+        old_dir.join("trust.crev"),
+        a.create_signed_trust_proof(vec![], TrustLevel::High)?
+            .to_string(),
+    )?;
+
+    // A proof repository of another id can't be taken over
+    let b = OwnId::generate_for_git_url("https://b");
+    let b_dir = local.get_proofs_dir_path_for_url(&b.id.url)?;
+    std::fs::create_dir_all(&b_dir)?;
+    std::fs::write(
+        b_dir.join("trust.crev"),
+        b.create_signed_trust_proof(vec![], TrustLevel::High)?
+            .to_string(),
+    )?;
+    assert!(local.change_current_id_url("https://b", true).is_err());
+
+    let new_url = "https://github.com/a/crev-proofs";
+    local.change_current_id_url(new_url, true)?;
+    let new_dir = local.get_proofs_dir_path()?;
+    assert_ne!(old_dir, new_dir);
+    assert!(!old_dir.exists());
+    assert!(new_dir.join("trust.crev").exists());
+    assert_eq!(local.read_current_locked_id()?.url.url, new_url);
+    assert_eq!(local.list_ids()?[0].url.url, new_url);
+    let repo = git2::Repository::open(&new_dir)?;
+    let origin = repo.find_remote("origin")?;
+    assert_eq!(origin.url(), Some(new_url));
+    assert_eq!(origin.pushurl(), Some("git@github.com:a/crev-proofs.git"));
+
+    Ok(())
+}
+
+#[test]
+fn review_start_times_one_review() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-review-start")?;
+    let local = Local::auto_create_at(&dir.path().join("config"), &dir.path().join("cache"))?;
+    let version = Version::parse("1.0.0")?;
+
+    assert!(local
+        .read_review_start("source", "foo", &version)?
+        .is_none());
+    local.record_review_start("source", "foo", &version)?;
+    let start = local.read_review_start("source", "foo", &version)?.unwrap();
+    assert!(start.timestamp <= crev_common::now());
+    assert!(local
+        .read_review_start("source", "bar", &version)?
+        .is_none());
+
+    local.clear_review_start("source", "foo", &version)?;
+    assert!(local
+        .read_review_start("source", "foo", &version)?
+        .is_none());
+    local.clear_review_start("source", "foo", &version)?;
+
+    Ok(())
+}
+
+#[test]
+fn store_build_log() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-build-log")?;
+    let local = Local::auto_create_at(&dir.path().join("config"), &dir.path().join("cache"))?;
+    let a = OwnId::generate_for_git_url("https://a");
+    local.save_locked_id(&id::LockedId::from_own_id(&a, "")?)?;
+    local.save_current_id(a.as_ref())?;
+    let proofs_dir = local.get_proofs_dir_path()?;
+    git2::Repository::init(&proofs_dir)?;
+
+    let log_path = dir.path().join("build.log");
+    std::fs::write(&log_path, "Compiling foo v1.0.0\n")?;
+    let build_log = local.store_build_log(&log_path, "foo", &Version::parse("1.0.0")?)?;
+
+    assert_eq!(
+        build_log.digest,
+        crev_common::blake2b256sum(b"Compiling foo v1.0.0\n")
+    );
+    assert!(build_log.path.starts_with("build-logs/foo/1.0.0-"));
+    assert_eq!(
+        std::fs::read_to_string(proofs_dir.join(&build_log.path))?,
+        "Compiling foo v1.0.0\n"
+    );
+    let repo = git2::Repository::open(&proofs_dir)?;
+    assert!(repo
+        .index()?
+        .get_path(Path::new(&build_log.path), 0)
+        .is_some());
+
+    Ok(())
+}
+
+#[test]
+fn future_dated_proofs_are_reported() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-future-dates")?;
+    let local = Local::auto_create_at(&dir.path().join("config"), &dir.path().join("cache"))?;
+    let a = OwnId::generate_for_git_url("https://a");
+    local.save_locked_id(&id::LockedId::from_own_id(&a, "")?)?;
+    local.save_current_id(a.as_ref())?;
+    let proofs_dir = local.get_proofs_dir_path()?;
+    std::fs::create_dir_all(&proofs_dir)?;
+
+    let b = OwnId::generate_for_git_url("https://b");
+    let mut trust = a.id.create_trust_proof(vec![&b.id], TrustLevel::High)?;
+    trust.date = crev_common::now() + chrono::Duration::days(3);
+    std::fs::write(
+        proofs_dir.join("trust.crev"),
+        crev_data::proof::Content::from(trust)
+            .sign_by(&a)?
+            .to_string(),
+    )?;
+
+    let (db, report) = local.load_db_with_report()?;
+    assert_eq!(db.unique_trust_proof_count(), 0);
+    assert_eq!(report.warnings.len(), 1);
+    assert_eq!(report.warnings[0].path, proofs_dir.join("trust.crev"));
+
+    let mut config = local.load_user_config()?;
+    config.clamp_future_dates = true;
+    local.store_user_config(&config)?;
+    let (db, report) = local.load_db_with_report()?;
+    assert_eq!(db.unique_trust_proof_count(), 1);
+    assert_eq!(report.warnings.len(), 1);
+
+    config.clamp_future_dates = false;
+    config.max_date_skew = Some(24 * 7);
+    local.store_user_config(&config)?;
+    let (db, report) = local.load_db_with_report()?;
+    assert_eq!(db.unique_trust_proof_count(), 1);
+    assert!(report.warnings.is_empty());
+
+    config.max_date_skew = Some(1 << 50);
+    local.store_user_config(&config)?;
+    assert!(local.load_db_with_report().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn sign_and_store_with_id_override() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-id-override")?;
+    let local = Local::auto_create_at(&dir.path().join("config"), &dir.path().join("cache"))?;
+    let a = OwnId::generate_for_git_url("https://a");
+    local.save_locked_id(&id::LockedId::from_own_id(&a, "")?)?;
+    local.save_current_id(a.as_ref())?;
+    let b = OwnId::generate_for_git_url("https://b");
+    local.save_locked_id(&id::LockedId::from_own_id(&b, "")?)?;
+    let b_dir = local.get_proofs_dir_path_for_url(&b.id.url)?;
+    git2::Repository::init(&b_dir)?;
+
+    let b_str = b.id.id.to_string();
+    local.set_id_override(Some(local.resolve_id_prefix(&b_str[..10])?));
+    assert_eq!(local.read_current_locked_id()?.url.url, "https://b");
+    assert_eq!(local.get_proofs_dir_path()?, b_dir);
+
+    let signer = local.read_current_unlocked_id(&|| Ok("".into()))?;
+    let proof = signer.create_signed_trust_proof(vec![a.as_ref()], TrustLevel::High)?;
+    local.insert(&proof)?;
+    let stored: Vec<_> = local.proofs_iter()?.collect();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].content.author_id(), b.id.id);
+
+    // The user config isn't changed
+    local.set_id_override(None);
+    assert_eq!(local.get_current_userid()?, a.id.id);
+    assert_eq!(local.proofs_iter()?.count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn resolve_id_prefix_rejects_ambiguity() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-id-prefix")?;
+    let local = Local::auto_create_at(&dir.path().join("config"), &dir.path().join("cache"))?;
+    let a = OwnId::generate_for_git_url("https://a");
+    local.save_locked_id(&id::LockedId::from_own_id(&a, "")?)?;
+    let a_str = a.id.id.to_string();
+    // Another id sharing the first character
+    let b = std::iter::repeat_with(|| OwnId::generate_for_git_url("https://b"))
+        .find(|b| b.id.id.to_string()[..1] == a_str[..1])
+        .expect("infinite");
+    local.save_locked_id(&id::LockedId::from_own_id(&b, "")?)?;
+
+    assert!(local.resolve_id_prefix(&a_str[..1]).is_err());
+    assert!(local.resolve_id_prefix("").is_err());
+    assert_eq!(local.resolve_id_prefix(&a_str)?, a.id.id);
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use super::*;
+
+use crate::{context::PackageVerification, CrevContext};
+use crev_data::{proof, TrustLevel};
+use std::{fs, path::Path};
+
+const SOURCE: &str = "SOURCE_ID";
+const NAME: &str = "name";
+
+fn build_review(
+    id: &OwnId,
+    version: &str,
+    digest: &[u8],
+    review: crev_data::Review,
+) -> Result<proof::Proof> {
+    id.as_pubid()
+        .create_package_review_proof(
+            package_info(SOURCE, NAME, version, digest),
+            review,
+            "".into(),
+        )?
+        .sign_by(id)
+}
+
+// Config dir with the current id `a`, which trusts `b` and `c`
+fn create_fixture_config(
+    root: &Path,
+    cache: &Path,
+    proofs: impl Fn(&OwnId, &OwnId, &OwnId) -> Result<Vec<proof::Proof>>,
+) -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let c = OwnId::generate_for_git_url("https://c");
+
+    let local = Local::auto_create_at(root, cache)?;
+    local.save_locked_id(&id::LockedId::from_own_id(&a, "")?)?;
+    local.save_current_id(a.as_ref())?;
+
+    let mut content = a
+        .create_signed_trust_proof(vec![b.as_pubid(), c.as_pubid()], TrustLevel::High)?
+        .to_string();
+    for proof in proofs(&a, &b, &c)? {
+        content.push_str(&proof.to_string());
+    }
+
+    let proofs_dir = local.get_proofs_dir_path()?;
+    fs::create_dir_all(&proofs_dir)?;
+    fs::write(proofs_dir.join("fixture.crev"), content)?;
+
+    Ok(())
+}
+
+fn requirements(redundancy: u64) -> VerificationRequirements {
+    VerificationRequirements {
+        trust_level: crev_data::Level::None,
+        understanding: crev_data::Level::None,
+        thoroughness: crev_data::Level::None,
+        redundancy,
+    }
+}
+
+#[test]
+fn context_verify_package() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-context")?;
+    let root = dir.path().join("config");
+    let cache = dir.path().join("cache");
+    let digest = vec![0; 32];
+
+    create_fixture_config(&root, &cache, |_a, b, c| {
+        Ok(vec![
+            build_review(b, "1.0.0", &digest, crev_data::Review::new_positive())?,
+            build_review(b, "1.1.0", &digest, crev_data::Review::new_positive())?,
+            build_review(c, "1.1.0", &digest, crev_data::Review::new_negative())?,
+        ])
+    })?;
+
+    let context = CrevContext::auto_open_at(&root, &cache, &default())?;
+    let verify = |version: &str, digest: Option<&Digest>, redundancy| {
+        context.verify_package(
+            SOURCE,
+            NAME,
+            &Version::parse(version).unwrap(),
+            digest,
+            &requirements(redundancy),
+        )
+    };
+
+    assert_eq!(verify("1.0.0", None, 1), PackageVerification::Verified);
+    assert_eq!(
+        verify("1.0.0", None, 3),
+        PackageVerification::Insufficient { reviews_needed: 2 }
+    );
+    assert_eq!(
+        verify("1.0.0", Some(&Digest::from_vec(digest.clone())), 1),
+        PackageVerification::Verified
+    );
+    assert_eq!(
+        verify("1.0.0", Some(&Digest::from_vec(vec![1; 32])), 1),
+        PackageVerification::Unknown
+    );
+    assert_eq!(verify("2.0.0", None, 1), PackageVerification::Unknown);
+
+    match verify("1.1.0", None, 1) {
+        PackageVerification::Flagged { by } => assert_eq!(by.len(), 1),
+        other => panic!("unexpected verification: {:?}", other),
+    }
+
+    // Cached results are the same
+    assert_eq!(verify("1.0.0", None, 1), PackageVerification::Verified);
+
+    Ok(())
+}
+
+#[test]
+fn context_requires_initialized_config() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-context")?;
+    let root = dir.path().join("config");
+    let cache = dir.path().join("cache");
+
+    assert!(CrevContext::auto_open_at(&root, &cache, &default()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn find_and_quarantine_broken_proof_files() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-context")?;
+    let root = dir.path().join("config");
+    let cache = dir.path().join("cache");
+
+    create_fixture_config(&root, &cache, |_a, b, _c| {
+        Ok(vec![build_review(
+            b,
+            "1.0.0",
+            &[0; 32],
+            crev_data::Review::new_positive(),
+        )?])
+    })?;
+
+    let local = Local::auto_open_at(&root, &cache)?;
+    assert!(local.find_broken_proof_files()?.is_empty());
+
+    let proofs_dir = local.get_proofs_dir_path()?;
+    let repo = git2::Repository::init(&proofs_dir)?;
+    let broken_path = proofs_dir.join("sub").join("broken.crev");
+    fs::create_dir_all(broken_path.parent().unwrap())?;
+    fs::write(&broken_path, "-----BEGIN CREV PROOF-----\ngarbage\n")?;
+
+    let broken = local.find_broken_proof_files()?;
+    assert_eq!(broken.len(), 1);
+    assert_eq!(broken[0].path, broken_path);
+
+    let new_path = local.quarantine_proof_file(&broken_path)?;
+    assert_eq!(
+        new_path,
+        proofs_dir.join(".broken").join("sub").join("broken.crev")
+    );
+    assert!(!broken_path.exists());
+    // Never committed with the rest of the proofs
+    assert!(repo.status_should_ignore(Path::new(".broken/sub/broken.crev"))?);
+    assert!(util::git::changed_paths(&repo)?
+        .iter()
+        .all(|path| !path.starts_with(".broken")));
+    assert!(local.find_broken_proof_files()?.is_empty());
+    assert_eq!(
+        local
+            .load_db()?
+            .get_pkg_reviews_for_name(SOURCE, NAME)
+            .count(),
+        1
+    );
+
+    Ok(())
+}
+
+#[test]
+fn list_all_known_ids_includes_untrusted() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-context")?;
+    let root = dir.path().join("config");
+    let cache = dir.path().join("cache");
+    create_fixture_config(&root, &cache, |_a, _b, _c| Ok(vec![]))?;
+
+    // Fetched repository of an id nobody trusts
+    let d = OwnId::generate_for_git_url("https://d");
+    let remote = cache.join("remotes").join("d");
+    fs::create_dir_all(&remote)?;
+    fs::write(
+        remote.join("d.crev"),
+        build_review(&d, "1.0.0", &[0; 32], crev_data::Review::new_positive())?.to_string(),
+    )?;
+
+    let local = Local::auto_open_at(&root, &cache)?;
+    let ids = local.list_all_known_ids()?;
+    assert_eq!(ids.len(), 4);
+    assert!(ids.contains(&(d.id.id.clone(), Some("https://d".into()))));
+
+    Ok(())
+}
@@ -0,0 +1,604 @@
+use super::*;
+
+use crev_data::proof;
+use std::{fs, path::Path, process::Command};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .expect("failed to execute git");
+    assert!(status.success());
+}
+
+fn build_review(id: &OwnId, name: &str) -> Result<proof::Proof> {
+    let package = package_info("SOURCE_ID", name, "1.0.0", &[0; 32]);
+    id.as_pubid()
+        .create_package_review_proof(package, crev_data::Review::new_positive(), "".into())?
+        .sign_by(id)
+}
+
+fn commit_proof(repo: &Path, file: &str, proof: &proof::Proof) -> Result<()> {
+    let path = repo.join(file);
+    let mut content = fs::read_to_string(&path).unwrap_or_default();
+    content.push_str(&proof.to_string());
+    fs::write(&path, content)?;
+    git(repo, &["add", file]);
+    git(repo, &["commit", "--quiet", "-m", file]);
+    Ok(())
+}
+
+fn create_local(root: &Path) -> Result<Local> {
+    let local = Local::auto_create_at(&root.join("config"), &root.join("cache"))?;
+    let id = OwnId::generate_for_git_url("https://local");
+    local.save_locked_id(&id::LockedId::from_own_id(&id, "")?)?;
+    local.save_current_id(id.as_ref())?;
+    Ok(local)
+}
+
+fn db_contents(local: &Local) -> Result<Vec<String>> {
+    let db = local.load_db()?;
+    let mut contents: Vec<_> = db
+        .get_package_reviews_for_package("SOURCE_ID", None, None)
+        .map(|review| format!("{} {}", review.package.name, review.from.id))
+        .collect();
+    contents.sort();
+    contents.push(db.unique_package_review_proof_count().to_string());
+    contents.push(format!("{:?}", db.all_author_ids()));
+    Ok(contents)
+}
+
+#[test]
+fn shallow_and_full_fetch_load_same_proofs() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-fetch")?;
+    let remote = dir.path().join("remote");
+    fs::create_dir_all(&remote)?;
+    git(&remote, &["init", "--quiet"]);
+
+    let a = OwnId::generate_for_git_url("https://a");
+    commit_proof(&remote, "a.crev", &build_review(&a, "one")?)?;
+    commit_proof(&remote, "a.crev", &build_review(&a, "two")?)?;
+    commit_proof(&remote, "b.crev", &build_review(&a, "three")?)?;
+    let url = format!("file://{}", remote.display());
+
+    let shallow = create_local(&dir.path().join("shallow"))?;
+    let full = create_local(&dir.path().join("full"))?;
+    full.set_fetch_full_history(true);
+
+    shallow.fetch_remote_git(&url)?;
+    full.fetch_remote_git(&url)?;
+    assert!(git2::Repository::open(shallow.get_remote_git_cache_path(&url))?.is_shallow());
+    assert!(!git2::Repository::open(full.get_remote_git_cache_path(&url))?.is_shallow());
+    assert_eq!(db_contents(&shallow)?, db_contents(&full)?);
+    assert_eq!(db_contents(&shallow)?.len(), 5);
+
+    // Updating existing clones of both kinds
+    commit_proof(&remote, "b.crev", &build_review(&a, "four")?)?;
+    shallow.fetch_remote_git(&url)?;
+    full.fetch_remote_git(&url)?;
+    assert_eq!(db_contents(&shallow)?, db_contents(&full)?);
+    assert_eq!(db_contents(&shallow)?.len(), 6);
+
+    Ok(())
+}
+
+#[test]
+fn commit_pull_and_push_without_git_binary() -> Result<()> {
+    use crate::util::git::{self as git2_ops, GitError};
+
+    let dir = tempdir::TempDir::new("crev-git")?;
+    let seed = dir.path().join("seed");
+    fs::create_dir_all(&seed)?;
+    git(&seed, &["init", "--quiet"]);
+    fs::write(seed.join("a.txt"), "1")?;
+    git(&seed, &["add", "a.txt"]);
+    git(&seed, &["commit", "--quiet", "-m", "seed"]);
+    git(
+        dir.path(),
+        &["clone", "--quiet", "--bare", "seed", "remote"],
+    );
+    let remote = dir.path().join("remote");
+    let remote_url = remote.to_str().unwrap();
+
+    let clone = |name: &str| -> Result<(std::path::PathBuf, git2::Repository)> {
+        let path = dir.path().join(name);
+        let repo = git2_ops::clone_git_repo(remote_url, &path)?;
+        git(&path, &["config", "user.name", "test"]);
+        git(&path, &["config", "user.email", "test@example.com"]);
+        Ok((path, repo))
+    };
+    let (a_path, a) = clone("a")?;
+    let (b_path, b) = clone("b")?;
+
+    assert!(git2_ops::changed_paths(&a)?.is_empty());
+    assert!(!git2_ops::commit_all(&a, "nothing")?);
+    fs::write(a_path.join("a.txt"), "2")?;
+    assert_eq!(
+        git2_ops::changed_paths(&a)?,
+        vec![std::path::PathBuf::from("a.txt")]
+    );
+    assert!(git2_ops::commit_all(&a, "change")?);
+    assert!(git2_ops::changed_paths(&a)?.is_empty());
+    git2_ops::push(&a)?;
+
+    git2_ops::pull(&b, |_| Ok("merge".to_owned()))?;
+    assert_eq!(fs::read_to_string(b_path.join("a.txt"))?, "2");
+
+    // Both change history, and `b` merges the changes of `a`
+    fs::write(a_path.join("b.txt"), "1")?;
+    let mut index = a.index()?;
+    index.add_path(std::path::Path::new("b.txt"))?;
+    index.write()?;
+    git2_ops::commit_all(&a, "add b")?;
+    git2_ops::push(&a)?;
+    fs::write(b_path.join("a.txt"), "3")?;
+    git2_ops::commit_all(&b, "change a")?;
+    git2_ops::pull(&b, |_| Ok("merge".to_owned()))?;
+    assert_eq!(fs::read_to_string(b_path.join("a.txt"))?, "3");
+    assert_eq!(fs::read_to_string(b_path.join("b.txt"))?, "1");
+    let merge = b.head()?.peel_to_commit()?;
+    assert_eq!((merge.parent_count(), merge.message()), (2, Some("merge")));
+    git2_ops::push(&b)?;
+    git2_ops::pull(&a, |_| Ok("merge".to_owned()))?;
+    assert_eq!(fs::read_to_string(a_path.join("a.txt"))?, "3");
+
+    // Both change the same file, so `b` can't merge
+    fs::write(a_path.join("a.txt"), "5")?;
+    git2_ops::commit_all(&a, "change a")?;
+    git2_ops::push(&a)?;
+    fs::write(b_path.join("a.txt"), "4")?;
+    git2_ops::commit_all(&b, "change b")?;
+    match git2_ops::pull(&b, |_| Ok("merge".to_owned())) {
+        Err(e) => match e.downcast_ref::<GitError>() {
+            Some(GitError::Diverged) => {}
+            _ => panic!("unexpected error: {}", e),
+        },
+        Ok(()) => panic!("conflicting history was merged"),
+    }
+    assert_eq!(fs::read_to_string(b_path.join("a.txt"))?, "4");
+
+    Ok(())
+}
+
+#[test]
+fn proof_dir_lock_serializes_inserts() -> Result<()> {
+    use std::{
+        sync::mpsc,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    let dir = tempdir::TempDir::new("crev-lock")?;
+    let root = dir.path().to_owned();
+    let local = create_local(&root)?;
+    git2::Repository::init(local.get_proofs_dir_path()?)?;
+    let open = |root: &Path| Local::auto_open_at(&root.join("config"), &root.join("cache"));
+
+    let a = OwnId::generate_for_git_url("https://a");
+    let first = build_review(&a, "first")?;
+    let second = build_review(&a, "second")?;
+
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let holder = {
+        let local = open(&root)?;
+        thread::spawn(move || -> Result<Instant> {
+            let _lock = local.lock_proof_dir()?;
+            local.insert(&first)?;
+            locked_tx.send(()).unwrap();
+            thread::sleep(Duration::from_millis(500));
+            Ok(Instant::now())
+        })
+    };
+    locked_rx.recv().unwrap();
+
+    let impatient = open(&root)?;
+    impatient.set_lock_timeout(Duration::from_millis(100));
+    let err = impatient.insert(&second).unwrap_err().to_string();
+    assert!(err.contains("Another crev process is running"), "{}", err);
+
+    let waiter = {
+        let local = open(&root)?;
+        thread::spawn(move || -> Result<Instant> {
+            local.insert(&second)?;
+            Ok(Instant::now())
+        })
+    };
+    let released = holder.join().unwrap()?;
+    let inserted = waiter.join().unwrap()?;
+    assert!(released <= inserted);
+    assert_eq!(local.load_db()?.unique_package_review_proof_count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn fetch_quarantines_forged_proofs() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-fetch-verify")?;
+    let remote = dir.path().join("remote");
+    fs::create_dir_all(&remote)?;
+    git(&remote, &["init", "--quiet"]);
+
+    let a = OwnId::generate_for_git_url("https://a");
+    commit_proof(&remote, "a.crev", &build_review(&a, "one")?)?;
+    let forged = build_review(&a, "two")?
+        .to_string()
+        .replace("name: two", "name: evil");
+    assert!(forged.contains("name: evil"));
+    fs::create_dir_all(remote.join("sub"))?;
+    fs::write(remote.join("sub").join("forged.crev"), forged)?;
+    git(&remote, &["add", "sub/forged.crev"]);
+    git(&remote, &["commit", "--quiet", "-m", "forged"]);
+    let url = format!("file://{}", remote.display());
+
+    let local = create_local(dir.path())?;
+    local.set_verify_fetched_signatures(true);
+    local.fetch_url(&url)?;
+
+    let cache_dir = local.get_remote_git_cache_path(&url);
+    assert!(cache_dir.join("a.crev").exists());
+    assert!(!cache_dir.join("sub").join("forged.crev").exists());
+    assert!(cache_dir
+        .join(crate::local::QUARANTINE_DIR)
+        .join("sub")
+        .join("forged.crev")
+        .exists());
+    // Not even loaded to be skipped anymore
+    assert!(!local
+        .load_db_with_report()?
+        .1
+        .warnings
+        .iter()
+        .any(|warning| warning.path.ends_with("forged.crev")));
+    assert_eq!(db_contents(&local)?[0], format!("one {}", a.id.id));
+
+    Ok(())
+}
+
+fn commit_signed_proof(
+    repo: &Path,
+    file: &str,
+    proof: &proof::Proof,
+    signer: &OwnId,
+) -> Result<()> {
+    let path = repo.join(file);
+    fs::write(path, proof.to_string())?;
+    git(repo, &["add", file]);
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .arg("write-tree")
+        .output()
+        .expect("failed to execute git");
+    let tree_id = String::from_utf8(output.stdout)?;
+    let msg = signer.sign_commit_message(file, tree_id.trim());
+    git(repo, &["commit", "--quiet", "-m", &msg]);
+    Ok(())
+}
+
+#[test]
+fn fetch_requiring_signed_commits() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-fetch-signed")?;
+    let remote = dir.path().join("remote");
+    fs::create_dir_all(&remote)?;
+    git(&remote, &["init", "--quiet"]);
+    let url = format!("file://{}", remote.display());
+
+    let a = OwnId::generate_for_git_url(&url);
+    let b = OwnId::generate_for_git_url("https://b");
+    commit_signed_proof(&remote, "a.crev", &build_review(&a, "one")?, &a)?;
+
+    // Without any known owner, nothing vouches for the commits
+    let local = create_local(dir.path())?;
+    local.set_require_signed_commits(true);
+    local.fetch_url(&url)?;
+    let cache_dir = local.get_remote_git_cache_path(&url);
+    assert!(!cache_dir.exists());
+
+    // The owner is known from the local trust proof
+    let local_id = local.read_current_unlocked_id(&|| Ok("".into()))?;
+    git2::Repository::init(local.get_proofs_dir_path()?)?;
+    local.insert(&local_id.create_signed_trust_proof(vec![a.as_pubid()], TrustLevel::High)?)?;
+    local.fetch_url(&url)?;
+    assert!(cache_dir.join("a.crev").exists());
+    assert_eq!(db_contents(&local)?[0], format!("one {}", a.id.id));
+
+    let report = crate::local::check_commit_signatures(&cache_dir, &[a.id.id.clone()], None)?;
+    assert!(report.is_fully_signed());
+    assert_eq!(report.signed, 1);
+    let report = crate::local::check_commit_signatures(&cache_dir, &[b.id.id.clone()], None)?;
+    assert_eq!(report.invalid, 1);
+    assert!(report.is_suspicious());
+
+    // Only the fetched commits are checked
+    let head = git2::Repository::open(&cache_dir)?
+        .head()?
+        .peel_to_commit()?
+        .id();
+    commit_signed_proof(&remote, "a.crev", &build_review(&a, "two")?, &a)?;
+    local.fetch_url(&url)?;
+    assert!(cache_dir.exists());
+    let report = crate::local::check_commit_signatures(&cache_dir, &[a.id.id.clone()], Some(head))?;
+    assert_eq!((report.signed, report.invalid), (1, 0));
+
+    // Signed by another id claiming the repository, in a proof of the repository itself
+    let c = OwnId::generate_for_git_url(&url);
+    commit_signed_proof(&remote, "c.crev", &build_review(&c, "three")?, &c)?;
+    local.fetch_url(&url)?;
+    assert!(!cache_dir.exists());
+
+    // Signed by someone other than the owner
+    commit_signed_proof(&remote, "b.crev", &build_review(&a, "four")?, &b)?;
+    local.fetch_url(&url)?;
+    assert!(!cache_dir.exists());
+    assert_eq!(db_contents(&local)?[0], "0");
+
+    // Accepted when not required; with full history all the commits are checked
+    local.set_require_signed_commits(false);
+    local.set_fetch_full_history(true);
+    local.fetch_url(&url)?;
+    assert!(cache_dir.join("b.crev").exists());
+    let report = crate::local::check_commit_signatures(&cache_dir, &[a.id.id.clone()], None)?;
+    assert_eq!((report.signed, report.invalid), (2, 2));
+
+    Ok(())
+}
+
+/// A `Local` that knows `owner` from its own trust proof
+fn create_local_trusting(root: &Path, owner: &OwnId) -> Result<Local> {
+    let local = create_local(root)?;
+    let local_id = local.read_current_unlocked_id(&|| Ok("".into()))?;
+    git2::Repository::init(local.get_proofs_dir_path()?)?;
+    local.insert(&local_id.create_signed_trust_proof(vec![owner.as_pubid()], TrustLevel::High)?)?;
+    Ok(local)
+}
+
+#[test]
+fn cached_unsigned_history_does_not_satisfy_strict_fetch() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-fetch-cached-unsigned")?;
+    let remote = dir.path().join("remote");
+    fs::create_dir_all(&remote)?;
+    git(&remote, &["init", "--quiet"]);
+    let url = format!("file://{}", remote.display());
+
+    let a = OwnId::generate_for_git_url(&url);
+    commit_proof(&remote, "a.crev", &build_review(&a, "one")?)?;
+
+    let local = create_local_trusting(dir.path(), &a)?;
+    local.fetch_url(&url)?;
+    let cache_dir = local.get_remote_git_cache_path(&url);
+    assert!(cache_dir.join("a.crev").exists());
+
+    // Nothing new is fetched, but nothing in the cache is signed either
+    let head = git2::Repository::open(&cache_dir)?
+        .head()?
+        .peel_to_commit()?
+        .id();
+    let report = crate::local::check_commit_signatures(&cache_dir, &[a.id.id.clone()], Some(head))?;
+    assert_eq!((report.signed, report.unsigned), (0, 0));
+    assert!(!report.is_fully_signed());
+    local.set_require_signed_commits(true);
+    local.fetch_url(&url)?;
+    assert!(!cache_dir.exists());
+
+    // Signing the latest commit vouches for it; the older ones are tolerated
+    local.set_require_signed_commits(false);
+    local.fetch_url(&url)?;
+    local.set_require_signed_commits(true);
+    commit_signed_proof(&remote, "b.crev", &build_review(&a, "two")?, &a)?;
+    local.fetch_url(&url)?;
+    assert!(cache_dir.join("b.crev").exists());
+
+    // But not once it stopped signing
+    commit_proof(&remote, "c.crev", &build_review(&a, "three")?)?;
+    local.fetch_url(&url)?;
+    assert!(!cache_dir.exists());
+    Ok(())
+}
+
+#[test]
+fn unsigned_merge_parents_are_checked() -> Result<()> {
+    let dir = tempdir::TempDir::new("crev-fetch-merge")?;
+    let remote = dir.path().join("remote");
+    fs::create_dir_all(&remote)?;
+    git(&remote, &["init", "--quiet"]);
+    let url = format!("file://{}", remote.display());
+
+    let a = OwnId::generate_for_git_url(&url);
+    commit_signed_proof(&remote, "a.crev", &build_review(&a, "one")?, &a)?;
+    git(&remote, &["checkout", "--quiet", "-b", "side"]);
+    commit_proof(&remote, "b.crev", &build_review(&a, "two")?)?;
+    git(&remote, &["checkout", "--quiet", "-"]);
+    commit_signed_proof(&remote, "c.crev", &build_review(&a, "three")?, &a)?;
+    // A merge signed by the owner, with an unsigned second parent
+    git(&remote, &["merge", "--quiet", "--no-commit", "side"]);
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(&remote)
+        .arg("write-tree")
+        .output()
+        .expect("failed to execute git");
+    let tree_id = String::from_utf8(output.stdout)?;
+    let msg = a.sign_commit_message("merge", tree_id.trim());
+    git(&remote, &["commit", "--quiet", "-m", &msg]);
+
+    let local = create_local_trusting(dir.path(), &a)?;
+    local.set_fetch_full_history(true);
+    local.set_require_signed_commits(true);
+    local.fetch_url(&url)?;
+    let cache_dir = local.get_remote_git_cache_path(&url);
+    assert!(!cache_dir.exists());
+
+    local.set_require_signed_commits(false);
+    local.fetch_url(&url)?;
+    let report = crate::local::check_commit_signatures(&cache_dir, &[a.id.id.clone()], None)?;
+    assert_eq!((report.signed, report.unsigned), (3, 1));
+    assert!(!report.is_fully_signed());
+    assert!(report.is_suspicious());
+    Ok(())
+}
+
+/// Serve `files` over HTTP, with an `ETag` of their content, counting full responses
+fn serve_http(
+    files: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, String>>>,
+    full_responses: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+) -> Result<u16> {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let path = request_line.split(' ').nth(1).unwrap_or("").to_owned();
+            let mut if_none_match = None;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                let mut parts = line.splitn(2, ':');
+                let name = parts.next().unwrap().trim().to_lowercase();
+                if name == "if-none-match" {
+                    if_none_match = parts.next().map(|value| value.trim().to_owned());
+                }
+            }
+            let file = files.lock().unwrap().get(&path).cloned();
+            let response = match file {
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_owned(),
+                Some(body) => {
+                    let etag = format!(
+                        "\"{}\"",
+                        crev_common::base64_encode(&crev_common::blake2b256sum(body.as_bytes()))
+                    );
+                    if if_none_match.as_ref() == Some(&etag) {
+                        "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_owned()
+                    } else {
+                        full_responses.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        format!(
+                            "HTTP/1.1 200 OK\r\nETag: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            etag,
+                            body.len(),
+                            body
+                        )
+                    }
+                }
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    Ok(port)
+}
+
+#[test]
+fn https_proof_repos_are_fetched_with_conditional_gets() -> Result<()> {
+    use crate::local::{parse_https_manifest, ProofRepoKind, QUARANTINE_DIR};
+    use std::sync::{atomic::Ordering, Arc, Mutex};
+
+    assert_eq!(
+        ProofRepoKind::from_url("https://github.com/dpc/crev-proofs"),
+        Some(ProofRepoKind::Git)
+    );
+    assert_eq!(
+        ProofRepoKind::from_url("git@example.com:proofs"),
+        Some(ProofRepoKind::Git)
+    );
+    assert_eq!(
+        ProofRepoKind::from_url("https://example.com/all.crev"),
+        Some(ProofRepoKind::HttpsBundle)
+    );
+    assert_eq!(
+        ProofRepoKind::from_url("https://example.com/proofs/crev-manifest.txt"),
+        Some(ProofRepoKind::HttpsDir("https://example.com/proofs".into()))
+    );
+    assert_eq!(ProofRepoKind::from_url("https://example.com/proofs/"), None);
+    assert_eq!(
+        parse_https_manifest("# proofs\n\na.crev\n  sub/b.crev \n")?,
+        ["a.crev", "sub/b.crev"]
+    );
+    assert!(parse_https_manifest("../a.crev").is_err());
+    assert!(parse_https_manifest("/a.crev").is_err());
+    assert!(parse_https_manifest("a.txt").is_err());
+
+    let a = OwnId::generate_for_git_url("https://a");
+    let files = Arc::new(Mutex::new(std::collections::HashMap::new()));
+    let full_responses = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let publish = |path: &str, content: String| {
+        files.lock().unwrap().insert(path.to_owned(), content);
+    };
+    publish("/proofs/crev-manifest.txt", "a.crev\nsub/b.crev\n".into());
+    publish("/proofs/a.crev", build_review(&a, "one")?.to_string());
+    publish("/proofs/sub/b.crev", build_review(&a, "two")?.to_string());
+    publish("/all.crev", build_review(&a, "bundled")?.to_string());
+    let port = serve_http(files.clone(), full_responses.clone())?;
+    let url = format!("http://127.0.0.1:{}/proofs", port);
+
+    let dir = tempdir::TempDir::new("crev-https")?;
+    let local = create_local(dir.path())?;
+    assert_eq!(
+        local.proof_repo_kind(&url)?,
+        ProofRepoKind::HttpsDir(url.clone())
+    );
+    // Only a missing manifest means a git repository
+    assert_eq!(
+        local.proof_repo_kind(&format!("http://127.0.0.1:{}/git", port))?,
+        ProofRepoKind::Git
+    );
+    publish("/broken/crev-manifest.txt", "../a.crev\n".into());
+    assert!(local
+        .proof_repo_kind(&format!("http://127.0.0.1:{}/broken", port))
+        .is_err());
+    let closed_port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    assert!(local
+        .proof_repo_kind(&format!("http://127.0.0.1:{}/proofs", closed_port))
+        .is_err());
+    let fetch = |url: &str| {
+        let mut db = ProofDB::new();
+        local.fetch_proof_repo_import_and_print_counts(url, None, &mut db)
+    };
+    assert!(fetch(&url).is_some());
+    let names = |local: &Local| -> Result<Vec<String>> {
+        let mut contents = db_contents(local)?;
+        contents.truncate(contents.len() - 2);
+        Ok(contents
+            .into_iter()
+            .map(|line| line.split(' ').next().unwrap().to_owned())
+            .collect())
+    };
+    assert_eq!(names(&local)?, ["one", "two"]);
+    let downloaded = full_responses.load(Ordering::SeqCst);
+
+    // Nothing changed, nothing downloaded
+    assert!(fetch(&url).is_some());
+    assert_eq!(full_responses.load(Ordering::SeqCst), downloaded);
+
+    // Unpublished files are removed, and broken ones quarantined
+    let mut forged = build_review(&a, "three")?.to_string();
+    forged = forged.replace("three", "thref");
+    publish("/proofs/crev-manifest.txt", "a.crev\nc.crev\n".into());
+    publish("/proofs/c.crev", forged);
+    assert!(fetch(&url).is_some());
+    assert_eq!(full_responses.load(Ordering::SeqCst), downloaded + 2);
+    assert_eq!(names(&local)?, ["one"]);
+    let cache = local.get_remote_git_cache_path(&url);
+    assert!(!cache.join("sub/b.crev").exists());
+    assert!(cache.join(QUARANTINE_DIR).join("c.crev").exists());
+
+    let bundle_url = format!("http://127.0.0.1:{}/all.crev", port);
+    assert!(fetch(&bundle_url).is_some());
+    assert_eq!(names(&local)?, ["bundled", "one"]);
+
+    // Without commits, signed commits can't be required
+    local.set_require_signed_commits(true);
+    assert!(fetch(&url).is_none());
+    Ok(())
+}
@@ -1,8 +1,8 @@
 use super::*;
 
 use crev_data::review::{Advisory, Issue, VersionRange};
-use crev_data::TrustLevel;
 use crev_data::{proof, OwnId};
+use crev_data::{Level, TrustLevel};
 use ifmt::iformat;
 use semver::Version;
 
@@ -31,16 +31,7 @@ fn build_proof_with_advisories(
     version: Version,
     advisories: Vec<Advisory>,
 ) -> proof::Proof {
-    let package_info = proof::PackageInfo {
-        id: None,
-        source: "SOURCE_ID".to_owned(),
-        name: NAME.into(),
-        version: version,
-        digest: vec![0, 1, 2, 3],
-        digest_type: proof::default_digest_type(),
-        revision: "".into(),
-        revision_type: proof::default_revision_type(),
-    };
+    let package_info = package_info("SOURCE_ID", NAME, &version.to_string(), &[0, 1, 2, 3]);
     let review = proof::review::PackageBuilder::default()
         .from(id.id.to_owned())
         .package(package_info.clone())
@@ -55,16 +46,7 @@ fn build_proof_with_advisories(
 }
 
 fn build_proof_with_issues(id: &OwnId, version: Version, issues: Vec<Issue>) -> proof::Proof {
-    let package_info = proof::PackageInfo {
-        id: None,
-        source: "SOURCE_ID".to_owned(),
-        name: NAME.into(),
-        version: version,
-        digest: vec![0, 1, 2, 3],
-        digest_type: proof::default_digest_type(),
-        revision: "".into(),
-        revision_type: proof::default_revision_type(),
-    };
+    let package_info = package_info("SOURCE_ID", NAME, &version.to_string(), &[0, 1, 2, 3]);
     let review = proof::review::PackageBuilder::default()
         .from(id.id.to_owned())
         .package(package_info.clone())
@@ -331,3 +313,79 @@ fn issues_sanity() -> Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn issues_with_affected_versions() -> Result<()> {
+    let id = OwnId::generate_for_git_url("https://a");
+    let mut trustdb = ProofDB::new();
+    let trust_set = trustdb.calculate_trust_set(id.as_ref(), &TrustDistanceParams::new_no_wot());
+
+    let mut issue = build_issue("issueX");
+    issue.affected = Some(semver::VersionReq::parse("< 0.3.5").unwrap());
+    let proof = build_proof_with_issues(&id, Version::parse("0.3.5").unwrap(), vec![issue]);
+    trustdb.import_from_iter(vec![proof].into_iter());
+
+    let open_issues_count = |version: &str| {
+        trustdb
+            .get_open_issues_for_version(
+                SOURCE,
+                NAME,
+                &Version::parse(version).unwrap(),
+                &trust_set,
+                TrustLevel::Medium,
+            )
+            .len()
+    };
+
+    // reported in a later version, but affecting the earlier ones
+    assert_eq!(open_issues_count("0.3.0"), 1);
+    assert_eq!(open_issues_count("0.1.0"), 1);
+    assert_eq!(open_issues_count("0.3.5"), 0);
+    assert_eq!(open_issues_count("0.4.0"), 0);
+    Ok(())
+}
+
+#[test]
+fn open_issues_have_highest_reported_severity() -> Result<()> {
+    let a = OwnId::generate_for_git_url("https://a");
+    let b = OwnId::generate_for_git_url("https://b");
+    let mut trustdb = ProofDB::new();
+    trustdb.import_from_iter(
+        vec![a
+            .create_signed_trust_proof(vec![b.as_pubid()], TrustLevel::High)
+            .unwrap()]
+        .into_iter(),
+    );
+    trustdb.import_from_iter(
+        vec![
+            build_proof_with_issues(
+                &a,
+                Version::parse("1.0.0").unwrap(),
+                vec![Issue::new_with_severity("RUSTSEC-1".into(), Level::Low)],
+            ),
+            build_proof_with_issues(
+                &b,
+                Version::parse("1.0.0").unwrap(),
+                vec![
+                    Issue::new_with_severity("RUSTSEC-1".into(), Level::High),
+                    build_issue("other"),
+                ],
+            ),
+        ]
+        .into_iter(),
+    );
+    let trust_set = trustdb.calculate_trust_set(a.as_ref(), &Default::default());
+
+    let issues = trustdb.get_open_issues_for_version(
+        SOURCE,
+        NAME,
+        &Version::parse("1.0.0").unwrap(),
+        &trust_set,
+        TrustLevel::None,
+    );
+    assert_eq!(issues.len(), 2);
+    assert_eq!(issues["RUSTSEC-1"].severity, Level::High);
+    assert_eq!(issues["other"].severity, Level::Medium);
+
+    Ok(())
+}
@@ -1,4 +1,26 @@
 use crate::prelude::*;
+use failure::{bail, format_err, Fail};
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Git operation that needs to be finished by hand
+#[derive(Fail, Debug)]
+pub enum GitError {
+    #[fail(
+        display = "Branch `{}` has no upstream branch to pull from or push to",
+        _0
+    )]
+    NoUpstream(String),
+    #[fail(
+        display = "Local and remote history have conflicting changes; merge or rebase by hand, eg. with `cargo crev git pull --rebase`"
+    )]
+    Diverged,
+    #[fail(display = "Push of `{}` rejected: {}", _0, _1)]
+    PushRejected(String, String),
+}
 
 #[derive(PartialEq, Debug, Default)]
 pub struct GitUrlComponents {
@@ -33,8 +55,217 @@ pub fn parse_git_url_https(http_url: &str) -> Option<GitUrlComponents> {
     })
 }
 
+/// Callbacks authenticating with the ssh agent, or git credential helpers
+pub fn remote_callbacks<'a>() -> git2::RemoteCallbacks<'a> {
+    let mut callbacks = git2::RemoteCallbacks::new();
+    let mut tried_ssh_agent = false;
+    let mut tried_credential_helper = false;
+    callbacks.credentials(move |url, username, allowed| {
+        if allowed.contains(git2::CredentialType::SSH_KEY) && !tried_ssh_agent {
+            tried_ssh_agent = true;
+            return git2::Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        if allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT) && !tried_credential_helper {
+            tried_credential_helper = true;
+            let config = git2::Config::open_default()?;
+            return git2::Cred::credential_helper(&config, url, username);
+        }
+        if allowed.contains(git2::CredentialType::DEFAULT) {
+            return git2::Cred::default();
+        }
+        Err(git2::Error::from_str("no usable credentials"))
+    });
+    callbacks
+}
+
+pub fn fetch_options<'a>() -> git2::FetchOptions<'a> {
+    let mut options = git2::FetchOptions::new();
+    options.remote_callbacks(remote_callbacks());
+    options
+}
+
+/// Clone `url` into `dir`, authenticating if needed
+pub fn clone_git_repo(url: &str, dir: &Path) -> Result<git2::Repository> {
+    Ok(git2::build::RepoBuilder::new()
+        .fetch_options(fetch_options())
+        .clone(url, dir)?)
+}
+
+/// Tracked files with changes in the working directory or the index, compared to `HEAD`
+pub fn changed_paths(repo: &git2::Repository) -> Result<Vec<PathBuf>> {
+    let mut status_opts = git2::StatusOptions::new();
+    status_opts.include_untracked(false);
+    let mut paths = vec![];
+    for entry in repo.statuses(Some(&mut status_opts))?.iter() {
+        if entry.status() != git2::Status::CURRENT {
+            if let Some(path) = entry.path() {
+                paths.push(PathBuf::from(path));
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// Make git ignore `pattern` in `repo`, without touching any tracked `.gitignore`
+pub fn exclude_locally(repo: &git2::Repository, pattern: &str) -> Result<()> {
+    let path = repo.path().join("info").join("exclude");
+    let mut exclude = match std::fs::read_to_string(&path) {
+        Ok(exclude) => exclude,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    if exclude.lines().any(|line| line == pattern) {
+        return Ok(());
+    }
+    if !exclude.is_empty() && !exclude.ends_with('\n') {
+        exclude.push('\n');
+    }
+    exclude.push_str(pattern);
+    exclude.push('\n');
+    std::fs::create_dir_all(path.parent().expect("Not a root dir"))?;
+    std::fs::write(&path, exclude)?;
+    Ok(())
+}
+
+/// Commit all changes to tracked files, like `git commit -a`
+///
+/// Returns `false` if there was nothing to commit.
+pub fn commit_all(repo: &git2::Repository, commit_msg: &str) -> Result<bool> {
+    commit_all_with(repo, |_tree_id| commit_msg.to_owned())
+}
+
+/// Like `commit_all`, with the commit message made for the id of the committed tree
+pub fn commit_all_with(
+    repo: &git2::Repository,
+    commit_msg: impl FnOnce(git2::Oid) -> String,
+) -> Result<bool> {
+    let mut index = repo.index()?;
+    index.update_all(["*"].iter(), None)?;
+    index.write()?;
+    let tree_id = index.write_tree()?;
+    let head = repo.head()?.peel_to_commit()?;
+    if head.tree_id() == tree_id {
+        return Ok(false);
+    }
+    let tree = repo.find_tree(tree_id)?;
+    let signature = repo.signature()?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &commit_msg(tree_id),
+        &tree,
+        &[&head],
+    )?;
+    Ok(true)
+}
+
+/// Current branch: its name, reference name and upstream remote name
+fn current_branch_and_remote(repo: &git2::Repository) -> Result<(String, String, String)> {
+    let head = repo.head()?;
+    let refname = head
+        .name()
+        .ok_or_else(|| format_err!("Branch name is not valid UTF-8"))?
+        .to_owned();
+    let name = head
+        .shorthand()
+        .ok_or_else(|| format_err!("Branch name is not valid UTF-8"))?
+        .to_owned();
+    let remote = repo
+        .config()?
+        .get_string(&format!("branch.{}.remote", name))
+        .map_err(|_| GitError::NoUpstream(name.clone()))?;
+    Ok((name, refname, remote))
+}
+
+/// Fetch the upstream of the current branch and merge it, like `git pull`
+///
+/// The current branch is fast-forwarded if possible. Otherwise a merge
+/// commit is made, with the message `merge_msg` makes for the id of the
+/// merged tree. Fails with `GitError::Diverged` if the changes conflict.
+pub fn pull(
+    repo: &git2::Repository,
+    merge_msg: impl FnOnce(git2::Oid) -> Result<String>,
+) -> Result<()> {
+    let (name, _refname, remote) = current_branch_and_remote(repo)?;
+    repo.find_remote(&remote)?
+        .fetch(&[] as &[&str], Some(&mut fetch_options()), None)?;
+
+    let branch = repo.find_branch(&name, git2::BranchType::Local)?;
+    let upstream = branch
+        .upstream()
+        .map_err(|_| GitError::NoUpstream(name.clone()))?;
+    let upstream_commit = repo.reference_to_annotated_commit(upstream.get())?;
+    let (analysis, _) = repo.merge_analysis(&[&upstream_commit])?;
+    if analysis.is_up_to_date() {
+        return Ok(());
+    }
+    if analysis.is_fast_forward() {
+        let target = repo.find_object(upstream_commit.id(), None)?;
+        repo.checkout_tree(&target, Some(git2::build::CheckoutBuilder::new().safe()))?;
+        repo.head()?
+            .set_target(upstream_commit.id(), "crev: fast-forward")?;
+        return Ok(());
+    }
+
+    let head = repo.head()?.peel_to_commit()?;
+    let theirs = repo.find_commit(upstream_commit.id())?;
+    let mut index = repo.merge_commits(&head, &theirs, None)?;
+    if index.has_conflicts() {
+        return Err(GitError::Diverged.into());
+    }
+    let tree_id = index.write_tree_to(repo)?;
+    let tree = repo.find_tree(tree_id)?;
+    let signature = repo.signature()?;
+    let merge_id = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &merge_msg(tree_id)?,
+        &tree,
+        &[&head, &theirs],
+    )?;
+    repo.checkout_tree(
+        tree.as_object(),
+        Some(git2::build::CheckoutBuilder::new().safe()),
+    )?;
+    repo.head()?.set_target(merge_id, "crev: merge")?;
+    Ok(())
+}
+
+/// Push the current branch to its upstream
+pub fn push(repo: &git2::Repository) -> Result<()> {
+    let (name, refname, remote) = current_branch_and_remote(repo)?;
+    let merge_ref = repo
+        .config()?
+        .get_string(&format!("branch.{}.merge", name))
+        .unwrap_or_else(|_| refname.clone());
+
+    let mut rejected = None;
+    {
+        let mut callbacks = remote_callbacks();
+        callbacks.push_update_reference(|refname, status| {
+            if let Some(status) = status {
+                rejected = Some((refname.to_owned(), status.to_owned()));
+            }
+            Ok(())
+        });
+        let mut options = git2::PushOptions::new();
+        options.remote_callbacks(callbacks);
+        repo.find_remote(&remote)?.push(
+            &[format!("{}:{}", refname, merge_ref).as_str()],
+            Some(&mut options),
+        )?;
+    }
+    if let Some((refname, status)) = rejected {
+        return Err(GitError::PushRejected(refname, status).into());
+    }
+    Ok(())
+}
+
 pub fn fetch_and_checkout_git_repo(repo: &git2::Repository) -> Result<()> {
-    repo.find_remote("origin")?.fetch(&["master"], None, None)?;
+    repo.find_remote("origin")?
+        .fetch(&["master"], Some(&mut fetch_options()), None)?;
     repo.set_head("FETCH_HEAD")?;
     let mut opts = git2::build::CheckoutBuilder::new();
     opts.force();
@@ -42,6 +273,276 @@ pub fn fetch_and_checkout_git_repo(repo: &git2::Repository) -> Result<()> {
     Ok(())
 }
 
+fn run_git_cmd<I, S>(args: I) -> Result<()>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    run_git_cmd_output(args)?;
+    Ok(())
+}
+
+/// Run `git`, returning its standard output
+fn run_git_cmd_output<I, S>(args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        bail!(
+            "git failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Tags of the remote repository at `url`, with the commit they point to
+pub fn list_remote_tags(url: &str) -> Result<Vec<(String, String)>> {
+    let output = run_git_cmd_output(vec!["ls-remote", "--tags", "--", url])?;
+    Ok(parse_ls_remote_tags(&output))
+}
+
+/// Parse `git ls-remote --tags` output
+///
+/// Annotated tags are listed twice: the tag object itself, and peeled
+/// (`^{}`) to the commit it points to. Only the commit is kept.
+fn parse_ls_remote_tags(output: &str) -> Vec<(String, String)> {
+    let mut tags: Vec<(String, String)> = vec![];
+    for line in output.lines() {
+        let mut parts = line.split_whitespace();
+        let (sha, tag) = match (
+            parts.next(),
+            parts
+                .next()
+                .and_then(|name| name.strip_prefix("refs/tags/")),
+        ) {
+            (Some(sha), Some(tag)) => (sha, tag),
+            _ => continue,
+        };
+        if let Some(tag) = tag.strip_suffix("^{}") {
+            if let Some(existing) = tags.iter_mut().find(|(name, _)| name == tag) {
+                existing.1 = sha.to_owned();
+                continue;
+            }
+            tags.push((tag.to_owned(), sha.to_owned()));
+        } else if !tags.iter().any(|(name, _)| name == tag) {
+            tags.push((tag.to_owned(), sha.to_owned()));
+        }
+    }
+    tags
+}
+
+/// Find the tag of a released crate version among `tags`
+///
+/// Tries the naming schemes in common use, eg. `v1.2.3`, `1.2.3`
+/// and `name-v1.2.3` (for workspaces with many crates), then any other
+/// tag of the version that isn't named after a different crate, eg.
+/// `release-1.2.3` or `name@1.2.3`.
+pub fn find_version_tag<'a>(
+    tags: &'a [(String, String)],
+    name: &str,
+    version: &semver::Version,
+) -> Option<&'a (String, String)> {
+    let candidates = [
+        format!("v{}", version),
+        format!("{}", version),
+        format!("{}-v{}", name, version),
+        format!("{}-{}", name, version),
+        format!("{}/v{}", name, version),
+        format!("{}/{}", name, version),
+        format!("{}_v{}", name, version),
+        format!("{}_{}", name, version),
+    ];
+    let version = version.to_string();
+    // What's before the version, without separators, must be nothing,
+    // the crate name (possibly under a directory) or a release keyword
+    let is_version_prefix = |prefix: &str| {
+        let prefix = prefix.trim_end_matches(|c: char| "-_/@ ".contains(c));
+        prefix.is_empty()
+            || prefix.rsplit('/').next() == Some(name)
+            || ["release", "rel", "version"].contains(&prefix.to_lowercase().as_str())
+    };
+    candidates
+        .iter()
+        .filter_map(|candidate| tags.iter().find(|(tag, _)| tag == candidate))
+        .next()
+        .or_else(|| {
+            tags.iter().find(|(tag, _)| {
+                let prefix = match tag.strip_suffix(&version) {
+                    Some(prefix) => prefix,
+                    None => return false,
+                };
+                // `11.2.3` is not version `1.2.3`
+                if prefix.ends_with(|c: char| c.is_ascii_digit() || c == '.') {
+                    return false;
+                }
+                is_version_prefix(prefix)
+                    || prefix.strip_suffix('v').map_or(false, is_version_prefix)
+            })
+        })
+}
+
+#[test]
+fn find_version_tag_test() {
+    let tags = parse_ls_remote_tags(
+        "aaaa\trefs/tags/v0.1.0\n\
+         bbbb\trefs/tags/v0.2.0\n\
+         cccc\trefs/tags/v0.2.0^{}\n\
+         dddd\trefs/tags/foo-v0.3.0\n\
+         eeee\trefs/heads/master\n\
+         ffff\trefs/tags/release-0.4.0\n\
+         gggg\trefs/tags/crates/foo@0.5.0\n\
+         hhhh\trefs/tags/foo_bar-0.6.0\n\
+         iiii\trefs/tags/foo-v10.7.0\n",
+    );
+    assert_eq!(tags.len(), 7);
+    let version = |v| semver::Version::parse(v).unwrap();
+
+    assert_eq!(
+        find_version_tag(&tags, "foo", &version("0.1.0")),
+        Some(&("v0.1.0".to_owned(), "aaaa".to_owned()))
+    );
+    assert_eq!(
+        find_version_tag(&tags, "foo", &version("0.2.0")),
+        Some(&("v0.2.0".to_owned(), "cccc".to_owned()))
+    );
+    assert_eq!(
+        find_version_tag(&tags, "foo", &version("0.3.0")),
+        Some(&("foo-v0.3.0".to_owned(), "dddd".to_owned()))
+    );
+    assert_eq!(find_version_tag(&tags, "bar", &version("0.3.0")), None);
+    assert_eq!(
+        find_version_tag(&tags, "bar", &version("0.4.0")),
+        Some(&("release-0.4.0".to_owned(), "ffff".to_owned()))
+    );
+    assert_eq!(
+        find_version_tag(&tags, "foo", &version("0.5.0")),
+        Some(&("crates/foo@0.5.0".to_owned(), "gggg".to_owned()))
+    );
+    assert_eq!(find_version_tag(&tags, "foo", &version("0.1.1")), None);
+    // Tags of other crates, and of other versions ending the same
+    assert_eq!(find_version_tag(&tags, "foo", &version("0.6.0")), None);
+    assert_eq!(
+        find_version_tag(&tags, "foo_bar", &version("0.6.0")),
+        Some(&("foo_bar-0.6.0".to_owned(), "hhhh".to_owned()))
+    );
+    assert_eq!(find_version_tag(&tags, "foo", &version("0.7.0")), None);
+}
+
+/// Is `rev` a full commit id
+pub fn is_commit_id(rev: &str) -> bool {
+    rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Check out a single commit of the repository at `url` into a new `dir`
+///
+/// `rev` is either a full commit id (if the server allows fetching it),
+/// or a `refs/tags/` reference.
+pub fn shallow_checkout_git_rev(url: &str, rev: &str, dir: &Path) -> Result<()> {
+    if !is_commit_id(rev) && !rev.starts_with("refs/tags/") {
+        bail!("`{}` is neither a commit id nor a tag", rev);
+    }
+    let git_dir = dir.as_os_str();
+    run_git_cmd(vec![
+        OsStr::new("init"),
+        OsStr::new("--quiet"),
+        OsStr::new("--"),
+        git_dir,
+    ])?;
+    run_git_cmd(vec![
+        OsStr::new("-C"),
+        git_dir,
+        OsStr::new("fetch"),
+        OsStr::new("--quiet"),
+        OsStr::new("--depth=1"),
+        OsStr::new("--no-tags"),
+        OsStr::new("--"),
+        OsStr::new(url),
+        OsStr::new(rev),
+    ])?;
+    run_git_cmd(vec![
+        OsStr::new("-C"),
+        git_dir,
+        OsStr::new("checkout"),
+        OsStr::new("--quiet"),
+        OsStr::new("FETCH_HEAD"),
+    ])
+}
+
+#[test]
+fn untrusted_urls_and_revs_are_not_options() {
+    let tmp = tempdir::TempDir::new("crev-git-args").unwrap();
+    let marker = tmp.path().join("marker");
+    let injected = format!("--upload-pack=touch {}", marker.display());
+
+    assert!(list_remote_tags(&injected).is_err());
+    assert!(shallow_checkout_git_rev(&injected, "refs/tags/v1", &tmp.path().join("a")).is_err());
+    assert!(shallow_checkout_git_rev("https://a", &injected, &tmp.path().join("b")).is_err());
+    assert!(shallow_clone_git_repo(&injected, &tmp.path().join("c")).is_err());
+    assert!(!marker.exists());
+
+    assert!(is_commit_id("277c6c67a361a7223738ab67dd911ea736af70ef"));
+    assert!(!is_commit_id("277c6c67"));
+    assert!(!is_commit_id("-77c6c67a361a7223738ab67dd911ea736af70ef"));
+}
+
+/// Is there a `git` binary to run
+fn has_git_binary() -> bool {
+    Command::new("git").arg("--version").output().is_ok()
+}
+
+/// Clone only the latest commit of the default branch
+///
+/// libgit2 can't do shallow clones, so this uses the `git` binary, or
+/// does a full clone with libgit2 if there is none.
+pub fn shallow_clone_git_repo(url: &str, dir: &Path) -> Result<()> {
+    if !has_git_binary() {
+        clone_git_repo(url, dir)?;
+        return Ok(());
+    }
+    run_git_cmd(vec![
+        OsStr::new("clone"),
+        OsStr::new("--quiet"),
+        OsStr::new("--depth=1"),
+        OsStr::new("--single-branch"),
+        OsStr::new("--no-tags"),
+        OsStr::new("--"),
+        OsStr::new(url),
+        dir.as_os_str(),
+    ])
+}
+
+/// Update a shallow clone to the latest commit of the remote default branch
+///
+/// Without a `git` binary, the full history is fetched with libgit2.
+pub fn shallow_fetch_and_checkout_git_repo(dir: &Path) -> Result<()> {
+    if !has_git_binary() {
+        return fetch_and_checkout_git_repo(&git2::Repository::open(dir)?);
+    }
+    let git_dir = dir.as_os_str();
+    run_git_cmd(vec![
+        OsStr::new("-C"),
+        git_dir,
+        OsStr::new("fetch"),
+        OsStr::new("--quiet"),
+        OsStr::new("--depth=1"),
+        OsStr::new("--no-tags"),
+        OsStr::new("--"),
+        OsStr::new("origin"),
+        OsStr::new("HEAD"),
+    ])?;
+    run_git_cmd(vec![
+        OsStr::new("-C"),
+        git_dir,
+        OsStr::new("reset"),
+        OsStr::new("--quiet"),
+        OsStr::new("--hard"),
+        OsStr::new("FETCH_HEAD"),
+    ])
+}
+
 #[test]
 fn parse_git_url_https_test() {
     assert_eq!(
@@ -0,0 +1,81 @@
+//! Downloads of proof repositories published as static files over HTTPS
+
+use crate::prelude::*;
+use failure::bail;
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+
+/// Headers of a previous download, to download a file again only if it changed
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Validators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Result of a conditional GET
+#[derive(Debug, PartialEq)]
+pub enum Fetched {
+    /// Unchanged since the download the validators are from
+    NotModified,
+    Modified {
+        body: Vec<u8>,
+        validators: Validators,
+    },
+    NotFound,
+}
+
+fn header_string(headers: &header::HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(ToOwned::to_owned)
+}
+
+pub fn client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder().build()?)
+}
+
+/// GET `url`, unless it didn't change since the download `validators` are from
+///
+/// Fails on bodies larger than `max_size`.
+pub fn conditional_get(
+    client: &reqwest::Client,
+    url: &str,
+    validators: &Validators,
+    max_size: u64,
+) -> Result<Fetched> {
+    let mut request = client.get(url);
+    if let Some(ref etag) = validators.etag {
+        request = request.header(header::IF_NONE_MATCH, etag.as_str());
+    }
+    if let Some(ref last_modified) = validators.last_modified {
+        request = request.header(header::IF_MODIFIED_SINCE, last_modified.as_str());
+    }
+    let response = request.send()?;
+    match response.status() {
+        StatusCode::NOT_MODIFIED => return Ok(Fetched::NotModified),
+        StatusCode::NOT_FOUND | StatusCode::GONE => return Ok(Fetched::NotFound),
+        status if !status.is_success() => bail!("GET {} returned {}", url, status),
+        _ => {}
+    }
+    let validators = Validators {
+        etag: header_string(response.headers(), header::ETAG),
+        last_modified: header_string(response.headers(), header::LAST_MODIFIED),
+    };
+    let mut body = vec![];
+    response.take(max_size + 1).read_to_end(&mut body)?;
+    if body.len() as u64 > max_size {
+        bail!("{} is larger than {} bytes", url, max_size);
+    }
+    Ok(Fetched::Modified { body, validators })
+}
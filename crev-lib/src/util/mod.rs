@@ -1,4 +1,5 @@
 pub mod git;
+pub mod http;
 
 use crate::prelude::*;
 use crev_common;
@@ -129,6 +130,16 @@ pub fn edit_proof_content_iteractively(
     content: &proof::Content,
     previous_date: Option<&proof::Date>,
     base_version: Option<&semver::Version>,
+) -> Result<proof::Content> {
+    edit_proof_content_iteractively_with_notes(content, previous_date, base_version, "")
+}
+
+/// Like `edit_proof_content_iteractively`, with `notes` for the user commented out above the proof
+pub fn edit_proof_content_iteractively_with_notes(
+    content: &proof::Content,
+    previous_date: Option<&proof::Date>,
+    base_version: Option<&semver::Version>,
+    notes: &str,
 ) -> Result<proof::Content> {
     let mut text = String::new();
     if let Some(date) = previous_date {
@@ -142,6 +153,9 @@ pub fn edit_proof_content_iteractively(
     if let Some(base_version) = base_version {
         text.write_str(&format!("# Diff base version: {}\n", base_version))?;
     }
+    for line in notes.lines() {
+        text.write_fmt(format_args!("# {}\n", line))?;
+    }
     text.write_str(&content.to_draft_string())?;
     text.write_str("\n\n")?;
     for line in get_documentation_for(content).lines() {
@@ -169,6 +183,26 @@ pub fn err_eprint_and_ignore<O, E: std::error::Error>(res: std::result::Result<O
     }
 }
 
+/// Create a new file, readable and writable only by the user from the start
+#[cfg(target_family = "unix")]
+pub fn create_new_600_file(path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(target_family = "unix"))]
+pub fn create_new_600_file(path: &Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+}
+
 #[cfg(target_family = "unix")]
 pub fn chmod_path_to_600(path: &Path) -> io::Result<()> {
     use std::fs::Permissions;
@@ -0,0 +1,32 @@
+//! Proof contents that parse must serialize back to something that parses
+//! to the same serialized form.
+//!
+//! Run with `cargo fuzz run proof_roundtrip` from the repository root.
+#![no_main]
+use crev_data::proof::{Content, Proof, ProofType};
+use libfuzzer_sys::fuzz_target;
+
+fn check_roundtrip(content: &Content, type_: ProofType) {
+    let serialized = content.to_string();
+    let reparsed = Content::parse(&serialized, type_)
+        .unwrap_or_else(|e| panic!("serialized proof doesn't parse: {}\n{}", e, serialized));
+    assert_eq!(serialized, reparsed.to_string());
+}
+
+fuzz_target!(|data: &[u8]| {
+    // As a whole signed proof
+    if let Ok(proofs) = Proof::parse(data) {
+        for proof in proofs {
+            check_roundtrip(&proof.content, proof.content.proof_type());
+        }
+    }
+
+    // As a bare proof body of every type
+    if let Ok(s) = std::str::from_utf8(data) {
+        for &type_ in &[ProofType::Code, ProofType::Package, ProofType::Trust] {
+            if let Ok(content) = Content::parse(s, type_) {
+                check_roundtrip(&content, type_);
+            }
+        }
+    }
+});